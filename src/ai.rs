@@ -1,7 +1,11 @@
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
-use crate::git::DiffInfo;
+use crate::git::{self, ChangeKind, CommitInfo, DiffInfo, FileChange};
+use crate::journal;
 use crate::config::Config;
+use crate::conflicts::{ConflictHunk, ConflictSuggestion};
+use crate::patches::CoverLetter;
+use crate::split::{SplitGroup, SplitUnit};
 use async_openai::{
     Client,
     types::{
@@ -74,20 +78,22 @@ pub async fn generate_pr_description(diff_info: &DiffInfo, config: &Config) -> R
         .join("\n");
     
     let files_summary = diff_info.commits.iter()
-        .flat_map(|c| &c.files_changed)
-        .collect::<std::collections::HashSet<_>>()
-        .iter()
-        .map(|f| format!("- {}", f))
+        .flat_map(|c| &c.file_changes)
+        .map(|f| format!("- {}", f.describe()))
+        .collect::<std::collections::BTreeSet<_>>()
+        .into_iter()
         .collect::<Vec<_>>()
         .join("\n");
     
     let system_prompt = "You are an expert software engineer creating a pull request description. Generate a comprehensive PR description in markdown format that includes a clear title, summary of changes, what was modified and why, any breaking changes, testing instructions, and screenshots if relevant.";
     
     let user_prompt = format!(
-        "Based on the following commit information, generate a comprehensive PR description:\n\nCommits:\n{}\n\nFiles changed:\n{}\n\nTotal files changed: {}\n\nPlease create a professional PR description with proper markdown formatting.",
+        "Based on the following commit information, generate a comprehensive PR description:\n\nCommits:\n{}\n\nFiles changed:\n{}\n\nTotal files changed: {} (+{} -{})\n\nPlease create a professional PR description with proper markdown formatting.",
         commits_summary,
         files_summary,
-        diff_info.total_files_changed
+        diff_info.total_files_changed,
+        diff_info.total_additions,
+        diff_info.total_deletions
     );
     
     call_openai_api(system_prompt, &user_prompt, config).await
@@ -100,7 +106,7 @@ pub async fn generate_tests(diff_info: &DiffInfo, _framework: &str, config: &Con
         .join("\n\n");
     
     // Detect project type and determine appropriate test framework and directory structure
-    let project_info = detect_project_type(&diff_info);
+    let project_info = detect_project_type(diff_info);
     
         let system_prompt = "You are an expert software engineer writing comprehensive unit tests. Generate working Rust test functions that will compile and run. Focus on testing both core functionality and the new interactive CLI interface. Return ONLY the test code without any markdown formatting, explanations, or additional text.";
         
@@ -114,40 +120,47 @@ pub async fn generate_tests(diff_info: &DiffInfo, _framework: &str, config: &Con
     let test_content = call_openai_api(system_prompt, &user_prompt, config).await?;
     
     // Create the test directory if it doesn't exist
-    let test_dir = Path::new(&project_info.test_directory);
-    if !test_dir.exists() {
-        fs::create_dir_all(test_dir)?;
-        println!("📁 Created test directory: {}", project_info.test_directory);
-    }
-    
-    // Generate test files based on project type
-    match project_info.project_type.as_str() {
-        "Rust" => {
-            create_rust_tests(&test_content, test_dir)?;
-        }
-        "Python" => {
-            create_python_tests(&test_content, test_dir)?;
-        }
-        "JavaScript/TypeScript" => {
-            create_js_tests(&test_content, test_dir)?;
-        }
-        "Java" => {
-            create_java_tests(&test_content, test_dir)?;
-        }
-        "Go" => {
-            create_go_tests(&test_content, test_dir)?;
-        }
-        "C/C++" => {
-            create_cpp_tests(&test_content, test_dir)?;
-        }
-        "C#" => {
-            create_csharp_tests(&test_content, test_dir)?;
-        }
-        _ => {
-            create_generic_tests(&test_content, test_dir)?;
-        }
-    }
-    
+    let test_dir = &diff_info.path(&project_info.test_directory);
+    // Overwritten test files can be restored with `commit-buddy undo`
+    let repo = git::open_repository()?;
+    journal::action(&repo, "generate tests")
+        .files(&[test_dir.to_path_buf()])
+        .run(|| {
+            if !test_dir.exists() {
+                fs::create_dir_all(test_dir)?;
+                println!("📁 Created test directory: {}", project_info.test_directory);
+            }
+
+            // Generate test files based on project type
+            match project_info.project_type.as_str() {
+                "Rust" => {
+                    create_rust_tests(&test_content, test_dir)?;
+                }
+                "Python" => {
+                    create_python_tests(&test_content, test_dir)?;
+                }
+                "JavaScript/TypeScript" => {
+                    create_js_tests(&test_content, test_dir)?;
+                }
+                "Java" => {
+                    create_java_tests(&test_content, test_dir)?;
+                }
+                "Go" => {
+                    create_go_tests(&test_content, test_dir)?;
+                }
+                "C/C++" => {
+                    create_cpp_tests(&test_content, test_dir)?;
+                }
+                "C#" => {
+                    create_csharp_tests(&test_content, test_dir)?;
+                }
+                _ => {
+                    create_generic_tests(&test_content, test_dir)?;
+                }
+            }
+            Ok(())
+        })?;
+
     Ok(format!("✅ Tests generated successfully in {} directory using {} framework!", 
                project_info.test_directory, project_info.test_framework))
 }
@@ -161,8 +174,8 @@ fn detect_project_type(diff_info: &DiffInfo) -> ProjectInfo {
     let file_extensions: std::collections::HashSet<String> = diff_info.commits.iter()
         .flat_map(|c| &c.files_changed)
         .map(|f| {
-            f.split('.')
-                .last()
+            f.rsplit('.')
+                .next()
                 .unwrap_or("")
                 .to_lowercase()
         })
@@ -355,8 +368,18 @@ pub async fn improve_commit_message(message: &str, config: &Config) -> Result<St
 }
 
 pub async fn generate_commit_suggestions(diff_info: &DiffInfo, config: &Config) -> Result<Vec<String>> {
+    if !config.has_openai_key() {
+        return Ok(heuristic_commit_suggestions(diff_info));
+    }
+
     let staged_changes = diff_info.commits.iter()
-        .map(|c| format!("Files: {}\nDiff:\n{}", c.files_changed.join(", "), c.diff))
+        .map(|c| {
+            let files = c.file_changes.iter()
+                .map(|f| f.describe())
+                .collect::<Vec<_>>()
+                .join(", ");
+            format!("Files: {}\nDiff:\n{}", files, c.diff)
+        })
         .collect::<Vec<_>>()
         .join("\n\n");
     
@@ -369,41 +392,515 @@ pub async fn generate_commit_suggestions(diff_info: &DiffInfo, config: &Config)
     
     let response = call_openai_api(system_prompt, &user_prompt, config).await?;
     
+    // Models sometimes number or quote the lines; keep only real conventional commits
     let suggestions: Vec<String> = response.lines()
-        .filter(|line| !line.trim().is_empty())
-        .map(|line| line.trim().to_string())
+        .map(|line| line.trim()
+            .trim_start_matches(|c: char| c.is_ascii_digit() || matches!(c, '.' | ')' | '-' | '*'))
+            .trim()
+            .trim_matches(['`', '"']))
+        .filter(|line| crate::utils::is_conventional_commit(line))
+        .map(str::to_string)
         .collect();
     
     // Ensure we have exactly 3 suggestions
     if suggestions.len() < 3 {
-        // Fallback suggestions if AI doesn't provide enough
+        // Fill up with heuristic suggestions if AI doesn't provide enough
         let mut fallback = suggestions;
-        fallback.push("feat: add new functionality".to_string());
-        fallback.push("fix: resolve issue".to_string());
-        fallback.push("chore: update code".to_string());
+        fallback.extend(heuristic_commit_suggestions(diff_info));
         Ok(fallback.into_iter().take(3).collect())
     } else {
         Ok(suggestions.into_iter().take(3).collect())
     }
 }
 
-pub async fn generate_changelog(diff_info: &DiffInfo, config: &Config) -> Result<String> {
-    let commits_summary = diff_info.commits.iter()
-        .map(|c| format!("- {}: {}", &c.hash[..8], c.message))
+/// Builds three conventional commit messages from the shape of the change
+/// alone, for use when no AI backend is configured.
+pub fn heuristic_commit_suggestions(diff_info: &DiffInfo) -> Vec<String> {
+    let changes: Vec<&FileChange> = diff_info.commits.iter()
+        .flat_map(|c| &c.file_changes)
+        .collect();
+
+    if changes.is_empty() {
+        return vec![
+            "chore: update code".to_string(),
+            "chore: update project files".to_string(),
+            "chore: misc changes".to_string(),
+        ];
+    }
+
+    let is_doc = |p: &str| p.ends_with(".md") || p.ends_with(".txt") || p.starts_with("docs/");
+    let is_test = |p: &str| p.starts_with("tests/") || p.contains("_test.") || p.contains(".test.") || p.contains("/test_");
+    let is_ci = |p: &str| p.starts_with(".github/") || p.starts_with(".gitlab-ci") || p.starts_with(".circleci/");
+    let is_build = |p: &str| {
+        let name = p.rsplit('/').next().unwrap_or(p);
+        matches!(name, "Cargo.toml" | "Cargo.lock" | "package.json" | "package-lock.json" | "yarn.lock" | "pyproject.toml" | "go.mod" | "go.sum" | "Makefile" | "Dockerfile")
+    };
+    let all = |pred: &dyn Fn(&str) -> bool| changes.iter().all(|c| pred(&c.path));
+
+    let commit_type = if all(&is_doc) {
+        "docs"
+    } else if all(&is_test) {
+        "test"
+    } else if all(&is_ci) {
+        "ci"
+    } else if all(&is_build) {
+        "build"
+    } else if changes.iter().all(|c| matches!(c.kind, ChangeKind::Renamed | ChangeKind::Deleted)) {
+        "refactor"
+    } else if changes.iter().any(|c| c.kind == ChangeKind::Added && !is_test(&c.path) && !is_doc(&c.path)) {
+        "feat"
+    } else {
+        "chore"
+    };
+
+    // Scope: the shared top-level directory, or the file stem for single-file changes
+    let stem = |p: &str| {
+        let name = p.rsplit('/').next().unwrap_or(p);
+        name.split('.').next().unwrap_or(name).to_string()
+    };
+    let scope = if changes.len() == 1 {
+        Some(stem(&changes[0].path))
+    } else {
+        let dirs: std::collections::BTreeSet<&str> = changes.iter()
+            .filter_map(|c| c.path.split_once('/').map(|(dir, _)| dir))
+            .collect();
+        if dirs.len() == 1 && changes.iter().all(|c| c.path.contains('/')) {
+            dirs.into_iter().next().map(|d| d.to_string())
+        } else {
+            None
+        }
+    };
+
+    let subject = if changes.len() == 1 {
+        let change = changes[0];
+        let name = change.path.rsplit('/').next().unwrap_or(&change.path);
+        match (change.kind, &change.old_path) {
+            (ChangeKind::Renamed, Some(old)) => format!("rename {} to {}", old.rsplit('/').next().unwrap_or(old), name),
+            (ChangeKind::Added, _) => format!("add {}", name),
+            (ChangeKind::Deleted, _) => format!("remove {}", name),
+            _ => format!("update {}", name),
+        }
+    } else {
+        let verb = match commit_type {
+            "feat" => "add",
+            "refactor" => "reorganize",
+            _ => "update",
+        };
+        match &scope {
+            Some(scope) => format!("{} {} files", verb, scope),
+            None => format!("{} {} files", verb, changes.len()),
+        }
+    };
+
+    let general = match commit_type {
+        "docs" => "update documentation",
+        "test" => "update tests",
+        "ci" => "update CI configuration",
+        "build" => "update build configuration",
+        "refactor" => "restructure code",
+        "feat" => "add new functionality",
+        _ => "update code",
+    };
+
+    let mut suggestions = Vec::new();
+    if let Some(scope) = &scope {
+        suggestions.push(format!("{}({}): {}", commit_type, scope, subject));
+    }
+    suggestions.push(format!("{}: {}", commit_type, subject));
+    suggestions.push(format!("{}: {}", commit_type, general));
+    if suggestions.len() < 3 {
+        suggestions.push(format!("chore: {}", general));
+    }
+    suggestions.truncate(3);
+    suggestions
+}
+
+/// Asks the model to cluster the numbered hunks of a staged diff into
+/// logical commits. The returned plan is not validated here.
+pub async fn plan_commit_split(units: &[SplitUnit], config: &Config) -> Result<Vec<SplitGroup>> {
+    let hunks = units.iter()
+        .map(|u| format!("### Hunk {} — {}\n{}", u.id, u.change.describe(), crate::utils::truncate_string(&u.patch, 1500)))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+
+    let system_prompt = "You are an expert software engineer who splits large changes into small, logical, self-contained commits that follow the conventional commit specification.";
+
+    let user_prompt = format!(
+        "Group the following numbered hunks into logical commits. Every hunk must belong to exactly one commit, and hunks that depend on each other must be in the same commit or in an earlier one.\n\n{}\n\nReturn ONLY a JSON array, ordered the way the commits should be made, in this shape:\n[{{\"message\": \"feat(scope): description\", \"hunks\": [0, 2]}}]",
+        hunks
+    );
+
+    let response = call_openai_api(system_prompt, &user_prompt, config).await?;
+
+    #[derive(Deserialize)]
+    struct PlannedCommit {
+        message: String,
+        hunks: Vec<usize>,
+    }
+
+    let json = match (response.find('['), response.rfind(']')) {
+        (Some(start), Some(end)) if start < end => &response[start..=end],
+        _ => anyhow::bail!("The model did not return a JSON plan"),
+    };
+    let planned: Vec<PlannedCommit> = serde_json::from_str(json)?;
+
+    Ok(planned.into_iter()
+        .map(|p| SplitGroup { message: p.message, units: p.hunks })
+        .collect())
+}
+
+/// Suggests a branch name for the staged changes. Without an API key the
+/// name is derived from the first heuristic commit message.
+pub async fn suggest_branch_name(diff_info: &DiffInfo, config: &Config) -> Result<String> {
+    let fallback = crate::branches::branch_name_from_message(&heuristic_commit_suggestions(diff_info)[0]);
+    if !config.has_openai_key() {
+        return Ok(fallback);
+    }
+
+    let changes = diff_info.commits.iter()
+        .flat_map(|c| &c.file_changes)
+        .map(|f| f.describe())
         .collect::<Vec<_>>()
         .join("\n");
-    
-    let system_prompt = "You are an expert software engineer creating a changelog. Generate a professional changelog in markdown format following Keep a Changelog standards.";
-    
+    let diff = diff_info.commits.iter()
+        .map(|c| crate::utils::truncate_string(&c.diff, 3000))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let system_prompt = "You are an expert software engineer who names git branches after the work they contain.";
     let user_prompt = format!(
-        "Based on the following commits, generate a professional changelog:\n\n{}\n\nPlease create a changelog that includes:\n1. A clear version header\n2. Categorized changes (Added, Changed, Fixed, Removed, etc.)\n3. Breaking changes section if applicable\n4. Contributors if available\n5. Links to issues/PRs if mentioned in commits\n\nFormat as proper markdown following Keep a Changelog format.",
-        commits_summary
+        "Suggest a branch name for the following staged changes.\n\nFiles:\n{}\n\nDiff:\n{}\n\nRequirements:\n1. Use the form <type>/<short-description>, e.g. feat/add-branch-panel\n2. Use a conventional commit type as the prefix\n3. Use lower case words separated by '-', at most 5 words\n\nReturn ONLY the branch name.",
+        changes, diff
     );
-    
+
+    let response = call_openai_api(system_prompt, &user_prompt, config).await?;
+    let name = crate::branches::sanitize_branch_name(response.lines().next().unwrap_or(""));
+    Ok(if name.is_empty() { fallback } else { name })
+}
+
+/// A one-line description of stashed or about-to-be-stashed changes.
+/// Without an API key the first heuristic commit suggestion is used.
+pub async fn describe_stash(diff_info: &DiffInfo, config: &Config) -> Result<String> {
+    let fallback = heuristic_commit_suggestions(diff_info).into_iter().next().unwrap_or_default();
+    if !config.has_openai_key() || diff_info.commits.is_empty() {
+        return Ok(fallback);
+    }
+
+    let changes = diff_info.commits.iter()
+        .flat_map(|c| &c.file_changes)
+        .map(|f| f.describe())
+        .collect::<Vec<_>>()
+        .join("\n");
+    let diff = diff_info.commits.iter()
+        .map(|c| crate::utils::truncate_string(&c.diff, 3000))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let system_prompt = "You are an expert software engineer who labels work in progress so it can be found again later.";
+    let user_prompt = format!(
+        "Describe the following uncommitted changes in one line of at most 60 characters, saying what the work is about rather than listing files.\n\nFiles:\n{}\n\nDiff:\n{}\n\nReturn ONLY the description.",
+        changes, diff
+    );
+
+    let response = call_openai_api(system_prompt, &user_prompt, config).await?;
+    let description = response.lines().next().unwrap_or("").trim().trim_matches(['`', '"']).to_string();
+    Ok(if description.is_empty() { fallback } else { description })
+}
+
+/// Asks the model to resolve one conflict hunk of `path`, returning the
+/// merged text together with its reasoning.
+pub async fn suggest_conflict_resolution(path: &str, hunk: &ConflictHunk, config: &Config) -> Result<ConflictSuggestion> {
+    if !config.has_openai_key() {
+        anyhow::bail!("Suggesting conflict resolutions requires OPENAI_API_KEY to be set");
+    }
+
+    let system_prompt = "You are an expert software engineer resolving git merge conflicts. You keep the intent of both sides whenever they are compatible.";
+    let user_prompt = format!(
+        "Resolve this merge conflict in {}.\n\n### Ours (current branch)\n{}\n### Base (common ancestor)\n{}\n### Theirs (incoming)\n{}\nReturn ONLY a JSON object in this shape:\n{{\"resolution\": \"<the merged text, without conflict markers>\", \"reasoning\": \"<one or two sentences>\"}}",
+        path,
+        hunk.ours,
+        hunk.base.as_deref().unwrap_or("(none, added on both sides)\n"),
+        hunk.theirs
+    );
+
+    let response = call_openai_api(system_prompt, &user_prompt, config).await?;
+
+    #[derive(Deserialize)]
+    struct Suggested {
+        resolution: String,
+        reasoning: String,
+    }
+
+    let json = match (response.find('{'), response.rfind('}')) {
+        (Some(start), Some(end)) if start < end => &response[start..=end],
+        _ => anyhow::bail!("The model did not return a JSON resolution"),
+    };
+    let suggested: Suggested = serde_json::from_str(json)?;
+
+    // Keep the hunk's line structure so it splices cleanly into the file
+    let mut resolution = suggested.resolution;
+    if !resolution.is_empty() && !resolution.ends_with('\n') {
+        resolution.push('\n');
+    }
+    Ok(ConflictSuggestion { resolution, reasoning: suggested.reasoning })
+}
+
+/// Explains what a commit does and why, for readers new to the change.
+pub async fn explain_commit(commit: &CommitInfo, config: &Config) -> Result<String> {
+    let subject = format!("commit {}", &commit.hash[..commit.hash.len().min(8)]);
+    explain_changes(&subject, &DiffInfo::from_commits(vec![commit.clone()]), false, config).await
+}
+
+/// A narrative of what a commit or range of commits does and why, citing
+/// commit hashes. Without an API key the commit outline is returned.
+pub async fn explain_changes(subject: &str, diff_info: &DiffInfo, markdown: bool, config: &Config) -> Result<String> {
+    let task = format!(
+        "Explain {} to an engineer who is new to this codebase: what changed, why it was likely done, and how the commits build on each other. Point out anything a reviewer should watch out for.",
+        subject
+    );
+    explain_history(&task, diff_info, markdown, config).await
+}
+
+/// How and why a file evolved over its history, citing commit hashes.
+/// Without an API key the commit outline is returned.
+pub async fn explain_file_history(path: &str, diff_info: &DiffInfo, markdown: bool, config: &Config) -> Result<String> {
+    let task = format!(
+        "Summarise how {} evolved over the commits below, oldest first, and why: its purpose, the major changes and the reasons behind them. Group related commits into phases instead of retelling every commit.",
+        path
+    );
+    explain_history(&task, diff_info, markdown, config).await
+}
+
+async fn explain_history(task: &str, diff_info: &DiffInfo, markdown: bool, config: &Config) -> Result<String> {
+    let outline = commit_outline(diff_info, markdown);
+    if !config.has_openai_key() {
+        return Ok(outline);
+    }
+
+    // Share the diff budget evenly so long histories still fit the prompt
+    let budget = 12000 / diff_info.commits.len().max(1);
+    let diffs = diff_info.commits.iter()
+        .map(|c| format!("Commit {}:\n{}", &c.hash[..8], crate::utils::truncate_string(&c.diff, budget.max(500))))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    let style = if markdown {
+        "Format the answer as markdown with short paragraphs or bullet points, and no top-level heading."
+    } else {
+        "Format the answer as plain text without markdown syntax."
+    };
+
+    let system_prompt = "You are an expert software engineer explaining the history of a codebase to a teammate who has not seen the code before. You only state what the commits support.";
+    let user_prompt = format!(
+        "{}\n\nCite the short commit hashes from the list for every claim. {}\n\nCommits:\n{}\n\nDiffs:\n{}",
+        task,
+        style,
+        outline,
+        diffs
+    );
+
+    call_openai_api(system_prompt, &user_prompt, config).await
+}
+
+/// Why a range of lines looks the way it does, from the commits that last
+/// changed them. Without an API key the blame and commit outline are
+/// returned.
+pub async fn explain_lines(location: &str, code: &str, blame: &str, diff_info: &DiffInfo, config: &Config) -> Result<String> {
+    let outline = commit_outline(diff_info, false);
+    if !config.has_openai_key() {
+        return Ok(format!("Commits behind these lines:\n\n{}", outline));
+    }
+
+    let budget = 12000 / diff_info.commits.len().max(1);
+    let diffs = diff_info.commits.iter()
+        .map(|c| format!("Commit {}: {}\n{}", &c.hash[..8], c.message.trim(), crate::utils::truncate_string(&c.diff, budget.max(500))))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+
+    let system_prompt = "You are an expert software engineer doing code archaeology. You explain why code is written the way it is, based only on its history.";
+    let user_prompt = format!(
+        "Explain concisely why the code at {} looks the way it does: what each change was for and which constraints or decisions shaped it. Cite the short commit hashes, and say so when the history does not explain something.\n\nCode:\n{}\n\nBlame (line range, commit, author, date, summary):\n{}\n\nCommits:\n{}",
+        location,
+        code,
+        blame,
+        diffs
+    );
+
+    call_openai_api(system_prompt, &user_prompt, config).await
+}
+
+/// Every commit with its hash, subject, author, date and file changes,
+/// in the order given.
+fn commit_outline(diff_info: &DiffInfo, markdown: bool) -> String {
+    let mut outline = format!(
+        "{} commit(s), {} file(s) changed, +{} -{}\n",
+        diff_info.commits.len(),
+        diff_info.total_files_changed,
+        diff_info.total_additions,
+        diff_info.total_deletions
+    );
+    for commit in &diff_info.commits {
+        let date = commit.date.parse::<i64>().ok()
+            .and_then(|seconds| chrono::DateTime::from_timestamp(seconds, 0))
+            .map(|date| date.format("%Y-%m-%d").to_string())
+            .unwrap_or_default();
+        let summary = commit.message.lines().next().unwrap_or("");
+        let hash = &commit.hash[..8];
+        if markdown {
+            outline.push_str(&format!("\n- `{}` {} ({}, {})\n", hash, summary, commit.author, date));
+        } else {
+            outline.push_str(&format!("\n{} {} ({}, {})\n", hash, summary, commit.author, date));
+        }
+        for change in &commit.file_changes {
+            let bullet = if markdown { "  - " } else { "    " };
+            outline.push_str(&format!("{}{}\n", bullet, change.describe()));
+        }
+    }
+    outline
+}
+
+/// One message for a commit and the fixups squashed into it, from their
+/// messages and diffs. Without an API key the first commit's message is
+/// returned.
+pub async fn combine_commit_messages(diff_info: &DiffInfo, config: &Config) -> Result<String> {
+    let original = diff_info.commits.first().map(|c| c.message.clone()).unwrap_or_default();
+    if !config.has_openai_key() {
+        return Ok(original);
+    }
+
+    let budget = 12000 / diff_info.commits.len().max(1);
+    let commits = diff_info.commits.iter()
+        .map(|c| format!("Commit {}:\n{}\n\nDiff:\n{}", &c.hash[..8], c.message.trim(), crate::utils::truncate_string(&c.diff, budget.max(500))))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+
+    let system_prompt = "You are an expert software engineer cleaning up a branch before review. You write commit messages that follow the conventional commit format.";
+    let user_prompt = format!(
+        "The commits below are being squashed into one: the first is the real change, the rest are fixups such as typo fixes, formatting or review feedback. Write the message of the combined commit.\n\nRules:\n1. Describe the combined change, not the fixups; never mention \"wip\", \"typo\" or \"review\" unless that is the change itself\n2. Keep the first commit's type and scope unless the diff clearly says otherwise\n3. Imperative mood, subject under 72 characters, optional body explaining what and why\n4. Keep issue references from the original messages\n\nReply with the commit message only.\n\n{}",
+        commits
+    );
+
     call_openai_api(system_prompt, &user_prompt, config).await
 }
 
+/// The subject, overview and per-patch summaries of a patch series cover
+/// letter, mentioning `changes_since` for a reroll. Without an API key the
+/// subject and overview are git's placeholders (or the only patch's
+/// subject) and each summary is the first paragraph of the commit body.
+pub async fn write_cover_letter(diff_info: &DiffInfo, changes_since: &[String], config: &Config) -> Result<CoverLetter> {
+    let subjects: Vec<&str> = diff_info.commits.iter()
+        .map(|c| c.message.lines().next().unwrap_or("").trim())
+        .collect();
+    let bodies: Vec<String> = diff_info.commits.iter()
+        .map(|c| {
+            let body = c.message.lines().skip(1).skip_while(|line| line.trim().is_empty());
+            body.take_while(|line| !line.trim().is_empty()).collect::<Vec<_>>().join(" ")
+        })
+        .collect();
+    let fallback = CoverLetter {
+        subject: match subjects.as_slice() {
+            [only] => only.to_string(),
+            _ => "*** SUBJECT HERE ***".to_string(),
+        },
+        overview: "*** BLURB HERE ***".to_string(),
+        summaries: bodies,
+    };
+    if !config.has_openai_key() || diff_info.commits.is_empty() {
+        return Ok(fallback);
+    }
+
+    let budget = 12000 / diff_info.commits.len().max(1);
+    let patches = diff_info.commits.iter().enumerate()
+        .map(|(i, c)| format!("### Patch {}/{}\n{}\n\nDiff:\n{}", i + 1, subjects.len(), c.message.trim(), crate::utils::truncate_string(&c.diff, budget.max(500))))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    let changes = if changes_since.is_empty() {
+        String::new()
+    } else {
+        format!("\n\nThis is a new version of the series. Changes since the previous version:\n{}", changes_since.join("\n"))
+    };
+
+    let system_prompt = "You are an expert software engineer sending a patch series to a project's mailing list. You write cover letters that maintainers can review from quickly: plain text, no markdown.";
+    let user_prompt = format!(
+        "Write the cover letter for the patch series below.{}\n\n{}\n\nReturn ONLY a JSON object in this shape:\n{{\"subject\": \"<series subject under 60 characters, no [PATCH] tag>\", \"overview\": \"<one or two short paragraphs: the problem and how the series solves it; mention what changed since the previous version if there is one>\", \"patches\": [\"<one sentence per patch, in order>\"]}}",
+        changes, patches
+    );
+
+    let response = call_openai_api(system_prompt, &user_prompt, config).await?;
+
+    #[derive(Deserialize)]
+    struct Written {
+        subject: String,
+        overview: String,
+        patches: Vec<String>,
+    }
+
+    let json = match (response.find('{'), response.rfind('}')) {
+        (Some(start), Some(end)) if start < end => &response[start..=end],
+        _ => anyhow::bail!("The model did not return a JSON cover letter"),
+    };
+    let written: Written = serde_json::from_str(json)?;
+
+    // Keep the fallback summary for any patch the model skipped
+    let mut summaries = written.patches;
+    summaries.resize(subjects.len(), String::new());
+    for (summary, body) in summaries.iter_mut().zip(fallback.summaries) {
+        if summary.trim().is_empty() {
+            *summary = body;
+        }
+    }
+    Ok(CoverLetter {
+        subject: Some(written.subject.trim().to_string()).filter(|s| !s.is_empty()).unwrap_or(fallback.subject),
+        overview: written.overview,
+        summaries,
+    })
+}
+
+/// Rewords the entries of a generated changelog without touching its
+/// structure. Without an API key the changelog is returned unchanged.
+pub async fn polish_changelog(changelog: &str, config: &Config) -> Result<String> {
+    if !config.has_openai_key() {
+        return Ok(changelog.to_string());
+    }
+
+    let system_prompt = "You are an expert technical writer editing a changelog that follows Keep a Changelog. You only improve wording.";
+    let user_prompt = format!(
+        "Polish the wording of the bullet points in this changelog so they read well for users of the project.\n\nRules:\n1. Keep every heading, version, date and bullet, in the same order\n2. Keep every link and commit or pull request reference exactly as written\n3. Do not add or remove entries or sections\n4. Reply with the markdown only, without code fences\n\n{}",
+        changelog
+    );
+
+    let polished = call_openai_api(system_prompt, &user_prompt, config).await?;
+    Ok(format!("{}\n", polished.trim()))
+}
+
 pub async fn code_review(diff_info: &DiffInfo, config: &Config) -> Result<String> {
+    let focus = "Please review and provide feedback on:\n1. Code quality and best practices\n2. Potential bugs or issues\n3. Performance considerations\n4. Security concerns\n5. Maintainability and readability\n6. Testing coverage\n7. Documentation needs\n\nFormat your review as constructive feedback with specific suggestions for improvement.";
+    review_changes(diff_info, focus, config).await
+}
+
+/// Summarises the first bad commit found by a bisect and ranks its hunks
+/// by how likely they are to cause the failure. Without an API key the
+/// hunks are listed in diff order.
+pub async fn pinpoint_regression(diff_info: &DiffInfo, command: &str, failure_output: &str, config: &Config) -> Result<String> {
+    if !config.has_openai_key() {
+        let hunks = diff_info.commits.iter()
+            .flat_map(|c| hunk_headers(&c.diff))
+            .map(|(file, header)| format!("- {} {}", file, header))
+            .collect::<Vec<_>>()
+            .join("\n");
+        return Ok(format!("Hunks of the first bad commit:\n{}\n\n*Note: Set OPENAI_API_KEY to rank them by suspicion.*", hunks));
+    }
+
+    let focus = format!(
+        "git bisect found this commit to be the first one for which `{}` fails. The failing run ended with:\n{}\n\nStart with a two or three sentence summary of what the commit changes. Then list the hunks most likely to cause the failure, most suspicious first, each as the file and its @@ header with one sentence on why. Leave out feedback unrelated to the failure.",
+        command,
+        failure_output
+    );
+    review_changes(diff_info, &focus, config).await
+}
+
+/// The review pipeline: the commits and their diffs, with `focus` saying
+/// what to look for.
+async fn review_changes(diff_info: &DiffInfo, focus: &str, config: &Config) -> Result<String> {
     let code_changes = diff_info.commits.iter()
         .map(|c| format!("Commit {}: {}\nDiff:\n{}", &c.hash[..8], c.message, c.diff))
         .collect::<Vec<_>>()
@@ -412,9 +909,24 @@ pub async fn code_review(diff_info: &DiffInfo, config: &Config) -> Result<String
     let system_prompt = "You are an expert software engineer performing a code review. Provide comprehensive feedback on code quality, potential bugs, performance, security, maintainability, and testing.";
     
     let user_prompt = format!(
-        "Please review the following code changes and provide feedback:\n\n{}\n\nPlease review and provide feedback on:\n1. Code quality and best practices\n2. Potential bugs or issues\n3. Performance considerations\n4. Security concerns\n5. Maintainability and readability\n6. Testing coverage\n7. Documentation needs\n\nFormat your review as constructive feedback with specific suggestions for improvement.",
-        code_changes
+        "Please review the following code changes and provide feedback:\n\n{}\n\n{}",
+        code_changes,
+        focus
     );
     
     call_openai_api(system_prompt, &user_prompt, config).await
+}
+
+/// The file and `@@` header of every hunk in a patch.
+fn hunk_headers(diff: &str) -> Vec<(String, String)> {
+    let mut file = String::new();
+    let mut headers = Vec::new();
+    for line in diff.lines() {
+        if let Some(path) = line.strip_prefix("+++ ") {
+            file = path.trim_start_matches("b/").to_string();
+        } else if line.starts_with("@@") {
+            headers.push((file.clone(), line.to_string()));
+        }
+    }
+    headers
 }
\ No newline at end of file
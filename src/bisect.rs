@@ -0,0 +1,323 @@
+use anyhow::{Context, Result};
+use git2::{Oid, Repository};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+use crate::ai;
+use crate::config::Config;
+use crate::git::{self, DiffInfo};
+
+/// Lines of test output kept for each step
+const OUTPUT_TAIL_LINES: usize = 20;
+
+/// How a commit was judged, following the `git bisect run` convention:
+/// exit code 0 is good, 125 skips the commit and 1 to 127 is bad.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Verdict {
+    Good,
+    Bad,
+    Skip,
+}
+
+impl Verdict {
+    pub fn from_exit_code(code: i32) -> Option<Self> {
+        match code {
+            0 => Some(Verdict::Good),
+            125 => Some(Verdict::Skip),
+            1..=127 => Some(Verdict::Bad),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Verdict::Good => "good",
+            Verdict::Bad => "bad",
+            Verdict::Skip => "skip",
+        }
+    }
+}
+
+/// One tested commit.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BisectStep {
+    pub commit: String,
+    pub summary: String,
+    pub verdict: Verdict,
+    pub exit_code: i32,
+    /// The last lines the test command printed
+    pub output_tail: String,
+}
+
+/// A bisect session, saved under `.git` after every step so that an
+/// interrupted run can be resumed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BisectSession {
+    /// Shell command that tells good commits from bad ones
+    pub command: String,
+    pub good: String,
+    pub bad: String,
+    pub steps: Vec<BisectStep>,
+    pub first_bad: Option<String>,
+    /// Summary and suspicious hunks of the first bad commit
+    pub report: Option<String>,
+}
+
+/// Where a bisect session ended.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BisectOutcome {
+    FirstBad(Oid),
+    /// Only skipped commits are left; the first bad commit is one of these
+    OnlySkipped(Vec<Oid>),
+}
+
+fn state_path(repo: &Repository) -> PathBuf {
+    repo.path().join("commit-buddy-bisect.json")
+}
+
+/// The saved session, if one was started in this work tree.
+pub fn load(repo: &Repository) -> Result<Option<BisectSession>> {
+    let path = state_path(repo);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let json = fs::read_to_string(&path)?;
+    let session = serde_json::from_str(&json)
+        .with_context(|| format!("{} is not a valid bisect session", path.display()))?;
+    Ok(Some(session))
+}
+
+pub fn save(repo: &Repository, session: &BisectSession) -> Result<()> {
+    fs::write(state_path(repo), serde_json::to_string_pretty(session)?)?;
+    Ok(())
+}
+
+fn in_progress(repo: &Repository) -> bool {
+    repo.path().join("BISECT_START").exists()
+}
+
+/// Starts `git bisect` between `good` and `bad` and saves a new session.
+pub fn start(repo: &Repository, good: &str, bad: &str, command: &str) -> Result<BisectSession> {
+    if load(repo)?.is_some() || in_progress(repo) {
+        anyhow::bail!("A bisect session is already running; resume it or run `commit-buddy bisect reset`");
+    }
+    git::ensure_clean_worktree(repo, "bisecting")?;
+    let good = git::resolve_commit(repo, good)?.to_string();
+    let bad = git::resolve_commit(repo, bad)?.to_string();
+
+    git::run_git_in(repo, &["bisect", "start", &bad, &good])?;
+    let session = BisectSession {
+        command: command.to_string(),
+        good,
+        bad,
+        steps: Vec::new(),
+        first_bad: None,
+        report: None,
+    };
+    save(repo, &session)?;
+    Ok(session)
+}
+
+/// Tests commits until the first bad one is found, telling git each
+/// verdict and saving the session after every step. `on_step` sees each
+/// step as it is recorded.
+pub fn run_steps(repo: &Repository, session: &mut BisectSession, mut on_step: impl FnMut(&BisectStep)) -> Result<BisectOutcome> {
+    if !in_progress(repo) {
+        anyhow::bail!("git bisect is not running in this work tree; run `commit-buddy bisect reset` and start again");
+    }
+
+    loop {
+        if let Some(outcome) = outcome(repo)? {
+            if let BisectOutcome::FirstBad(id) = outcome {
+                session.first_bad = Some(id.to_string());
+                save(repo, session)?;
+            }
+            return Ok(outcome);
+        }
+
+        let commit = repo.head()?.peel_to_commit()?;
+        let (exit_code, output_tail) = run_test(repo, &session.command)?;
+        let verdict = Verdict::from_exit_code(exit_code).with_context(|| format!(
+            "The test command exited with {} at {}, which stops the bisect; fix it and run `commit-buddy bisect resume`",
+            exit_code,
+            git::short_oid(commit.id())
+        ))?;
+
+        git::run_git_in(repo, &["bisect", verdict.as_str()])?;
+        let step = BisectStep {
+            commit: commit.id().to_string(),
+            summary: commit.summary().unwrap_or("").to_string(),
+            verdict,
+            exit_code,
+            output_tail,
+        };
+        on_step(&step);
+        session.steps.push(step);
+        save(repo, session)?;
+    }
+}
+
+/// Runs the test command in the work tree. A command killed by a signal
+/// counts as exit code -1.
+fn run_test(repo: &Repository, command: &str) -> Result<(i32, String)> {
+    let workdir = repo.workdir().context("Cannot bisect a bare repository")?;
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .current_dir(workdir)
+        .output()
+        .with_context(|| format!("Failed to run '{}'", command))?;
+
+    let text = format!("{}{}", String::from_utf8_lossy(&output.stdout), String::from_utf8_lossy(&output.stderr));
+    let lines: Vec<&str> = text.lines().collect();
+    let tail = lines[lines.len().saturating_sub(OUTPUT_TAIL_LINES)..].join("\n");
+    Ok((output.status.code().unwrap_or(-1), tail))
+}
+
+/// Whether the bisect is over: a single candidate left, or only skipped
+/// ones. Read from the `refs/bisect` refs rather than git's output.
+fn outcome(repo: &Repository) -> Result<Option<BisectOutcome>> {
+    let bad = repo.refname_to_id("refs/bisect/bad")?;
+    let mut goods = Vec::new();
+    let mut skipped = Vec::new();
+    for reference in repo.references_glob("refs/bisect/*")? {
+        let reference = reference?;
+        let (Some(name), Some(target)) = (reference.name(), reference.target()) else {
+            continue;
+        };
+        if name.starts_with("refs/bisect/good-") {
+            goods.push(target);
+        } else if name.starts_with("refs/bisect/skip-") {
+            skipped.push(target);
+        }
+    }
+
+    let mut walk = repo.revwalk()?;
+    walk.push(bad)?;
+    for good in &goods {
+        walk.hide(*good)?;
+    }
+    let candidates: Vec<Oid> = walk.collect::<Result<_, _>>()?;
+
+    if candidates.len() == 1 {
+        return Ok(Some(BisectOutcome::FirstBad(bad)));
+    }
+    if candidates.iter().all(|id| *id == bad || skipped.contains(id)) {
+        return Ok(Some(BisectOutcome::OnlySkipped(candidates)));
+    }
+    Ok(None)
+}
+
+/// Ends `git bisect`, returning to the commit it started from, and
+/// forgets the session.
+pub fn reset(repo: &Repository) -> Result<()> {
+    if in_progress(repo) {
+        git::run_git_in(repo, &["bisect", "reset"])?;
+    }
+    let path = state_path(repo);
+    if path.exists() {
+        fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+/// Abbreviates a commit id read from the session file, which may have
+/// been edited by hand.
+fn short(id: &str) -> &str {
+    id.get(..7).unwrap_or(id)
+}
+
+fn print_step(step: &BisectStep) {
+    let icon = match step.verdict {
+        Verdict::Good => "✅",
+        Verdict::Bad => "❌",
+        Verdict::Skip => "⏭️",
+    };
+    println!("{} {} {} {} (exit {})", icon, short(&step.commit), step.verdict.as_str(), step.summary, step.exit_code);
+}
+
+/// `bisect start`: starts a session and runs it.
+pub async fn start_session(good: &str, bad: &str, command: &str, config: &Config) -> Result<()> {
+    let repo = git::open_repository()?;
+    let session = start(&repo, good, bad, command)?;
+    println!("🔍 Bisecting {}..{} with `{}`", git::short_oid(Oid::from_str(&session.good)?), git::short_oid(Oid::from_str(&session.bad)?), command);
+    drive(&repo, session, config).await
+}
+
+/// `bisect resume`: continues an interrupted session, or shows the report
+/// of a finished one.
+pub async fn resume_session(config: &Config) -> Result<()> {
+    let repo = git::open_repository()?;
+    let session = load(&repo)?.context("No bisect session to resume; start one with `commit-buddy bisect start`")?;
+    println!("🔁 Resuming after {} step(s) with `{}`", session.steps.len(), session.command);
+    drive(&repo, session, config).await
+}
+
+async fn drive(repo: &Repository, mut session: BisectSession, config: &Config) -> Result<()> {
+    let outcome = match &session.first_bad {
+        Some(first_bad) => BisectOutcome::FirstBad(Oid::from_str(first_bad)?),
+        None => run_steps(repo, &mut session, print_step)?,
+    };
+
+    let first_bad = match outcome {
+        BisectOutcome::FirstBad(id) => id,
+        BisectOutcome::OnlySkipped(candidates) => {
+            println!("\n⚠️ Only skipped commits are left; the first bad commit is one of:");
+            for id in candidates {
+                let commit = repo.find_commit(id)?;
+                println!("   {} {}", git::short_oid(id), commit.summary().unwrap_or(""));
+            }
+            println!("Run `commit-buddy bisect reset` when you are done.");
+            return Ok(());
+        }
+    };
+
+    let commit = repo.find_commit(first_bad)?;
+    println!("\n🎯 First bad commit: {} {}", git::short_oid(first_bad), commit.summary().unwrap_or(""));
+    println!("   Author: {}", commit.author().name().unwrap_or("Unknown"));
+
+    if session.report.is_none() {
+        let info = git::commit_info(repo, &commit)?;
+        let failure = session.steps.iter()
+            .find(|step| Some(&step.commit) == session.first_bad.as_ref())
+            .or_else(|| session.steps.iter().rev().find(|step| step.verdict == Verdict::Bad))
+            .map(|step| step.output_tail.clone())
+            .unwrap_or_default();
+        println!("🤖 Looking for the suspicious hunks...");
+        let report = ai::pinpoint_regression(&DiffInfo::from_commits(vec![info]).with_workdir(repo), &session.command, &failure, config).await?;
+        session.report = Some(report);
+        save(repo, &session)?;
+    }
+    println!("\n{}", session.report.as_deref().unwrap_or("").trim());
+
+    if in_progress(repo) {
+        git::run_git_in(repo, &["bisect", "reset"])?;
+        println!("\n✅ Returned to where the bisect started; the session is kept until `commit-buddy bisect reset`");
+    }
+    Ok(())
+}
+
+/// `bisect log`: prints the recorded steps.
+pub fn print_log() -> Result<()> {
+    let repo = git::open_repository()?;
+    let session = load(&repo)?.context("No bisect session in this work tree")?;
+    println!("🔍 Bisecting {}..{} with `{}`", short(&session.good), short(&session.bad), session.command);
+    for step in &session.steps {
+        print_step(step);
+    }
+    match &session.first_bad {
+        Some(first_bad) => println!("🎯 First bad commit: {}", short(first_bad)),
+        None => println!("⏳ Not finished; run `commit-buddy bisect resume`"),
+    }
+    Ok(())
+}
+
+/// `bisect reset`
+pub fn reset_session() -> Result<()> {
+    let repo = git::open_repository()?;
+    reset(&repo)?;
+    println!("✅ Bisect session ended");
+    Ok(())
+}
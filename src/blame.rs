@@ -0,0 +1,196 @@
+use anyhow::{Context, Result};
+use git2::{BlameOptions, Oid, Repository};
+use std::path::Path;
+use crate::ai;
+use crate::config::Config;
+use crate::git::{self, DiffInfo};
+
+/// Consecutive lines last changed by the same commit.
+#[derive(Debug, Clone)]
+pub struct BlameHunk {
+    /// `None` for lines that are not committed yet
+    pub commit: Option<Oid>,
+    /// The file's path in that commit, which differs after a rename
+    pub path: String,
+    /// Line number of the first line, starting at 1
+    pub start: usize,
+    pub lines: Vec<String>,
+    pub author: String,
+    /// Commit time in seconds since the epoch
+    pub time: i64,
+    pub summary: String,
+}
+
+impl BlameHunk {
+    pub fn end(&self) -> usize {
+        self.start + self.lines.len() - 1
+    }
+}
+
+/// Which version of the file line numbers refer to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BlameSource {
+    /// The file as committed at HEAD
+    Head,
+    /// The file in the work tree, with uncommitted lines included
+    Workdir,
+}
+
+/// Splits `file:start-end` (or `file:line`) into the path and the line
+/// range.
+pub fn parse_line_range(spec: &str) -> Result<(String, usize, usize)> {
+    let usage = || format!("Expected <file>:<start>-<end>, got '{}'", spec);
+    let (path, range) = spec.rsplit_once(':').with_context(usage)?;
+    let (start, end) = match range.split_once('-') {
+        Some((start, end)) => (start.trim().parse::<usize>(), end.trim().parse::<usize>()),
+        None => (range.trim().parse::<usize>(), range.trim().parse::<usize>()),
+    };
+    let (Ok(start), Ok(end)) = (start, end) else {
+        anyhow::bail!(usage());
+    };
+    if path.is_empty() || start == 0 || end < start {
+        anyhow::bail!(usage());
+    }
+    Ok((path.to_string(), start, end))
+}
+
+/// Blames lines `start..=end` of `path` (relative to the top of the work
+/// tree) and groups them by the commit that last changed them.
+pub fn blame_lines(repo: &Repository, path: &str, start: usize, end: usize, source: BlameSource) -> Result<Vec<BlameHunk>> {
+    let head_blame = repo.blame_file(Path::new(path), Some(&mut BlameOptions::new()))
+        .with_context(|| format!("{} has no committed history to blame", path))?;
+
+    let content = match source {
+        BlameSource::Head => {
+            let head = git::head_commit(repo)?.context("HEAD has no commits")?;
+            let entry = head.tree()?.get_path(Path::new(path))?;
+            repo.find_blob(entry.id())?.content().to_vec()
+        }
+        BlameSource::Workdir => {
+            let workdir = repo.workdir().context("Cannot blame the work tree of a bare repository")?;
+            std::fs::read(workdir.join(path)).with_context(|| format!("Could not read {}", path))?
+        }
+    };
+    let blame = match source {
+        BlameSource::Head => head_blame,
+        BlameSource::Workdir => head_blame.blame_buffer(&content)?,
+    };
+
+    let text = String::from_utf8_lossy(&content);
+    let lines: Vec<&str> = text.lines().collect();
+    if end > lines.len() {
+        anyhow::bail!("{} has only {} line(s)", path, lines.len());
+    }
+
+    let mut hunks: Vec<BlameHunk> = Vec::new();
+    for number in start..=end {
+        let hunk = blame.get_line(number).with_context(|| format!("No blame for line {} of {}", number, path))?;
+        let id = hunk.final_commit_id();
+        let commit = if id.is_zero() { None } else { Some(id) };
+        let line = lines[number - 1].to_string();
+
+        if let Some(last) = hunks.last_mut() {
+            if last.commit == commit {
+                last.lines.push(line);
+                continue;
+            }
+        }
+
+        let (author, time, summary) = match commit {
+            Some(id) => {
+                let commit = repo.find_commit(id)?;
+                let author = commit.author().name().unwrap_or("Unknown").to_string();
+                (author, commit.time().seconds(), commit.summary().unwrap_or("").to_string())
+            }
+            None => (String::new(), 0, "Not committed yet".to_string()),
+        };
+        hunks.push(BlameHunk {
+            commit,
+            path: hunk.path().map(|p| p.to_string_lossy().to_string()).unwrap_or_else(|| path.to_string()),
+            start: number,
+            lines: vec![line],
+            author,
+            time,
+            summary,
+        });
+    }
+
+    Ok(hunks)
+}
+
+/// The commits behind the blamed lines, oldest first, each with its diff
+/// limited to the blamed file.
+pub fn blamed_commits(repo: &Repository, hunks: &[BlameHunk]) -> Result<DiffInfo> {
+    let mut ids: Vec<Oid> = Vec::new();
+    for id in hunks.iter().filter_map(|hunk| hunk.commit) {
+        if !ids.contains(&id) {
+            ids.push(id);
+        }
+    }
+    let mut commits = ids.into_iter()
+        .map(|id| repo.find_commit(id))
+        .collect::<Result<Vec<_>, _>>()?;
+    commits.sort_by_key(|commit| commit.time().seconds());
+
+    let mut infos = Vec::new();
+    for commit in commits {
+        let paths: Vec<String> = hunks.iter()
+            .filter(|hunk| hunk.commit == Some(commit.id()))
+            .map(|hunk| hunk.path.clone())
+            .collect();
+        infos.push(git::commit_info_for_paths(repo, &commit, &paths)?);
+    }
+    Ok(DiffInfo::from_commits(infos).with_workdir(repo))
+}
+
+/// The blamed lines as a table: line range, commit, author, date and
+/// summary, followed by the lines themselves.
+pub fn format_blame(hunks: &[BlameHunk]) -> String {
+    let mut table = String::new();
+    for hunk in hunks {
+        let origin = match hunk.commit {
+            Some(id) => {
+                let date = chrono::DateTime::from_timestamp(hunk.time, 0)
+                    .map(|date| date.format("%Y-%m-%d").to_string())
+                    .unwrap_or_default();
+                format!("{} {} {}", git::short_oid(id), hunk.author, date)
+            }
+            None => "-------".to_string(),
+        };
+        table.push_str(&format!("{}-{} {} {}\n", hunk.start, hunk.end(), origin, hunk.summary));
+        for (offset, line) in hunk.lines.iter().enumerate() {
+            table.push_str(&format!("    {:>5} │ {}\n", hunk.start + offset, line));
+        }
+    }
+    table
+}
+
+/// Blames the lines and asks why they look the way they do. Returns the
+/// blame table and the explanation.
+pub async fn explain_lines(repo: &Repository, path: &str, start: usize, end: usize, source: BlameSource, config: &Config) -> Result<(String, String)> {
+    let hunks = blame_lines(repo, path, start, end, source)?;
+    let table = format_blame(&hunks);
+    let diff_info = blamed_commits(repo, &hunks)?;
+    let code = hunks.iter().flat_map(|hunk| &hunk.lines).cloned().collect::<Vec<_>>().join("\n");
+    let explanation = ai::explain_lines(&format!("{}:{}-{}", path, start, end), &code, &table, &diff_info, config).await?;
+    Ok((table, explanation))
+}
+
+/// `why`: blames a line range of a file and explains why the code looks
+/// the way it does.
+pub async fn why(spec: &str, config: &Config) -> Result<()> {
+    let (file, start, end) = parse_line_range(spec)?;
+    let repo = git::open_repository()?;
+    let top = repo.workdir().context("Cannot blame files in a bare repository")?;
+    let cwd = std::env::current_dir()?;
+    let path = [cwd.join(&file), top.join(&file)].iter()
+        .filter(|candidate| candidate.is_file())
+        .find_map(|candidate| git::workdir_relative(&repo, candidate))
+        .with_context(|| format!("{} is not a file in the work tree", file))?;
+
+    println!("🔍 Blaming {}:{}-{}...", path, start, end);
+    let (table, explanation) = explain_lines(&repo, &path, start, end, BlameSource::Workdir, config).await?;
+    println!("\n{}", table.trim_end());
+    println!("\n💡 Why it looks this way:\n{}", explanation.trim());
+    Ok(())
+}
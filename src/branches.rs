@@ -0,0 +1,354 @@
+use anyhow::{Context, Result};
+use git2::{build::CheckoutBuilder, BranchType, Oid, Repository};
+use crate::conventional::ConventionalCommit;
+use crate::git;
+use crate::journal;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BranchKind {
+    Local,
+    Remote,
+}
+
+/// A local or remote-tracking branch as shown in the branch panel.
+#[derive(Debug, Clone)]
+pub struct BranchInfo {
+    /// Short name, e.g. `main` or `origin/main`
+    pub name: String,
+    pub kind: BranchKind,
+    pub is_head: bool,
+    /// Upstream of a local branch, e.g. `origin/main`
+    pub upstream: Option<String>,
+    /// Commits ahead of and behind the upstream for local branches, or
+    /// HEAD for remote branches; `None` when there is nothing to compare
+    pub ahead_behind: Option<(usize, usize)>,
+    pub last_commit: Oid,
+    pub last_summary: String,
+    /// Commit time of the tip, in seconds since the epoch
+    pub last_time: i64,
+}
+
+impl BranchInfo {
+    /// The branch name without its remote, e.g. `feature` for `origin/feature`.
+    pub fn local_name(&self) -> &str {
+        match self.kind {
+            BranchKind::Local => &self.name,
+            BranchKind::Remote => self.name.split_once('/').map(|(_, rest)| rest).unwrap_or(&self.name),
+        }
+    }
+}
+
+/// Every local and remote-tracking branch: the current branch first, then
+/// local and remote branches, each most recently committed first.
+pub fn list(repo: &Repository) -> Result<Vec<BranchInfo>> {
+    let head = git::head_commit(repo)?.map(|commit| commit.id());
+    let mut branches = Vec::new();
+
+    for entry in repo.branches(None)? {
+        let (branch, branch_type) = entry?;
+        let Some(name) = branch.name()?.map(str::to_string) else {
+            continue;
+        };
+        // `origin/HEAD` is only an alias of another remote branch
+        if branch_type == BranchType::Remote && name.ends_with("/HEAD") {
+            continue;
+        }
+        let commit = branch.get().peel_to_commit()?;
+
+        let (kind, upstream, compare_to) = match branch_type {
+            BranchType::Local => {
+                let upstream = branch.upstream().ok();
+                let upstream_name = upstream.as_ref()
+                    .and_then(|u| u.name().ok().flatten())
+                    .map(str::to_string);
+                let upstream_tip = upstream.and_then(|u| u.get().target());
+                (BranchKind::Local, upstream_name, upstream_tip)
+            }
+            BranchType::Remote => (BranchKind::Remote, None, head),
+        };
+        let ahead_behind = match compare_to {
+            Some(other) => Some(repo.graph_ahead_behind(commit.id(), other)?),
+            None => None,
+        };
+
+        branches.push(BranchInfo {
+            is_head: branch_type == BranchType::Local && branch.is_head(),
+            name,
+            kind,
+            upstream,
+            ahead_behind,
+            last_commit: commit.id(),
+            last_summary: commit.summary().unwrap_or("").to_string(),
+            last_time: commit.time().seconds(),
+        });
+    }
+
+    branches.sort_by(|a, b| {
+        b.is_head.cmp(&a.is_head)
+            .then((a.kind == BranchKind::Remote).cmp(&(b.kind == BranchKind::Remote)))
+            .then(b.last_time.cmp(&a.last_time))
+            .then(a.name.cmp(&b.name))
+    });
+    Ok(branches)
+}
+
+/// Scores `candidate` against a fuzzy `query`: every query character has to
+/// appear in order (case-insensitively). Consecutive matches and matches at
+/// the start of a word score higher. `None` when it does not match.
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<i64> {
+    let candidate: Vec<char> = candidate.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous: Option<usize> = None;
+
+    for wanted in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let found = (position..candidate.len()).find(|&i| candidate[i] == wanted)?;
+        score += 1;
+        if previous.is_some_and(|p| p + 1 == found) {
+            score += 5;
+        }
+        if found == 0 || matches!(candidate[found - 1], '/' | '-' | '_' | '.') {
+            score += 3;
+        }
+        // Prefer matches that start early and stay close together
+        score -= (found - position) as i64;
+        previous = Some(found);
+        position = found + 1;
+    }
+
+    Some(score)
+}
+
+/// Indices of the branches matching `query`, best match first. An empty
+/// query keeps every branch in its original order.
+pub fn filter(branches: &[BranchInfo], query: &str) -> Vec<usize> {
+    let mut matches: Vec<(usize, i64)> = branches.iter()
+        .enumerate()
+        .filter_map(|(i, branch)| fuzzy_score(query, &branch.name).map(|score| (i, score)))
+        .collect();
+    // Stable, so equally good matches keep the panel order
+    matches.sort_by_key(|&(_, score)| std::cmp::Reverse(score));
+    matches.into_iter().map(|(i, _)| i).collect()
+}
+
+/// Checks out a local branch, or creates a local branch tracking a remote
+/// one and checks that out. Returns the name of the local branch.
+pub fn checkout(repo: &Repository, branch: &BranchInfo) -> Result<String> {
+    git::ensure_clean_worktree(repo, "switching branches")?;
+    journal::action(repo, format!("checkout {}", branch.local_name()))
+        .worktree()
+        .run(|| switch_to(repo, branch))
+}
+
+fn switch_to(repo: &Repository, branch: &BranchInfo) -> Result<String> {
+    let local_name = branch.local_name().to_string();
+    if branch.kind == BranchKind::Remote {
+        match repo.find_branch(&local_name, BranchType::Local) {
+            Ok(existing) => {
+                let tracks_it = existing.upstream().ok()
+                    .and_then(|u| u.name().ok().flatten().map(str::to_string))
+                    .is_some_and(|upstream| upstream == branch.name);
+                if !tracks_it {
+                    anyhow::bail!("A local branch named {} already exists and does not track {}", local_name, branch.name);
+                }
+            }
+            Err(_) => {
+                let commit = repo.find_commit(branch.last_commit)?;
+                let mut created = repo.branch(&local_name, &commit, false)?;
+                created.set_upstream(Some(&branch.name))?;
+            }
+        }
+    }
+
+    let refname = format!("refs/heads/{}", local_name);
+    let target = repo.revparse_single(&refname)?;
+    repo.checkout_tree(&target, Some(CheckoutBuilder::new().safe()))
+        .with_context(|| format!("Could not check out {}", local_name))?;
+    repo.set_head(&refname)?;
+    Ok(local_name)
+}
+
+/// Creates a branch at HEAD and switches to it, carrying any uncommitted
+/// changes over like `git switch -c`. On an unborn branch HEAD is simply
+/// pointed at the new name.
+pub fn create(repo: &Repository, name: &str) -> Result<()> {
+    if !git2::Branch::name_is_valid(name)? {
+        anyhow::bail!("'{}' is not a valid branch name", name);
+    }
+    if repo.find_branch(name, BranchType::Local).is_ok() {
+        anyhow::bail!("A branch named {} already exists", name);
+    }
+
+    journal::action(repo, format!("create branch {}", name)).run(|| {
+        if let Some(head) = git::head_commit(repo)? {
+            repo.branch(name, &head, false)?;
+        }
+        repo.set_head(&format!("refs/heads/{}", name))?;
+        Ok(())
+    })
+}
+
+/// Deletes a local branch. Branches that are not merged into HEAD are only
+/// deleted with `force`, and the current branch never is.
+pub fn delete(repo: &Repository, name: &str, force: bool) -> Result<()> {
+    let mut branch = repo.find_branch(name, BranchType::Local)
+        .with_context(|| format!("No local branch named {}", name))?;
+    if branch.is_head() {
+        anyhow::bail!("Cannot delete {}, it is the current branch", name);
+    }
+
+    if !force {
+        let tip = branch.get().peel_to_commit()?.id();
+        let merged = match git::head_commit(repo)? {
+            Some(head) => head.id() == tip || repo.graph_descendant_of(head.id(), tip)?,
+            None => false,
+        };
+        if !merged {
+            anyhow::bail!("{} is not merged into the current branch; delete it again to force", name);
+        }
+    }
+
+    journal::action(repo, format!("delete branch {}", name)).run(|| {
+        branch.delete()?;
+        Ok(())
+    })
+}
+
+/// Renames a local branch, keeping HEAD on it when it is checked out.
+pub fn rename(repo: &Repository, name: &str, new_name: &str) -> Result<()> {
+    if !git2::Branch::name_is_valid(new_name)? {
+        anyhow::bail!("'{}' is not a valid branch name", new_name);
+    }
+    let mut branch = repo.find_branch(name, BranchType::Local)
+        .with_context(|| format!("No local branch named {}", name))?;
+    journal::action(repo, format!("rename branch {} to {}", name, new_name)).run(|| {
+        branch.rename(new_name, false)
+            .with_context(|| format!("Could not rename {} to {}", name, new_name))?;
+        Ok(())
+    })
+}
+
+/// Merges `name` into the current branch with `git merge`, so merge hooks
+/// and the configured merge strategy apply. Returns git's output.
+pub fn merge(repo: &Repository, name: &str) -> Result<String> {
+    git::ensure_clean_worktree(repo, "merging")?;
+    journal::action(repo, format!("merge {}", name))
+        .worktree()
+        .run(|| git::run_git_in(repo, &["merge", "--no-edit", name]))
+        .with_context(|| format!("Merging {} stopped; resolve the conflicts and commit, or run `git merge --abort`", name))
+}
+
+/// Rebases the current branch onto `name` with `git rebase`. Returns git's
+/// output.
+pub fn rebase(repo: &Repository, name: &str) -> Result<String> {
+    git::ensure_clean_worktree(repo, "rebasing")?;
+    journal::action(repo, format!("rebase onto {}", name))
+        .worktree()
+        .run(|| git::run_git_in(repo, &["rebase", name]))
+        .with_context(|| format!("Rebasing onto {} stopped; resolve the conflicts and run `git rebase --continue`, or `git rebase --abort`", name))
+}
+
+/// Where the current branch stands against its upstream.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Tracking {
+    /// Upstream of the current branch, e.g. `origin/main`
+    pub upstream: Option<String>,
+    pub ahead: usize,
+    pub behind: usize,
+}
+
+impl Tracking {
+    /// E.g. `origin/main ↑2 ↓1`, `origin/main (up to date)` or `no upstream`.
+    pub fn describe(&self) -> String {
+        let Some(upstream) = &self.upstream else {
+            return "no upstream".to_string();
+        };
+        match (self.ahead, self.behind) {
+            (0, 0) => format!("{} (up to date)", upstream),
+            (ahead, 0) => format!("{} ↑{}", upstream, ahead),
+            (0, behind) => format!("{} ↓{}", upstream, behind),
+            (ahead, behind) => format!("{} ↑{} ↓{}", upstream, ahead, behind),
+        }
+    }
+}
+
+/// The upstream of the current branch and the commits ahead of and behind
+/// it. Empty on a detached HEAD, an unborn branch or without an upstream.
+pub fn tracking(repo: &Repository) -> Result<Tracking> {
+    let Some(name) = git::current_branch_name(repo) else {
+        return Ok(Tracking::default());
+    };
+    let Ok(branch) = repo.find_branch(&name, BranchType::Local) else {
+        return Ok(Tracking::default());
+    };
+    let Ok(upstream) = branch.upstream() else {
+        return Ok(Tracking::default());
+    };
+
+    let (ahead, behind) = match (branch.get().target(), upstream.get().target()) {
+        (Some(local), Some(remote)) => repo.graph_ahead_behind(local, remote)?,
+        _ => (0, 0),
+    };
+    Ok(Tracking {
+        upstream: upstream.name()?.map(str::to_string),
+        ahead,
+        behind,
+    })
+}
+
+/// Names of the configured remotes, `origin` first.
+pub fn remotes(repo: &Repository) -> Result<Vec<String>> {
+    let mut names: Vec<String> = repo.remotes()?.iter().flatten().map(str::to_string).collect();
+    names.sort_by_key(|name| (name != "origin", name.clone()));
+    Ok(names)
+}
+
+/// Pushes the current branch with `git push`. A branch without an upstream
+/// is pushed to `remote` and starts tracking it, like `git push -u`.
+/// Returns git's output.
+pub fn push(repo: &Repository, remote: Option<&str>) -> Result<String> {
+    if tracking(repo)?.upstream.is_some() {
+        return git::run_git_in(repo, &["push"]);
+    }
+    let branch = git::current_branch_name(repo).context("HEAD is detached; check out a branch to push")?;
+    let remote = remote.with_context(|| format!("{} has no upstream; choose a remote to push it to", branch))?;
+    git::run_git_in(repo, &["push", "--set-upstream", remote, &branch])
+}
+
+/// Pulls into the current branch with `git pull`. Returns git's output.
+pub fn pull(repo: &Repository) -> Result<String> {
+    journal::action(repo, "pull")
+        .worktree()
+        .run(|| git::run_git_in(repo, &["pull"]))
+}
+
+/// Turns free text into a valid branch name: lower case, words joined by
+/// `-`, with at most one `type/` prefix kept.
+pub fn sanitize_branch_name(text: &str) -> String {
+    let text = text.trim().trim_matches(['`', '"', '\'']).to_lowercase();
+    let (prefix, rest) = match text.split_once('/') {
+        Some((prefix, rest)) if !prefix.is_empty() && prefix.chars().all(|c| c.is_ascii_alphanumeric()) => (Some(prefix), rest),
+        _ => (None, text.as_str()),
+    };
+
+    let slug = rest.split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .take(6)
+        .collect::<Vec<_>>()
+        .join("-");
+
+    match (prefix, slug.is_empty()) {
+        (Some(prefix), false) => format!("{}/{}", prefix, slug),
+        (Some(prefix), true) => prefix.to_string(),
+        (None, _) => slug,
+    }
+}
+
+/// A branch name such as `feat/add-branch-panel` derived from a commit
+/// message; conventional commits contribute their type as the prefix.
+pub fn branch_name_from_message(message: &str) -> String {
+    match ConventionalCommit::parse(message) {
+        Ok(parsed) => sanitize_branch_name(&format!("{}/{}", parsed.commit_type, parsed.subject)),
+        Err(_) => sanitize_branch_name(message.lines().next().unwrap_or("")),
+    }
+}
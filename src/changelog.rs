@@ -0,0 +1,547 @@
+use anyhow::Result;
+use git2::{Oid, Repository};
+use regex::Regex;
+use crate::ai;
+use crate::config::Config;
+use crate::conventional::ConventionalCommit;
+use crate::git;
+use crate::journal;
+use crate::release::Version;
+use crate::rewrite;
+
+pub const HEADER: &str = "# Changelog
+
+All notable changes to this project will be documented in this file.
+
+The format is based on [Keep a Changelog](https://keepachangelog.com/en/1.1.0/),
+and this project adheres to [Semantic Versioning](https://semver.org/spec/v2.0.0.html).
+";
+
+/// Where commit, pull request and compare links point.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ForgeLinks {
+    /// `https://host/owner/repo`
+    pub base: String,
+    gitlab: bool,
+}
+
+impl ForgeLinks {
+    /// Understands `https://host/owner/repo(.git)`, `git@host:owner/repo`
+    /// and `ssh://git@host/owner/repo` remotes.
+    pub fn from_remote_url(url: &str) -> Option<Self> {
+        let url = url.trim().trim_end_matches('/').trim_end_matches(".git");
+        let (host, path) = if let Some(rest) = url.strip_prefix("https://").or_else(|| url.strip_prefix("http://")) {
+            rest.split_once('/')?
+        } else if let Some(rest) = url.strip_prefix("ssh://") {
+            let rest = rest.split_once('@').map(|(_, r)| r).unwrap_or(rest);
+            let (host, path) = rest.split_once('/')?;
+            (host.split(':').next()?, path)
+        } else {
+            let rest = url.split_once('@').map(|(_, r)| r).unwrap_or(url);
+            rest.split_once(':')?
+        };
+        // Drop credentials from https remotes
+        let host = host.rsplit('@').next()?;
+        if host.is_empty() || !path.contains('/') {
+            return None;
+        }
+        Some(Self {
+            base: format!("https://{}/{}", host, path),
+            gitlab: host.contains("gitlab"),
+        })
+    }
+
+    /// Links for the `origin` remote, if it looks like a forge.
+    pub fn detect(repo: &Repository) -> Option<Self> {
+        let remote = repo.find_remote("origin").ok()?;
+        Self::from_remote_url(remote.url()?)
+    }
+
+    fn gitlab_prefix(&self) -> &'static str {
+        if self.gitlab { "/-" } else { "" }
+    }
+
+    pub fn commit(&self, id: Oid) -> String {
+        format!("{}{}/commit/{}", self.base, self.gitlab_prefix(), id)
+    }
+
+    pub fn pull_request(&self, number: u64) -> String {
+        if self.gitlab {
+            format!("{}/-/merge_requests/{}", self.base, number)
+        } else {
+            format!("{}/pull/{}", self.base, number)
+        }
+    }
+
+    pub fn compare(&self, from: &str, to: &str) -> String {
+        format!("{}{}/compare/{}...{}", self.base, self.gitlab_prefix(), from, to)
+    }
+
+    pub fn tag(&self, tag: &str) -> String {
+        if self.gitlab {
+            format!("{}/-/tags/{}", self.base, tag)
+        } else {
+            format!("{}/releases/tag/{}", self.base, tag)
+        }
+    }
+}
+
+/// A conventional commit in the changelog, with the pull request it came
+/// from when that is known.
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub id: Oid,
+    pub commit: ConventionalCommit,
+    pub pull_request: Option<u64>,
+}
+
+impl Entry {
+    /// Whether the entry shows up in a section; docs, tests and chores
+    /// do not.
+    pub fn is_listed(&self) -> bool {
+        self.commit.breaking || category(&self.commit).is_some()
+    }
+}
+
+/// The changes of one release, or the unreleased ones when `tag` is `None`.
+#[derive(Debug, Clone)]
+pub struct ReleaseSection {
+    /// The version as shown in the heading; `None` for `[Unreleased]`
+    pub version: Option<String>,
+    pub tag: Option<String>,
+    /// The tag or revision the section starts after, for compare links
+    pub previous: Option<String>,
+    pub date: Option<String>,
+    pub entries: Vec<Entry>,
+}
+
+impl ReleaseSection {
+    /// The heading label: the version, or `Unreleased`.
+    pub fn label(&self) -> &str {
+        self.version.as_deref().unwrap_or("Unreleased")
+    }
+}
+
+/// Conventional commits in `base..tip`, oldest first. Merge commits are
+/// left out, but a GitHub or GitLab merge tags the commits it brought in
+/// with its pull request number; squash merges carry `(#N)` in the subject.
+pub fn entries(repo: &Repository, base: Option<Oid>, tip: Oid) -> Result<Vec<Entry>> {
+    let merge_pattern = Regex::new(r"^Merge pull request #(\d+)|See merge request \S*!(\d+)")?;
+    let ids = rewrite::commits_between(repo, base, tip)?;
+
+    let mut merged_prs: Vec<(Oid, u64)> = Vec::new();
+    for &id in &ids {
+        let commit = repo.find_commit(id)?;
+        if commit.parent_count() < 2 {
+            continue;
+        }
+        let number = merge_pattern.captures(commit.message().unwrap_or(""))
+            .and_then(|caps| caps.get(1).or(caps.get(2)))
+            .and_then(|number| number.as_str().parse().ok());
+        if let Some(number) = number {
+            for merged in rewrite::commits_between(repo, Some(commit.parent_id(0)?), commit.parent_id(1)?)? {
+                if !merged_prs.iter().any(|(id, _)| *id == merged) {
+                    merged_prs.push((merged, number));
+                }
+            }
+        }
+    }
+
+    let mut entries = Vec::new();
+    for id in ids {
+        let commit = repo.find_commit(id)?;
+        if commit.parent_count() > 1 {
+            continue;
+        }
+        let Ok(mut parsed) = ConventionalCommit::parse(commit.message().unwrap_or("")) else {
+            continue;
+        };
+        let mut pull_request = merged_prs.iter().find(|(merged, _)| *merged == id).map(|(_, n)| *n);
+        if let Some((subject, number)) = split_pull_request(&parsed.subject) {
+            parsed.subject = subject;
+            pull_request = Some(number);
+        }
+        entries.push(Entry { id, commit: parsed, pull_request });
+    }
+    Ok(entries)
+}
+
+/// Splits a squash-merge suffix such as ` (#42)` off a subject.
+fn split_pull_request(subject: &str) -> Option<(String, u64)> {
+    let rest = subject.strip_suffix(')')?;
+    let (subject, number) = rest.rsplit_once(" (#")?;
+    Some((subject.to_string(), number.parse().ok()?))
+}
+
+/// Semver tags whose commit is reachable from `tip` and comes after
+/// `after`, oldest version first.
+fn tags_between(repo: &Repository, after: Option<Oid>, tip: Oid) -> Result<Vec<(String, Version, Oid)>> {
+    let mut tags = Vec::new();
+    for name in repo.tag_names(None)?.iter().flatten() {
+        let Some(version) = Version::parse(name) else {
+            continue;
+        };
+        let target = repo.revparse_single(&format!("refs/tags/{}", name))?.peel_to_commit()?.id();
+        let reachable = target == tip || repo.graph_descendant_of(tip, target)?;
+        let in_window = after.is_none_or(|after| repo.graph_descendant_of(target, after).unwrap_or(false));
+        if reachable && in_window {
+            tags.push((name.to_string(), version, target));
+        }
+    }
+    tags.sort_by(|a, b| a.1.cmp(&b.1));
+    Ok(tags)
+}
+
+fn commit_date(repo: &Repository, id: Oid) -> Result<String> {
+    let seconds = repo.find_commit(id)?.time().seconds();
+    Ok(chrono::DateTime::from_timestamp(seconds, 0)
+        .map(|date| date.format("%Y-%m-%d").to_string())
+        .unwrap_or_default())
+}
+
+/// Release sections for the commits after `from` up to `to` (HEAD when
+/// not given), newest first. Commits after the last tag form an
+/// `[Unreleased]` section when any of them would be listed.
+pub fn collect(repo: &Repository, from: Option<&str>, to: Option<&str>) -> Result<Vec<ReleaseSection>> {
+    let tip = git::resolve_commit(repo, to.unwrap_or("HEAD"))?;
+    let start = from.map(|from| git::resolve_commit(repo, from)).transpose()?;
+
+    let mut sections = Vec::new();
+    let mut previous: Option<(String, Oid)> = from.zip(start).map(|(name, id)| (name.to_string(), id));
+    for (name, version, target) in tags_between(repo, start, tip)? {
+        sections.push(ReleaseSection {
+            version: Some(version.to_string()),
+            tag: Some(name.clone()),
+            previous: previous.as_ref().map(|(name, _)| name.clone()),
+            date: Some(commit_date(repo, target)?),
+            entries: entries(repo, previous.as_ref().map(|(_, id)| *id), target)?,
+        });
+        previous = Some((name, target));
+    }
+
+    if previous.as_ref().is_none_or(|(_, id)| *id != tip) {
+        let unreleased = entries(repo, previous.as_ref().map(|(_, id)| *id), tip)?;
+        if unreleased.iter().any(Entry::is_listed) {
+            sections.push(ReleaseSection {
+                version: None,
+                tag: None,
+                previous: previous.map(|(name, _)| name),
+                date: None,
+                entries: unreleased,
+            });
+        }
+    }
+
+    sections.reverse();
+    Ok(sections)
+}
+
+fn entry_line(entry: &Entry, text: &str, links: Option<&ForgeLinks>) -> String {
+    let scope = entry.commit.scope.as_deref().map(|s| format!("**{}:** ", s)).unwrap_or_default();
+    let short = git::short_oid(entry.id);
+    let mut refs = Vec::new();
+    if let Some(number) = entry.pull_request {
+        refs.push(match links {
+            Some(links) => format!("[#{}]({})", number, links.pull_request(number)),
+            None => format!("#{}", number),
+        });
+    }
+    refs.push(match links {
+        Some(links) => format!("[{}]({})", short, links.commit(entry.id)),
+        None => short,
+    });
+    format!("- {}{} ({})", scope, text, refs.join(", "))
+}
+
+/// The Keep a Changelog category a commit type belongs in.
+fn category(commit: &ConventionalCommit) -> Option<&'static str> {
+    if commit.scope.as_deref() == Some("security") {
+        return Some("Security");
+    }
+    match commit.kind().as_str() {
+        "feat" => Some("Added"),
+        "fix" => Some("Fixed"),
+        "perf" | "refactor" | "revert" => Some("Changed"),
+        "deprecate" => Some("Deprecated"),
+        "remove" => Some("Removed"),
+        "security" => Some("Security"),
+        _ => None,
+    }
+}
+
+const CATEGORIES: [&str; 6] = ["Added", "Changed", "Deprecated", "Removed", "Fixed", "Security"];
+
+/// One `## [version] - date` section. Breaking changes are listed first;
+/// docs, tests and chores are left out.
+pub fn render_section(section: &ReleaseSection, links: Option<&ForgeLinks>) -> String {
+    let mut text = match &section.date {
+        Some(date) => format!("## [{}] - {}\n", section.label(), date),
+        None => format!("## [{}]\n", section.label()),
+    };
+
+    let breaking: Vec<String> = section.entries.iter()
+        .filter_map(|entry| entry.commit.breaking_description().map(|d| entry_line(entry, d, links)))
+        .collect();
+    if !breaking.is_empty() {
+        text.push_str(&format!("\n### ⚠ BREAKING CHANGES\n\n{}\n", breaking.join("\n")));
+    }
+
+    for name in CATEGORIES {
+        let lines: Vec<String> = section.entries.iter()
+            .filter(|entry| category(&entry.commit) == Some(name))
+            .map(|entry| entry_line(entry, &entry.commit.subject, links))
+            .collect();
+        if !lines.is_empty() {
+            text.push_str(&format!("\n### {}\n\n{}\n", name, lines.join("\n")));
+        }
+    }
+    text
+}
+
+/// The link reference definition for a section heading, e.g.
+/// `[1.1.0]: https://github.com/o/r/compare/v1.0.0...v1.1.0`.
+pub fn link_definition(section: &ReleaseSection, links: &ForgeLinks) -> String {
+    let to = section.tag.as_deref().unwrap_or("HEAD");
+    let url = match &section.previous {
+        Some(previous) => links.compare(previous, to),
+        None if section.tag.is_some() => links.tag(to),
+        None => format!("{}/commits", links.base),
+    };
+    format!("[{}]: {}", section.label(), url)
+}
+
+/// The whole changelog document: header, sections and link definitions.
+pub fn render(sections: &[ReleaseSection], links: Option<&ForgeLinks>) -> String {
+    let mut text = HEADER.to_string();
+    for section in sections {
+        text.push('\n');
+        text.push_str(&render_section(section, links));
+    }
+    if let Some(links) = links {
+        text.push('\n');
+        for section in sections {
+            text.push_str(&link_definition(section, links));
+            text.push('\n');
+        }
+    }
+    text
+}
+
+/// An existing changelog split into the parts that are updated
+/// separately: the text before the first `## ` heading, one entry per
+/// release heading, and the link reference definitions at the bottom.
+#[derive(Debug, Clone, Default)]
+pub struct ChangelogFile {
+    pub preamble: String,
+    pub sections: Vec<FileSection>,
+    /// `[label]: url` definitions, in file order
+    pub links: Vec<(String, String)>,
+}
+
+/// A `## ` section of a changelog file, heading included, kept verbatim.
+#[derive(Debug, Clone)]
+pub struct FileSection {
+    pub label: String,
+    pub text: String,
+}
+
+impl FileSection {
+    fn is_unreleased(&self) -> bool {
+        self.label.eq_ignore_ascii_case("Unreleased")
+    }
+}
+
+fn link_definition_line(line: &str) -> Option<(String, String)> {
+    let (label, url) = line.trim().strip_prefix('[')?.split_once("]:")?;
+    let url = url.trim();
+    (!label.is_empty() && !url.is_empty() && !url.contains(char::is_whitespace))
+        .then(|| (label.to_string(), url.to_string()))
+}
+
+/// `## [1.2.0] - 2024-01-01` → `1.2.0`; `## 1.2.0 (date)` → `1.2.0`.
+fn heading_label(heading: &str) -> String {
+    let heading = heading.trim_start_matches('#').trim();
+    match heading.strip_prefix('[').and_then(|rest| rest.split_once(']')) {
+        Some((label, _)) => label.to_string(),
+        None => heading.split_whitespace().next().unwrap_or("").to_string(),
+    }
+}
+
+impl ChangelogFile {
+    pub fn parse(text: &str) -> Self {
+        let mut lines: Vec<&str> = text.lines().collect();
+
+        let mut links = Vec::new();
+        while let Some(last) = lines.last() {
+            if last.trim().is_empty() {
+                lines.pop();
+            } else if let Some(link) = link_definition_line(last) {
+                links.push(link);
+                lines.pop();
+            } else {
+                break;
+            }
+        }
+        links.reverse();
+
+        let mut file = ChangelogFile { links, ..Default::default() };
+        for line in lines {
+            if line.starts_with("## ") {
+                file.sections.push(FileSection { label: heading_label(line), text: String::new() });
+            }
+            let text = match file.sections.last_mut() {
+                Some(section) => &mut section.text,
+                None => &mut file.preamble,
+            };
+            text.push_str(line);
+            text.push('\n');
+        }
+        file
+    }
+
+    /// Merges freshly generated sections into the file. `[Unreleased]` is
+    /// replaced, releases missing from the file are inserted in version
+    /// order, and releases already in the file keep their text, manual
+    /// edits included. With `replace_unreleased` an existing
+    /// `[Unreleased]` section is dropped when nothing is unreleased any
+    /// more. Returns a description of each change.
+    pub fn merge(&mut self, generated: ChangelogFile, replace_unreleased: bool) -> Vec<String> {
+        let mut changes = Vec::new();
+        let mut touched: Vec<String> = Vec::new();
+
+        let has_unreleased = generated.sections.iter().any(FileSection::is_unreleased);
+        if replace_unreleased && !has_unreleased {
+            if let Some(position) = self.sections.iter().position(FileSection::is_unreleased) {
+                let removed = self.sections.remove(position);
+                changes.push(format!("removed [{}]", removed.label));
+                touched.push(removed.label);
+            }
+        }
+
+        for section in generated.sections {
+            if section.is_unreleased() {
+                match self.sections.iter().position(FileSection::is_unreleased) {
+                    Some(position) => {
+                        if self.sections[position].text.trim_end() != section.text.trim_end() {
+                            changes.push(format!("updated [{}]", section.label));
+                        }
+                        self.sections[position] = section.clone();
+                    }
+                    None => {
+                        changes.push(format!("added [{}]", section.label));
+                        self.sections.insert(0, section.clone());
+                    }
+                }
+                touched.push(section.label);
+                continue;
+            }
+            if self.sections.iter().any(|existing| existing.label == section.label) {
+                continue;
+            }
+
+            let version = Version::parse(&section.label);
+            let position = self.sections.iter()
+                .position(|existing| !existing.is_unreleased() && Version::parse(&existing.label)
+                    .is_none_or(|old| version.as_ref().is_none_or(|new| old < *new)))
+                .unwrap_or(self.sections.len());
+            changes.push(format!("added [{}]", section.label));
+            touched.push(section.label.clone());
+            self.sections.insert(position, section);
+        }
+
+        // Headings link to their definitions; regenerated sections get the
+        // new ones, the rest keep theirs and unrelated definitions stay last
+        let find = |links: &[(String, String)], label: &str| links.iter().find(|(l, _)| l == label).cloned();
+        let mut links = Vec::new();
+        for section in &self.sections {
+            let link = if touched.contains(&section.label) {
+                find(&generated.links, &section.label).or_else(|| find(&self.links, &section.label))
+            } else {
+                find(&self.links, &section.label).or_else(|| find(&generated.links, &section.label))
+            };
+            links.extend(link);
+        }
+        for link in &self.links {
+            let is_section = self.sections.iter().any(|s| s.label == link.0);
+            if !is_section && !touched.contains(&link.0) {
+                links.push(link.clone());
+            }
+        }
+        self.links = links;
+
+        changes
+    }
+}
+
+impl std::fmt::Display for ChangelogFile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let preamble = self.preamble.trim_end();
+        if !preamble.is_empty() {
+            writeln!(f, "{}", preamble)?;
+        }
+        for (index, section) in self.sections.iter().enumerate() {
+            if index > 0 || !preamble.is_empty() {
+                writeln!(f)?;
+            }
+            writeln!(f, "{}", section.text.trim_end())?;
+        }
+        if !self.links.is_empty() {
+            writeln!(f)?;
+            for (label, url) in &self.links {
+                writeln!(f, "[{}]: {}", label, url)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Merges a rendered changelog into `existing` (see
+/// [`ChangelogFile::merge`]), or returns it as is for a new file.
+pub fn update(existing: Option<&str>, generated: &str, replace_unreleased: bool) -> (String, Vec<String>) {
+    let generated = ChangelogFile::parse(generated);
+    match existing {
+        Some(existing) => {
+            let mut file = ChangelogFile::parse(existing);
+            let changes = file.merge(generated, replace_unreleased);
+            (file.to_string(), changes)
+        }
+        None => (generated.to_string(), Vec::new()),
+    }
+}
+
+/// `changelog`: renders the changelog from tags, optionally polished by
+/// the model, and prints it or merges it into `output`.
+pub async fn generate(from: Option<&str>, to: Option<&str>, use_ai: bool, output: Option<&str>, config: &Config) -> Result<()> {
+    let repo = git::open_repository()?;
+    let sections = collect(&repo, from, to)?;
+    if sections.is_empty() {
+        println!("No conventional commits found for the changelog.");
+        return Ok(());
+    }
+
+    let links = ForgeLinks::detect(&repo);
+    let mut changelog = render(&sections, links.as_ref());
+    if use_ai {
+        println!("🤖 Polishing the changelog wording...");
+        changelog = ai::polish_changelog(&changelog, config).await?;
+    }
+
+    let Some(file_path) = output else {
+        println!("\n{}", changelog);
+        return Ok(());
+    };
+
+    let existing = std::fs::read_to_string(file_path).ok();
+    // Only a changelog that runs up to HEAD knows what is unreleased
+    let (updated, changes) = update(existing.as_deref(), &changelog, to.is_none());
+    let written = std::env::current_dir()?.join(file_path);
+    journal::action(&repo, format!("write changelog to {}", file_path))
+        .files(&[written])
+        .run(|| Ok(std::fs::write(file_path, updated)?))?;
+    match existing {
+        None => println!("✅ Changelog written to {}", file_path),
+        Some(_) if changes.is_empty() => println!("✅ {} is already up to date", file_path),
+        Some(_) => println!("✅ Updated {}: {}", file_path, changes.join(", ")),
+    }
+    Ok(())
+}
@@ -0,0 +1,208 @@
+use anyhow::{Context, Result};
+use git2::Repository;
+use serde::Serialize;
+use crate::config::{Config, LintRules};
+use crate::conventional::ConventionalCommit;
+use crate::git;
+use crate::rewrite;
+use crate::trailers;
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct Violation {
+    pub rule: &'static str,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CommitReport {
+    /// Short commit hash, or `None` for a message file
+    pub commit: Option<String>,
+    pub subject: String,
+    pub violations: Vec<Violation>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LintSummary {
+    pub checked: usize,
+    pub failed: usize,
+    pub commits: Vec<CommitReport>,
+}
+
+/// Checks one commit message against the rules. Merge commits created by
+/// git ("Merge branch ...") are accepted as they are.
+pub fn lint_message(message: &str, rules: &LintRules) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    let subject = message.lines().next().unwrap_or("").trim_end();
+    if subject.starts_with("Merge ") {
+        return violations;
+    }
+
+    let mut violation = |rule: &'static str, message: String| violations.push(Violation { rule, message });
+
+    if !rules.allow_wip {
+        let lower = subject.to_lowercase();
+        let work_in_progress = ["fixup!", "squash!", "amend!"].iter().any(|p| lower.starts_with(p))
+            || lower.split(|c: char| !c.is_ascii_alphanumeric()).any(|word| word == "wip");
+        if work_in_progress {
+            violation("no-wip", "WIP, fixup! and squash! commits must be squashed before merging".to_string());
+        }
+    }
+
+    if subject.chars().count() > rules.subject_max_length {
+        violation("subject-max-length", format!(
+            "the first line is {} characters long (max {})",
+            subject.chars().count(),
+            rules.subject_max_length
+        ));
+    }
+
+    match ConventionalCommit::parse(message) {
+        Ok(parsed) => {
+            if parsed.commit_type != parsed.kind() {
+                violation("type-case", format!("type '{}' must be lowercase", parsed.commit_type));
+            }
+            if !rules.types.iter().any(|t| *t == parsed.kind()) {
+                violation("type-enum", format!(
+                    "type '{}' is not one of: {}",
+                    parsed.commit_type,
+                    rules.types.join(", ")
+                ));
+            }
+            if let Some(scope) = &parsed.scope {
+                if !rules.scopes.is_empty() && !rules.scopes.contains(scope) {
+                    violation("scope-enum", format!(
+                        "scope '{}' is not one of: {}",
+                        scope,
+                        rules.scopes.join(", ")
+                    ));
+                }
+            }
+
+            let first = parsed.subject.chars().next();
+            match rules.subject_case.as_str() {
+                "lower" if first.is_some_and(char::is_uppercase) => {
+                    violation("subject-case", "the subject must start with a lowercase letter".to_string());
+                }
+                "sentence" if first.is_some_and(char::is_lowercase) => {
+                    violation("subject-case", "the subject must start with an uppercase letter".to_string());
+                }
+                _ => {}
+            }
+            if parsed.subject.ends_with('.') {
+                violation("subject-full-stop", "the subject must not end with a period".to_string());
+            }
+        }
+        Err(e) => violation("header-format", e.to_string()),
+    }
+
+    // Trailers are exempt from wrapping; so are lines holding a URL
+    let (without_trailers, present) = trailers::parse(message);
+    for (number, line) in without_trailers.lines().enumerate().skip(1) {
+        if line.chars().count() > rules.body_max_line_length && !line.contains("://") {
+            violation("body-max-line-length", format!(
+                "line {} is {} characters long (max {})",
+                number + 1,
+                line.chars().count(),
+                rules.body_max_line_length
+            ));
+        }
+    }
+
+    for required in &rules.required_trailers {
+        if !present.iter().any(|t| t.key.eq_ignore_ascii_case(required)) {
+            violation("trailer-required", format!("missing '{}:' trailer", required));
+        }
+    }
+
+    violations
+}
+
+/// Lints every non-merge commit in `spec` (`base..tip`, or a bare base
+/// meaning `base..HEAD`), oldest first.
+pub fn lint_range(repo: &Repository, spec: &str, rules: &LintRules) -> Result<LintSummary> {
+    let (base, tip) = git::resolve_range(repo, spec)?;
+    let mut commits = Vec::new();
+    for id in rewrite::commits_between(repo, base, tip)? {
+        let commit = repo.find_commit(id)?;
+        if commit.parent_count() > 1 {
+            continue;
+        }
+        let message = commit.message().unwrap_or("");
+        commits.push(CommitReport {
+            commit: Some(git::short_oid(id)),
+            subject: commit.summary().unwrap_or("").to_string(),
+            violations: lint_message(message, rules),
+        });
+    }
+    Ok(summarize(commits))
+}
+
+/// Lints a commit message file the way git hands it to the commit-msg
+/// hook: comments and everything below the scissors line are ignored.
+pub fn lint_message_file(repo: &Repository, path: &str, rules: &LintRules) -> Result<LintSummary> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Could not read {}", path))?;
+    let comment = git::comment_char(repo)?;
+
+    let scissors = format!("{} ------------------------ >8 ------------------------", comment);
+    let content = content.split(&scissors).next().unwrap_or("");
+    let message = git2::message_prettify(content, Some(comment as u8))?;
+
+    let report = CommitReport {
+        commit: None,
+        subject: message.lines().next().unwrap_or("").to_string(),
+        violations: lint_message(&message, rules),
+    };
+    Ok(summarize(vec![report]))
+}
+
+fn summarize(commits: Vec<CommitReport>) -> LintSummary {
+    LintSummary {
+        checked: commits.len(),
+        failed: commits.iter().filter(|c| !c.violations.is_empty()).count(),
+        commits,
+    }
+}
+
+/// `lint-commits`: prints the report and returns whether every commit passed.
+pub fn lint_commits(range: Option<&str>, message_file: Option<&str>, format: &str, config: &Config) -> Result<bool> {
+    let repo = git::open_repository()?;
+    let rules = &config.lint_rules;
+
+    let summary = match message_file {
+        Some(path) => lint_message_file(&repo, path, rules)?,
+        None => {
+            let default_range = format!("{}..HEAD", config.get_default_branch());
+            lint_range(&repo, range.unwrap_or(&default_range), rules)?
+        }
+    };
+
+    match format {
+        "json" => println!("{}", serde_json::to_string_pretty(&summary)?),
+        // Stay quiet when the commit-msg hook lets a commit through
+        _ if message_file.is_some() && summary.failed == 0 => {}
+        _ => print_summary(&summary),
+    }
+
+    Ok(summary.failed == 0)
+}
+
+fn print_summary(summary: &LintSummary) {
+    for report in &summary.commits {
+        let label = report.commit.as_deref().unwrap_or("message");
+        if report.violations.is_empty() {
+            println!("✅ {} {}", label, report.subject);
+            continue;
+        }
+        println!("❌ {} {}", label, report.subject);
+        for violation in &report.violations {
+            println!("   {}: {}", violation.rule, violation.message);
+        }
+    }
+
+    if summary.failed == 0 {
+        println!("\n✅ {} commit message(s) checked, no problems found", summary.checked);
+    } else {
+        println!("\n❌ {} of {} commit message(s) have problems", summary.failed, summary.checked);
+    }
+}
@@ -0,0 +1,377 @@
+use anyhow::{Context, Result};
+use git2::{Oid, Repository, RepositoryState};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use crate::git;
+use crate::journal;
+use crate::trailers::{self, Trailer};
+
+/// Creates commits the way `git commit` does: runs the configured hooks and
+/// signs the commit when `commit.gpgsign` is enabled.
+pub struct Committer<'repo> {
+    repo: &'repo Repository,
+    no_verify: bool,
+    trailers: Vec<Trailer>,
+}
+
+#[derive(Debug)]
+pub struct CommitOutcome {
+    pub id: Oid,
+    pub message: String,
+    pub hook_output: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum SigningFormat {
+    OpenPgp,
+    X509,
+    Ssh,
+}
+
+impl<'repo> Committer<'repo> {
+    pub fn new(repo: &'repo Repository) -> Self {
+        Self {
+            repo,
+            no_verify: false,
+            trailers: Vec::new(),
+        }
+    }
+
+    /// Skip the `pre-commit` and `commit-msg` hooks, like `git commit --no-verify`.
+    pub fn no_verify(mut self, no_verify: bool) -> Self {
+        self.no_verify = no_verify;
+        self
+    }
+
+    /// Trailers to append to every message (duplicates are skipped), like
+    /// `git commit --trailer`.
+    pub fn trailers(mut self, trailers: Vec<Trailer>) -> Self {
+        self.trailers = trailers;
+        self
+    }
+
+    /// Commits the current index on top of HEAD (or as a root commit on an
+    /// unborn branch) and moves the current branch to the new commit. While
+    /// a merge is in progress the commit concludes it.
+    pub fn commit(&self, message: &str) -> Result<CommitOutcome> {
+        journal::action(self.repo, format!("commit: {}", first_line(message)))
+            .run(|| self.commit_index(message))
+    }
+
+    fn commit_index(&self, message: &str) -> Result<CommitOutcome> {
+        // Concluding a merge records MERGE_HEAD as further parents, like
+        // `git commit` does; anything else in progress is left to git
+        let mut parents: Vec<git2::Commit> = git::head_commit(self.repo)?.into_iter().collect();
+        let merging = match self.repo.state() {
+            RepositoryState::Clean | RepositoryState::Bisect => false,
+            RepositoryState::Merge => {
+                parents.extend(merge_heads(self.repo)?);
+                true
+            }
+            state => anyhow::bail!(
+                "Cannot commit while a {} is in progress; finish it with git first",
+                operation_name(state)
+            ),
+        };
+
+        let mut hook_output = self.pre_commit()?;
+
+        // pre-commit hooks may have restaged files, so reload the index from disk
+        let mut index = self.repo.index()?;
+        index.read(true)?;
+        let tree = self.repo.find_tree(index.write_tree()?)?;
+
+        let mut outcome = self.commit_tree(message, &tree, &parents.iter().collect::<Vec<_>>())?;
+        hook_output.push_str(&outcome.hook_output);
+        outcome.hook_output = hook_output;
+
+        let reflog = if parents.is_empty() {
+            format!("commit (initial): {}", first_line(&outcome.message))
+        } else if merging {
+            format!("commit (merge): {}", first_line(&outcome.message))
+        } else {
+            format!("commit: {}", first_line(&outcome.message))
+        };
+        update_head(self.repo, outcome.id, &reflog)?;
+        if merging {
+            // Drops MERGE_HEAD and MERGE_MSG so git no longer reports a merge
+            self.repo.cleanup_state()?;
+        }
+
+        outcome.hook_output.push_str(&self.post_commit());
+        Ok(outcome)
+    }
+
+    /// Runs the `pre-commit` hook unless verification is disabled.
+    pub fn pre_commit(&self) -> Result<String> {
+        if self.no_verify {
+            return Ok(String::new());
+        }
+        self.run_hook("pre-commit", &[])
+    }
+
+    /// Runs the `post-commit` hook. It cannot abort the commit, so failures
+    /// are only reported in the returned output.
+    pub fn post_commit(&self) -> String {
+        match self.run_hook("post-commit", &[]) {
+            Ok(output) => output,
+            Err(e) => format!("{}\n", e),
+        }
+    }
+
+    /// Writes a commit for an explicit tree without moving any ref. The
+    /// message hooks (`prepare-commit-msg`, `commit-msg`) run and signing
+    /// applies, but `pre-commit` is left to the caller because the index
+    /// does not necessarily match `tree`.
+    pub fn commit_tree(&self, message: &str, tree: &git2::Tree, parents: &[&git2::Commit]) -> Result<CommitOutcome> {
+        let mut hook_output = String::new();
+
+        // Hooks communicate through the message file, just like git
+        let message_file = self.repo.path().join("COMMIT_EDITMSG");
+        let message = if self.trailers.is_empty() {
+            format!("{}\n", message.trim_end())
+        } else {
+            trailers::merge(message, &self.trailers)
+        };
+        fs::write(&message_file, message)?;
+        let message_arg = message_file.to_string_lossy().to_string();
+
+        hook_output.push_str(&self.run_hook("prepare-commit-msg", &[&message_arg, "message"])?);
+        if !self.no_verify {
+            hook_output.push_str(&self.run_hook("commit-msg", &[&message_arg])?);
+        }
+
+        let message = git2::message_prettify(fs::read_to_string(&message_file)?, Some(b'#'))?;
+        if message.trim().is_empty() {
+            anyhow::bail!("Aborting commit due to empty commit message");
+        }
+
+        let signature = self.repo.signature()?;
+        let id = write_commit(self.repo, &signature, &signature, &message, tree, parents)?;
+
+        Ok(CommitOutcome {
+            id,
+            message,
+            hook_output,
+        })
+    }
+
+    /// Runs a hook if it exists and is executable. Returns its combined
+    /// output, or an error carrying that output when the hook fails.
+    fn run_hook(&self, name: &str, args: &[&str]) -> Result<String> {
+        let hook = hooks_dir(self.repo).join(name);
+        if !is_executable(&hook) {
+            return Ok(String::new());
+        }
+
+        let output = Command::new(&hook)
+            .args(args)
+            .current_dir(self.repo.workdir().unwrap_or(self.repo.path()))
+            .env("GIT_DIR", self.repo.path())
+            .env("GIT_INDEX_FILE", self.repo.path().join("index"))
+            .stdin(Stdio::null())
+            .output()
+            .with_context(|| format!("Failed to run {} hook", name))?;
+
+        let mut text = String::from_utf8_lossy(&output.stdout).to_string();
+        text.push_str(&String::from_utf8_lossy(&output.stderr));
+
+        if !output.status.success() {
+            anyhow::bail!("{} hook failed ({}):\n{}", name, output.status, text.trim_end());
+        }
+
+        Ok(text)
+    }
+}
+
+/// Writes a commit object without moving any ref, signing it when
+/// `commit.gpgsign` is enabled. Used for new commits and history rewrites.
+pub fn write_commit(
+    repo: &Repository,
+    author: &git2::Signature,
+    committer: &git2::Signature,
+    message: &str,
+    tree: &git2::Tree,
+    parents: &[&git2::Commit],
+) -> Result<Oid> {
+    let buffer = repo.commit_create_buffer(author, committer, message, tree, parents)?;
+    let buffer = buffer.as_str().context("Commit buffer is not valid UTF-8")?.to_string();
+
+    let id = match signing_format(repo)? {
+        Some(format) => {
+            let signature = sign(repo, &buffer, &format)?;
+            repo.commit_signed(&buffer, &signature, Some("gpgsig"))?
+        }
+        None => repo.odb()?.write(git2::ObjectType::Commit, buffer.as_bytes())?,
+    };
+    Ok(id)
+}
+
+fn signing_format(repo: &Repository) -> Result<Option<SigningFormat>> {
+    let config = repo.config()?;
+    if !config.get_bool("commit.gpgsign").unwrap_or(false) {
+        return Ok(None);
+    }
+
+    let format = match config.get_string("gpg.format").as_deref() {
+        Ok("ssh") => SigningFormat::Ssh,
+        Ok("x509") => SigningFormat::X509,
+        _ => SigningFormat::OpenPgp,
+    };
+    Ok(Some(format))
+}
+
+fn sign(repo: &Repository, buffer: &str, format: &SigningFormat) -> Result<String> {
+    let config = repo.config()?;
+    let signing_key = config.get_string("user.signingkey").ok();
+
+    match format {
+        SigningFormat::OpenPgp | SigningFormat::X509 => {
+            let program = if *format == SigningFormat::X509 {
+                config.get_string("gpg.x509.program")
+                    .unwrap_or_else(|_| "gpgsm".to_string())
+            } else {
+                config.get_string("gpg.openpgp.program")
+                    .or_else(|_| config.get_string("gpg.program"))
+                    .unwrap_or_else(|_| "gpg".to_string())
+            };
+
+            // Without an explicit key git signs as the committer identity
+            let key = match signing_key {
+                Some(key) => key,
+                None => {
+                    let signature = repo.signature()?;
+                    format!("{} <{}>", signature.name().unwrap_or(""), signature.email().unwrap_or(""))
+                }
+            };
+
+            let mut child = Command::new(&program)
+                .args(["--status-fd=2", "-bsau", &key])
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .stderr(Stdio::piped())
+                .spawn()
+                .with_context(|| format!("Failed to start signing program '{}'", program))?;
+            child.stdin.take().context("Signing program has no stdin")?
+                .write_all(buffer.as_bytes())?;
+            let output = child.wait_with_output()?;
+
+            if !output.status.success() {
+                anyhow::bail!("{} failed to sign the data:\n{}", program, String::from_utf8_lossy(&output.stderr));
+            }
+            Ok(String::from_utf8(output.stdout)?)
+        }
+        SigningFormat::Ssh => {
+            let program = config.get_string("gpg.ssh.program")
+                .unwrap_or_else(|_| "ssh-keygen".to_string());
+            let key = signing_key.context("gpg.format=ssh requires user.signingkey to be set")?;
+
+            let scratch = repo.path().join("commit-buddy-sign");
+            fs::create_dir_all(&scratch)?;
+            let payload = scratch.join("payload");
+            fs::write(&payload, buffer)?;
+
+            // A literal public key means the private half lives in ssh-agent
+            let literal = key.strip_prefix("key::").map(str::to_string)
+                .or_else(|| key.starts_with("ssh-").then(|| key.clone()));
+            let key_file = match literal {
+                Some(public_key) => {
+                    let path = scratch.join("signing_key.pub");
+                    fs::write(&path, public_key)?;
+                    path
+                }
+                None => PathBuf::from(&key),
+            };
+
+            let mut command = Command::new(&program);
+            command.args(["-Y", "sign", "-n", "git", "-f"]).arg(&key_file);
+            if key_file.starts_with(&scratch) {
+                command.arg("-U");
+            }
+            let output = command.arg(&payload).output()
+                .with_context(|| format!("Failed to start signing program '{}'", program))?;
+
+            let signature_file = scratch.join("payload.sig");
+            let result = if output.status.success() {
+                fs::read_to_string(&signature_file).context("ssh-keygen did not produce a signature")
+            } else {
+                Err(anyhow::anyhow!("{} failed to sign the data:\n{}", program, String::from_utf8_lossy(&output.stderr)))
+            };
+            let _ = fs::remove_dir_all(&scratch);
+            result
+        }
+    }
+}
+
+/// The directory git looks for hooks in, honouring `core.hooksPath`.
+pub fn hooks_dir(repo: &Repository) -> PathBuf {
+    let configured = repo.config().ok()
+        .and_then(|config| config.get_path("core.hooksPath").ok());
+    match configured {
+        Some(path) if path.is_absolute() => path,
+        Some(path) => repo.workdir().unwrap_or(repo.path()).join(path),
+        None => common_dir(repo).join("hooks"),
+    }
+}
+
+/// The git directory shared by all worktrees. A linked worktree's own
+/// git directory names it in its `commondir` file.
+pub fn common_dir(repo: &Repository) -> PathBuf {
+    match fs::read_to_string(repo.path().join("commondir")) {
+        Ok(common) => {
+            let common = repo.path().join(common.trim());
+            common.canonicalize().unwrap_or(common)
+        }
+        Err(_) => repo.path().to_path_buf(),
+    }
+}
+
+/// The commits named in MERGE_HEAD, in the order git recorded them.
+fn merge_heads(repo: &Repository) -> Result<Vec<git2::Commit<'_>>> {
+    let merge_head = fs::read_to_string(repo.path().join("MERGE_HEAD"))
+        .context("A merge is in progress but MERGE_HEAD could not be read")?;
+    merge_head.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| Ok(repo.find_commit(Oid::from_str(line.trim())?)?))
+        .collect()
+}
+
+fn operation_name(state: RepositoryState) -> &'static str {
+    match state {
+        RepositoryState::Revert | RepositoryState::RevertSequence => "revert",
+        RepositoryState::CherryPick | RepositoryState::CherryPickSequence => "cherry-pick",
+        RepositoryState::ApplyMailbox | RepositoryState::ApplyMailboxOrRebase => "git am session",
+        _ => "rebase",
+    }
+}
+
+/// Points the current branch (or a detached HEAD) at `id`.
+pub fn update_head(repo: &Repository, id: Oid, reflog: &str) -> Result<()> {
+    let head = repo.find_reference("HEAD")?;
+    match head.symbolic_target() {
+        Some(branch) => {
+            repo.reference(branch, id, true, reflog)?;
+        }
+        None => repo.set_head_detached(id)?,
+    }
+    Ok(())
+}
+
+fn first_line(message: &str) -> &str {
+    message.lines().next().unwrap_or("")
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    fs::metadata(path)
+        .map(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
@@ -6,6 +6,46 @@ pub struct Config {
     pub default_branch: String,
     pub openai_api_key: Option<String>,
     pub github_token: Option<String>,
+    /// Add `Signed-off-by` to every commit
+    pub signoff: bool,
+    /// Regex for ticket IDs in branch names, e.g. `[A-Z]+-[0-9]+`
+    pub ticket_pattern: Option<String>,
+    /// Trailer key used for ticket references
+    pub ticket_trailer: String,
+    pub lint_rules: LintRules,
+}
+
+/// Rules for `lint-commits` and the commit-msg hook.
+#[derive(Debug, Clone)]
+pub struct LintRules {
+    pub types: Vec<String>,
+    /// Allowed scopes; empty allows any
+    pub scopes: Vec<String>,
+    /// Maximum length of the first line
+    pub subject_max_length: usize,
+    /// "lower", "sentence" or "any"
+    pub subject_case: String,
+    pub body_max_line_length: usize,
+    pub required_trailers: Vec<String>,
+    /// Accept WIP, fixup! and squash! commits
+    pub allow_wip: bool,
+}
+
+impl Default for LintRules {
+    fn default() -> Self {
+        Self {
+            types: ["feat", "fix", "docs", "style", "refactor", "perf", "test", "build", "ci", "chore", "revert"]
+                .iter()
+                .map(|t| t.to_string())
+                .collect(),
+            scopes: Vec::new(),
+            subject_max_length: 72,
+            subject_case: "lower".to_string(),
+            body_max_line_length: 100,
+            required_trailers: Vec::new(),
+            allow_wip: false,
+        }
+    }
 }
 
 impl Default for Config {
@@ -14,6 +54,10 @@ impl Default for Config {
             default_branch: "master".to_string(),
             openai_api_key: None,
             github_token: None,
+            signoff: false,
+            ticket_pattern: None,
+            ticket_trailer: "Refs".to_string(),
+            lint_rules: LintRules::default(),
         }
     }
 }
@@ -42,6 +86,55 @@ impl Config {
             }
         }
         
+        if let Ok(signoff) = env::var("COMMIT_BUDDY_SIGNOFF") {
+            config.signoff = matches!(signoff.to_lowercase().as_str(), "1" | "true" | "yes");
+        }
+        
+        if let Ok(pattern) = env::var("COMMIT_BUDDY_TICKET_PATTERN") {
+            if !pattern.is_empty() {
+                config.ticket_pattern = Some(pattern);
+            }
+        }
+        
+        if let Ok(trailer) = env::var("COMMIT_BUDDY_TICKET_TRAILER") {
+            if !trailer.is_empty() {
+                config.ticket_trailer = trailer;
+            }
+        }
+        
+        if let Ok(types) = env::var("COMMIT_BUDDY_LINT_TYPES") {
+            config.lint_rules.types = comma_list(&types);
+        }
+        
+        if let Ok(scopes) = env::var("COMMIT_BUDDY_LINT_SCOPES") {
+            config.lint_rules.scopes = comma_list(&scopes);
+        }
+        
+        if let Ok(length) = env::var("COMMIT_BUDDY_LINT_SUBJECT_MAX_LENGTH") {
+            config.lint_rules.subject_max_length = length.parse()
+                .map_err(|_| anyhow::anyhow!("COMMIT_BUDDY_LINT_SUBJECT_MAX_LENGTH must be a number"))?;
+        }
+        
+        if let Ok(case) = env::var("COMMIT_BUDDY_LINT_SUBJECT_CASE") {
+            if !matches!(case.as_str(), "lower" | "sentence" | "any") {
+                anyhow::bail!("COMMIT_BUDDY_LINT_SUBJECT_CASE must be lower, sentence or any");
+            }
+            config.lint_rules.subject_case = case;
+        }
+        
+        if let Ok(length) = env::var("COMMIT_BUDDY_LINT_BODY_MAX_LINE_LENGTH") {
+            config.lint_rules.body_max_line_length = length.parse()
+                .map_err(|_| anyhow::anyhow!("COMMIT_BUDDY_LINT_BODY_MAX_LINE_LENGTH must be a number"))?;
+        }
+        
+        if let Ok(trailers) = env::var("COMMIT_BUDDY_LINT_REQUIRED_TRAILERS") {
+            config.lint_rules.required_trailers = comma_list(&trailers);
+        }
+        
+        if let Ok(allow) = env::var("COMMIT_BUDDY_LINT_ALLOW_WIP") {
+            config.lint_rules.allow_wip = matches!(allow.to_lowercase().as_str(), "1" | "true" | "yes");
+        }
+        
         Ok(config)
     }
     
@@ -57,3 +150,11 @@ impl Config {
         self.github_token.is_some()
     }
 }
+
+fn comma_list(value: &str) -> Vec<String> {
+    value.split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(str::to_string)
+        .collect()
+}
@@ -0,0 +1,359 @@
+use anyhow::{Context, Result};
+use git2::{IndexEntry, Repository};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use crate::journal;
+
+/// One conflicting region of a file, as the three sides saw it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConflictHunk {
+    pub ours: String,
+    /// The common ancestor; `None` when the file was added on both sides
+    pub base: Option<String>,
+    pub theirs: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Segment {
+    /// Text both sides agree on
+    Clean(String),
+    Conflict(ConflictHunk),
+}
+
+/// A file with unmerged entries in the index.
+#[derive(Debug, Clone)]
+pub struct ConflictFile {
+    pub path: String,
+    /// Deleted on our side (a modify/delete conflict)
+    pub ours_deleted: bool,
+    /// Deleted on their side
+    pub theirs_deleted: bool,
+    /// Binary files can only be resolved by taking one side as a whole
+    pub binary: bool,
+    pub segments: Vec<Segment>,
+}
+
+impl ConflictFile {
+    pub fn hunks(&self) -> Vec<&ConflictHunk> {
+        self.segments.iter()
+            .filter_map(|segment| match segment {
+                Segment::Conflict(hunk) => Some(hunk),
+                Segment::Clean(_) => None,
+            })
+            .collect()
+    }
+}
+
+/// How a single conflict hunk is resolved.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Resolution {
+    Ours,
+    Theirs,
+    /// A suggested or hand-edited replacement
+    Text(String),
+}
+
+/// A proposed resolution of one hunk and why it was chosen.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConflictSuggestion {
+    pub resolution: String,
+    pub reasoning: String,
+}
+
+/// Whether the index has unmerged entries, e.g. after a merge, pull or
+/// rebase stopped on conflicts.
+pub fn has_conflicts(repo: &Repository) -> Result<bool> {
+    Ok(fresh_index(repo)?.has_conflicts())
+}
+
+/// The index as git left it on disk; the repository caches its index,
+/// which goes stale once `git merge` or `git pull` rewrote the file.
+fn fresh_index(repo: &Repository) -> Result<git2::Index> {
+    let mut index = repo.index()?;
+    index.read(true)?;
+    Ok(index)
+}
+
+/// Every conflicted file, split into clean text and conflict hunks.
+///
+/// The hunks are rebuilt from the index stages with `git merge-file --diff3`
+/// rather than read from the working tree, so every hunk has its base
+/// version even when `merge.conflictStyle` is not `diff3`.
+pub fn conflicted_files(repo: &Repository) -> Result<Vec<ConflictFile>> {
+    let index = fresh_index(repo)?;
+    let mut files = Vec::new();
+
+    for conflict in index.conflicts()? {
+        let conflict = conflict?;
+        let Some(entry) = conflict.our.as_ref().or(conflict.their.as_ref()).or(conflict.ancestor.as_ref()) else {
+            continue;
+        };
+        let path = String::from_utf8_lossy(&entry.path).to_string();
+
+        let blob = |entry: &Option<IndexEntry>| -> Result<Option<Vec<u8>>> {
+            match entry {
+                Some(entry) => Ok(Some(repo.find_blob(entry.id)?.content().to_vec())),
+                None => Ok(None),
+            }
+        };
+        let ours = blob(&conflict.our)?;
+        let base = blob(&conflict.ancestor)?;
+        let theirs = blob(&conflict.their)?;
+
+        let binary = [&ours, &base, &theirs].iter()
+            .any(|side| side.as_ref().is_some_and(|content| content.contains(&0)));
+        let text = |side: &Option<Vec<u8>>| side.as_ref()
+            .map(|content| String::from_utf8_lossy(content).to_string());
+
+        let segments = if binary {
+            Vec::new()
+        } else if ours.is_none() || theirs.is_none() {
+            // A modify/delete conflict is one hunk spanning the whole file
+            vec![Segment::Conflict(ConflictHunk {
+                ours: text(&ours).unwrap_or_default(),
+                base: text(&base),
+                theirs: text(&theirs).unwrap_or_default(),
+            })]
+        } else {
+            let merged = merge_file(repo, &ours.clone().unwrap_or_default(), base.as_deref(), &theirs.clone().unwrap_or_default())?;
+            let mut segments = parse_conflict_markers(&merged);
+            if base.is_none() {
+                // Without an ancestor merge-file diffs against an empty file
+                for segment in &mut segments {
+                    if let Segment::Conflict(hunk) = segment {
+                        hunk.base = None;
+                    }
+                }
+            }
+            segments
+        };
+
+        files.push(ConflictFile {
+            path,
+            ours_deleted: conflict.our.is_none(),
+            theirs_deleted: conflict.their.is_none(),
+            binary,
+            segments,
+        });
+    }
+
+    Ok(files)
+}
+
+fn merge_file(repo: &Repository, ours: &[u8], base: Option<&[u8]>, theirs: &[u8]) -> Result<String> {
+    let scratch = repo.path().join("commit-buddy-merge");
+    fs::create_dir_all(&scratch)?;
+    let (ours_path, base_path, theirs_path) = (scratch.join("ours"), scratch.join("base"), scratch.join("theirs"));
+    fs::write(&ours_path, ours)?;
+    fs::write(&base_path, base.unwrap_or_default())?;
+    fs::write(&theirs_path, theirs)?;
+
+    let output = Command::new("git")
+        .args(["merge-file", "-p", "--diff3", "-L", "ours", "-L", "base", "-L", "theirs"])
+        .arg(&ours_path)
+        .arg(&base_path)
+        .arg(&theirs_path)
+        .output()
+        .context("Failed to run git merge-file");
+    let _ = fs::remove_dir_all(&scratch);
+    let output = output?;
+
+    // The exit status is the number of conflicts; only negative values are errors
+    match output.status.code() {
+        Some(code) if (0..=127).contains(&code) => Ok(String::from_utf8_lossy(&output.stdout).to_string()),
+        _ => anyhow::bail!("git merge-file failed:\n{}", String::from_utf8_lossy(&output.stderr)),
+    }
+}
+
+/// Splits text with `<<<<<<<`, `|||||||`, `=======` and `>>>>>>>` markers
+/// into clean text and conflict hunks. An unterminated conflict is kept as
+/// clean text.
+pub fn parse_conflict_markers(text: &str) -> Vec<Segment> {
+    enum State {
+        Clean,
+        Ours,
+        Base,
+        Theirs,
+    }
+
+    let mut segments = Vec::new();
+    let mut clean = String::new();
+    let mut raw = String::new();
+    let mut hunk = ConflictHunk { ours: String::new(), base: None, theirs: String::new() };
+    let mut state = State::Clean;
+
+    for line in text.split_inclusive('\n') {
+        match state {
+            State::Clean if is_marker(line, '<') => {
+                raw = line.to_string();
+                hunk = ConflictHunk { ours: String::new(), base: None, theirs: String::new() };
+                state = State::Ours;
+                continue;
+            }
+            State::Clean => {
+                clean.push_str(line);
+                continue;
+            }
+            _ => raw.push_str(line),
+        }
+
+        match state {
+            State::Ours if is_marker(line, '|') => {
+                hunk.base = Some(String::new());
+                state = State::Base;
+            }
+            State::Ours | State::Base if is_marker(line, '=') => state = State::Theirs,
+            State::Ours => hunk.ours.push_str(line),
+            State::Base => hunk.base.get_or_insert_with(String::new).push_str(line),
+            State::Theirs if is_marker(line, '>') => {
+                if !clean.is_empty() {
+                    segments.push(Segment::Clean(std::mem::take(&mut clean)));
+                }
+                segments.push(Segment::Conflict(hunk.clone()));
+                state = State::Clean;
+            }
+            State::Theirs => hunk.theirs.push_str(line),
+            State::Clean => {}
+        }
+    }
+
+    if !matches!(state, State::Clean) {
+        clean.push_str(&raw);
+    }
+    if !clean.is_empty() {
+        segments.push(Segment::Clean(clean));
+    }
+    segments
+}
+
+fn is_marker(line: &str, marker: char) -> bool {
+    let rest = line.trim_end_matches(['\n', '\r']);
+    rest.len() >= 7
+        && rest.chars().take(7).all(|c| c == marker)
+        && matches!(rest[7..].chars().next(), None | Some(' '))
+}
+
+/// The text of a hunk with conflict markers, for editing by hand.
+pub fn hunk_with_markers(hunk: &ConflictHunk) -> String {
+    let mut text = format!("<<<<<<< ours\n{}", hunk.ours);
+    if let Some(base) = &hunk.base {
+        text.push_str(&format!("||||||| base\n{}", base));
+    }
+    text.push_str(&format!("=======\n{}>>>>>>> theirs\n", hunk.theirs));
+    text
+}
+
+/// Whether `text` still contains conflict markers.
+pub fn has_markers(text: &str) -> bool {
+    parse_conflict_markers(text).iter().any(|segment| matches!(segment, Segment::Conflict(_)))
+}
+
+/// The file content once every hunk is resolved, or `None` when the
+/// resolution deletes the file.
+pub fn render(file: &ConflictFile, resolutions: &[Resolution]) -> Result<Option<String>> {
+    let hunk_count = file.hunks().len();
+    if resolutions.len() != hunk_count {
+        anyhow::bail!("{} has {} conflict(s) but {} resolution(s) were given", file.path, hunk_count, resolutions.len());
+    }
+    // A modify/delete conflict resolved to the deleting side removes the file
+    match resolutions.first() {
+        Some(Resolution::Ours) if file.ours_deleted => return Ok(None),
+        Some(Resolution::Theirs) if file.theirs_deleted => return Ok(None),
+        _ => {}
+    }
+
+    let mut resolutions = resolutions.iter();
+    let mut content = String::new();
+    for segment in &file.segments {
+        match segment {
+            Segment::Clean(text) => content.push_str(text),
+            Segment::Conflict(hunk) => match resolutions.next() {
+                Some(Resolution::Ours) => content.push_str(&hunk.ours),
+                Some(Resolution::Theirs) => content.push_str(&hunk.theirs),
+                Some(Resolution::Text(text)) => content.push_str(text),
+                None => unreachable!("checked above"),
+            },
+        }
+    }
+    Ok(Some(content))
+}
+
+/// Writes the resolved file to the work tree and stages it, which marks the
+/// conflict as resolved. Binary files take one side as a whole.
+pub fn resolve(repo: &Repository, file: &ConflictFile, resolutions: &[Resolution]) -> Result<()> {
+    let workdir = repo.workdir().context("Cannot resolve conflicts in a bare repository")?;
+    let target = workdir.join(&file.path);
+
+    let content = if file.binary {
+        let stage = match resolutions {
+            [Resolution::Ours] => 2,
+            [Resolution::Theirs] => 3,
+            _ => anyhow::bail!("{} is binary; take ours or theirs", file.path),
+        };
+        let index = fresh_index(repo)?;
+        match index.get_path(Path::new(&file.path), stage) {
+            Some(entry) => Some(repo.find_blob(entry.id)?.content().to_vec()),
+            None => None,
+        }
+    } else {
+        render(file, resolutions)?.map(String::into_bytes)
+    };
+
+    if let Some(content) = &content {
+        if has_markers(&String::from_utf8_lossy(content)) {
+            anyhow::bail!("The resolution of {} still contains conflict markers", file.path);
+        }
+    }
+
+    journal::action(repo, format!("resolve {}", file.path))
+        .files(std::slice::from_ref(&target))
+        .run(|| {
+            let mut index = fresh_index(repo)?;
+            match content {
+                Some(content) => {
+                    if let Some(parent) = target.parent() {
+                        fs::create_dir_all(parent)?;
+                    }
+                    fs::write(&target, content)?;
+                    index.add_path(Path::new(&file.path))?;
+                }
+                None => {
+                    if target.exists() {
+                        fs::remove_file(&target)?;
+                    }
+                    index.remove_path(Path::new(&file.path))?;
+                }
+            }
+            index.write()?;
+            Ok(())
+        })
+}
+
+/// Opens `text` in the user's git editor and returns what was saved.
+pub fn edit_in_editor(repo: &Repository, text: &str) -> Result<String> {
+    let output = Command::new("git")
+        .args(["var", "GIT_EDITOR"])
+        .current_dir(repo.workdir().unwrap_or(repo.path()))
+        .output()?;
+    let editor = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    let editor = if editor.is_empty() { "vi".to_string() } else { editor };
+
+    let file = repo.path().join("COMMIT_BUDDY_CONFLICT");
+    fs::write(&file, text)?;
+    // Like git, let the shell split the editor command and its arguments
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(format!("{} \"$@\"", editor))
+        .arg(&editor)
+        .arg(&file)
+        .status()
+        .with_context(|| format!("Failed to start editor '{}'", editor));
+    let edited = fs::read_to_string(&file);
+    let _ = fs::remove_file(&file);
+
+    if !status?.success() {
+        anyhow::bail!("The editor exited with an error; the hunk was left unchanged");
+    }
+    Ok(edited?)
+}
@@ -0,0 +1,201 @@
+use std::fmt;
+use crate::utils;
+
+/// A commit message parsed according to the Conventional Commits 1.0.0
+/// specification: `type(scope)!: subject`, an optional body and footers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConventionalCommit {
+    /// The type as written; compare with [`ConventionalCommit::kind`]
+    pub commit_type: String,
+    pub scope: Option<String>,
+    /// Set by a `!` in the header or a `BREAKING CHANGE` footer
+    pub breaking: bool,
+    pub subject: String,
+    pub body: Option<String>,
+    pub footers: Vec<Footer>,
+}
+
+/// A footer such as `Refs: #123`, `Closes #4` or `BREAKING CHANGE: ...`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Footer {
+    pub token: String,
+    pub value: String,
+}
+
+impl Footer {
+    pub fn is_breaking_change(&self) -> bool {
+        self.token == "BREAKING CHANGE" || self.token == "BREAKING-CHANGE"
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseError {
+    Empty,
+    MissingType,
+    InvalidType(String),
+    EmptyScope,
+    UnclosedScope,
+    MissingSeparator,
+    EmptySubject,
+    MissingBlankLine,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Empty => write!(f, "the commit message is empty"),
+            ParseError::MissingType => write!(f, "the header must start with a type, e.g. 'feat: ...'"),
+            ParseError::InvalidType(t) => write!(f, "'{}' is not a valid type (letters, digits and '-' only)", t),
+            ParseError::EmptyScope => write!(f, "the scope in parentheses must not be empty"),
+            ParseError::UnclosedScope => write!(f, "the scope is missing its closing ')'"),
+            ParseError::MissingSeparator => write!(f, "the type must be followed by ': ' (colon and space)"),
+            ParseError::EmptySubject => write!(f, "the subject after ': ' must not be empty"),
+            ParseError::MissingBlankLine => write!(f, "the header must be followed by a blank line"),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl ConventionalCommit {
+    pub fn parse(message: &str) -> Result<Self, ParseError> {
+        let message = message.trim_matches('\n').trim_end();
+        let mut lines = message.lines();
+        let header = lines.next().filter(|h| !h.trim().is_empty()).ok_or(ParseError::Empty)?;
+        if lines.next().is_some_and(|line| !line.trim().is_empty()) {
+            return Err(ParseError::MissingBlankLine);
+        }
+
+        let (commit_type, scope, breaking, subject) = parse_header(header)?;
+
+        let rest = message.split_once('\n').map(|(_, rest)| rest.trim()).unwrap_or("");
+        let (body, footers) = split_footers(rest);
+
+        let breaking = breaking || footers.iter().any(Footer::is_breaking_change);
+        Ok(Self {
+            commit_type,
+            scope,
+            breaking,
+            subject,
+            body,
+            footers,
+        })
+    }
+
+    /// The type lowercased; the specification treats types case-insensitively.
+    pub fn kind(&self) -> String {
+        self.commit_type.to_lowercase()
+    }
+
+    /// The `BREAKING CHANGE` footer text, or the subject when the change is
+    /// only marked with `!`.
+    pub fn breaking_description(&self) -> Option<&str> {
+        if !self.breaking {
+            return None;
+        }
+        Some(self.footers.iter()
+            .find(|f| f.is_breaking_change())
+            .map(|f| f.value.as_str())
+            .unwrap_or(&self.subject))
+    }
+
+    pub fn emoji(&self) -> &'static str {
+        if self.breaking {
+            "💥"
+        } else {
+            utils::get_commit_emoji(&self.kind())
+        }
+    }
+}
+
+fn parse_header(header: &str) -> Result<(String, Option<String>, bool, String), ParseError> {
+    let type_end = header.find(|c: char| !(c.is_ascii_alphanumeric() || c == '-'))
+        .unwrap_or(header.len());
+    let commit_type = &header[..type_end];
+    if commit_type.is_empty() {
+        return match header.chars().next() {
+            Some('(') | Some(':') | Some('!') => Err(ParseError::MissingType),
+            _ => Err(ParseError::InvalidType(header.split([':', '(', ' ']).next().unwrap_or("").to_string())),
+        };
+    }
+    if !commit_type.chars().next().is_some_and(|c| c.is_ascii_alphabetic()) {
+        return Err(ParseError::InvalidType(commit_type.to_string()));
+    }
+
+    let mut rest = &header[type_end..];
+    let mut scope = None;
+    if let Some(after_paren) = rest.strip_prefix('(') {
+        let close = after_paren.find(')').ok_or(ParseError::UnclosedScope)?;
+        let value = after_paren[..close].trim();
+        if value.is_empty() {
+            return Err(ParseError::EmptyScope);
+        }
+        scope = Some(value.to_string());
+        rest = &after_paren[close + 1..];
+    }
+
+    let breaking = rest.starts_with('!');
+    if breaking {
+        rest = &rest[1..];
+    }
+
+    let subject = match rest.strip_prefix(':') {
+        Some(subject) if subject.starts_with(' ') => subject.trim(),
+        Some(subject) if subject.trim().is_empty() => return Err(ParseError::EmptySubject),
+        _ => return Err(ParseError::MissingSeparator),
+    };
+    if subject.is_empty() {
+        return Err(ParseError::EmptySubject);
+    }
+
+    Ok((commit_type.to_string(), scope, breaking, subject.to_string()))
+}
+
+/// Splits everything after the header into the body and the footers. The
+/// footers are the final paragraph when every line in it is a footer (or a
+/// continuation of one).
+fn split_footers(rest: &str) -> (Option<String>, Vec<Footer>) {
+    if rest.is_empty() {
+        return (None, Vec::new());
+    }
+
+    let (body, block) = match rest.rfind("\n\n") {
+        Some(split) => (Some(rest[..split].trim()), &rest[split + 2..]),
+        None => (None, rest),
+    };
+
+    let mut footers: Vec<Footer> = Vec::new();
+    for line in block.lines() {
+        if let Some(footer) = parse_footer_line(line) {
+            footers.push(footer);
+        } else if let (Some(last), true) = (footers.last_mut(), !line.trim().is_empty()) {
+            // Footer values may span several lines
+            last.value.push('\n');
+            last.value.push_str(line);
+        } else {
+            return (Some(rest.to_string()), Vec::new());
+        }
+    }
+
+    (body.filter(|b| !b.is_empty()).map(str::to_string), footers)
+}
+
+fn parse_footer_line(line: &str) -> Option<Footer> {
+    for token in ["BREAKING CHANGE", "BREAKING-CHANGE"] {
+        if let Some(value) = line.strip_prefix(token).and_then(|r| r.strip_prefix(": ")) {
+            return Some(Footer { token: token.to_string(), value: value.trim().to_string() });
+        }
+    }
+
+    // For `Closes #12` the `#` stays in the value
+    let separator = line.find(": ").map(|i| (i, 2))
+        .into_iter()
+        .chain(line.find(" #").map(|i| (i, 1)))
+        .min_by_key(|(i, _)| *i)?;
+    let token = &line[..separator.0];
+    let valid = !token.is_empty() && token.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+    valid.then(|| Footer {
+        token: token.to_string(),
+        value: line[separator.0 + separator.1..].trim().to_string(),
+    })
+}
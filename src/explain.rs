@@ -0,0 +1,193 @@
+use anyhow::{Context, Result};
+use git2::{ObjectType, Oid, Repository, Sort};
+use std::path::Path;
+use crate::ai;
+use crate::config::Config;
+use crate::git::{self, ChangeKind, DiffInfo};
+use crate::rewrite;
+
+/// Commits of a file's history handed to the explanation
+const FILE_HISTORY_LIMIT: usize = 30;
+
+/// What `explain` was asked about.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExplainTarget {
+    Commit(Oid),
+    /// `base..tip`; `base` is `None` when the range starts at the root
+    Range { base: Option<Oid>, tip: Oid },
+    /// A file or directory, relative to the top of the work tree
+    Path(String),
+}
+
+/// Resolves `spec` like git does: a range when it contains `..`, otherwise
+/// a revision, and only then a path (relative to `cwd` or to the top of
+/// the work tree). Prefix a path with `./` when it is also a branch name.
+pub fn resolve_target(repo: &Repository, spec: &str, cwd: &Path) -> Result<ExplainTarget> {
+    if spec.contains("..") && !cwd.join(spec).exists() {
+        let (base, tip) = git::resolve_range(repo, spec)?;
+        return Ok(ExplainTarget::Range { base, tip });
+    }
+
+    if !spec.starts_with("./") {
+        if let Ok(commit) = repo.revparse_single(spec).and_then(|object| object.peel_to_commit()) {
+            return Ok(ExplainTarget::Commit(commit.id()));
+        }
+    }
+
+    let top = repo.workdir().context("Cannot explain paths in a bare repository")?;
+    let candidates = [cwd.join(spec), top.join(spec)];
+    let head_tree = git::head_commit(repo)?.map(|commit| commit.tree()).transpose()?;
+
+    for candidate in candidates {
+        let Some(relative) = git::workdir_relative(repo, &candidate) else {
+            continue;
+        };
+        if relative.is_empty() {
+            continue;
+        }
+        let tracked = head_tree.as_ref().is_some_and(|tree| tree.get_path(Path::new(&relative)).is_ok());
+        if tracked || has_history(repo, &relative)? {
+            return Ok(ExplainTarget::Path(relative));
+        }
+    }
+
+    anyhow::bail!("{} is neither a revision nor a path with history in this repository", spec)
+}
+
+/// Whether a path that is gone from HEAD was ever committed.
+fn has_history(repo: &Repository, path: &str) -> Result<bool> {
+    Ok(!file_history(repo, path, 1)?.is_empty())
+}
+
+/// The commits of a target, oldest first. For a path each commit's diff is
+/// limited to that path.
+pub fn collect(repo: &Repository, target: &ExplainTarget) -> Result<DiffInfo> {
+    let ids = match target {
+        ExplainTarget::Commit(id) => vec![*id],
+        ExplainTarget::Range { base, tip } => rewrite::commits_between(repo, *base, *tip)?,
+        ExplainTarget::Path(path) => {
+            let mut history = file_history(repo, path, FILE_HISTORY_LIMIT)?;
+            history.reverse();
+            let mut commits = Vec::new();
+            for (id, paths) in history {
+                let commit = repo.find_commit(id)?;
+                commits.push(git::commit_info_for_paths(repo, &commit, &paths)?);
+            }
+            return Ok(DiffInfo::from_commits(commits).with_workdir(repo));
+        }
+    };
+
+    let mut commits = Vec::new();
+    for id in ids {
+        let commit = repo.find_commit(id)?;
+        commits.push(git::commit_info(repo, &commit)?);
+    }
+    Ok(DiffInfo::from_commits(commits).with_workdir(repo))
+}
+
+/// The newest `limit` commits from HEAD that changed `path`, newest first,
+/// each with the path(s) it had in that commit. Renames of files are
+/// followed. Merges only count when the path differs from every parent.
+pub fn file_history(repo: &Repository, path: &str, limit: usize) -> Result<Vec<(Oid, Vec<String>)>> {
+    let mut history = Vec::new();
+    if git::head_commit(repo)?.is_none() {
+        return Ok(history);
+    }
+
+    let mut walk = repo.revwalk()?;
+    walk.set_sorting(Sort::TOPOLOGICAL | Sort::TIME)?;
+    walk.push_head()?;
+    let mut current = path.to_string();
+
+    for id in walk {
+        if history.len() >= limit {
+            break;
+        }
+        let commit = repo.find_commit(id?)?;
+        let entry = |tree: &git2::Tree| tree.get_path(Path::new(&current)).ok().map(|e| (e.id(), e.kind()));
+        let ours = entry(&commit.tree()?);
+
+        let mut parents = Vec::new();
+        for parent in commit.parents() {
+            parents.push(entry(&parent.tree()?));
+        }
+        let changed = if parents.is_empty() {
+            ours.is_some()
+        } else {
+            parents.iter().all(|parent| *parent != ours)
+        };
+        if !changed {
+            continue;
+        }
+
+        let mut paths = vec![current.clone()];
+        // A file that appears here may have been renamed from another path
+        let added = ours.is_some_and(|(_, kind)| kind == Some(ObjectType::Blob)) && parents.first() == Some(&None);
+        if added {
+            if let Some(old) = renamed_from(repo, &commit, &current)? {
+                paths.push(old.clone());
+                history.push((commit.id(), paths));
+                current = old;
+                continue;
+            }
+        }
+        history.push((commit.id(), paths));
+    }
+
+    Ok(history)
+}
+
+fn renamed_from(repo: &Repository, commit: &git2::Commit, path: &str) -> Result<Option<String>> {
+    let info = git::commit_info(repo, commit)?;
+    Ok(info.file_changes.into_iter()
+        .find(|change| change.kind == ChangeKind::Renamed && change.path == path)
+        .and_then(|change| change.old_path))
+}
+
+/// A short description of the target for headings, e.g. `main..HEAD`.
+pub fn describe_target(repo: &Repository, target: &ExplainTarget) -> String {
+    match target {
+        ExplainTarget::Commit(id) => {
+            let summary = repo.find_commit(*id).ok()
+                .and_then(|commit| commit.summary().map(str::to_string))
+                .unwrap_or_default();
+            format!("commit {} ({})", git::short_oid(*id), summary)
+        }
+        ExplainTarget::Range { base: Some(base), tip } => format!("{}..{}", git::short_oid(*base), git::short_oid(*tip)),
+        ExplainTarget::Range { base: None, tip } => format!("the history up to {}", git::short_oid(*tip)),
+        ExplainTarget::Path(path) => format!("the history of {}", path),
+    }
+}
+
+/// `explain`: prints a narrative explanation of a commit, a range or the
+/// history of a path, as plain text or markdown.
+pub async fn explain(spec: &str, format: &str, config: &Config) -> Result<()> {
+    let markdown = match format {
+        "markdown" | "md" => true,
+        "text" => false,
+        other => anyhow::bail!("Unknown format '{}' (expected text or markdown)", other),
+    };
+
+    let repo = git::open_repository()?;
+    let target = resolve_target(&repo, spec, &std::env::current_dir()?)?;
+    let subject = describe_target(&repo, &target);
+    let diff_info = collect(&repo, &target)?;
+
+    if diff_info.commits.is_empty() {
+        println!("No commits found to explain.");
+        return Ok(());
+    }
+
+    println!("🔎 Explaining {} ({} commit(s))...", subject, diff_info.commits.len());
+    let explanation = match &target {
+        ExplainTarget::Path(path) => ai::explain_file_history(path, &diff_info, markdown, config).await?,
+        _ => ai::explain_changes(&subject, &diff_info, markdown, config).await?,
+    };
+
+    if markdown {
+        println!("## Explanation of {}\n\n{}", subject, explanation.trim());
+    } else {
+        println!("{}\n\n{}", subject, explanation.trim());
+    }
+    Ok(())
+}
@@ -1,25 +1,90 @@
 use anyhow::{Context, Result};
-use git2::{Repository, Diff, DiffFormat};
+use git2::{Delta, Diff, DiffFindOptions, DiffOptions, FileMode, Oid, Patch, Repository};
 use serde::{Deserialize, Serialize};
 use crate::ai;
+use crate::committer::Committer;
 use crate::config::Config;
+use crate::journal;
+use crate::rewrite;
+use crate::split;
+use crate::trailers::{self, TrailerOptions};
+use crate::utils;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CommitInfo {
     pub hash: String,
     pub message: String,
     pub author: String,
     pub date: String,
     pub files_changed: Vec<String>,
+    pub file_changes: Vec<FileChange>,
     pub diff: String,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ChangeKind {
+    Added,
+    Deleted,
+    Modified,
+    Renamed,
+    Copied,
+    TypeChanged,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileChange {
+    pub path: String,
+    pub old_path: Option<String>,
+    pub kind: ChangeKind,
+    pub binary: bool,
+    pub submodule: bool,
+    pub old_mode: Option<u32>,
+    pub new_mode: Option<u32>,
+    pub additions: usize,
+    pub deletions: usize,
+}
+
+impl FileChange {
+    /// One-line human readable summary, used in AI prompts and file lists.
+    pub fn describe(&self) -> String {
+        let mut text = match (self.kind, &self.old_path) {
+            (ChangeKind::Renamed, Some(old)) => format!("renamed {} → {}", old, self.path),
+            (ChangeKind::Copied, Some(old)) => format!("copied {} → {}", old, self.path),
+            (ChangeKind::Added, _) => format!("added {}", self.path),
+            (ChangeKind::Deleted, _) => format!("deleted {}", self.path),
+            (ChangeKind::TypeChanged, _) => format!("type changed {}", self.path),
+            _ => format!("modified {}", self.path),
+        };
+
+        if self.submodule {
+            text.push_str(" (submodule pointer)");
+        } else if self.binary {
+            text.push_str(" (binary)");
+        } else if self.additions > 0 || self.deletions > 0 {
+            text.push_str(&format!(" (+{} -{})", self.additions, self.deletions));
+        }
+
+        if let (Some(old), Some(new)) = (self.old_mode, self.new_mode) {
+            if old != new && self.kind != ChangeKind::TypeChanged {
+                text.push_str(&format!(" [mode {:o} → {:o}]", old, new));
+            }
+        }
+
+        text
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct DiffInfo {
     pub commits: Vec<CommitInfo>,
     pub total_files_changed: usize,
     pub total_additions: i32,
     pub total_deletions: i32,
+    /// Top of the work tree; the file paths above are relative to it
+    #[serde(default)]
+    pub workdir: Option<PathBuf>,
 }
 
 pub async fn generate_pr_description(base: &str, format: &str, config: &Config) -> Result<()> {
@@ -40,7 +105,7 @@ pub async fn generate_pr_description(base: &str, format: &str, config: &Config)
             let json = serde_json::to_string_pretty(&description)?;
             println!("{}", json);
         }
-        "markdown" | _ => {
+        _ => {
             println!("\n{}", description);
         }
     }
@@ -65,12 +130,13 @@ pub async fn generate_tests(base: &str, framework: &str, config: &Config) -> Res
     Ok(())
 }
 
-pub async fn improve_commit_message(commit_hash: Option<&str>, config: &Config) -> Result<()> {
-    let repo = Repository::open(".")?;
+pub async fn improve_commit_message(commit_hash: Option<&str>, apply: bool, force: bool, config: &Config) -> Result<()> {
+    let repo = open_repository()?;
     let commit_hash = commit_hash.unwrap_or("HEAD");
     
-    let commit_obj = repo.revparse_single(commit_hash)?;
-    let commit = commit_obj.as_commit()
+    let commit_obj = repo.revparse_single(commit_hash)
+        .with_context(|| format!("Could not resolve {} (does the repository have any commits yet?)", commit_hash))?;
+    let commit = commit_obj.peel_to_commit()
         .context("Could not find commit")?;
     
     let message = commit.message().unwrap_or("No message").to_string();
@@ -80,23 +146,82 @@ pub async fn improve_commit_message(commit_hash: Option<&str>, config: &Config)
     println!("Current message: {}", message);
     println!("Author: {}", author);
     
+    if apply {
+        ensure_rewordable(config)?;
+        let head = repo.head()?.peel_to_commit()?;
+        if head.id() != commit.id() {
+            anyhow::bail!("--apply only rewords HEAD; use --range <base>..HEAD to reword older commits");
+        }
+        rewrite::ensure_unpublished(&repo, &[commit.id()], force)?;
+    }
+    
     let improved_message = ai::improve_commit_message(&message, config).await?;
     
     println!("\n💡 Suggested improved message:");
     println!("{}", improved_message);
     
+    if apply {
+        let base = commit.parent_ids().next();
+        let range = rewrite::HeadRange {
+            base,
+            head: commit.id(),
+            commits: vec![commit.id()],
+        };
+        let messages = HashMap::from([(commit.id(), utils::clean_commit_message(&improved_message))]);
+        let outcome = rewrite::reword(&repo, &range, &messages)?;
+        rewrite::print_reword_table(&repo, &outcome)?;
+        println!("✅ Reworded HEAD");
+    }
+    
+    Ok(())
+}
+
+/// Rewrites every commit in `range` (which must end at HEAD) with an
+/// AI-improved message, keeping trees identical.
+pub async fn improve_commit_range(range: &str, force: bool, config: &Config) -> Result<()> {
+    ensure_rewordable(config)?;
+    
+    let repo = open_repository()?;
+    let range = rewrite::resolve_head_range(&repo, range)?;
+    
+    if range.commits.is_empty() {
+        println!("No commits found in range.");
+        return Ok(());
+    }
+    
+    rewrite::ensure_unpublished(&repo, &range.commits, force)?;
+    
+    println!("💬 Improving {} commit message(s)...", range.commits.len());
+    let mut messages = HashMap::new();
+    for &id in &range.commits {
+        let commit = repo.find_commit(id)?;
+        let message = commit.message().unwrap_or("").to_string();
+        println!("  {} {}", short_oid(id), commit.summary().unwrap_or(""));
+        let improved = ai::improve_commit_message(&message, config).await?;
+        messages.insert(id, utils::clean_commit_message(&improved));
+    }
+    
+    let outcome = rewrite::reword(&repo, &range, &messages)?;
+    rewrite::print_reword_table(&repo, &outcome)?;
+    println!("✅ Rewrote {} commit(s)", outcome.mapping.len());
+    
     Ok(())
 }
 
-pub async fn interactive_commit(all: bool, config: &Config) -> Result<()> {
-    let repo = Repository::open(".")?;
+fn ensure_rewordable(config: &Config) -> Result<()> {
+    // Without a model the "improved" message is just the fallback notice
+    if !config.has_openai_key() {
+        anyhow::bail!("Rewording commits requires OPENAI_API_KEY to be set");
+    }
+    Ok(())
+}
+
+pub async fn interactive_commit(all: bool, no_verify: bool, trailer_options: &TrailerOptions, config: &Config) -> Result<()> {
+    let repo = open_repository()?;
     
     if all {
         println!("📁 Staging all changes...");
-        // Stage all changes
-        let mut index = repo.index()?;
-        index.add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None)?;
-        index.write()?;
+        stage_all(&repo)?;
     }
     
     // Get staged changes
@@ -123,27 +248,23 @@ pub async fn interactive_commit(all: bool, config: &Config) -> Result<()> {
     if let Ok(choice) = input.trim().parse::<usize>() {
         if choice >= 1 && choice <= suggestions.len() {
             let selected_message = &suggestions[choice - 1];
-            println!("\n🚀 Committing with message: {}", selected_message);
             
-            // Perform the actual commit
-            let mut index = repo.index()?;
-            let tree_id = index.write_tree()?;
-            let tree = repo.find_tree(tree_id)?;
+            let mut trailer_options = trailer_options.clone();
+            if trailer_options.co_authors.is_empty() {
+                trailer_options.co_authors = pick_co_authors(&repo)?;
+            }
+            let trailers = trailers::for_commit(&repo, config, &trailer_options)?;
             
-            let signature = repo.signature()?;
-            let head = repo.head()?;
-            let parent_commit = head.peel_to_commit()?;
+            println!("\n🚀 Committing with message: {}", selected_message);
             
-            let commit_id = repo.commit(
-                Some("HEAD"),
-                &signature,
-                &signature,
-                selected_message,
-                &tree,
-                &[&parent_commit],
-            )?;
+            // Perform the actual commit
+            let outcome = Committer::new(&repo)
+                .no_verify(no_verify)
+                .trailers(trailers)
+                .commit(selected_message)?;
+            print_hook_output(&outcome.hook_output);
             
-            println!("✅ Commit created successfully: {}", commit_id);
+            println!("✅ Commit created successfully: {}", outcome.id);
             return Ok(());
         }
     }
@@ -152,15 +273,32 @@ pub async fn interactive_commit(all: bool, config: &Config) -> Result<()> {
     Ok(())
 }
 
-pub async fn ai_commit(all: bool, config: &Config) -> Result<()> {
-    let repo = Repository::open(".")?;
+/// Offers the recent authors of the repository as co-authors.
+fn pick_co_authors(repo: &Repository) -> Result<Vec<String>> {
+    let authors = trailers::recent_authors(repo, 10)?;
+    if authors.is_empty() {
+        return Ok(Vec::new());
+    }
+    
+    println!("\n👥 Add co-authors? Enter numbers separated by commas, or press Enter to skip:");
+    for (i, author) in authors.iter().enumerate() {
+        println!("{}. {}", i + 1, author);
+    }
+    let mut input = String::new();
+    std::io::stdin().read_line(&mut input)?;
+    
+    Ok(input.split(',')
+        .filter_map(|choice| choice.trim().parse::<usize>().ok())
+        .filter_map(|choice| authors.get(choice.wrapping_sub(1)).cloned())
+        .collect())
+}
+
+pub async fn ai_commit(all: bool, no_verify: bool, trailer_options: &TrailerOptions, config: &Config) -> Result<()> {
+    let repo = open_repository()?;
     
     if all {
         println!("📁 Staging all changes...");
-        // Stage all changes
-        let mut index = repo.index()?;
-        index.add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None)?;
-        index.write()?;
+        stage_all(&repo)?;
     }
     
     // Get staged changes
@@ -187,54 +325,66 @@ pub async fn ai_commit(all: bool, config: &Config) -> Result<()> {
     println!("\n🚀 Committing with AI-generated message...");
     
     // Perform the actual commit
-    let mut index = repo.index()?;
-    let tree_id = index.write_tree()?;
-    let tree = repo.find_tree(tree_id)?;
-    
-    let signature = repo.signature()?;
-    let head = repo.head()?;
-    let parent_commit = head.peel_to_commit()?;
-    
-    let commit_id = repo.commit(
-        Some("HEAD"),
-        &signature,
-        &signature,
-        commit_message,
-        &tree,
-        &[&parent_commit],
-    )?;
-    
-    println!("✅ Commit created successfully: {}", commit_id);
-    println!("📋 Message: {}", commit_message);
+    let outcome = Committer::new(&repo)
+        .no_verify(no_verify)
+        .trailers(trailers::for_commit(&repo, config, trailer_options)?)
+        .commit(commit_message)?;
+    print_hook_output(&outcome.hook_output);
+    
+    println!("✅ Commit created successfully: {}", outcome.id);
+    println!("📋 Message: {}", outcome.message.trim_end());
     
     Ok(())
 }
 
-pub async fn generate_changelog(base: &str, output: Option<&str>, config: &Config) -> Result<()> {
-    println!("📋 Generating changelog since {}...", base);
-    
-    let diff_info = get_diff_info(base)?;
-    
-    if diff_info.commits.is_empty() {
-        println!("No commits found to analyze.");
+/// `ai-commit --split`: splits the staged changes into several logical
+/// commits. The working tree and index are left as they are.
+pub async fn split_commit(all: bool, no_verify: bool, trailer_options: &TrailerOptions, config: &Config) -> Result<()> {
+    let repo = open_repository()?;
+
+    if all {
+        println!("📁 Staging all changes...");
+        stage_all(&repo)?;
+    }
+
+    let units = split::staged_units(&repo)?;
+    if units.is_empty() {
+        println!("No staged changes found.");
         return Ok(());
     }
 
-    let changelog = ai::generate_changelog(&diff_info, config).await?;
-    
-    match output {
-        Some(file_path) => {
-            std::fs::write(file_path, &changelog)?;
-            println!("✅ Changelog written to {}", file_path);
-        }
-        None => {
-            println!("\n{}", changelog);
-        }
+    println!("🤖 Grouping {} hunk(s) into logical commits...", units.len());
+    let groups = split::plan(&units, config).await?;
+    split::print_plan(&units, &groups);
+
+    print!("\nCreate these {} commit(s)? [y/N] ", groups.len());
+    std::io::Write::flush(&mut std::io::stdout())?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    if !matches!(answer.trim().to_lowercase().as_str(), "y" | "yes") {
+        println!("❌ Split cancelled, nothing was committed.");
+        return Ok(());
     }
-    
+
+    let committer = Committer::new(&repo)
+        .no_verify(no_verify)
+        .trailers(trailers::for_commit(&repo, config, trailer_options)?);
+    let created = split::execute(&repo, &committer, &units, &groups)?;
+    println!("✅ Created {} commit(s):", created.len());
+    for id in &created {
+        let commit = repo.find_commit(*id)?;
+        println!("   {} {}", short_oid(*id), commit.summary().unwrap_or(""));
+    }
+
     Ok(())
 }
 
+fn print_hook_output(output: &str) {
+    if !output.trim().is_empty() {
+        println!("🪝 Hook output:\n{}", output.trim_end());
+    }
+}
+
 pub async fn code_review(base: &str, config: &Config) -> Result<()> {
     println!("🔍 Performing AI code review since {}...", base);
     
@@ -252,8 +402,11 @@ pub async fn code_review(base: &str, config: &Config) -> Result<()> {
 }
 
 pub fn get_diff_info(base: &str) -> Result<DiffInfo> {
-    let repo = Repository::open(".")?;
-    let head = repo.head()?.peel_to_commit()?;
+    let repo = open_repository()?;
+    let Some(head) = head_commit(&repo)? else {
+        // Nothing has been committed yet, so there is nothing to compare
+        return Ok(DiffInfo::from_commits(Vec::new()).with_workdir(&repo));
+    };
     let base_obj = repo.revparse_single(base)?;
     let base_commit = base_obj.as_commit()
         .context("Could not find base commit")?;
@@ -266,84 +419,293 @@ pub fn get_diff_info(base: &str) -> Result<DiffInfo> {
     for commit_id in walk {
         let commit_id = commit_id?;
         let commit = repo.find_commit(commit_id)?;
-        
-        let message = commit.message().unwrap_or("No message").to_string();
-        let author = commit.author().name().unwrap_or("Unknown").to_string();
-        let date = commit.time().seconds().to_string();
-        
-        // Get diff for this commit
-        let diff = get_commit_diff(&repo, &commit)?;
-        let files_changed = get_files_changed(&diff);
-        
-        commits.push(CommitInfo {
-            hash: commit_id.to_string(),
-            message,
-            author,
-            date,
-            files_changed,
-            diff,
-        });
+        commits.push(commit_info(&repo, &commit)?);
     }
     
-    // Calculate totals before moving commits
-    let total_files_changed = commits.iter()
-        .flat_map(|c| &c.files_changed)
-        .collect::<std::collections::HashSet<_>>()
-        .len();
-    
-    Ok(DiffInfo {
-        commits,
-        total_files_changed,
-        total_additions: 0, // Would need more complex diff analysis
-        total_deletions: 0, // Would need more complex diff analysis
+    Ok(DiffInfo::from_commits(commits).with_workdir(&repo))
+}
+
+/// A commit with its message, author, file changes and diff against its
+/// first parent.
+pub fn commit_info(repo: &Repository, commit: &git2::Commit) -> Result<CommitInfo> {
+    commit_info_for_paths(repo, commit, &[])
+}
+
+/// Like `commit_info`, with the file changes and diff limited to `paths`
+/// (all files when empty).
+pub fn commit_info_for_paths(repo: &Repository, commit: &git2::Commit, paths: &[String]) -> Result<CommitInfo> {
+    let message = commit.message().unwrap_or("No message").to_string();
+    let author = commit.author().name().unwrap_or("Unknown").to_string();
+    let date = commit.time().seconds().to_string();
+    
+    // Get diff for this commit
+    let (diff, file_changes) = get_commit_diff(repo, commit, paths)?;
+    
+    Ok(CommitInfo {
+        hash: commit.id().to_string(),
+        message,
+        author,
+        date,
+        files_changed: files_changed(&file_changes),
+        file_changes,
+        diff,
     })
 }
 
 pub fn get_staged_changes() -> Result<DiffInfo> {
-    let repo = Repository::open(".")?;
+    let repo = open_repository()?;
     
     let mut commits = Vec::new();
     
-    // Get staged changes by comparing HEAD to index
-    let head = repo.head()?;
-    let head_commit = head.peel_to_commit()?;
-    let head_tree = head_commit.tree()?;
+    // Get staged changes by comparing HEAD to index; an unborn branch
+    // compares against the empty tree so everything staged shows as added
+    let head_tree = match head_commit(&repo)? {
+        Some(commit) => Some(commit.tree()?),
+        None => None,
+    };
     
-    let mut index = repo.index()?;
-    let index_tree_id = index.write_tree()?;
+    // Inside git hooks `git commit -a` and `git commit <paths>` stage into a
+    // temporary index advertised through GIT_INDEX_FILE
+    let mut index = match std::env::var_os("GIT_INDEX_FILE") {
+        Some(path) => git2::Index::open(std::path::Path::new(&path))?,
+        None => repo.index()?,
+    };
+    let index_tree_id = index.write_tree_to(&repo)?;
     let index_tree = repo.find_tree(index_tree_id)?;
     
     // Compare HEAD tree to index tree to get staged changes
-    let diff = repo.diff_tree_to_tree(Some(&head_tree), Some(&index_tree), None)?;
-    let diff_str = format_diff(&diff)?;
-    let files_changed = get_files_changed(&diff_str);
+    let mut diff = repo.diff_tree_to_tree(head_tree.as_ref(), Some(&index_tree), None)?;
+    let (diff_str, file_changes) = format_diff(&repo, &mut diff)?;
     
-    if !files_changed.is_empty() {
+    if !file_changes.is_empty() {
         commits.push(CommitInfo {
             hash: "STAGED".to_string(),
             message: "Staged changes".to_string(),
             author: "Current user".to_string(),
             date: chrono::Utc::now().to_rfc3339(),
-            files_changed,
+            files_changed: files_changed(&file_changes),
+            file_changes,
             diff: diff_str,
         });
     }
     
-    // Calculate totals before moving commits
-    let total_files_changed = commits.iter()
-        .flat_map(|c| &c.files_changed)
-        .collect::<std::collections::HashSet<_>>()
-        .len();
-    
-    Ok(DiffInfo {
-        commits,
-        total_files_changed,
-        total_additions: 0,
-        total_deletions: 0,
+    Ok(DiffInfo::from_commits(commits).with_workdir(&repo))
+}
+
+/// Changes of tracked files between HEAD and the work tree, staged or
+/// not, as a single pseudo-commit.
+pub fn get_uncommitted_changes(repo: &Repository) -> Result<DiffInfo> {
+    let head_tree = match head_commit(repo)? {
+        Some(commit) => Some(commit.tree()?),
+        None => None,
+    };
+    let mut diff = repo.diff_tree_to_workdir_with_index(head_tree.as_ref(), None)?;
+    let (diff_str, file_changes) = format_diff(repo, &mut diff)?;
+
+    let mut commits = Vec::new();
+    if !file_changes.is_empty() {
+        commits.push(CommitInfo {
+            hash: "WORKDIR".to_string(),
+            message: "Uncommitted changes".to_string(),
+            author: "Current user".to_string(),
+            date: chrono::Utc::now().to_rfc3339(),
+            files_changed: files_changed(&file_changes),
+            file_changes,
+            diff: diff_str,
+        });
+    }
+    Ok(DiffInfo::from_commits(commits).with_workdir(repo))
+}
+
+/// Opens the repository containing the current directory, searching
+/// parent directories like git does, so commands also work from
+/// subdirectories and linked worktrees.
+pub fn open_repository() -> Result<Repository> {
+    Repository::discover(".").context("Not a git repository (or any of the parent directories)")
+}
+
+/// Resolves a path relative to the top of the work tree. Absolute paths,
+/// and any path outside a repository, are returned unchanged.
+pub fn workdir_path(path: &str) -> PathBuf {
+    let path = Path::new(path);
+    if path.is_absolute() {
+        return path.to_path_buf();
+    }
+    match open_repository().ok().and_then(|repo| repo.workdir().map(Path::to_path_buf)) {
+        Some(workdir) => workdir.join(path),
+        None => path.to_path_buf(),
+    }
+}
+
+/// `path` relative to the top of the work tree, e.g. `src/git.rs`, or
+/// `None` when it lies outside the work tree. Symlinks in existing paths
+/// are resolved first.
+pub fn workdir_relative(repo: &Repository, path: &Path) -> Option<String> {
+    let workdir = repo.workdir()?;
+    let top = workdir.canonicalize().unwrap_or_else(|_| workdir.to_path_buf());
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    let relative = path.strip_prefix(&top).ok()?.to_string_lossy().replace('\\', "/");
+    Some(relative.trim_start_matches("./").trim_end_matches('/').to_string())
+}
+
+/// The character git starts comment lines of commit messages with
+/// (`core.commentChar`, `#` by default).
+pub fn comment_char(repo: &Repository) -> Result<char> {
+    Ok(repo.config()?
+        .get_string("core.commentChar")
+        .ok()
+        .and_then(|c| c.chars().next())
+        .filter(|c| *c != 'a') // "auto" is not a real comment character
+        .unwrap_or('#'))
+}
+
+/// Returns the commit HEAD points at, or `None` on an unborn branch
+/// (a freshly initialised repository before its first commit).
+pub fn head_commit(repo: &Repository) -> Result<Option<git2::Commit<'_>>> {
+    match repo.head() {
+        Ok(head) => Ok(Some(head.peel_to_commit()?)),
+        Err(e) if matches!(e.code(), git2::ErrorCode::UnbornBranch | git2::ErrorCode::NotFound) => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Resolves a revision to the commit it names, e.g. `main`, `v1.2` or `HEAD~2`.
+pub fn resolve_commit(repo: &Repository, rev: &str) -> Result<Oid> {
+    Ok(repo.revparse_single(rev)
+        .with_context(|| format!("Could not resolve {}", rev))?
+        .peel_to_commit()?
+        .id())
+}
+
+/// Resolves `base..tip` to its ends. `base..` and a bare `base` both end at
+/// HEAD, and `..tip` starts at HEAD, like git.
+pub fn resolve_range(repo: &Repository, spec: &str) -> Result<(Option<Oid>, Oid)> {
+    if !spec.contains("..") {
+        return Ok((Some(resolve_commit(repo, spec)?), repo.head()?.peel_to_commit()?.id()));
+    }
+
+    let revspec = repo.revparse(spec)
+        .with_context(|| format!("Could not resolve range {}", spec))?;
+    let base = revspec.from().map(|o| o.peel_to_commit()).transpose()?.map(|c| c.id());
+    let tip = match revspec.to() {
+        Some(to) => to.peel_to_commit()?.id(),
+        None => repo.head()?.peel_to_commit()?.id(),
+    };
+    Ok((base, tip))
+}
+
+/// The branch HEAD points at, including an unborn one, or `None` when
+/// HEAD is detached.
+pub fn current_branch_name(repo: &Repository) -> Option<String> {
+    let head = repo.find_reference("HEAD").ok()?;
+    head.symbolic_target()
+        .and_then(|target| target.strip_prefix("refs/heads/"))
+        .map(str::to_string)
+}
+
+/// Number of entries in the stash.
+pub fn stash_count(repo: &mut Repository) -> Result<usize> {
+    let mut count = 0;
+    repo.stash_foreach(|_, _, _| {
+        count += 1;
+        true
+    })?;
+    Ok(count)
+}
+
+/// Fails when tracked files have uncommitted changes; `action` completes
+/// the message, e.g. "releasing".
+pub fn ensure_clean_worktree(repo: &Repository, action: &str) -> Result<()> {
+    let mut options = git2::StatusOptions::new();
+    options.include_untracked(false).include_ignored(false);
+    if !repo.statuses(Some(&mut options))?.is_empty() {
+        anyhow::bail!("The working tree has uncommitted changes; commit or stash them before {}", action);
+    }
+    Ok(())
+}
+
+/// Runs `git` at the top of `repo`'s work tree, returning stdout and
+/// stderr together. Fails with that output when git does.
+pub fn run_git_in(repo: &Repository, args: &[&str]) -> Result<String> {
+    let output = std::process::Command::new("git")
+        .args(args)
+        .current_dir(repo.workdir().unwrap_or(repo.path()))
+        .output()?;
+    let text = format!("{}{}", String::from_utf8_lossy(&output.stdout), String::from_utf8_lossy(&output.stderr));
+    if !output.status.success() {
+        anyhow::bail!("git {} failed:\n{}", args.join(" "), text.trim_end());
+    }
+    Ok(text)
+}
+
+/// Removes paths from the index, restoring them to their HEAD state.
+/// On an unborn branch the entries are dropped entirely.
+pub fn unstage_paths(paths: &[&str]) -> Result<()> {
+    let repo = open_repository()?;
+    journal::action(&repo, format!("unstage {}", paths.join(" "))).run(|| {
+        let head = head_commit(&repo)?.map(|commit| commit.into_object());
+        repo.reset_default(head.as_ref(), paths)?;
+        Ok(())
+    })
+}
+
+/// Stages paths with `git add`, relative to the top of the work tree.
+pub fn stage_paths(repo: &Repository, paths: &[&str]) -> Result<()> {
+    journal::action(repo, format!("stage {}", paths.join(" "))).run(|| {
+        run_git_in(repo, &[&["add", "--"], paths].concat())?;
+        Ok(())
+    })
+}
+
+/// Stages every modified and untracked file, like `git add *`.
+pub fn stage_all(repo: &Repository) -> Result<()> {
+    journal::action(repo, "stage all changes").run(|| {
+        let mut index = repo.index()?;
+        index.add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None)?;
+        index.write()?;
+        Ok(())
     })
 }
 
-fn get_commit_diff(repo: &Repository, commit: &git2::Commit) -> Result<String> {
+impl DiffInfo {
+    pub fn from_commits(commits: Vec<CommitInfo>) -> Self {
+        let total_files_changed = commits.iter()
+            .flat_map(|c| &c.files_changed)
+            .collect::<std::collections::HashSet<_>>()
+            .len();
+        let total_additions = commits.iter()
+            .flat_map(|c| &c.file_changes)
+            .map(|f| f.additions as i32)
+            .sum();
+        let total_deletions = commits.iter()
+            .flat_map(|c| &c.file_changes)
+            .map(|f| f.deletions as i32)
+            .sum();
+
+        DiffInfo {
+            commits,
+            total_files_changed,
+            total_additions,
+            total_deletions,
+            workdir: None,
+        }
+    }
+
+    pub fn with_workdir(mut self, repo: &Repository) -> Self {
+        self.workdir = repo.workdir().map(Path::to_path_buf);
+        self
+    }
+
+    /// Resolves a repository-relative path against the work tree.
+    pub fn path(&self, relative: &str) -> PathBuf {
+        match &self.workdir {
+            Some(workdir) => workdir.join(relative),
+            None => PathBuf::from(relative),
+        }
+    }
+}
+
+fn get_commit_diff(repo: &Repository, commit: &git2::Commit, paths: &[String]) -> Result<(String, Vec<FileChange>)> {
     let tree = commit.tree()?;
     let parent = if commit.parent_count() > 0 {
         Some(commit.parent(0)?.tree()?)
@@ -351,39 +713,150 @@ fn get_commit_diff(repo: &Repository, commit: &git2::Commit) -> Result<String> {
         None
     };
     
-    let diff = repo.diff_tree_to_tree(parent.as_ref(), Some(&tree), None)?;
-    format_diff(&diff)
+    let mut opts = DiffOptions::new();
+    for path in paths {
+        opts.pathspec(path);
+    }
+    let mut diff = repo.diff_tree_to_tree(parent.as_ref(), Some(&tree), Some(&mut opts))?;
+    format_diff(repo, &mut diff)
 }
 
-fn format_diff(diff: &Diff) -> Result<String> {
-    let mut output = Vec::new();
-    diff.print(DiffFormat::Patch, |_delta, _hunk, line| {
-        let content = std::str::from_utf8(line.content()).unwrap_or("");
-        output.push(content.to_string());
-        true
-    })?;
-    
-    Ok(output.join(""))
-}
-
-fn get_files_changed(diff: &str) -> Vec<String> {
-    diff.lines()
-        .filter(|line| line.starts_with("diff --git") || line.starts_with("+++") || line.starts_with("---"))
-        .filter_map(|line| {
-            if line.starts_with("diff --git") {
-                line.split_whitespace().nth(2).map(|s| s.trim_start_matches("a/").to_string())
-            } else if line.starts_with("+++") || line.starts_with("---") {
-                let path = line.trim_start_matches("+++ ").trim_start_matches("--- ");
-                if !path.starts_with("/dev/null") {
-                    Some(path.trim_start_matches("a/").trim_start_matches("b/").to_string())
-                } else {
-                    None
-                }
-            } else {
-                None
+/// Renders a diff as patch text with rename/copy detection enabled.
+///
+/// Binary files and submodule pointer bumps are collapsed into a one-line
+/// summary instead of raw patch noise, and every delta is also returned as a
+/// structured `FileChange`.
+fn format_diff(repo: &Repository, diff: &mut Diff) -> Result<(String, Vec<FileChange>)> {
+    let mut find_opts = DiffFindOptions::new();
+    find_opts.renames(true).copies(true);
+    diff.find_similar(Some(&mut find_opts))?;
+
+    let mut output = String::new();
+    let mut changes = Vec::new();
+
+    for idx in 0..diff.deltas().len() {
+        let delta = diff.get_delta(idx).context("Diff delta out of range")?;
+        let mut change = file_change_from_delta(&delta);
+
+        if change.submodule {
+            output.push_str(&format!(
+                "Submodule {} {}..{}\n",
+                change.path,
+                short_oid(delta.old_file().id()),
+                short_oid(delta.new_file().id()),
+            ));
+            changes.push(change);
+            continue;
+        }
+
+        let Some(mut patch) = Patch::from_diff(diff, idx)? else {
+            changes.push(change);
+            continue;
+        };
+
+        if patch.delta().flags().is_binary() {
+            change.binary = true;
+            output.push_str(&binary_summary(repo, &change, &delta));
+        } else {
+            let (_, additions, deletions) = patch.line_stats()?;
+            change.additions = additions;
+            change.deletions = deletions;
+            let buf = patch.to_buf()?;
+            output.push_str(&String::from_utf8_lossy(&buf));
+        }
+
+        changes.push(change);
+    }
+
+    Ok((output, changes))
+}
+
+pub(crate) fn file_change_from_delta(delta: &git2::DiffDelta) -> FileChange {
+    let old_file = delta.old_file();
+    let new_file = delta.new_file();
+    let old_path = old_file.path().map(|p| p.to_string_lossy().to_string());
+    let new_path = new_file.path().map(|p| p.to_string_lossy().to_string());
+
+    let kind = match delta.status() {
+        Delta::Added | Delta::Untracked => ChangeKind::Added,
+        Delta::Deleted => ChangeKind::Deleted,
+        Delta::Renamed => ChangeKind::Renamed,
+        Delta::Copied => ChangeKind::Copied,
+        Delta::Typechange => ChangeKind::TypeChanged,
+        _ => ChangeKind::Modified,
+    };
+
+    let path = match kind {
+        ChangeKind::Deleted => old_path.clone(),
+        _ => new_path.clone(),
+    }
+    .unwrap_or_default();
+
+    let mode = |m: FileMode| if m == FileMode::Unreadable { None } else { Some(u32::from(m)) };
+
+    FileChange {
+        old_path: if matches!(kind, ChangeKind::Renamed | ChangeKind::Copied) { old_path } else { None },
+        path,
+        kind,
+        binary: old_file.is_binary() || new_file.is_binary(),
+        submodule: old_file.mode() == FileMode::Commit || new_file.mode() == FileMode::Commit,
+        old_mode: mode(old_file.mode()),
+        new_mode: mode(new_file.mode()),
+        additions: 0,
+        deletions: 0,
+    }
+}
+
+fn binary_summary(repo: &Repository, change: &FileChange, delta: &git2::DiffDelta) -> String {
+    let blob_size = |id: Oid| -> Option<u64> {
+        if id.is_zero() {
+            return None;
+        }
+        repo.find_blob(id).ok().map(|blob| blob.size() as u64)
+    };
+    let old_size = blob_size(delta.old_file().id());
+    let new_size = blob_size(delta.new_file().id());
+
+    let sizes = match (old_size, new_size) {
+        (Some(old), Some(new)) => format!("{} → {}", utils::format_file_size(old), utils::format_file_size(new)),
+        (None, Some(new)) => utils::format_file_size(new),
+        (Some(old), None) => utils::format_file_size(old),
+        (None, None) => "unknown size".to_string(),
+    };
+
+    let action = match change.kind {
+        ChangeKind::Added => "added",
+        ChangeKind::Deleted => "deleted",
+        ChangeKind::Renamed => "renamed",
+        ChangeKind::Copied => "copied",
+        _ => "changed",
+    };
+    match &change.old_path {
+        Some(old) => format!("Binary file {} → {} {} ({})\n", old, change.path, action, sizes),
+        None => format!("Binary file {} {} ({})\n", change.path, action, sizes),
+    }
+}
+
+/// The seven character abbreviation of `id` used in all output.
+pub fn short_oid(id: Oid) -> String {
+    if id.is_zero() {
+        "0000000".to_string()
+    } else {
+        id.to_string()[..7].to_string()
+    }
+}
+
+fn files_changed(changes: &[FileChange]) -> Vec<String> {
+    let mut files = Vec::new();
+    for change in changes {
+        if let Some(old) = &change.old_path {
+            if change.kind == ChangeKind::Renamed && !files.contains(old) {
+                files.push(old.clone());
             }
-        })
-        .collect::<std::collections::HashSet<_>>()
-        .into_iter()
-        .collect()
+        }
+        if !files.contains(&change.path) {
+            files.push(change.path.clone());
+        }
+    }
+    files
 }
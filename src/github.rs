@@ -88,13 +88,7 @@ struct GitRepoInfo {
 }
 
 fn get_git_repo_info() -> Result<GitRepoInfo> {
-    use std::process::Command;
-    
-    let output = Command::new("git")
-        .args(&["remote", "get-url", "origin"])
-        .output()?;
-    
-    let url = String::from_utf8(output.stdout)?;
+    let url = crate::utils::run_git_command(&["remote", "get-url", "origin"])?;
     
     // Parse GitHub URL (supports both HTTPS and SSH formats)
     let url = url.trim();
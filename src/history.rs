@@ -0,0 +1,219 @@
+use anyhow::{Context, Result};
+use git2::{Oid, Repository, Sort};
+use std::collections::HashMap;
+use crate::git;
+use crate::journal;
+
+/// One commit of the log with the lanes drawn to its left.
+#[derive(Debug, Clone)]
+pub struct GraphRow {
+    pub id: Oid,
+    /// Lane drawing, e.g. `● │` for a commit next to another branch or
+    /// `●─╮` for a merge
+    pub graph: String,
+    pub summary: String,
+    pub author: String,
+    /// Commit time in seconds since the epoch
+    pub time: i64,
+    /// Branches and tags pointing at the commit, `HEAD -> main` first
+    pub refs: Vec<String>,
+    pub parent_count: usize,
+}
+
+/// The newest `limit` commits reachable from HEAD, branches and tags, in
+/// topological order, with a lane per line of history like
+/// `git log --graph`.
+pub fn log_graph(repo: &Repository, limit: usize) -> Result<Vec<GraphRow>> {
+    let refs = ref_names(repo)?;
+    let mut walk = repo.revwalk()?;
+    walk.set_sorting(Sort::TOPOLOGICAL | Sort::TIME)?;
+    if git::head_commit(repo)?.is_some() {
+        walk.push_head()?;
+    }
+    for glob in ["refs/heads/*", "refs/remotes/*", "refs/tags/*"] {
+        walk.push_glob(glob)?;
+    }
+
+    // Each lane holds the commit expected next on that line of history
+    let mut lanes: Vec<Option<Oid>> = Vec::new();
+    let mut rows = Vec::new();
+
+    for id in walk.take(limit) {
+        let id = id?;
+        let commit = repo.find_commit(id)?;
+
+        let col = match lanes.iter().position(|lane| *lane == Some(id)) {
+            Some(col) => col,
+            None => match lanes.iter().position(Option::is_none) {
+                Some(free) => free,
+                None => {
+                    lanes.push(None);
+                    lanes.len() - 1
+                }
+            },
+        };
+        let before: Vec<bool> = lanes.iter().map(Option::is_some).collect();
+
+        // Other lanes waiting for this commit end here
+        let converging: Vec<usize> = (0..lanes.len())
+            .filter(|&i| i != col && lanes[i] == Some(id))
+            .collect();
+        for &i in &converging {
+            lanes[i] = None;
+        }
+
+        let parents: Vec<Oid> = commit.parent_ids().collect();
+        lanes[col] = parents.first().copied();
+        let mut opening = Vec::new();
+        for parent in parents.iter().skip(1) {
+            let lane = match lanes.iter().position(|lane| *lane == Some(*parent)) {
+                Some(existing) => existing,
+                None => match lanes.iter().position(Option::is_none) {
+                    Some(free) => {
+                        lanes[free] = Some(*parent);
+                        free
+                    }
+                    None => {
+                        lanes.push(Some(*parent));
+                        lanes.len() - 1
+                    }
+                },
+            };
+            opening.push(lane);
+        }
+
+        let graph = draw_row(col, &before, &converging, &opening, lanes.len());
+        while lanes.last() == Some(&None) {
+            lanes.pop();
+        }
+
+        rows.push(GraphRow {
+            id,
+            graph,
+            summary: commit.summary().unwrap_or("").to_string(),
+            author: commit.author().name().unwrap_or("Unknown").to_string(),
+            time: commit.time().seconds(),
+            refs: refs.get(&id).cloned().unwrap_or_default(),
+            parent_count: parents.len(),
+        });
+    }
+
+    Ok(rows)
+}
+
+fn draw_row(col: usize, before: &[bool], converging: &[usize], opening: &[usize], lane_count: usize) -> String {
+    let width = before.len().max(lane_count).max(col + 1);
+    let mut cells: Vec<char> = (0..width)
+        .map(|i| {
+            if i == col {
+                '●'
+            } else if converging.contains(&i) {
+                if i > col { '╯' } else { '╰' }
+            } else if opening.contains(&i) {
+                if i > col { '╮' } else { '╭' }
+            } else if before.get(i).copied().unwrap_or(false) {
+                '│'
+            } else {
+                ' '
+            }
+        })
+        .collect();
+
+    // Horizontal lines run from the commit to every lane joining or leaving it
+    let ends = converging.iter().chain(opening).copied();
+    let low = ends.clone().chain([col]).min().unwrap_or(col);
+    let high = ends.chain([col]).max().unwrap_or(col);
+    for cell in cells.iter_mut().take(high).skip(low + 1) {
+        *cell = match *cell {
+            ' ' => '─',
+            '│' => '┼',
+            other => other,
+        };
+    }
+
+    let mut graph = String::new();
+    for (i, cell) in cells.iter().enumerate() {
+        if i > 0 {
+            graph.push(if i > low && i <= high { '─' } else { ' ' });
+        }
+        graph.push(*cell);
+    }
+    graph.trim_end().to_string()
+}
+
+/// Branch and tag names by the commit they point at.
+fn ref_names(repo: &Repository) -> Result<HashMap<Oid, Vec<String>>> {
+    let mut names: HashMap<Oid, Vec<String>> = HashMap::new();
+    let head_branch = git::current_branch_name(repo);
+
+    for reference in repo.references()? {
+        let reference = reference?;
+        let Some(full) = reference.name() else {
+            continue;
+        };
+        let name = if let Some(branch) = full.strip_prefix("refs/heads/") {
+            if head_branch.as_deref() == Some(branch) {
+                format!("HEAD -> {}", branch)
+            } else {
+                branch.to_string()
+            }
+        } else if let Some(remote) = full.strip_prefix("refs/remotes/") {
+            if remote.ends_with("/HEAD") {
+                continue;
+            }
+            remote.to_string()
+        } else if let Some(tag) = full.strip_prefix("refs/tags/") {
+            format!("tag: {}", tag)
+        } else {
+            continue;
+        };
+        let Ok(commit) = reference.peel_to_commit() else {
+            continue;
+        };
+        let entry = names.entry(commit.id()).or_default();
+        if name.starts_with("HEAD -> ") {
+            entry.insert(0, name);
+        } else {
+            entry.push(name);
+        }
+    }
+
+    if head_branch.is_none() {
+        if let Some(head) = git::head_commit(repo)? {
+            names.entry(head.id()).or_default().insert(0, "HEAD".to_string());
+        }
+    }
+    Ok(names)
+}
+
+/// Applies the change a commit introduced on top of HEAD with
+/// `git cherry-pick`. Merge commits are picked against their first parent.
+pub fn cherry_pick(repo: &Repository, id: Oid) -> Result<String> {
+    git::ensure_clean_worktree(repo, "cherry-picking")?;
+    let hash = id.to_string();
+    let mut args = vec!["cherry-pick"];
+    if repo.find_commit(id)?.parent_count() > 1 {
+        args.extend(["-m", "1"]);
+    }
+    args.push(&hash);
+    journal::action(repo, format!("cherry-pick {}", git::short_oid(id)))
+        .worktree()
+        .run(|| git::run_git_in(repo, &args))
+        .context("The cherry-pick stopped; resolve the conflicts and run `git cherry-pick --continue`, or `git cherry-pick --abort`")
+}
+
+/// Creates a commit undoing `id` with `git revert`. Merge commits are
+/// reverted against their first parent.
+pub fn revert(repo: &Repository, id: Oid) -> Result<String> {
+    git::ensure_clean_worktree(repo, "reverting")?;
+    let hash = id.to_string();
+    let mut args = vec!["revert", "--no-edit"];
+    if repo.find_commit(id)?.parent_count() > 1 {
+        args.extend(["-m", "1"]);
+    }
+    args.push(&hash);
+    journal::action(repo, format!("revert {}", git::short_oid(id)))
+        .worktree()
+        .run(|| git::run_git_in(repo, &args))
+        .context("The revert stopped; resolve the conflicts and run `git revert --continue`, or `git revert --abort`")
+}
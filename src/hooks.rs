@@ -0,0 +1,158 @@
+use anyhow::{Context, Result};
+use git2::Repository;
+use std::fs;
+use std::path::PathBuf;
+use crate::ai;
+use crate::committer;
+use crate::config::Config;
+use crate::git;
+
+/// First line after the shebang of every hook commit-buddy writes, so we
+/// never overwrite or remove a hook somebody else installed.
+const HOOK_MARKER: &str = "# Installed by commit-buddy";
+
+/// The hooks commit-buddy can install.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HookKind {
+    /// Pre-fills the message of a plain `git commit`
+    PrepareCommitMsg,
+    /// Rejects messages that break the `lint-commits` rules
+    CommitMsg,
+}
+
+impl HookKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            HookKind::PrepareCommitMsg => "prepare-commit-msg",
+            HookKind::CommitMsg => "commit-msg",
+        }
+    }
+
+    /// The commit-buddy arguments the hook script runs with.
+    fn command(self) -> &'static str {
+        match self {
+            HookKind::PrepareCommitMsg => "hook run \"$@\"",
+            HookKind::CommitMsg => "lint-commits --message-file \"$1\"",
+        }
+    }
+}
+
+pub fn install(kind: HookKind, force: bool) -> Result<()> {
+    let repo = git::open_repository()?;
+    let hook_path = hook_path(&repo, kind.name());
+
+    if hook_path.exists() && !is_ours(&hook_path) && !force {
+        anyhow::bail!(
+            "{} already exists and was not installed by commit-buddy. Re-run with --force to replace it.",
+            hook_path.display()
+        );
+    }
+
+    let exe = std::env::current_exe().context("Could not determine the commit-buddy executable path")?;
+    let script = format!(
+        "#!/bin/sh\n{}\nexec \"{}\" {}\n",
+        HOOK_MARKER,
+        exe.display(),
+        kind.command()
+    );
+
+    if let Some(dir) = hook_path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(&hook_path, script)?;
+    make_executable(&hook_path)?;
+
+    println!("✅ Installed {} hook at {}", kind.name(), hook_path.display());
+    match kind {
+        HookKind::PrepareCommitMsg => println!("💡 `git commit` will now open the editor with a suggested message."),
+        HookKind::CommitMsg => println!("💡 `git commit` will now reject messages that fail `commit-buddy lint-commits`."),
+    }
+    Ok(())
+}
+
+pub fn uninstall(kind: HookKind) -> Result<()> {
+    let repo = git::open_repository()?;
+    let hook_path = hook_path(&repo, kind.name());
+
+    if !hook_path.exists() {
+        println!("No {} hook installed.", kind.name());
+        return Ok(());
+    }
+    if !is_ours(&hook_path) {
+        anyhow::bail!("{} was not installed by commit-buddy; leaving it alone.", hook_path.display());
+    }
+
+    fs::remove_file(&hook_path)?;
+    println!("🗑️ Removed {} hook", kind.name());
+    Ok(())
+}
+
+/// Entry point for the installed `prepare-commit-msg` hook.
+///
+/// `source` is the second argument git passes to the hook. Anything other
+/// than a plain `git commit` (messages from `-m`/`-F`, templates, merges,
+/// squashes and amends) is left untouched.
+pub async fn run(message_file: &str, source: Option<&str>, config: &Config) -> Result<()> {
+    if source.is_some_and(|s| !s.is_empty()) {
+        return Ok(());
+    }
+
+    // A failing suggestion must never block the commit itself
+    if let Err(e) = fill_message_file(message_file, config).await {
+        eprintln!("commit-buddy: could not suggest a commit message: {}", e);
+    }
+    Ok(())
+}
+
+async fn fill_message_file(message_file: &str, config: &Config) -> Result<()> {
+    let diff_info = git::get_staged_changes()?;
+    if diff_info.commits.is_empty() {
+        return Ok(());
+    }
+
+    let suggestions = ai::generate_commit_suggestions(&diff_info, config).await?;
+    let Some((best, alternatives)) = suggestions.split_first() else {
+        return Ok(());
+    };
+
+    let repo = git::open_repository()?;
+    let comment = git::comment_char(&repo)?;
+
+    let existing = fs::read_to_string(message_file).unwrap_or_default();
+
+    let mut content = format!("{}\n\n", best);
+    if !alternatives.is_empty() {
+        content.push_str(&format!("{} commit-buddy alternatives:\n", comment));
+        for alternative in alternatives {
+            content.push_str(&format!("{}   {}\n", comment, alternative));
+        }
+    }
+    content.push_str(&existing);
+
+    fs::write(message_file, content)?;
+    Ok(())
+}
+
+fn hook_path(repo: &Repository, name: &str) -> PathBuf {
+    committer::hooks_dir(repo).join(name)
+}
+
+fn is_ours(path: &std::path::Path) -> bool {
+    fs::read_to_string(path)
+        .map(|content| content.contains(HOOK_MARKER))
+        .unwrap_or(false)
+}
+
+#[cfg(unix)]
+fn make_executable(path: &std::path::Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    let mut permissions = fs::metadata(path)?.permissions();
+    permissions.set_mode(0o755);
+    fs::set_permissions(path, permissions)?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn make_executable(_path: &std::path::Path) -> Result<()> {
+    Ok(())
+}
@@ -1,15 +1,26 @@
 use anyhow::Result;
+use crate::committer::Committer;
 use crate::config::Config;
+use crate::conventional::ConventionalCommit;
 use crate::git;
 use crate::ai;
+use crate::blame::{self, BlameSource};
+use crate::branches::{self, BranchInfo, BranchKind, Tracking};
+use crate::changelog;
+use crate::conflicts::{self, ConflictFile, ConflictSuggestion, Resolution};
 use crate::github;
-use git2;
+use crate::history::{self, GraphRow};
+use crate::journal;
+use crate::staging::{self, DiffSide, FileHunk};
+use crate::stash::{self, StashEntry};
+use crate::trailers::{self, TrailerOptions};
+use crate::utils;
 use ratatui::{
     backend::CrosstermBackend,
     layout::{Alignment, Constraint, Direction, Layout},
     style::{Color, Modifier, Style},
     text::{Line, Span, Text},
-    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph},
     Frame, Terminal,
 };
 use crossterm::{
@@ -17,10 +28,16 @@ use crossterm::{
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
+use git2::{Oid, RepositoryState};
+use std::collections::HashMap;
 use std::io;
-use std::process::Command;
 use std::time::{Duration, Instant};
 
+const TABS: [&str; 4] = ["Git Operations", "AI Features", "Utilities", "Log"];
+const LOG_TAB: usize = 3;
+/// Commits loaded into the log tab
+const LOG_LIMIT: usize = 500;
+
 #[derive(Clone)]
 pub struct FileItem {
     pub path: String,
@@ -36,6 +53,17 @@ pub enum FileStatus {
     Deleted,
 }
 
+/// What the text prompt of the branch panel is collecting.
+#[derive(Clone, PartialEq)]
+pub enum BranchInput {
+    /// Fuzzy filter for the branch list
+    Filter,
+    /// Name for a new branch
+    Create,
+    /// New name for the given branch
+    Rename(String),
+}
+
 #[derive(Clone)]
 pub struct GitStatus {
     pub branch: String,
@@ -43,6 +71,9 @@ pub struct GitStatus {
     pub staged_files: Vec<String>,
     pub unstaged_files: Vec<String>,
     pub untracked_files: Vec<String>,
+    pub tracking: Tracking,
+    pub remotes: Vec<String>,
+    pub stash_count: usize,
 }
 
 pub struct InteractiveCli {
@@ -54,9 +85,58 @@ pub struct InteractiveCli {
     pub commit_suggestions: Vec<String>,
     pub commit_list_state: ListState,
     pub in_commit_mode: bool,
+    pub commit_signoff: bool,
+    pub commit_tickets: Vec<String>,
+    pub co_authors: Vec<String>,
+    pub co_author_candidates: Vec<String>,
+    pub co_author_list_state: ListState,
+    pub in_co_author_mode: bool,
+    pub in_remote_mode: bool,
+    pub remote_list_state: ListState,
     pub in_file_mode: bool,
     pub file_items: Vec<FileItem>,
     pub file_list_state: ListState,
+    pub in_hunk_mode: bool,
+    pub hunk_path: String,
+    pub hunk_side: DiffSide,
+    pub hunks: Vec<FileHunk>,
+    pub hunk_list_state: ListState,
+    pub hunk_anchor: Option<usize>,
+    pub hunk_message: String,
+    pub in_branch_mode: bool,
+    pub branches: Vec<BranchInfo>,
+    pub branch_filter: String,
+    pub branch_matches: Vec<usize>,
+    pub branch_list_state: ListState,
+    pub branch_input: Option<BranchInput>,
+    pub branch_input_text: String,
+    pub branch_pending_delete: Option<String>,
+    pub branch_message: String,
+    pub in_stash_mode: bool,
+    pub stashes: Vec<StashEntry>,
+    /// Descriptions by stash commit, so entries are only described once
+    pub stash_descriptions: HashMap<Oid, String>,
+    pub stash_list_state: ListState,
+    /// Message of the stash being created, while it is being typed
+    pub stash_input: Option<String>,
+    pub stash_include_untracked: bool,
+    pub stash_pending_drop: Option<Oid>,
+    pub stash_message: String,
+    pub in_conflict_mode: bool,
+    pub conflict_files: Vec<ConflictFile>,
+    pub conflict_file_state: ListState,
+    pub conflict_hunk: usize,
+    pub conflict_choices: Vec<Option<Resolution>>,
+    pub conflict_suggestion: Option<ConflictSuggestion>,
+    pub conflict_message: String,
+    pub log_rows: Vec<GraphRow>,
+    pub log_message: String,
+    pub in_log_detail_mode: bool,
+    pub log_detail: Option<git::CommitInfo>,
+    pub log_detail_header: String,
+    pub log_scroll: u16,
+    /// Set after handing the terminal to another program, e.g. an editor
+    pub needs_clear: bool,
     pub in_display_mode: bool,
     pub display_content: String,
     pub display_title: String,
@@ -75,6 +155,9 @@ impl InteractiveCli {
                 staged_files: Vec::new(),
                 unstaged_files: Vec::new(),
                 untracked_files: Vec::new(),
+                tracking: Tracking::default(),
+                remotes: Vec::new(),
+                stash_count: 0,
             },
             list_state: ListState::default(),
             current_tab: 0,
@@ -82,9 +165,55 @@ impl InteractiveCli {
             commit_suggestions: Vec::new(),
             commit_list_state: ListState::default(),
             in_commit_mode: false,
+            commit_signoff: false,
+            commit_tickets: Vec::new(),
+            co_authors: Vec::new(),
+            co_author_candidates: Vec::new(),
+            co_author_list_state: ListState::default(),
+            in_co_author_mode: false,
+            in_remote_mode: false,
+            remote_list_state: ListState::default(),
             in_file_mode: false,
             file_items: Vec::new(),
             file_list_state: ListState::default(),
+            in_hunk_mode: false,
+            hunk_path: String::new(),
+            hunk_side: DiffSide::Unstaged,
+            hunks: Vec::new(),
+            hunk_list_state: ListState::default(),
+            hunk_anchor: None,
+            hunk_message: String::new(),
+            in_branch_mode: false,
+            branches: Vec::new(),
+            branch_filter: String::new(),
+            branch_matches: Vec::new(),
+            branch_list_state: ListState::default(),
+            branch_input: None,
+            branch_input_text: String::new(),
+            branch_pending_delete: None,
+            branch_message: String::new(),
+            in_stash_mode: false,
+            stashes: Vec::new(),
+            stash_descriptions: HashMap::new(),
+            stash_list_state: ListState::default(),
+            stash_input: None,
+            stash_include_untracked: false,
+            stash_pending_drop: None,
+            stash_message: String::new(),
+            in_conflict_mode: false,
+            conflict_files: Vec::new(),
+            conflict_file_state: ListState::default(),
+            conflict_hunk: 0,
+            conflict_choices: Vec::new(),
+            conflict_suggestion: None,
+            conflict_message: String::new(),
+            log_rows: Vec::new(),
+            log_message: String::new(),
+            in_log_detail_mode: false,
+            log_detail: None,
+            log_detail_header: String::new(),
+            log_scroll: 0,
+            needs_clear: false,
             in_display_mode: false,
             display_content: String::new(),
             display_title: String::new(),
@@ -109,6 +238,10 @@ impl InteractiveCli {
         // Main event loop
         let mut last_spinner_update = Instant::now();
         loop {
+            if self.needs_clear {
+                terminal.clear()?;
+                self.needs_clear = false;
+            }
             terminal.draw(|f| self.ui(f))?;
 
             // Update spinner if in loading mode
@@ -129,9 +262,38 @@ impl InteractiveCli {
                     if key.kind == KeyEventKind::Press {
                         if self.in_loading_mode {
                             // Only allow quit during loading
+                            if key.code == KeyCode::Char('q') {
+                                self.should_quit = true;
+                            }
+                        } else if self.in_co_author_mode {
                             match key.code {
-                                KeyCode::Char('q') => {
-                                    self.should_quit = true;
+                                KeyCode::Up => {
+                                    self.navigate_co_author(false);
+                                }
+                                KeyCode::Down => {
+                                    self.navigate_co_author(true);
+                                }
+                                KeyCode::Char(' ') => {
+                                    self.toggle_co_author();
+                                }
+                                KeyCode::Enter | KeyCode::Esc => {
+                                    self.in_co_author_mode = false;
+                                }
+                                _ => {}
+                            }
+                        } else if self.in_remote_mode {
+                            match key.code {
+                                KeyCode::Up => {
+                                    self.navigate_remote(false);
+                                }
+                                KeyCode::Down => {
+                                    self.navigate_remote(true);
+                                }
+                                KeyCode::Enter => {
+                                    self.push_to_selected_remote().await?;
+                                }
+                                KeyCode::Esc => {
+                                    self.in_remote_mode = false;
                                 }
                                 _ => {}
                             }
@@ -146,11 +308,204 @@ impl InteractiveCli {
                                 KeyCode::Enter => {
                                     self.execute_commit().await?;
                                 }
+                                KeyCode::Char('s') => {
+                                    self.commit_signoff = !self.commit_signoff;
+                                }
+                                KeyCode::Char('o') => {
+                                    self.enter_co_author_mode();
+                                }
                                 KeyCode::Esc => {
                                     self.exit_commit_mode();
                                 }
                                 _ => {}
                             }
+                        } else if self.in_display_mode {
+                            match key.code {
+                                KeyCode::Esc => {
+                                    self.exit_display_mode();
+                                }
+                                KeyCode::Char('q') => {
+                                    self.should_quit = true;
+                                }
+                                KeyCode::Char('c') => {
+                                    self.copy_to_clipboard().await?;
+                                }
+                                _ => {}
+                            }
+                        } else if self.in_hunk_mode {
+                            match key.code {
+                                KeyCode::Up => {
+                                    self.navigate_hunk_up();
+                                }
+                                KeyCode::Down => {
+                                    self.navigate_hunk_down();
+                                }
+                                KeyCode::Char('n') => {
+                                    self.jump_hunk(true);
+                                }
+                                KeyCode::Char('p') => {
+                                    self.jump_hunk(false);
+                                }
+                                KeyCode::Char('v') => {
+                                    self.toggle_hunk_anchor();
+                                }
+                                KeyCode::Char(' ') => {
+                                    self.apply_hunk_selection(false).await?;
+                                }
+                                KeyCode::Char('l') => {
+                                    self.apply_hunk_selection(true).await?;
+                                }
+                                KeyCode::Tab => {
+                                    self.switch_hunk_side();
+                                }
+                                KeyCode::Char('w') => {
+                                    self.explain_hunk_history().await?;
+                                }
+                                KeyCode::Esc => {
+                                    self.exit_hunk_mode();
+                                }
+                                _ => {}
+                            }
+                        } else if self.in_conflict_mode {
+                            match key.code {
+                                KeyCode::Up => {
+                                    self.navigate_conflict_file(false);
+                                }
+                                KeyCode::Down => {
+                                    self.navigate_conflict_file(true);
+                                }
+                                KeyCode::Char('n') => {
+                                    self.jump_conflict_hunk(true);
+                                }
+                                KeyCode::Char('p') => {
+                                    self.jump_conflict_hunk(false);
+                                }
+                                KeyCode::Char('o') => {
+                                    self.choose_conflict_resolution(Resolution::Ours);
+                                }
+                                KeyCode::Char('t') => {
+                                    self.choose_conflict_resolution(Resolution::Theirs);
+                                }
+                                KeyCode::Char('a') => {
+                                    self.suggest_conflict_resolution().await?;
+                                }
+                                KeyCode::Char('s') => {
+                                    self.accept_conflict_suggestion();
+                                }
+                                KeyCode::Char('e') => {
+                                    self.edit_conflict_hunk()?;
+                                }
+                                KeyCode::Enter => {
+                                    self.mark_conflict_resolved().await?;
+                                }
+                                KeyCode::Esc => {
+                                    self.exit_conflict_mode();
+                                }
+                                _ => {}
+                            }
+                        } else if self.in_branch_mode && self.branch_input.is_some() {
+                            match key.code {
+                                KeyCode::Char(c) => {
+                                    self.branch_input_text.push(c);
+                                    self.on_branch_input_changed();
+                                }
+                                KeyCode::Backspace => {
+                                    self.branch_input_text.pop();
+                                    self.on_branch_input_changed();
+                                }
+                                KeyCode::Enter => {
+                                    self.submit_branch_input().await?;
+                                }
+                                KeyCode::Esc => {
+                                    self.cancel_branch_input();
+                                }
+                                _ => {}
+                            }
+                        } else if self.in_branch_mode {
+                            match key.code {
+                                KeyCode::Up => {
+                                    self.navigate_branch(false);
+                                }
+                                KeyCode::Down => {
+                                    self.navigate_branch(true);
+                                }
+                                KeyCode::Char('/') => {
+                                    self.start_branch_input(BranchInput::Filter);
+                                }
+                                KeyCode::Enter => {
+                                    self.checkout_selected_branch().await?;
+                                }
+                                KeyCode::Char('n') => {
+                                    self.start_create_branch().await?;
+                                }
+                                KeyCode::Char('d') => {
+                                    self.delete_selected_branch().await?;
+                                }
+                                KeyCode::Char('r') => {
+                                    self.start_rename_branch();
+                                }
+                                KeyCode::Char('m') => {
+                                    self.merge_selected_branch(false).await?;
+                                }
+                                KeyCode::Char('b') => {
+                                    self.merge_selected_branch(true).await?;
+                                }
+                                KeyCode::Esc => {
+                                    self.exit_branch_mode();
+                                }
+                                _ => {}
+                            }
+                        } else if self.in_stash_mode && self.stash_input.is_some() {
+                            match key.code {
+                                KeyCode::Char(c) => {
+                                    if let Some(text) = self.stash_input.as_mut() {
+                                        text.push(c);
+                                    }
+                                }
+                                KeyCode::Backspace => {
+                                    if let Some(text) = self.stash_input.as_mut() {
+                                        text.pop();
+                                    }
+                                }
+                                KeyCode::Tab => {
+                                    self.stash_include_untracked = !self.stash_include_untracked;
+                                }
+                                KeyCode::Enter => {
+                                    self.create_stash().await?;
+                                }
+                                KeyCode::Esc => {
+                                    self.stash_input = None;
+                                }
+                                _ => {}
+                            }
+                        } else if self.in_stash_mode {
+                            match key.code {
+                                KeyCode::Up => {
+                                    self.navigate_stash(false);
+                                }
+                                KeyCode::Down => {
+                                    self.navigate_stash(true);
+                                }
+                                KeyCode::Enter => {
+                                    self.show_selected_stash();
+                                }
+                                KeyCode::Char('n') => {
+                                    self.start_create_stash().await?;
+                                }
+                                KeyCode::Char('a') => {
+                                    self.apply_selected_stash(false).await?;
+                                }
+                                KeyCode::Char('p') => {
+                                    self.apply_selected_stash(true).await?;
+                                }
+                                KeyCode::Char('d') => {
+                                    self.drop_selected_stash().await?;
+                                }
+                                KeyCode::Esc => {
+                                    self.exit_stash_mode();
+                                }
+                                _ => {}
+                            }
                         } else if self.in_file_mode {
                             match key.code {
                                 KeyCode::Up => {
@@ -162,6 +517,9 @@ impl InteractiveCli {
                                 KeyCode::Char(' ') => {
                                     self.toggle_file_staging().await?;
                                 }
+                                KeyCode::Enter => {
+                                    self.enter_hunk_mode();
+                                }
                                 KeyCode::Char('a') => {
                                     self.stage_all_files().await?;
                                 }
@@ -173,16 +531,37 @@ impl InteractiveCli {
                                 }
                                 _ => {}
                             }
-                        } else if self.in_display_mode {
+                        } else if self.in_log_detail_mode {
                             match key.code {
-                                KeyCode::Esc => {
-                                    self.exit_display_mode();
+                                KeyCode::Up => {
+                                    self.log_scroll = self.log_scroll.saturating_sub(1);
                                 }
-                                KeyCode::Char('q') => {
-                                    self.should_quit = true;
+                                KeyCode::Down => {
+                                    self.log_scroll = self.log_scroll.saturating_add(1);
+                                }
+                                KeyCode::PageUp => {
+                                    self.log_scroll = self.log_scroll.saturating_sub(20);
+                                }
+                                KeyCode::PageDown => {
+                                    self.log_scroll = self.log_scroll.saturating_add(20);
+                                }
+                                KeyCode::Char('i') => {
+                                    self.improve_selected_commit().await?;
+                                }
+                                KeyCode::Char('x') => {
+                                    self.explain_selected_commit().await?;
                                 }
                                 KeyCode::Char('c') => {
-                                    self.copy_to_clipboard().await?;
+                                    self.apply_selected_commit(false).await?;
+                                }
+                                KeyCode::Char('v') => {
+                                    self.apply_selected_commit(true).await?;
+                                }
+                                KeyCode::Char('y') => {
+                                    self.copy_selected_hash()?;
+                                }
+                                KeyCode::Esc => {
+                                    self.exit_log_detail_mode();
                                 }
                                 _ => {}
                             }
@@ -208,10 +587,22 @@ impl InteractiveCli {
                                 }
                                 KeyCode::Char('r') => {
                                     self.update_git_status().await?;
+                                    if self.current_tab == LOG_TAB {
+                                        self.load_log();
+                                    }
                                 }
                                 KeyCode::Char('f') => {
                                     self.enter_file_mode().await?;
                                 }
+                                KeyCode::Char('b') => {
+                                    self.enter_branch_mode().await?;
+                                }
+                                KeyCode::Char('s') => {
+                                    self.enter_stash_mode().await?;
+                                }
+                                KeyCode::Char('u') => {
+                                    self.undo_last_action().await?;
+                                }
                                 _ => {}
                             }
                         }
@@ -239,10 +630,20 @@ impl InteractiveCli {
             self.render_loading_mode(f);
         } else if self.in_commit_mode {
             self.render_commit_mode(f);
-        } else if self.in_file_mode {
-            self.render_file_mode(f);
         } else if self.in_display_mode {
             self.render_display_mode(f);
+        } else if self.in_hunk_mode {
+            self.render_hunk_mode(f);
+        } else if self.in_conflict_mode {
+            self.render_conflict_mode(f);
+        } else if self.in_branch_mode {
+            self.render_branch_mode(f);
+        } else if self.in_stash_mode {
+            self.render_stash_mode(f);
+        } else if self.in_file_mode {
+            self.render_file_mode(f);
+        } else if self.in_log_detail_mode {
+            self.render_log_detail_mode(f);
         } else {
             self.render_main_ui(f);
         }
@@ -274,8 +675,10 @@ impl InteractiveCli {
 
         // Status bar
         let status_text = format!(
-            "Branch: {} | Status: {} | AI: {}",
+            "Branch: {} → {} | Stashes: {} | Status: {} | AI: {}",
             self.git_status.branch,
+            self.git_status.tracking.describe(),
+            self.git_status.stash_count,
             self.git_status.status,
             if self.config.has_openai_key() {
                 "✅ Enabled"
@@ -291,20 +694,24 @@ impl InteractiveCli {
 
         f.render_widget(status, chunks[1]);
 
-        // Main content area
-        let main_chunks = Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
-            .split(chunks[2]);
+        // Main content area; the log tab uses the full width for the graph
+        if self.current_tab == LOG_TAB {
+            self.render_log(f, chunks[2]);
+        } else {
+            let main_chunks = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+                .split(chunks[2]);
 
-        // Left panel - Menu
-        self.render_menu(f, main_chunks[0]);
+            // Left panel - Menu
+            self.render_menu(f, main_chunks[0]);
 
-        // Right panel - File status
-        self.render_file_status(f, main_chunks[1]);
+            // Right panel - File status
+            self.render_file_status(f, main_chunks[1]);
+        }
 
         // Footer
-        let footer_text = "Press 'q' to quit | 'r' to refresh | 'f' for files | 'Tab' to switch tabs | ↑↓ to navigate | Enter to select";
+        let footer_text = "Press 'q' to quit | 'r' to refresh | 'f' for files | 'b' for branches | 's' for stashes | 'u' to undo | 'Tab' to switch tabs | ↑↓ to navigate | Enter to select";
         let footer = Paragraph::new(Text::styled(
             footer_text,
             Style::default().fg(Color::Gray),
@@ -313,6 +720,32 @@ impl InteractiveCli {
         .block(Block::default().borders(Borders::ALL));
 
         f.render_widget(footer, chunks[3]);
+
+        if self.in_remote_mode {
+            self.render_remote_picker(f);
+        }
+    }
+
+    fn render_remote_picker(&mut self, f: &mut Frame) {
+        let popup_area = centered_rect(50, 40, f.size());
+        f.render_widget(Clear, popup_area);
+
+        let items: Vec<ListItem> = self.git_status.remotes
+            .iter()
+            .map(|remote| ListItem::new(format!("{}/{}", remote, self.git_status.branch)))
+            .collect();
+
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(format!("🚀 {} has no upstream; push and track (Enter, Esc to cancel)", self.git_status.branch))
+                    .title_alignment(Alignment::Center)
+                    .style(Style::default().bg(Color::Black)),
+            )
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+
+        f.render_stateful_widget(list, popup_area, &mut self.remote_list_state);
     }
 
     fn render_commit_mode(&mut self, f: &mut Frame) {
@@ -339,9 +772,27 @@ impl InteractiveCli {
 
         f.render_widget(header, chunks[0]);
 
-        // Instructions
+        // Instructions and the trailers the commit will get
+        let mut trailer_summary = Vec::new();
+        if self.commit_signoff || self.config.signoff {
+            trailer_summary.push("✍️ Signed-off-by".to_string());
+        }
+        for ticket in &self.commit_tickets {
+            trailer_summary.push(format!("🎫 {}: {}", self.config.ticket_trailer, ticket));
+        }
+        if !self.co_authors.is_empty() {
+            trailer_summary.push(format!("👥 {} co-author(s)", self.co_authors.len()));
+        }
+        let trailer_line = if trailer_summary.is_empty() {
+            "Trailers: none".to_string()
+        } else {
+            format!("Trailers: {}", trailer_summary.join(" | "))
+        };
         let instructions = Paragraph::new(Text::styled(
-            "🤖 AI has generated conventional commit message suggestions.\nSelect one with ↑↓ and press Enter to commit, or 'Esc' to cancel.",
+            format!(
+                "🤖 AI has generated conventional commit message suggestions.\nSelect one with ↑↓ and press Enter to commit, or 'Esc' to cancel.\n{}",
+                trailer_line
+            ),
             Style::default().fg(Color::Yellow),
         ))
         .block(Block::default().borders(Borders::ALL));
@@ -360,8 +811,11 @@ impl InteractiveCli {
                 } else {
                     Style::default().fg(Color::White)
                 };
+                let emoji = ConventionalCommit::parse(suggestion)
+                    .map(|parsed| parsed.emoji())
+                    .unwrap_or("📝");
                 ListItem::new(Line::from(Span::styled(
-                    format!("{}. {}", i + 1, suggestion),
+                    format!("{}. {} {}", i + 1, emoji, suggestion),
                     style,
                 )))
             })
@@ -379,7 +833,7 @@ impl InteractiveCli {
         f.render_stateful_widget(list, chunks[2], &mut self.commit_list_state);
 
         // Footer
-        let footer_text = "Press ↑↓ to navigate | Enter to select | Esc to cancel";
+        let footer_text = "Press ↑↓ to navigate | Enter to select | 's' sign-off | 'o' co-authors | Esc to cancel";
         let footer = Paragraph::new(Text::styled(
             footer_text,
             Style::default().fg(Color::Gray),
@@ -388,6 +842,43 @@ impl InteractiveCli {
         .block(Block::default().borders(Borders::ALL));
 
         f.render_widget(footer, chunks[3]);
+
+        if self.in_co_author_mode {
+            self.render_co_author_picker(f);
+        }
+    }
+
+    fn render_co_author_picker(&mut self, f: &mut Frame) {
+        let popup_area = centered_rect(60, 50, f.size());
+        f.render_widget(Clear, popup_area);
+
+        let items: Vec<ListItem> = if self.co_author_candidates.is_empty() {
+            vec![ListItem::new("No other authors found in history or .mailmap")]
+        } else {
+            self.co_author_candidates
+                .iter()
+                .map(|author| {
+                    let (mark, style) = if self.co_authors.contains(author) {
+                        ("[x]", Style::default().fg(Color::Green))
+                    } else {
+                        ("[ ]", Style::default().fg(Color::White))
+                    };
+                    ListItem::new(Line::from(Span::styled(format!("{} {}", mark, author), style)))
+                })
+                .collect()
+        };
+
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title("👥 Co-authors (Space to toggle, Enter to close)")
+                    .title_alignment(Alignment::Center)
+                    .style(Style::default().bg(Color::Black)),
+            )
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+
+        f.render_stateful_widget(list, popup_area, &mut self.co_author_list_state);
     }
 
     fn render_file_mode(&mut self, f: &mut Frame) {
@@ -416,7 +907,7 @@ impl InteractiveCli {
 
         // Instructions
         let instructions = Paragraph::new(Text::styled(
-            "Use ↑↓ to navigate files | Space to stage/unstage | 'a' to stage all | 'u' to unstage all | Esc to return",
+            "Use ↑↓ to navigate files | Space to stage/unstage | Enter to stage individual hunks | 'a' to stage all | 'u' to unstage all | Esc to return",
             Style::default().fg(Color::Yellow),
         ))
         .block(Block::default().borders(Borders::ALL));
@@ -424,7 +915,31 @@ impl InteractiveCli {
         f.render_widget(instructions, chunks[1]);
 
         // File list
-        let items: Vec<ListItem> = self.file_items
+        let list = List::new(self.file_list_items())
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title("Files")
+                    .title_alignment(Alignment::Center),
+            )
+            .highlight_style(Style::default().add_modifier(Modifier::BOLD));
+
+        f.render_stateful_widget(list, chunks[2], &mut self.file_list_state);
+
+        // Footer
+        let footer_text = "Space: Toggle | Enter: Hunks | 'a': Stage All | 'u': Unstage All | Esc: Back";
+        let footer = Paragraph::new(Text::styled(
+            footer_text,
+            Style::default().fg(Color::Gray),
+        ))
+        .alignment(Alignment::Center)
+        .block(Block::default().borders(Borders::ALL));
+
+        f.render_widget(footer, chunks[3]);
+    }
+
+    fn file_list_items(&self) -> Vec<ListItem<'static>> {
+        self.file_items
             .iter()
             .enumerate()
             .map(|(i, file)| {
@@ -453,22 +968,298 @@ impl InteractiveCli {
                     style,
                 )))
             })
-            .collect();
+            .collect()
+    }
 
-        let list = List::new(items)
+    fn render_hunk_mode(&mut self, f: &mut Frame) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(1)
+            .constraints([
+                Constraint::Length(3), // Header
+                Constraint::Min(0),    // Files and hunks
+                Constraint::Length(3), // Footer
+            ])
+            .split(f.size());
+
+        let side = match self.hunk_side {
+            DiffSide::Unstaged => "unstaged",
+            DiffSide::Staged => "staged",
+        };
+        let header = Paragraph::new(Text::styled(
+            format!("🧩 {} ({} changes)", self.hunk_path, side),
+            Style::default()
+                .fg(Color::Blue)
+                .add_modifier(Modifier::BOLD),
+        ))
+        .alignment(Alignment::Center)
+        .block(Block::default().borders(Borders::ALL));
+
+        f.render_widget(header, chunks[0]);
+
+        let body = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(30), Constraint::Percentage(70)])
+            .split(chunks[1]);
+
+        // File list stays visible and is refreshed after every change
+        let files = List::new(self.file_list_items())
             .block(
                 Block::default()
                     .borders(Borders::ALL)
                     .title("Files")
                     .title_alignment(Alignment::Center),
-            )
-            .highlight_style(Style::default().add_modifier(Modifier::BOLD));
+            );
+        f.render_stateful_widget(files, body[0], &mut self.file_list_state);
+
+        let rows = self.hunk_rows();
+        let cursor = self.hunk_list_state.selected().unwrap_or(0);
+        let marked = self.hunk_anchor.map(|anchor| (anchor.min(cursor), anchor.max(cursor)));
+
+        let items: Vec<ListItem> = if rows.is_empty() {
+            vec![ListItem::new(Line::from(Span::styled(
+                format!("No {} changes in this file (Tab to switch)", side),
+                Style::default().fg(Color::Gray),
+            )))]
+        } else {
+            rows.iter()
+                .enumerate()
+                .map(|(row, (hunk_idx, line_idx))| {
+                    let hunk = &self.hunks[*hunk_idx];
+                    let (text, mut style) = match line_idx {
+                        None => (
+                            hunk.header.clone(),
+                            Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+                        ),
+                        Some(line_idx) => {
+                            let line = &hunk.lines[*line_idx];
+                            let color = match line.origin {
+                                '+' => Color::Green,
+                                '-' => Color::Red,
+                                _ => Color::Gray,
+                            };
+                            (format!("{}{}", line.origin, line.text()), Style::default().fg(color))
+                        }
+                    };
+                    if marked.is_some_and(|(start, end)| row >= start && row <= end) {
+                        style = style.add_modifier(Modifier::UNDERLINED);
+                    }
+                    ListItem::new(Line::from(Span::styled(text, style)))
+                })
+                .collect()
+        };
 
-        f.render_stateful_widget(list, chunks[2], &mut self.file_list_state);
+        let title = if self.hunk_message.is_empty() {
+            "Hunks".to_string()
+        } else {
+            self.hunk_message.clone()
+        };
+        let hunks = List::new(items)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(title)
+                    .title_alignment(Alignment::Center),
+            )
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        f.render_stateful_widget(hunks, body[1], &mut self.hunk_list_state);
 
-        // Footer
-        let footer_text = "Space: Toggle | 'a': Stage All | 'u': Unstage All | Esc: Back";
-        let footer = Paragraph::new(Text::styled(
+        let action = match self.hunk_side {
+            DiffSide::Unstaged => "Stage",
+            DiffSide::Staged => "Unstage",
+        };
+        let footer_text = format!(
+            "↑↓: Move | n/p: Next/Prev Hunk | Space: {} Hunk/Range | l: {} Line | v: Mark Range | w: Why | Tab: Staged/Unstaged | Esc: Back",
+            action, action
+        );
+        let footer = Paragraph::new(Text::styled(
+            footer_text,
+            Style::default().fg(Color::Gray),
+        ))
+        .alignment(Alignment::Center)
+        .block(Block::default().borders(Borders::ALL));
+
+        f.render_widget(footer, chunks[2]);
+    }
+
+    fn render_branch_mode(&mut self, f: &mut Frame) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(1)
+            .constraints([
+                Constraint::Length(3), // Header
+                Constraint::Length(3), // Filter or name prompt
+                Constraint::Min(0),    // Branch list
+                Constraint::Length(3), // Footer
+            ])
+            .split(f.size());
+
+        let header = Paragraph::new(Text::styled(
+            format!("🌿 Branches ({})", self.branches.len()),
+            Style::default()
+                .fg(Color::Green)
+                .add_modifier(Modifier::BOLD),
+        ))
+        .alignment(Alignment::Center)
+        .block(Block::default().borders(Borders::ALL));
+
+        f.render_widget(header, chunks[0]);
+
+        let (prompt, text) = match &self.branch_input {
+            Some(BranchInput::Filter) => ("Filter", format!("{}▏", self.branch_input_text)),
+            Some(BranchInput::Create) => ("New branch", format!("{}▏", self.branch_input_text)),
+            Some(BranchInput::Rename(name)) => ("Rename", format!("{} → {}▏", name, self.branch_input_text)),
+            None if self.branch_filter.is_empty() => ("Filter", "press '/' to filter".to_string()),
+            None => ("Filter", self.branch_filter.clone()),
+        };
+        let input = Paragraph::new(Text::styled(text, Style::default().fg(Color::Yellow)))
+            .block(Block::default().borders(Borders::ALL).title(prompt));
+
+        f.render_widget(input, chunks[1]);
+
+        let items: Vec<ListItem> = if self.branch_matches.is_empty() {
+            vec![ListItem::new(Line::from(Span::styled(
+                "No matching branches",
+                Style::default().fg(Color::Gray),
+            )))]
+        } else {
+            self.branch_matches.iter()
+                .map(|&i| {
+                    let branch = &self.branches[i];
+                    let marker = if branch.is_head { "*" } else { " " };
+                    let counts = match branch.ahead_behind {
+                        Some((0, 0)) => "=".to_string(),
+                        Some((ahead, behind)) => format!("↑{} ↓{}", ahead, behind),
+                        None => String::new(),
+                    };
+                    let date = chrono::DateTime::from_timestamp(branch.last_time, 0)
+                        .map(|d| d.format("%Y-%m-%d").to_string())
+                        .unwrap_or_default();
+                    let color = match (branch.is_head, branch.kind) {
+                        (true, _) => Color::Green,
+                        (false, BranchKind::Local) => Color::White,
+                        (false, BranchKind::Remote) => Color::Red,
+                    };
+                    ListItem::new(Line::from(vec![
+                        Span::styled(format!("{} {:<30} ", marker, branch.name), Style::default().fg(color)),
+                        Span::styled(format!("{:<10} ", counts), Style::default().fg(Color::Cyan)),
+                        Span::styled(format!("{} ", date), Style::default().fg(Color::Gray)),
+                        Span::raw(utils::truncate_string(&branch.last_summary, 60)),
+                    ]))
+                })
+                .collect()
+        };
+
+        let title = if self.branch_message.is_empty() {
+            "Local and remote branches".to_string()
+        } else {
+            self.branch_message.clone()
+        };
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(title)
+                    .title_alignment(Alignment::Center),
+            )
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+
+        f.render_stateful_widget(list, chunks[2], &mut self.branch_list_state);
+
+        let footer_text = if self.branch_input.is_some() {
+            "Type to edit | Enter: Confirm | Esc: Cancel"
+        } else {
+            "↑↓: Move | /: Filter | Enter: Checkout | n: New | d: Delete | r: Rename | m: Merge | b: Rebase | Esc: Back"
+        };
+        let footer = Paragraph::new(Text::styled(
+            footer_text,
+            Style::default().fg(Color::Gray),
+        ))
+        .alignment(Alignment::Center)
+        .block(Block::default().borders(Borders::ALL));
+
+        f.render_widget(footer, chunks[3]);
+    }
+
+    fn render_stash_mode(&mut self, f: &mut Frame) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(1)
+            .constraints([
+                Constraint::Length(3), // Header
+                Constraint::Length(3), // New stash prompt
+                Constraint::Min(0),    // Stash list
+                Constraint::Length(3), // Footer
+            ])
+            .split(f.size());
+
+        let header = Paragraph::new(Text::styled(
+            format!("📦 Stashes ({})", self.stashes.len()),
+            Style::default()
+                .fg(Color::Magenta)
+                .add_modifier(Modifier::BOLD),
+        ))
+        .alignment(Alignment::Center)
+        .block(Block::default().borders(Borders::ALL));
+
+        f.render_widget(header, chunks[0]);
+
+        let (prompt, text) = match &self.stash_input {
+            Some(text) => {
+                let untracked = if self.stash_include_untracked { "x" } else { " " };
+                (format!("New stash ([{}] include untracked files)", untracked), format!("{}▏", text))
+            }
+            None => ("New stash".to_string(), "press 'n' to stash your uncommitted changes".to_string()),
+        };
+        let input = Paragraph::new(Text::styled(text, Style::default().fg(Color::Yellow)))
+            .block(Block::default().borders(Borders::ALL).title(prompt));
+
+        f.render_widget(input, chunks[1]);
+
+        let items: Vec<ListItem> = if self.stashes.is_empty() {
+            vec![ListItem::new(Line::from(Span::styled(
+                "The stash is empty",
+                Style::default().fg(Color::Gray),
+            )))]
+        } else {
+            self.stashes.iter()
+                .map(|entry| {
+                    let date = chrono::DateTime::from_timestamp(entry.time, 0)
+                        .map(|d| d.format("%Y-%m-%d %H:%M").to_string())
+                        .unwrap_or_default();
+                    let description = self.stash_descriptions.get(&entry.id).cloned().unwrap_or_default();
+                    ListItem::new(Line::from(vec![
+                        Span::styled(format!("{:<11} ", entry.name()), Style::default().fg(Color::Cyan)),
+                        Span::styled(format!("{} ", date), Style::default().fg(Color::Gray)),
+                        Span::raw(format!("{:<62} ", utils::truncate_string(&description, 60))),
+                        Span::styled(utils::truncate_string(&entry.message, 50), Style::default().fg(Color::Gray)),
+                    ]))
+                })
+                .collect()
+        };
+
+        let title = if self.stash_message.is_empty() {
+            "Newest first".to_string()
+        } else {
+            self.stash_message.clone()
+        };
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(title)
+                    .title_alignment(Alignment::Center),
+            )
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+
+        f.render_stateful_widget(list, chunks[2], &mut self.stash_list_state);
+
+        let footer_text = if self.stash_input.is_some() {
+            "Type the message | Tab: Toggle untracked files | Enter: Stash | Esc: Cancel"
+        } else {
+            "↑↓: Move | Enter: Show diff | n: New | a: Apply | p: Pop | d: Drop | Esc: Back"
+        };
+        let footer = Paragraph::new(Text::styled(
             footer_text,
             Style::default().fg(Color::Gray),
         ))
@@ -478,14 +1269,144 @@ impl InteractiveCli {
         f.render_widget(footer, chunks[3]);
     }
 
+    fn render_conflict_mode(&mut self, f: &mut Frame) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(1)
+            .constraints([
+                Constraint::Length(3), // Header
+                Constraint::Min(0),    // Files and hunk
+                Constraint::Length(3), // Footer
+            ])
+            .split(f.size());
+
+        let header = Paragraph::new(Text::styled(
+            format!("⚔️ Merge Conflicts ({} file(s))", self.conflict_files.len()),
+            Style::default()
+                .fg(Color::Red)
+                .add_modifier(Modifier::BOLD),
+        ))
+        .alignment(Alignment::Center)
+        .block(Block::default().borders(Borders::ALL));
+
+        f.render_widget(header, chunks[0]);
+
+        let body = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(25), Constraint::Percentage(75)])
+            .split(chunks[1]);
+
+        let files: Vec<ListItem> = self.conflict_files.iter()
+            .map(|file| {
+                let detail = if file.binary {
+                    "binary".to_string()
+                } else if file.ours_deleted || file.theirs_deleted {
+                    "modify/delete".to_string()
+                } else {
+                    format!("{} hunk(s)", file.hunks().len())
+                };
+                ListItem::new(Line::from(Span::styled(
+                    format!("{} ({})", file.path, detail),
+                    Style::default().fg(Color::Yellow),
+                )))
+            })
+            .collect();
+        let files = List::new(files)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title("Files")
+                    .title_alignment(Alignment::Center),
+            )
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        f.render_stateful_widget(files, body[0], &mut self.conflict_file_state);
+
+        let right = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(8)])
+            .split(body[1]);
+        let sides = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(34), Constraint::Percentage(33), Constraint::Percentage(33)])
+            .split(right[0]);
+
+        let count = self.conflict_hunk_count();
+        let (ours, base, theirs) = match self.current_conflict_file() {
+            Some(file) if file.binary => (
+                "(binary file)".to_string(),
+                "(binary file)".to_string(),
+                "(binary file)".to_string(),
+            ),
+            Some(file) => match file.hunks().get(self.conflict_hunk) {
+                Some(hunk) => (
+                    if file.ours_deleted { "(deleted)".to_string() } else { hunk.ours.clone() },
+                    hunk.base.clone().unwrap_or_else(|| "(added on both sides)".to_string()),
+                    if file.theirs_deleted { "(deleted)".to_string() } else { hunk.theirs.clone() },
+                ),
+                None => Default::default(),
+            },
+            None => Default::default(),
+        };
+        let choice = self.conflict_choices.get(self.conflict_hunk).cloned().flatten();
+        let pane = |title: String, text: String, color: Color, chosen: bool| {
+            let title = if chosen { format!("{} ✅", title) } else { title };
+            Paragraph::new(Text::styled(text, Style::default().fg(color)))
+                .block(Block::default().borders(Borders::ALL).title(title))
+        };
+        f.render_widget(
+            pane(format!("Ours (hunk {}/{})", (self.conflict_hunk + 1).min(count), count), ours, Color::Green, choice == Some(Resolution::Ours)),
+            sides[0],
+        );
+        f.render_widget(pane("Base".to_string(), base, Color::Gray, false), sides[1]);
+        f.render_widget(pane("Theirs".to_string(), theirs, Color::Cyan, choice == Some(Resolution::Theirs)), sides[2]);
+
+        let (title, text) = match (&choice, &self.conflict_suggestion) {
+            (Some(Resolution::Text(text)), _) => ("Resolution ✅".to_string(), text.clone()),
+            (_, Some(suggestion)) => (
+                "🤖 Suggestion (s to accept)".to_string(),
+                format!("{}\n💡 {}", suggestion.resolution, suggestion.reasoning),
+            ),
+            _ => ("Resolution".to_string(), "Press 'a' for an AI suggestion".to_string()),
+        };
+        let title = if self.conflict_message.is_empty() {
+            title
+        } else {
+            format!("{} | {}", title, self.conflict_message)
+        };
+        let resolution = Paragraph::new(Text::styled(text, Style::default().fg(Color::White)))
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .wrap(ratatui::widgets::Wrap { trim: false });
+        f.render_widget(resolution, right[1]);
+
+        let footer_text = "↑↓: File | n/p: Hunk | o: Ours | t: Theirs | a: Ask AI | s: Accept Suggestion | e: Edit | Enter: Mark Resolved | Esc: Back";
+        let footer = Paragraph::new(Text::styled(
+            footer_text,
+            Style::default().fg(Color::Gray),
+        ))
+        .alignment(Alignment::Center)
+        .block(Block::default().borders(Borders::ALL));
+
+        f.render_widget(footer, chunks[2]);
+    }
+
     fn render_loading_mode(&mut self, f: &mut Frame) {
         // Render the normal UI first
         if self.in_commit_mode {
             self.render_commit_mode(f);
-        } else if self.in_file_mode {
-            self.render_file_mode(f);
         } else if self.in_display_mode {
             self.render_display_mode(f);
+        } else if self.in_hunk_mode {
+            self.render_hunk_mode(f);
+        } else if self.in_conflict_mode {
+            self.render_conflict_mode(f);
+        } else if self.in_branch_mode {
+            self.render_branch_mode(f);
+        } else if self.in_stash_mode {
+            self.render_stash_mode(f);
+        } else if self.in_file_mode {
+            self.render_file_mode(f);
+        } else if self.in_log_detail_mode {
+            self.render_log_detail_mode(f);
         } else {
             self.render_main_ui(f);
         }
@@ -599,8 +1520,7 @@ impl InteractiveCli {
     }
 
     fn render_menu(&mut self, f: &mut Frame, area: ratatui::layout::Rect) {
-        let tabs = vec!["Git Operations", "AI Features", "Utilities"];
-        let current_tab = tabs[self.current_tab];
+        let current_tab = TABS[self.current_tab];
 
         let menu_items = match self.current_tab {
             0 => vec![
@@ -609,9 +1529,10 @@ impl InteractiveCli {
                 "💾 Commit changes",
                 "🚀 Push to remote",
                 "📥 Pull from remote",
-                "🌿 Switch branch",
-                "🔀 Merge branch",
+                "🌿 Branches (b)",
+                "⚔️ Resolve conflicts",
                 "📋 View status",
+                "📦 Stashes (s)",
             ],
             1 => vec![
                 "✨ Generate PR description",
@@ -625,6 +1546,7 @@ impl InteractiveCli {
             2 => vec![
                 "🔄 Refresh status",
                 "⚙️ Configuration",
+                "↩️ Undo last action (u)",
                 "❌ Exit",
             ],
             _ => vec![],
@@ -657,6 +1579,119 @@ impl InteractiveCli {
         f.render_stateful_widget(list, area, &mut self.list_state);
     }
 
+    fn render_log(&mut self, f: &mut Frame, area: ratatui::layout::Rect) {
+        let items: Vec<ListItem> = if self.log_rows.is_empty() {
+            vec![ListItem::new(Line::from(Span::styled(
+                "No commits yet",
+                Style::default().fg(Color::Gray),
+            )))]
+        } else {
+            self.log_rows.iter()
+                .map(|row| {
+                    let date = chrono::DateTime::from_timestamp(row.time, 0)
+                        .map(|d| d.format("%Y-%m-%d").to_string())
+                        .unwrap_or_default();
+                    let mut spans = vec![
+                        Span::styled(format!("{} ", row.graph), Style::default().fg(Color::Magenta)),
+                        Span::styled(format!("{} ", &row.id.to_string()[..7]), Style::default().fg(Color::Yellow)),
+                    ];
+                    if !row.refs.is_empty() {
+                        spans.push(Span::styled(
+                            format!("({}) ", row.refs.join(", ")),
+                            Style::default().fg(Color::Green).add_modifier(Modifier::BOLD),
+                        ));
+                    }
+                    spans.push(Span::styled(row.summary.clone(), Style::default().fg(Color::White)));
+                    spans.push(Span::styled(format!("  {} {}", row.author, date), Style::default().fg(Color::Gray)));
+                    ListItem::new(Line::from(spans))
+                })
+                .collect()
+        };
+
+        let title = if self.log_message.is_empty() {
+            format!("{} | ↑↓ to navigate | Enter for details", TABS[LOG_TAB])
+        } else {
+            format!("{} | {}", TABS[LOG_TAB], self.log_message)
+        };
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(title)
+                    .title_alignment(Alignment::Center),
+            )
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+
+        f.render_stateful_widget(list, area, &mut self.list_state);
+    }
+
+    fn render_log_detail_mode(&mut self, f: &mut Frame) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(1)
+            .constraints([
+                Constraint::Length(3), // Header
+                Constraint::Min(0),    // Metadata, stats and diff
+                Constraint::Length(3), // Footer
+            ])
+            .split(f.size());
+
+        let Some(commit) = &self.log_detail else {
+            return;
+        };
+
+        let header = Paragraph::new(Text::styled(
+            format!("📜 {} {}", &commit.hash[..8], commit.message.lines().next().unwrap_or("")),
+            Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+        ))
+        .alignment(Alignment::Center)
+        .block(Block::default().borders(Borders::ALL));
+
+        f.render_widget(header, chunks[0]);
+
+        let mut lines: Vec<Line> = self.log_detail_header.lines()
+            .map(|line| Line::from(Span::styled(line.to_string(), Style::default().fg(Color::White))))
+            .collect();
+        lines.push(Line::from(""));
+        for line in commit.diff.lines() {
+            let color = if line.starts_with("+++") || line.starts_with("---") {
+                Color::White
+            } else if line.starts_with('+') {
+                Color::Green
+            } else if line.starts_with('-') {
+                Color::Red
+            } else if line.starts_with("@@") {
+                Color::Cyan
+            } else {
+                Color::Gray
+            };
+            lines.push(Line::from(Span::styled(line.to_string(), Style::default().fg(color))));
+        }
+
+        let title = if self.log_message.is_empty() {
+            "Commit".to_string()
+        } else {
+            self.log_message.clone()
+        };
+        let body = Paragraph::new(Text::from(lines))
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .scroll((self.log_scroll, 0));
+
+        f.render_widget(body, chunks[1]);
+
+        let footer_text = "↑↓/PgUp/PgDn: Scroll | i: Improve Message | x: Explain | c: Cherry-pick | v: Revert | y: Copy Hash | Esc: Back";
+        let footer = Paragraph::new(Text::styled(
+            footer_text,
+            Style::default().fg(Color::Gray),
+        ))
+        .alignment(Alignment::Center)
+        .block(Block::default().borders(Borders::ALL));
+
+        f.render_widget(footer, chunks[2]);
+    }
+
     fn render_file_status(&mut self, f: &mut Frame, area: ratatui::layout::Rect) {
         let chunks = Layout::default()
             .direction(Direction::Vertical)
@@ -727,7 +1762,10 @@ impl InteractiveCli {
 
     fn navigate_up(&mut self) {
         let current = self.list_state.selected().unwrap_or(0);
-        let max_items = self.get_current_menu_items().len();
+        let max_items = self.current_item_count();
+        if max_items == 0 {
+            return;
+        }
         if current > 0 {
             self.list_state.select(Some(current - 1));
         } else {
@@ -737,7 +1775,10 @@ impl InteractiveCli {
 
     fn navigate_down(&mut self) {
         let current = self.list_state.selected().unwrap_or(0);
-        let max_items = self.get_current_menu_items().len();
+        let max_items = self.current_item_count();
+        if max_items == 0 {
+            return;
+        }
         if current < max_items - 1 {
             self.list_state.select(Some(current + 1));
         } else {
@@ -746,17 +1787,32 @@ impl InteractiveCli {
     }
 
     fn next_tab(&mut self) {
-        self.current_tab = (self.current_tab + 1) % 3;
+        self.current_tab = (self.current_tab + 1) % TABS.len();
         self.list_state.select(Some(0));
+        if self.current_tab == LOG_TAB {
+            self.load_log();
+        }
     }
 
     fn prev_tab(&mut self) {
         self.current_tab = if self.current_tab > 0 {
             self.current_tab - 1
         } else {
-            2
+            TABS.len() - 1
         };
         self.list_state.select(Some(0));
+        if self.current_tab == LOG_TAB {
+            self.load_log();
+        }
+    }
+
+    /// Rows in the current tab: menu entries, or commits in the log tab.
+    fn current_item_count(&self) -> usize {
+        if self.current_tab == LOG_TAB {
+            self.log_rows.len()
+        } else {
+            self.get_current_menu_items().len()
+        }
     }
 
     fn get_current_menu_items(&self) -> Vec<&str> {
@@ -767,9 +1823,10 @@ impl InteractiveCli {
                 "💾 Commit changes",
                 "🚀 Push to remote",
                 "📥 Pull from remote",
-                "🌿 Switch branch",
-                "🔀 Merge branch",
+                "🌿 Branches (b)",
+                "⚔️ Resolve conflicts",
                 "📋 View status",
+                "📦 Stashes (s)",
             ],
             1 => vec![
                 "✨ Generate PR description",
@@ -783,6 +1840,7 @@ impl InteractiveCli {
             2 => vec![
                 "🔄 Refresh status",
                 "⚙️ Configuration",
+                "↩️ Undo last action (u)",
                 "❌ Exit",
             ],
             _ => vec![],
@@ -796,6 +1854,7 @@ impl InteractiveCli {
             0 => self.handle_git_operation(selected).await?,
             1 => self.handle_ai_operation(selected).await?,
             2 => self.handle_utility(selected).await?,
+            LOG_TAB => self.enter_log_detail_mode(selected)?,
             _ => {}
         }
         
@@ -811,9 +1870,10 @@ impl InteractiveCli {
             2 => self.commit_changes().await?,
             3 => self.push_to_remote().await?,
             4 => self.pull_from_remote().await?,
-            5 => self.switch_branch().await?,
-            6 => self.merge_branch().await?,
+            5 => self.enter_branch_mode().await?,
+            6 => self.show_conflicts().await?,
             7 => self.view_status().await?,
+            8 => self.enter_stash_mode().await?,
             _ => {}
         }
         Ok(())
@@ -837,7 +1897,8 @@ impl InteractiveCli {
         match selected {
             0 => self.update_git_status().await?,
             1 => self.show_configuration().await?,
-            2 => self.should_quit = true,
+            2 => self.undo_last_action().await?,
+            3 => self.should_quit = true,
             _ => {}
         }
         Ok(())
@@ -845,16 +1906,10 @@ impl InteractiveCli {
 
     async fn update_git_status(&mut self) -> Result<()> {
         // Get current branch
-        let output = Command::new("git")
-            .args(&["branch", "--show-current"])
-            .output()?;
-        self.git_status.branch = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        self.git_status.branch = utils::get_current_branch()?;
 
         // Get git status
-        let output = Command::new("git")
-            .args(&["status", "--porcelain"])
-            .output()?;
-        let status_output = String::from_utf8_lossy(&output.stdout);
+        let status_output = utils::get_git_status()?;
 
         // Parse status
         self.git_status.staged_files.clear();
@@ -890,18 +1945,19 @@ impl InteractiveCli {
             format!("{} files changed", total_changes)
         };
 
+        if let Ok(mut repo) = git::open_repository() {
+            self.git_status.tracking = branches::tracking(&repo).unwrap_or_default();
+            self.git_status.remotes = branches::remotes(&repo).unwrap_or_default();
+            self.git_status.stash_count = git::stash_count(&mut repo).unwrap_or(0);
+        }
+
         Ok(())
     }
 
     async fn add_files_to_staging(&mut self) -> Result<()> {
         // Simple implementation - stage all changes
-        let output = Command::new("git")
-            .args(&["add", "."])
-            .output()?;
-        
-        if output.status.success() {
-            // Files staged successfully
-        }
+        let repo = git::open_repository()?;
+        git::stage_paths(&repo, &["."])?;
         
         Ok(())
     }
@@ -912,60 +1968,93 @@ impl InteractiveCli {
     }
 
     async fn push_to_remote(&mut self) -> Result<()> {
-        let output = Command::new("git")
-            .args(&["push"])
-            .output()?;
-        
-        if output.status.success() {
-            // Push successful
+        self.update_git_status().await?;
+        if self.git_status.tracking.upstream.is_some() {
+            return self.push(None).await;
+        }
+
+        // The first push sets the upstream, so ask where it should go
+        if self.git_status.remotes.is_empty() {
+            self.display_title = "❌ Push failed".to_string();
+            self.display_content = "No remote is configured. Add one with `git remote add origin <url>`.".to_string();
+            self.in_display_mode = true;
         } else {
-            // Push failed - could show error message in TUI
+            self.remote_list_state.select(Some(0));
+            self.in_remote_mode = true;
         }
-        
         Ok(())
     }
 
-    async fn pull_from_remote(&mut self) -> Result<()> {
-        let output = Command::new("git")
-            .args(&["pull"])
-            .output()?;
-        
-        if output.status.success() {
-            // Pull successful
-        } else {
-            // Pull failed - could show error message in TUI
+    fn navigate_remote(&mut self, down: bool) {
+        let count = self.git_status.remotes.len();
+        if count == 0 {
+            return;
         }
-        
+        let i = self.remote_list_state.selected().unwrap_or(0);
+        let next = if down { (i + 1) % count } else { (i + count - 1) % count };
+        self.remote_list_state.select(Some(next));
+    }
+
+    async fn push_to_selected_remote(&mut self) -> Result<()> {
+        self.in_remote_mode = false;
+        let remote = self.remote_list_state.selected()
+            .and_then(|i| self.git_status.remotes.get(i))
+            .cloned();
+        self.push(remote).await
+    }
+
+    async fn push(&mut self, remote: Option<String>) -> Result<()> {
+        let result = git::open_repository().and_then(|repo| branches::push(&repo, remote.as_deref()));
+        self.update_git_status().await?;
+        let (title, output) = match result {
+            Ok(output) => ("✅ Pushed", output),
+            Err(e) => ("❌ Push failed", format!("{:#}", e)),
+        };
+        self.show_sync_result(title, &output);
         Ok(())
     }
 
-    async fn switch_branch(&mut self) -> Result<()> {
-        // Simple implementation - could be enhanced with branch selection
-        let output = Command::new("git")
-            .args(&["checkout", "-b", "new-branch"])
-            .output()?;
-        
-        if output.status.success() {
-            // Branch created successfully
-        } else {
-            // Branch creation failed
+    async fn pull_from_remote(&mut self) -> Result<()> {
+        let result = git::open_repository().and_then(|repo| branches::pull(&repo));
+        self.update_git_status().await?;
+        match result {
+            Ok(output) => self.show_sync_result("✅ Pulled", &output),
+            Err(e) => {
+                if !self.open_conflicts().await? {
+                    self.show_sync_result("❌ Pull failed", &format!("{:#}", e));
+                }
+            }
         }
-        
         Ok(())
     }
 
-    async fn merge_branch(&mut self) -> Result<()> {
-        // Simple implementation
-        let output = Command::new("git")
-            .args(&["merge", "main"])
-            .output()?;
-        
-        if output.status.success() {
-            // Merge successful
-        } else {
-            // Merge failed
-        }
-        
+    /// Shows the output of a push or pull with where the branch now stands.
+    fn show_sync_result(&mut self, title: &str, output: &str) {
+        self.display_title = title.to_string();
+        self.display_content = format!(
+            "{}\n\nBranch: {} → {}\nStashes: {}",
+            output.trim(),
+            self.git_status.branch,
+            self.git_status.tracking.describe(),
+            self.git_status.stash_count
+        );
+        self.in_display_mode = true;
+    }
+
+    /// Undoes the newest journaled action and shows what was restored.
+    async fn undo_last_action(&mut self) -> Result<()> {
+        let result = git::open_repository().and_then(|repo| journal::undo(&repo, 1, false));
+        self.update_git_status().await?;
+        let (title, content) = match result {
+            Ok(undone) => (
+                "↩️ Undone",
+                undone.iter().map(|entry| entry.summary()).collect::<Vec<_>>().join("\n"),
+            ),
+            Err(e) => ("❌ Undo failed", format!("{:#}", e)),
+        };
+        self.display_title = title.to_string();
+        self.display_content = content;
+        self.in_display_mode = true;
         Ok(())
     }
 
@@ -983,13 +2072,8 @@ impl InteractiveCli {
     async fn start_interactive_commit(&mut self, all: bool) -> Result<()> {
         if all {
             // Stage all changes
-            let output = Command::new("git")
-                .args(&["add", "."])
-                .output()?;
-            
-            if output.status.success() {
-                // Files staged successfully
-            }
+            let repo = git::open_repository()?;
+            git::stage_paths(&repo, &["."])?;
         }
 
         self.start_loading("Generating commit suggestions...".to_string());
@@ -1017,6 +2101,15 @@ impl InteractiveCli {
 
         self.stop_loading();
 
+        // Ticket references are derived from the branch name
+        self.commit_tickets = match (&self.config.ticket_pattern, git::open_repository()) {
+            (Some(pattern), Ok(repo)) => {
+                let branch = git::current_branch_name(&repo).unwrap_or_default();
+                trailers::ticket_ids(&branch, pattern).unwrap_or_default()
+            }
+            _ => Vec::new(),
+        };
+
         // Enter commit mode
         self.in_commit_mode = true;
         self.commit_list_state.select(Some(0));
@@ -1046,30 +2139,35 @@ impl InteractiveCli {
         let selected = self.commit_list_state.selected().unwrap_or(0);
         
         if selected < self.commit_suggestions.len() {
-            let commit_message = &self.commit_suggestions[selected];
-            
-            // Perform the actual commit
-            let repo = git2::Repository::open(".")?;
-            let mut index = repo.index()?;
-            let tree_id = index.write_tree()?;
-            let tree = repo.find_tree(tree_id)?;
-            
-            let signature = repo.signature()?;
-            let head = repo.head()?;
-            let parent_commit = head.peel_to_commit()?;
+            let commit_message = self.commit_suggestions[selected].clone();
             
-            let _commit_id = repo.commit(
-                Some("HEAD"),
-                &signature,
-                &signature,
-                commit_message,
-                &tree,
-                &[&parent_commit],
-            )?;
+            // Perform the actual commit through the hook-aware committer
+            let repo = git::open_repository()?;
+            let trailer_options = TrailerOptions {
+                signoff: self.commit_signoff,
+                co_authors: self.co_authors.clone(),
+            };
+            let result = trailers::for_commit(&repo, &self.config, &trailer_options)
+                .and_then(|trailers| Committer::new(&repo).trailers(trailers).commit(&commit_message));
             
             // Exit commit mode and refresh status
             self.exit_commit_mode();
             self.update_git_status().await?;
+            
+            // Surface hook output (or the hook failure) instead of hiding it
+            match result {
+                Ok(outcome) if !outcome.hook_output.trim().is_empty() => {
+                    self.display_title = format!("✅ Committed {}", &outcome.id.to_string()[..8]);
+                    self.display_content = outcome.hook_output;
+                    self.in_display_mode = true;
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    self.display_title = "❌ Commit aborted".to_string();
+                    self.display_content = e.to_string();
+                    self.in_display_mode = true;
+                }
+            }
         }
         
         Ok(())
@@ -1079,6 +2177,42 @@ impl InteractiveCli {
         self.in_commit_mode = false;
         self.commit_suggestions.clear();
         self.commit_list_state.select(None);
+        self.commit_signoff = false;
+        self.commit_tickets.clear();
+        self.co_authors.clear();
+    }
+
+    fn enter_co_author_mode(&mut self) {
+        if self.co_author_candidates.is_empty() {
+            self.co_author_candidates = git::open_repository()
+                .and_then(|repo| trailers::recent_authors(&repo, 20))
+                .unwrap_or_default();
+        }
+        self.co_author_list_state.select(Some(0));
+        self.in_co_author_mode = true;
+    }
+
+    fn navigate_co_author(&mut self, down: bool) {
+        let count = self.co_author_candidates.len();
+        if count == 0 {
+            return;
+        }
+        let current = self.co_author_list_state.selected().unwrap_or(0);
+        let next = if down { (current + 1) % count } else { (current + count - 1) % count };
+        self.co_author_list_state.select(Some(next));
+    }
+
+    fn toggle_co_author(&mut self) {
+        let selected = self.co_author_list_state.selected().unwrap_or(0);
+        let Some(author) = self.co_author_candidates.get(selected) else {
+            return;
+        };
+        match self.co_authors.iter().position(|a| a == author) {
+            Some(position) => {
+                self.co_authors.remove(position);
+            }
+            None => self.co_authors.push(author.clone()),
+        }
     }
 
     // File mode methods
@@ -1102,10 +2236,7 @@ impl InteractiveCli {
         self.file_items.clear();
         
         // Get git status
-        let output = Command::new("git")
-            .args(&["status", "--porcelain"])
-            .output()?;
-        let status_output = String::from_utf8_lossy(&output.stdout);
+        let status_output = utils::get_git_status()?;
 
         for line in status_output.lines() {
             if line.len() >= 2 {
@@ -1157,34 +2288,13 @@ impl InteractiveCli {
             
             match file.status {
                 FileStatus::Staged => {
-                    // Unstage the file
-                    let output = Command::new("git")
-                        .args(&["reset", "HEAD", "--", &file.path])
-                        .output()?;
-                    
-                    if output.status.success() {
-                        // File unstaged successfully - status will be updated by refresh
-                    }
-                }
-                FileStatus::Modified | FileStatus::Untracked => {
-                    // Stage the file
-                    let output = Command::new("git")
-                        .args(&["add", &file.path])
-                        .output()?;
-                    
-                    if output.status.success() {
-                        // File staged successfully - status will be updated by refresh
-                    }
+                    // Unstage the file (works on unborn branches too)
+                    git::unstage_paths(&[&file.path])?;
                 }
-                FileStatus::Deleted => {
-                    // Handle deleted files
-                    let output = Command::new("git")
-                        .args(&["rm", &file.path])
-                        .output()?;
-                    
-                    if output.status.success() {
-                        // File removed successfully - status will be updated by refresh
-                    }
+                FileStatus::Modified | FileStatus::Untracked | FileStatus::Deleted => {
+                    // Stage the file; `git add` records deletions too
+                    let repo = git::open_repository()?;
+                    git::stage_paths(&repo, &[&file.path])?;
                 }
             }
             
@@ -1199,13 +2309,8 @@ impl InteractiveCli {
     }
 
     async fn stage_all_files(&mut self) -> Result<()> {
-        let output = Command::new("git")
-            .args(&["add", "."])
-            .output()?;
-        
-        if output.status.success() {
-            // Files staged successfully
-        }
+        let repo = git::open_repository()?;
+        git::stage_paths(&repo, &["."])?;
         
         self.load_file_items().await?;
         // Refresh the main git status
@@ -1214,13 +2319,7 @@ impl InteractiveCli {
     }
 
     async fn unstage_all_files(&mut self) -> Result<()> {
-        let output = Command::new("git")
-            .args(&["reset", "HEAD", "--", "."])
-            .output()?;
-        
-        if output.status.success() {
-            // Files unstaged successfully
-        }
+        git::unstage_paths(&["*"])?;
         
         self.load_file_items().await?;
         // Refresh the main git status
@@ -1228,6 +2327,934 @@ impl InteractiveCli {
         Ok(())
     }
 
+    fn enter_hunk_mode(&mut self) {
+        let selected = self.file_list_state.selected().unwrap_or(0);
+        let Some(file) = self.file_items.get(selected) else {
+            return;
+        };
+
+        self.hunk_path = file.path.clone();
+        self.hunk_side = match file.status {
+            FileStatus::Staged => DiffSide::Staged,
+            _ => DiffSide::Unstaged,
+        };
+        self.hunk_anchor = None;
+        self.hunk_message.clear();
+        self.in_hunk_mode = true;
+        self.load_hunks();
+
+        // A partially staged file shows as staged; fall back to whichever side has changes
+        if self.hunks.is_empty() && self.hunk_message.is_empty() {
+            self.hunk_side = self.hunk_side.other();
+            self.load_hunks();
+        }
+        self.hunk_list_state.select(Some(0));
+    }
+
+    fn exit_hunk_mode(&mut self) {
+        self.in_hunk_mode = false;
+        self.hunks.clear();
+        self.hunk_anchor = None;
+        self.hunk_message.clear();
+        self.hunk_list_state.select(None);
+    }
+
+    fn load_hunks(&mut self) {
+        let result = git::open_repository()
+            .and_then(|repo| staging::file_hunks(&repo, &self.hunk_path, self.hunk_side));
+        match result {
+            Ok(hunks) => self.hunks = hunks,
+            Err(e) => {
+                self.hunks.clear();
+                self.hunk_message = format!("❌ {}", e);
+            }
+        }
+
+        let rows = self.hunk_rows().len();
+        let cursor = self.hunk_list_state.selected().unwrap_or(0);
+        self.hunk_list_state.select(Some(cursor.min(rows.saturating_sub(1))));
+    }
+
+    /// One row per hunk header and per diff line: (hunk index, line index).
+    fn hunk_rows(&self) -> Vec<(usize, Option<usize>)> {
+        self.hunks.iter()
+            .enumerate()
+            .flat_map(|(hunk_idx, hunk)| {
+                std::iter::once((hunk_idx, None))
+                    .chain((0..hunk.lines.len()).map(move |line_idx| (hunk_idx, Some(line_idx))))
+            })
+            .collect()
+    }
+
+    fn navigate_hunk_up(&mut self) {
+        let current = self.hunk_list_state.selected().unwrap_or(0);
+        if current > 0 {
+            self.hunk_list_state.select(Some(current - 1));
+        }
+    }
+
+    fn navigate_hunk_down(&mut self) {
+        let current = self.hunk_list_state.selected().unwrap_or(0);
+        if current + 1 < self.hunk_rows().len() {
+            self.hunk_list_state.select(Some(current + 1));
+        }
+    }
+
+    fn jump_hunk(&mut self, forward: bool) {
+        let rows = self.hunk_rows();
+        let current = self.hunk_list_state.selected().unwrap_or(0);
+        let headers: Vec<usize> = rows.iter()
+            .enumerate()
+            .filter(|(_, (_, line))| line.is_none())
+            .map(|(row, _)| row)
+            .collect();
+
+        let target = if forward {
+            headers.iter().find(|row| **row > current)
+        } else {
+            headers.iter().rev().find(|row| **row < current)
+        };
+        if let Some(row) = target {
+            self.hunk_list_state.select(Some(*row));
+        }
+    }
+
+    fn toggle_hunk_anchor(&mut self) {
+        self.hunk_anchor = match self.hunk_anchor {
+            Some(_) => None,
+            None => self.hunk_list_state.selected(),
+        };
+    }
+
+    fn switch_hunk_side(&mut self) {
+        self.hunk_side = self.hunk_side.other();
+        self.hunk_anchor = None;
+        self.hunk_message.clear();
+        self.hunk_list_state.select(Some(0));
+        self.load_hunks();
+    }
+
+    /// Blames the lines of the hunk under the cursor and explains why they
+    /// look the way they do. Unstaged hunks are blamed in the work tree,
+    /// staged ones at HEAD, the version their unstaging restores.
+    async fn explain_hunk_history(&mut self) -> Result<()> {
+        let rows = self.hunk_rows();
+        let cursor = self.hunk_list_state.selected().unwrap_or(0);
+        let Some(&(hunk_idx, _)) = rows.get(cursor) else {
+            return Ok(());
+        };
+        let hunk = &self.hunks[hunk_idx];
+        let (source, start, count) = match self.hunk_side {
+            DiffSide::Unstaged => (BlameSource::Workdir, hunk.new_start, hunk.new_lines),
+            DiffSide::Staged => (BlameSource::Head, hunk.old_start, hunk.old_lines),
+        };
+        if count == 0 {
+            self.hunk_message = "These lines have no history yet".to_string();
+            return Ok(());
+        }
+        let (start, end) = (start as usize, (start + count - 1) as usize);
+        let path = self.hunk_path.clone();
+
+        self.start_loading("Reading the history of these lines...".to_string());
+        let result = match git::open_repository() {
+            Ok(repo) => blame::explain_lines(&repo, &path, start, end, source, &self.config).await,
+            Err(e) => Err(e),
+        };
+        self.stop_loading();
+
+        match result {
+            Ok((table, explanation)) => {
+                self.display_title = format!("💡 Why {}:{}-{}", path, start, end);
+                self.display_content = format!("{}\n{}", table, explanation.trim());
+                self.in_display_mode = true;
+            }
+            Err(e) => self.hunk_message = format!("❌ {}", e),
+        }
+        Ok(())
+    }
+
+    /// Stages or unstages, depending on the side shown, the hunk under the
+    /// cursor, the marked range within it, or just the current line.
+    async fn apply_hunk_selection(&mut self, single_line: bool) -> Result<()> {
+        let rows = self.hunk_rows();
+        let cursor = self.hunk_list_state.selected().unwrap_or(0);
+        let Some(&(hunk_idx, line_idx)) = rows.get(cursor) else {
+            return Ok(());
+        };
+
+        let selection = if single_line {
+            match line_idx {
+                Some(line) if self.hunks[hunk_idx].lines[line].is_change() => Some(line..=line),
+                _ => {
+                    self.hunk_message = "Move to a +/- line to apply a single line".to_string();
+                    return Ok(());
+                }
+            }
+        } else if let Some(anchor) = self.hunk_anchor {
+            // Ranges never cross into another hunk
+            let (start, end) = (anchor.min(cursor), anchor.max(cursor));
+            let lines: Vec<usize> = rows[start..=end].iter()
+                .filter(|(hunk, _)| *hunk == hunk_idx)
+                .filter_map(|(_, line)| *line)
+                .collect();
+            match (lines.first(), lines.last()) {
+                (Some(first), Some(last)) => Some(*first..=*last),
+                _ => None,
+            }
+        } else {
+            None
+        };
+
+        let hunk = self.hunks[hunk_idx].clone();
+        let result = git::open_repository()
+            .and_then(|repo| staging::apply_hunk(&repo, &self.hunk_path, self.hunk_side, &hunk, selection));
+
+        self.hunk_message = match (result, self.hunk_side) {
+            (Ok(()), DiffSide::Unstaged) => "✅ Staged".to_string(),
+            (Ok(()), DiffSide::Staged) => "✅ Unstaged".to_string(),
+            (Err(e), _) => format!("❌ {}", e),
+        };
+        self.hunk_anchor = None;
+        self.load_hunks();
+
+        // Keep the file list live while staying on the same file
+        self.load_file_items().await?;
+        if let Some(position) = self.file_items.iter().position(|f| f.path == self.hunk_path) {
+            self.file_list_state.select(Some(position));
+        }
+        self.update_git_status().await?;
+        Ok(())
+    }
+
+    // Branch mode methods
+    async fn enter_branch_mode(&mut self) -> Result<()> {
+        self.branch_filter.clear();
+        self.branch_input = None;
+        self.branch_input_text.clear();
+        self.branch_pending_delete = None;
+        self.branch_message.clear();
+        self.in_branch_mode = true;
+        self.load_branches();
+        self.branch_list_state.select(Some(0));
+        Ok(())
+    }
+
+    fn exit_branch_mode(&mut self) {
+        if !self.branch_filter.is_empty() {
+            // The first Esc only clears the filter
+            self.branch_filter.clear();
+            self.refilter_branches();
+            return;
+        }
+        self.in_branch_mode = false;
+        self.branches.clear();
+        self.branch_matches.clear();
+        self.branch_message.clear();
+        self.branch_list_state.select(None);
+    }
+
+    fn load_branches(&mut self) {
+        match git::open_repository().and_then(|repo| branches::list(&repo)) {
+            Ok(list) => self.branches = list,
+            Err(e) => {
+                self.branches.clear();
+                self.branch_message = format!("❌ {}", e);
+            }
+        }
+        self.refilter_branches();
+    }
+
+    fn refilter_branches(&mut self) {
+        self.branch_matches = branches::filter(&self.branches, &self.branch_filter);
+        let cursor = self.branch_list_state.selected().unwrap_or(0);
+        self.branch_list_state.select(Some(cursor.min(self.branch_matches.len().saturating_sub(1))));
+    }
+
+    fn selected_branch(&self) -> Option<BranchInfo> {
+        let selected = self.branch_list_state.selected()?;
+        self.branch_matches.get(selected).map(|&i| self.branches[i].clone())
+    }
+
+    fn navigate_branch(&mut self, down: bool) {
+        let count = self.branch_matches.len();
+        if count == 0 {
+            return;
+        }
+        let current = self.branch_list_state.selected().unwrap_or(0);
+        let next = if down { (current + 1) % count } else { (current + count - 1) % count };
+        self.branch_list_state.select(Some(next));
+        self.branch_pending_delete = None;
+    }
+
+    fn start_branch_input(&mut self, input: BranchInput) {
+        self.branch_input_text = match input {
+            BranchInput::Filter => self.branch_filter.clone(),
+            _ => String::new(),
+        };
+        self.branch_input = Some(input);
+    }
+
+    fn on_branch_input_changed(&mut self) {
+        // The filter narrows the list while typing
+        if self.branch_input == Some(BranchInput::Filter) {
+            self.branch_filter = self.branch_input_text.clone();
+            self.branch_list_state.select(Some(0));
+            self.refilter_branches();
+        }
+    }
+
+    fn cancel_branch_input(&mut self) {
+        if self.branch_input == Some(BranchInput::Filter) {
+            self.branch_filter.clear();
+            self.refilter_branches();
+        }
+        self.branch_input = None;
+        self.branch_input_text.clear();
+    }
+
+    async fn submit_branch_input(&mut self) -> Result<()> {
+        let Some(input) = self.branch_input.take() else {
+            return Ok(());
+        };
+        let text = std::mem::take(&mut self.branch_input_text).trim().to_string();
+
+        let result = match input {
+            BranchInput::Filter => return Ok(()),
+            BranchInput::Create => git::open_repository()
+                .and_then(|repo| branches::create(&repo, &text))
+                .map(|_| format!("✅ Created and switched to {}", text)),
+            BranchInput::Rename(name) => git::open_repository()
+                .and_then(|repo| branches::rename(&repo, &name, &text))
+                .map(|_| format!("✅ Renamed {} to {}", name, text)),
+        };
+        self.finish_branch_action(result).await
+    }
+
+    async fn checkout_selected_branch(&mut self) -> Result<()> {
+        let Some(branch) = self.selected_branch() else {
+            return Ok(());
+        };
+        let result = git::open_repository()
+            .and_then(|repo| branches::checkout(&repo, &branch))
+            .map(|name| format!("✅ Switched to {}", name));
+        self.finish_branch_action(result).await
+    }
+
+    /// Opens the name prompt pre-filled with a name suggested from the
+    /// staged changes.
+    async fn start_create_branch(&mut self) -> Result<()> {
+        self.start_loading("Suggesting a branch name...".to_string());
+        let diff_info = git::get_staged_changes()?;
+        let suggestion = if diff_info.commits.is_empty() {
+            String::new()
+        } else {
+            ai::suggest_branch_name(&diff_info, &self.config).await.unwrap_or_default()
+        };
+        self.stop_loading();
+
+        self.start_branch_input(BranchInput::Create);
+        self.branch_input_text = suggestion;
+        Ok(())
+    }
+
+    fn start_rename_branch(&mut self) {
+        match self.selected_branch() {
+            Some(branch) if branch.kind == BranchKind::Local => {
+                self.start_branch_input(BranchInput::Rename(branch.name.clone()));
+                self.branch_input_text = branch.name;
+            }
+            Some(_) => self.branch_message = "Only local branches can be renamed".to_string(),
+            None => {}
+        }
+    }
+
+    /// Deletes the selected local branch; an unmerged branch needs a second
+    /// press to be deleted anyway.
+    async fn delete_selected_branch(&mut self) -> Result<()> {
+        let Some(branch) = self.selected_branch() else {
+            return Ok(());
+        };
+        if branch.kind == BranchKind::Remote {
+            self.branch_message = "Only local branches can be deleted".to_string();
+            return Ok(());
+        }
+
+        let force = self.branch_pending_delete.as_deref() == Some(branch.name.as_str());
+        let result = git::open_repository()
+            .and_then(|repo| branches::delete(&repo, &branch.name, force));
+        match result {
+            Ok(()) => {
+                self.branch_pending_delete = None;
+                self.finish_branch_action(Ok(format!("✅ Deleted {}", branch.name))).await?;
+            }
+            Err(e) => {
+                self.branch_pending_delete = Some(branch.name);
+                self.branch_message = format!("❌ {}", e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Merges the selected branch into the current one, or rebases the
+    /// current branch onto it.
+    async fn merge_selected_branch(&mut self, rebase: bool) -> Result<()> {
+        let Some(branch) = self.selected_branch() else {
+            return Ok(());
+        };
+        if branch.is_head {
+            self.branch_message = "Select a branch other than the current one".to_string();
+            return Ok(());
+        }
+
+        let result = git::open_repository().and_then(|repo| {
+            if rebase {
+                branches::rebase(&repo, &branch.name)
+            } else {
+                branches::merge(&repo, &branch.name)
+            }
+        });
+        match result {
+            Ok(_) if rebase => self.finish_branch_action(Ok(format!("✅ Rebased onto {}", branch.name))).await,
+            Ok(_) => self.finish_branch_action(Ok(format!("✅ Merged {}", branch.name))).await,
+            Err(e) => {
+                self.branch_filter.clear();
+                self.exit_branch_mode();
+                if !self.open_conflicts().await? {
+                    // Anything else needs more room than the list title
+                    self.display_title = if rebase { "❌ Rebase stopped" } else { "❌ Merge stopped" }.to_string();
+                    self.display_content = format!("{:#}", e);
+                    self.in_display_mode = true;
+                }
+                self.update_git_status().await
+            }
+        }
+    }
+
+    async fn finish_branch_action(&mut self, result: Result<String>) -> Result<()> {
+        self.branch_message = match result {
+            Ok(message) => message,
+            Err(e) => format!("❌ {}", e),
+        };
+        self.load_branches();
+        self.update_git_status().await
+    }
+
+    // Stash mode methods
+    async fn enter_stash_mode(&mut self) -> Result<()> {
+        self.stash_input = None;
+        self.stash_pending_drop = None;
+        self.stash_message.clear();
+        self.in_stash_mode = true;
+        self.stash_list_state.select(Some(0));
+        self.load_stashes().await;
+        Ok(())
+    }
+
+    fn exit_stash_mode(&mut self) {
+        self.in_stash_mode = false;
+        self.stashes.clear();
+        self.stash_message.clear();
+        self.stash_list_state.select(None);
+    }
+
+    /// Reloads the stash list and describes the entries not seen before.
+    async fn load_stashes(&mut self) {
+        match git::open_repository().and_then(|mut repo| stash::list(&mut repo)) {
+            Ok(list) => self.stashes = list,
+            Err(e) => {
+                self.stashes.clear();
+                self.stash_message = format!("❌ {}", e);
+            }
+        }
+        let cursor = self.stash_list_state.selected().unwrap_or(0);
+        self.stash_list_state.select(Some(cursor.min(self.stashes.len().saturating_sub(1))));
+
+        let undescribed: Vec<StashEntry> = self.stashes.iter()
+            .filter(|entry| !self.stash_descriptions.contains_key(&entry.id))
+            .cloned()
+            .collect();
+        if undescribed.is_empty() {
+            return;
+        }
+        let Ok(repo) = git::open_repository() else {
+            return;
+        };
+        self.start_loading("Describing stashes...".to_string());
+        for entry in undescribed {
+            let description = match stash::changes(&repo, &entry) {
+                Ok(diff_info) => ai::describe_stash(&diff_info, &self.config).await.unwrap_or_default(),
+                Err(_) => String::new(),
+            };
+            self.stash_descriptions.insert(entry.id, description);
+        }
+        self.stop_loading();
+    }
+
+    fn selected_stash(&self) -> Option<StashEntry> {
+        self.stash_list_state.selected().and_then(|i| self.stashes.get(i)).cloned()
+    }
+
+    fn navigate_stash(&mut self, down: bool) {
+        let count = self.stashes.len();
+        if count == 0 {
+            return;
+        }
+        let current = self.stash_list_state.selected().unwrap_or(0);
+        let next = if down { (current + 1) % count } else { (current + count - 1) % count };
+        self.stash_list_state.select(Some(next));
+        self.stash_pending_drop = None;
+    }
+
+    fn show_selected_stash(&mut self) {
+        let Some(entry) = self.selected_stash() else {
+            return;
+        };
+        let result = git::open_repository().and_then(|repo| stash::changes(&repo, &entry));
+        match result {
+            Ok(diff_info) => {
+                let description = self.stash_descriptions.get(&entry.id).cloned().unwrap_or_default();
+                self.display_title = format!("📦 {}: {}", entry.name(), description);
+                self.display_content = match diff_info.commits.first() {
+                    Some(commit) if !commit.diff.is_empty() => format!("{}\n\n{}", entry.message, commit.diff),
+                    _ => format!("{}\n\nNo changes to tracked files.", entry.message),
+                };
+                self.in_display_mode = true;
+            }
+            Err(e) => self.stash_message = format!("❌ {}", e),
+        }
+    }
+
+    /// Prefills the message of a new stash with a description of the
+    /// uncommitted changes.
+    async fn start_create_stash(&mut self) -> Result<()> {
+        self.start_loading("Suggesting a stash message...".to_string());
+        let diff_info = git::open_repository().and_then(|repo| git::get_uncommitted_changes(&repo))?;
+        let suggestion = if diff_info.commits.is_empty() {
+            String::new()
+        } else {
+            ai::describe_stash(&diff_info, &self.config).await.unwrap_or_default()
+        };
+        self.stop_loading();
+
+        self.stash_input = Some(suggestion);
+        Ok(())
+    }
+
+    async fn create_stash(&mut self) -> Result<()> {
+        let message = self.stash_input.take().unwrap_or_default();
+        let include_untracked = self.stash_include_untracked;
+        let result = git::open_repository()
+            .and_then(|mut repo| stash::create(&mut repo, &message, include_untracked));
+        self.stash_message = match result {
+            Ok(_) => "✅ Stashed the uncommitted changes as stash@{0}".to_string(),
+            Err(e) => format!("❌ {}", e),
+        };
+        self.stash_list_state.select(Some(0));
+        self.load_stashes().await;
+        self.update_git_status().await
+    }
+
+    /// Applies the selected stash, dropping it as well for a pop. Failures,
+    /// conflicts included, are shown in full since they need attention.
+    async fn apply_selected_stash(&mut self, pop: bool) -> Result<()> {
+        let Some(entry) = self.selected_stash() else {
+            return Ok(());
+        };
+        let result = git::open_repository().and_then(|mut repo| stash::apply(&mut repo, entry.index, pop));
+        match result {
+            Ok(()) => {
+                self.stash_message = format!("✅ {} {}", if pop { "Popped" } else { "Applied" }, entry.name());
+            }
+            Err(e) => {
+                self.stash_message.clear();
+                self.display_title = format!("❌ Could not {} {}", if pop { "pop" } else { "apply" }, entry.name());
+                self.display_content = format!("{:#}", e);
+                self.in_display_mode = true;
+            }
+        }
+        self.load_stashes().await;
+        self.update_git_status().await
+    }
+
+    /// Drops the selected stash after a second press.
+    async fn drop_selected_stash(&mut self) -> Result<()> {
+        let Some(entry) = self.selected_stash() else {
+            return Ok(());
+        };
+        if self.stash_pending_drop != Some(entry.id) {
+            self.stash_pending_drop = Some(entry.id);
+            self.stash_message = format!("Press d again to drop {}", entry.name());
+            return Ok(());
+        }
+
+        self.stash_pending_drop = None;
+        let result = git::open_repository().and_then(|mut repo| stash::drop(&mut repo, entry.index));
+        self.stash_message = match result {
+            Ok(()) => format!("🗑️ Dropped {}", entry.name()),
+            Err(e) => format!("❌ {}", e),
+        };
+        self.load_stashes().await;
+        self.update_git_status().await
+    }
+
+    // Conflict mode methods
+
+    /// Opens the conflict view when the index has unmerged entries.
+    /// Returns whether it did.
+    async fn open_conflicts(&mut self) -> Result<bool> {
+        let repo = git::open_repository()?;
+        if !conflicts::has_conflicts(&repo)? {
+            return Ok(false);
+        }
+        self.conflict_files = conflicts::conflicted_files(&repo)?;
+        self.conflict_file_state.select(Some(0));
+        self.conflict_message.clear();
+        self.reset_conflict_choices();
+        self.in_conflict_mode = true;
+        Ok(true)
+    }
+
+    async fn show_conflicts(&mut self) -> Result<()> {
+        if !self.open_conflicts().await? {
+            self.display_title = "⚔️ Merge Conflicts".to_string();
+            self.display_content = "There are no conflicted files.".to_string();
+            self.in_display_mode = true;
+        }
+        Ok(())
+    }
+
+    fn exit_conflict_mode(&mut self) {
+        self.in_conflict_mode = false;
+        self.conflict_files.clear();
+        self.conflict_choices.clear();
+        self.conflict_suggestion = None;
+        self.conflict_message.clear();
+        self.conflict_file_state.select(None);
+    }
+
+    fn current_conflict_file(&self) -> Option<&ConflictFile> {
+        self.conflict_files.get(self.conflict_file_state.selected()?)
+    }
+
+    /// Binary files are resolved as a single whole-file choice.
+    fn conflict_hunk_count(&self) -> usize {
+        match self.current_conflict_file() {
+            Some(file) if file.binary => 1,
+            Some(file) => file.hunks().len(),
+            None => 0,
+        }
+    }
+
+    fn reset_conflict_choices(&mut self) {
+        self.conflict_hunk = 0;
+        self.conflict_choices = vec![None; self.conflict_hunk_count()];
+        self.conflict_suggestion = None;
+    }
+
+    fn navigate_conflict_file(&mut self, down: bool) {
+        let count = self.conflict_files.len();
+        if count == 0 {
+            return;
+        }
+        let current = self.conflict_file_state.selected().unwrap_or(0);
+        let next = if down { (current + 1) % count } else { (current + count - 1) % count };
+        self.conflict_file_state.select(Some(next));
+        self.conflict_message.clear();
+        self.reset_conflict_choices();
+    }
+
+    fn jump_conflict_hunk(&mut self, forward: bool) {
+        let count = self.conflict_hunk_count();
+        if forward && self.conflict_hunk + 1 < count {
+            self.conflict_hunk += 1;
+        } else if !forward && self.conflict_hunk > 0 {
+            self.conflict_hunk -= 1;
+        } else {
+            return;
+        }
+        self.conflict_suggestion = None;
+    }
+
+    fn choose_conflict_resolution(&mut self, resolution: Resolution) {
+        if let Some(choice) = self.conflict_choices.get_mut(self.conflict_hunk) {
+            *choice = Some(resolution);
+            self.conflict_message.clear();
+            // Move on to the next undecided hunk
+            if let Some(next) = self.conflict_choices.iter().position(Option::is_none) {
+                self.conflict_hunk = next;
+                self.conflict_suggestion = None;
+            }
+        }
+    }
+
+    async fn suggest_conflict_resolution(&mut self) -> Result<()> {
+        let Some(file) = self.current_conflict_file() else {
+            return Ok(());
+        };
+        if file.binary {
+            self.conflict_message = "Binary files can only take ours or theirs".to_string();
+            return Ok(());
+        }
+        let Some(hunk) = file.hunks().get(self.conflict_hunk).map(|hunk| (*hunk).clone()) else {
+            return Ok(());
+        };
+        let path = file.path.clone();
+
+        self.start_loading("Suggesting a conflict resolution...".to_string());
+        let result = ai::suggest_conflict_resolution(&path, &hunk, &self.config).await;
+        self.stop_loading();
+
+        match result {
+            Ok(suggestion) => {
+                self.conflict_suggestion = Some(suggestion);
+                self.conflict_message.clear();
+            }
+            Err(e) => self.conflict_message = format!("❌ {}", e),
+        }
+        Ok(())
+    }
+
+    fn accept_conflict_suggestion(&mut self) {
+        match self.conflict_suggestion.take() {
+            Some(suggestion) => self.choose_conflict_resolution(Resolution::Text(suggestion.resolution)),
+            None => self.conflict_message = "No suggestion yet; press 'a' first".to_string(),
+        }
+    }
+
+    /// Opens the current hunk, or its suggestion or earlier edit, in the
+    /// user's editor and takes the saved text as the resolution.
+    fn edit_conflict_hunk(&mut self) -> Result<()> {
+        let Some(file) = self.current_conflict_file() else {
+            return Ok(());
+        };
+        if file.binary {
+            self.conflict_message = "Binary files can only take ours or theirs".to_string();
+            return Ok(());
+        }
+        let Some(hunk) = file.hunks().get(self.conflict_hunk).map(|hunk| (*hunk).clone()) else {
+            return Ok(());
+        };
+        let initial = match (self.conflict_choices.get(self.conflict_hunk).cloned().flatten(), &self.conflict_suggestion) {
+            (Some(Resolution::Text(text)), _) => text,
+            (_, Some(suggestion)) => suggestion.resolution.clone(),
+            _ => conflicts::hunk_with_markers(&hunk),
+        };
+
+        // Hand the terminal to the editor for the duration of the edit
+        disable_raw_mode()?;
+        execute!(io::stdout(), LeaveAlternateScreen, DisableMouseCapture)?;
+        let edited = git::open_repository().and_then(|repo| conflicts::edit_in_editor(&repo, &initial));
+        enable_raw_mode()?;
+        execute!(io::stdout(), EnterAlternateScreen, EnableMouseCapture)?;
+        self.needs_clear = true;
+
+        match edited {
+            Ok(text) if conflicts::has_markers(&text) => {
+                self.conflict_message = "The edit still has conflict markers".to_string();
+            }
+            Ok(text) => self.choose_conflict_resolution(Resolution::Text(text)),
+            Err(e) => self.conflict_message = format!("❌ {}", e),
+        }
+        Ok(())
+    }
+
+    /// Writes the chosen resolutions and stages the file once every hunk
+    /// has one.
+    async fn mark_conflict_resolved(&mut self) -> Result<()> {
+        let Some(file) = self.current_conflict_file().cloned() else {
+            return Ok(());
+        };
+        let Some(resolutions) = self.conflict_choices.iter().cloned().collect::<Option<Vec<_>>>() else {
+            let open = self.conflict_choices.iter().filter(|c| c.is_none()).count();
+            self.conflict_message = format!("{} hunk(s) still need a resolution", open);
+            return Ok(());
+        };
+
+        let result = git::open_repository()
+            .and_then(|repo| conflicts::resolve(&repo, &file, &resolutions));
+        if let Err(e) = result {
+            self.conflict_message = format!("❌ {}", e);
+            return Ok(());
+        }
+
+        let selected = self.conflict_file_state.selected().unwrap_or(0);
+        if !self.open_conflicts().await? {
+            self.exit_conflict_mode();
+            self.display_title = "✅ All conflicts resolved".to_string();
+            // Commits made here conclude a merge; other operations have
+            // their own state that only git can carry on with
+            let next_step = match git::open_repository().map(|repo| repo.state()) {
+                Ok(RepositoryState::Merge) => "Commit to conclude the merge.",
+                Ok(RepositoryState::CherryPick | RepositoryState::CherryPickSequence) => "Run `git cherry-pick --continue` to carry on.",
+                Ok(RepositoryState::Revert | RepositoryState::RevertSequence) => "Run `git revert --continue` to carry on.",
+                Ok(RepositoryState::ApplyMailbox | RepositoryState::ApplyMailboxOrRebase) => "Run `git am --continue` to carry on.",
+                Ok(RepositoryState::Rebase | RepositoryState::RebaseInteractive | RepositoryState::RebaseMerge) => "Run `git rebase --continue` to carry on.",
+                _ => "Commit the result.",
+            };
+            self.display_content = format!("Every conflicted file is resolved and staged.\n\n{}", next_step);
+            self.in_display_mode = true;
+        } else {
+            self.conflict_file_state.select(Some(selected.min(self.conflict_files.len() - 1)));
+            self.reset_conflict_choices();
+            self.conflict_message = format!("✅ Resolved {}", file.path);
+        }
+        self.update_git_status().await
+    }
+
+    // Log methods
+    fn load_log(&mut self) {
+        match git::open_repository().and_then(|repo| history::log_graph(&repo, LOG_LIMIT)) {
+            Ok(rows) => {
+                self.log_rows = rows;
+                self.log_message.clear();
+            }
+            Err(e) => {
+                self.log_rows.clear();
+                self.log_message = format!("❌ {}", e);
+            }
+        }
+        let cursor = self.list_state.selected().unwrap_or(0);
+        self.list_state.select(Some(cursor.min(self.log_rows.len().saturating_sub(1))));
+    }
+
+    fn enter_log_detail_mode(&mut self, selected: usize) -> Result<()> {
+        let Some(row) = self.log_rows.get(selected) else {
+            return Ok(());
+        };
+        let repo = git::open_repository()?;
+        let commit = repo.find_commit(row.id)?;
+        let info = git::commit_info(&repo, &commit)?;
+
+        let author = commit.author();
+        let date = chrono::DateTime::from_timestamp(commit.time().seconds(), 0)
+            .map(|d| d.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+            .unwrap_or_default();
+        let mut header = format!(
+            "Commit:  {}\nAuthor:  {} <{}>\nDate:    {}\n",
+            info.hash,
+            author.name().unwrap_or("Unknown"),
+            author.email().unwrap_or(""),
+            date
+        );
+        if commit.parent_count() > 0 {
+            let parents: Vec<String> = commit.parent_ids().map(|id| id.to_string()[..8].to_string()).collect();
+            header.push_str(&format!("Parents: {}\n", parents.join(" ")));
+        }
+        if !row.refs.is_empty() {
+            header.push_str(&format!("Refs:    {}\n", row.refs.join(", ")));
+        }
+        header.push('\n');
+        for line in info.message.trim_end().lines() {
+            header.push_str(&format!("    {}\n", line));
+        }
+        let (additions, deletions) = info.file_changes.iter()
+            .fold((0, 0), |(a, d), change| (a + change.additions, d + change.deletions));
+        header.push_str(&format!("\n{} file(s) changed, +{} -{}\n", info.file_changes.len(), additions, deletions));
+        for change in &info.file_changes {
+            header.push_str(&format!("  {}\n", change.describe()));
+        }
+
+        self.log_detail = Some(info);
+        self.log_detail_header = header;
+        self.log_scroll = 0;
+        self.log_message.clear();
+        self.in_log_detail_mode = true;
+        Ok(())
+    }
+
+    fn exit_log_detail_mode(&mut self) {
+        self.in_log_detail_mode = false;
+        self.log_detail = None;
+        self.log_detail_header.clear();
+        self.log_scroll = 0;
+        self.log_message.clear();
+    }
+
+    async fn improve_selected_commit(&mut self) -> Result<()> {
+        let Some(commit) = &self.log_detail else {
+            return Ok(());
+        };
+        let message = commit.message.clone();
+
+        self.start_loading("Improving commit message...".to_string());
+        let improved = ai::improve_commit_message(&message, &self.config).await;
+        self.stop_loading();
+
+        self.display_title = "💬 AI-Improved Commit Message".to_string();
+        self.display_content = match improved {
+            Ok(improved) => improved,
+            Err(e) => format!("❌ {}", e),
+        };
+        self.in_display_mode = true;
+        Ok(())
+    }
+
+    async fn explain_selected_commit(&mut self) -> Result<()> {
+        let Some(commit) = self.log_detail.clone() else {
+            return Ok(());
+        };
+        let title = format!("🔎 Commit {}", &commit.hash[..8]);
+
+        self.start_loading("Explaining commit...".to_string());
+        let explanation = ai::explain_commit(&commit, &self.config).await;
+        self.stop_loading();
+
+        self.display_title = title;
+        self.display_content = match explanation {
+            Ok(explanation) => explanation,
+            Err(e) => format!("❌ {}", e),
+        };
+        self.in_display_mode = true;
+        Ok(())
+    }
+
+    /// Cherry-picks the commit shown onto HEAD, or reverts it.
+    async fn apply_selected_commit(&mut self, revert: bool) -> Result<()> {
+        let Some(commit) = &self.log_detail else {
+            return Ok(());
+        };
+        let id = git2::Oid::from_str(&commit.hash)?;
+        let short = commit.hash[..8].to_string();
+
+        let result = git::open_repository().and_then(|repo| {
+            if revert {
+                history::revert(&repo, id)
+            } else {
+                history::cherry_pick(&repo, id)
+            }
+        });
+
+        match result {
+            Ok(_) => {
+                self.exit_log_detail_mode();
+                self.load_log();
+                self.log_message = if revert {
+                    format!("✅ Reverted {}", short)
+                } else {
+                    format!("✅ Cherry-picked {}", short)
+                };
+            }
+            Err(e) => {
+                if !self.open_conflicts().await? {
+                    self.log_message = format!("❌ {}", e);
+                } else {
+                    self.exit_log_detail_mode();
+                }
+            }
+        }
+        self.update_git_status().await
+    }
+
+    fn copy_selected_hash(&mut self) -> Result<()> {
+        let Some(commit) = &self.log_detail else {
+            return Ok(());
+        };
+        let hash = commit.hash.clone();
+        copy_text(&hash)?;
+        self.log_message = format!("📋 Copied {}", &hash[..8]);
+        Ok(())
+    }
+
     // PR creation method
     async fn create_pr_with_ai_description(&mut self) -> Result<()> {
         // Check if GitHub token is available
@@ -1239,10 +3266,7 @@ impl InteractiveCli {
         self.start_loading("Creating PR with AI description...".to_string());
 
         // Get current branch
-        let output = Command::new("git")
-            .args(&["branch", "--show-current"])
-            .output()?;
-        let current_branch = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        let current_branch = utils::get_current_branch()?;
 
         // Get base branch (default to master)
         let base_branch = self.config.get_default_branch();
@@ -1257,7 +3281,7 @@ impl InteractiveCli {
 
         // Create PR info
         let pr_info = github::PullRequest {
-            title: format!("feat: {}", current_branch.replace('-', " ").replace('_', " ")),
+            title: format!("feat: {}", current_branch.replace(['-', '_'], " ")),
             body: pr_description,
             head: current_branch.clone(),
             base: base_branch.to_string(),
@@ -1282,58 +3306,8 @@ impl InteractiveCli {
     }
 
     async fn copy_to_clipboard(&mut self) -> Result<()> {
-        // Try to copy to clipboard using different methods
         let content = format!("{}\n\n{}\n", self.display_title, self.display_content);
-        
-        // Method 1: Try using pbcopy on macOS
-        if cfg!(target_os = "macos") {
-            let mut child = std::process::Command::new("pbcopy")
-                .stdin(std::process::Stdio::piped())
-                .spawn()?;
-            
-            if let Some(stdin) = child.stdin.as_mut() {
-                use std::io::Write;
-                stdin.write_all(content.as_bytes())?;
-            }
-            
-            let _ = child.wait();
-            return Ok(());
-        }
-        
-        // Method 2: Try using xclip on Linux
-        if cfg!(target_os = "linux") {
-            let mut child = std::process::Command::new("xclip")
-                .args(&["-selection", "clipboard"])
-                .stdin(std::process::Stdio::piped())
-                .spawn()?;
-            
-            if let Some(stdin) = child.stdin.as_mut() {
-                use std::io::Write;
-                stdin.write_all(content.as_bytes())?;
-            }
-            
-            let _ = child.wait();
-            return Ok(());
-        }
-        
-        // Method 3: Try using clip.exe on Windows
-        if cfg!(target_os = "windows") {
-            let mut child = std::process::Command::new("clip")
-                .stdin(std::process::Stdio::piped())
-                .spawn()?;
-            
-            if let Some(stdin) = child.stdin.as_mut() {
-                use std::io::Write;
-                stdin.write_all(content.as_bytes())?;
-            }
-            
-            let _ = child.wait();
-            return Ok(());
-        }
-        
-        // If no clipboard tool is available, show a message
-        // For now, we'll just return Ok() - could show a message in the future
-        Ok(())
+        copy_text(&content)
     }
 
     async fn show_pr_description(&mut self) -> Result<()> {
@@ -1385,13 +3359,15 @@ impl InteractiveCli {
     async fn show_changelog(&mut self) -> Result<()> {
         self.start_loading("Generating changelog...".to_string());
         
-        let base_branch = self.config.get_default_branch();
-        let diff_info = git::get_diff_info(base_branch)?;
-        let changelog = ai::generate_changelog(&diff_info, &self.config).await?;
+        let repo = git::open_repository()?;
+        let sections = changelog::collect(&repo, None, None)?;
+        let links = changelog::ForgeLinks::detect(&repo);
+        let changelog = changelog::render(&sections, links.as_ref());
+        let changelog = ai::polish_changelog(&changelog, &self.config).await?;
         
         self.stop_loading();
         
-        self.display_title = "📋 AI-Generated Changelog".to_string();
+        self.display_title = "📋 Changelog".to_string();
         self.display_content = changelog;
         self.in_display_mode = true;
         
@@ -1429,6 +3405,33 @@ impl InteractiveCli {
 }
 
 // Helper function to create a centered rectangle
+/// Copies `content` to the system clipboard with pbcopy, xclip or clip.
+fn copy_text(content: &str) -> Result<()> {
+    let command: &[&str] = if cfg!(target_os = "macos") {
+        &["pbcopy"]
+    } else if cfg!(target_os = "linux") {
+        &["xclip", "-selection", "clipboard"]
+    } else if cfg!(target_os = "windows") {
+        &["clip"]
+    } else {
+        // No known clipboard tool on this platform
+        return Ok(());
+    };
+
+    let mut child = std::process::Command::new(command[0])
+        .args(&command[1..])
+        .stdin(std::process::Stdio::piped())
+        .spawn()?;
+
+    if let Some(stdin) = child.stdin.as_mut() {
+        use std::io::Write;
+        stdin.write_all(content.as_bytes())?;
+    }
+
+    let _ = child.wait();
+    Ok(())
+}
+
 fn centered_rect(percent_x: u16, percent_y: u16, r: ratatui::layout::Rect) -> ratatui::layout::Rect {
     let popup_layout = Layout::default()
         .direction(Direction::Vertical)
@@ -0,0 +1,542 @@
+use anyhow::{Context, Result};
+use git2::{Oid, Repository};
+use serde::{Deserialize, Serialize};
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use crate::git;
+
+/// Only branches and tags are journaled; backup refs, remote-tracking refs
+/// and bisect state are left alone by undo. The stash is journaled
+/// separately, since its entries live in the reflog of `refs/stash`.
+const JOURNALED_REFS: [&str; 2] = ["refs/heads/", "refs/tags/"];
+
+const STASH_REF: &str = "refs/stash";
+
+thread_local! {
+    /// Depth of running actions, so that an action made of smaller ones
+    /// (a release commits, for instance) is journaled once as a whole.
+    static DEPTH: Cell<usize> = const { Cell::new(0) };
+}
+
+/// A value before and after an action; `None` means it did not exist.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Change<T = String> {
+    pub before: Option<T>,
+    pub after: Option<T>,
+}
+
+/// One journaled action and everything it changed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub id: u64,
+    /// Seconds since the epoch
+    pub time: i64,
+    pub action: String,
+    /// Whether the action updated the work tree through git (checkout,
+    /// merge, ...), so undoing it has to as well
+    pub worktree: bool,
+    /// Branch HEAD points at, or the commit when detached
+    pub head: Option<Change>,
+    pub refs: BTreeMap<String, Change>,
+    /// Tree written from the index
+    pub index: Option<Change>,
+    /// Stash entries as `<id> <message>`, oldest first
+    pub stash: Option<Change<Vec<String>>>,
+    /// Written files, by path relative to the work tree, as the ids of
+    /// blobs holding their contents
+    pub files: BTreeMap<String, Change>,
+}
+
+impl JournalEntry {
+    /// The action with what it touched, e.g. `commit: feat: x (1 ref, index)`.
+    pub fn summary(&self) -> String {
+        let mut touched = Vec::new();
+        if self.head.is_some() {
+            touched.push("HEAD".to_string());
+        }
+        if !self.refs.is_empty() {
+            touched.push(format!("{} ref(s)", self.refs.len()));
+        }
+        if self.index.is_some() {
+            touched.push("index".to_string());
+        }
+        if self.stash.is_some() {
+            touched.push("stash".to_string());
+        }
+        if !self.files.is_empty() {
+            touched.push(format!("{} file(s)", self.files.len()));
+        }
+        format!("{} ({})", self.action, touched.join(", "))
+    }
+}
+
+/// The state an action can change.
+#[derive(Debug, Default)]
+struct Snapshot {
+    head: Option<String>,
+    refs: BTreeMap<String, String>,
+    index: Option<String>,
+    stash: Option<Vec<String>>,
+    files: BTreeMap<String, Option<String>>,
+}
+
+impl Snapshot {
+    fn take(repo: &Repository, files: &[String]) -> Result<Self> {
+        let head = repo.find_reference("HEAD")?;
+        let head = match head.symbolic_target() {
+            Some(target) => Some(target.to_string()),
+            None => head.target().map(|id| id.to_string()),
+        };
+
+        let mut refs = BTreeMap::new();
+        for reference in repo.references()? {
+            let reference = reference?;
+            let (Some(name), Some(target)) = (reference.name(), reference.target()) else {
+                continue;
+            };
+            if JOURNALED_REFS.iter().any(|prefix| name.starts_with(prefix)) {
+                refs.insert(name.to_string(), target.to_string());
+            }
+        }
+
+        // An index with conflicts cannot be written as a tree
+        let mut index = repo.index()?;
+        index.read(true)?;
+        let index = index.write_tree().ok().map(|id| id.to_string());
+
+        let stash = match repo.find_reference(STASH_REF) {
+            Ok(_) => {
+                let reflog = repo.reflog(STASH_REF)?;
+                Some(reflog.iter()
+                    .map(|entry| format!("{} {}", entry.id_new(), entry.message().unwrap_or("")))
+                    .rev()
+                    .collect())
+            }
+            Err(_) => None,
+        };
+
+        let files = files.iter()
+            .map(|path| {
+                let blob = read_file(repo, path)?.map(|content| repo.blob(&content)).transpose()?;
+                Ok((path.clone(), blob.map(|id| id.to_string())))
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Snapshot { head, refs, index, stash, files })
+    }
+}
+
+/// A file's contents, or `None` when it does not exist. Any other read
+/// error fails, so that undo never mistakes an unreadable file for one the
+/// action created.
+fn read_file(repo: &Repository, path: &str) -> Result<Option<Vec<u8>>> {
+    let Some(workdir) = repo.workdir() else {
+        return Ok(None);
+    };
+    let target = workdir.join(path);
+    match fs::read(&target) {
+        Ok(content) => Ok(Some(content)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e).with_context(|| format!("Could not read {}", target.display())),
+    }
+}
+
+fn change<T: Clone + PartialEq>(before: Option<&T>, after: Option<&T>) -> Option<Change<T>> {
+    (before != after).then(|| Change { before: before.cloned(), after: after.cloned() })
+}
+
+/// A mutating action to journal; see [`action`].
+pub struct Action<'repo> {
+    repo: &'repo Repository,
+    name: String,
+    files: Vec<PathBuf>,
+    worktree: bool,
+}
+
+/// Starts describing an action on `repo`. Nothing is recorded until
+/// [`Action::run`] succeeds.
+pub fn action(repo: &Repository, name: impl Into<String>) -> Action<'_> {
+    Action {
+        repo,
+        name: name.into(),
+        files: Vec::new(),
+        worktree: false,
+    }
+}
+
+impl<'repo> Action<'repo> {
+    /// Files the action writes, so undo can restore their previous
+    /// contents. Directories cover every file below them.
+    pub fn files(mut self, paths: &[PathBuf]) -> Self {
+        self.files.extend(paths.iter().cloned());
+        self
+    }
+
+    /// The action updates the work tree through git, like a checkout or a
+    /// merge.
+    pub fn worktree(mut self) -> Self {
+        self.worktree = true;
+        self
+    }
+
+    /// Runs `f` and, if it succeeds and changed anything, appends an entry
+    /// to the journal. Inside another action `f` just runs, since the
+    /// outer action records the combined change.
+    pub fn run<T>(self, f: impl FnOnce() -> Result<T>) -> Result<T> {
+        let depth = DEPTH.with(|depth| depth.replace(depth.get() + 1));
+        let result = self.run_outermost(depth == 0, f);
+        DEPTH.with(|depth| depth.set(depth.get() - 1));
+        result
+    }
+
+    fn run_outermost<T>(self, outermost: bool, f: impl FnOnce() -> Result<T>) -> Result<T> {
+        if !outermost {
+            return f();
+        }
+
+        let mut paths = self.expand_files();
+        let before = Snapshot::take(self.repo, &paths)?;
+        let value = f()?;
+
+        // Files created below a watched directory only exist now
+        for path in self.expand_files() {
+            if !paths.contains(&path) {
+                paths.push(path);
+            }
+        }
+        let mut before = before;
+        for path in &paths {
+            before.files.entry(path.clone()).or_insert(None);
+        }
+        let after = Snapshot::take(self.repo, &paths)?;
+
+        let entry = JournalEntry {
+            id: 0,
+            time: chrono::Utc::now().timestamp(),
+            action: self.name.clone(),
+            worktree: self.worktree,
+            head: change(before.head.as_ref(), after.head.as_ref()),
+            refs: before.refs.keys().chain(after.refs.keys())
+                .filter_map(|name| change(before.refs.get(name), after.refs.get(name)).map(|c| (name.clone(), c)))
+                .collect(),
+            index: change(before.index.as_ref(), after.index.as_ref()),
+            stash: change(before.stash.as_ref(), after.stash.as_ref()),
+            files: paths.iter()
+                .filter_map(|path| {
+                    let before = before.files.get(path).cloned().flatten();
+                    let after = after.files.get(path).cloned().flatten();
+                    change(before.as_ref(), after.as_ref()).map(|c| (path.clone(), c))
+                })
+                .collect(),
+        };
+        if entry.head.is_some() || !entry.refs.is_empty() || entry.index.is_some() || entry.stash.is_some() || !entry.files.is_empty() {
+            append(self.repo, entry).context("The action succeeded but could not be written to the journal")?;
+        }
+        Ok(value)
+    }
+
+    /// Watched paths relative to the work tree, with directories expanded
+    /// to the files in them.
+    fn expand_files(&self) -> Vec<String> {
+        let Some(workdir) = self.repo.workdir() else {
+            return Vec::new();
+        };
+        let mut found = Vec::new();
+        for path in &self.files {
+            let absolute = if path.is_absolute() { path.clone() } else { workdir.join(path) };
+            collect_files(&absolute, &mut found);
+            if !absolute.exists() {
+                found.push(absolute);
+            }
+        }
+        let mut relative: Vec<String> = found.iter()
+            .filter_map(|path| {
+                let parent = path.parent()?;
+                let parent = parent.canonicalize().ok()?;
+                let top = workdir.canonicalize().ok()?;
+                let name = path.file_name()?;
+                let relative = parent.strip_prefix(&top).ok()?.join(name);
+                Some(relative.to_string_lossy().replace('\\', "/"))
+            })
+            .collect();
+        relative.sort();
+        relative.dedup();
+        relative
+    }
+}
+
+fn collect_files(path: &Path, found: &mut Vec<PathBuf>) {
+    if path.is_file() {
+        found.push(path.to_path_buf());
+    } else if let Ok(entries) = fs::read_dir(path) {
+        for entry in entries.flatten() {
+            if !entry.file_name().to_string_lossy().starts_with('.') {
+                collect_files(&entry.path(), found);
+            }
+        }
+    }
+}
+
+/// How many actions undo can go back. The journal is pruned back to this
+/// once it holds twice as many, so most actions only append a line.
+const MAX_ENTRIES: usize = 100;
+
+fn journal_path(repo: &Repository) -> PathBuf {
+    repo.path().join("commit-buddy").join("journal")
+}
+
+/// Every journaled action, oldest first.
+pub fn entries(repo: &Repository) -> Result<Vec<JournalEntry>> {
+    let path = journal_path(repo);
+    lines(&path)?
+        .iter()
+        .map(|line| parse(&path, line))
+        .collect()
+}
+
+/// The non-empty lines of the journal at `path`, one entry each.
+fn lines(path: &Path) -> Result<Vec<String>> {
+    match fs::read_to_string(path) {
+        Ok(text) => Ok(text.lines().filter(|line| !line.trim().is_empty()).map(str::to_string).collect()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e).with_context(|| format!("Could not read {}", path.display())),
+    }
+}
+
+fn parse(path: &Path, line: &str) -> Result<JournalEntry> {
+    serde_json::from_str(line).with_context(|| format!("{} has an invalid entry", path.display()))
+}
+
+fn append(repo: &Repository, mut entry: JournalEntry) -> Result<()> {
+    let path = journal_path(repo);
+    let mut lines = lines(&path)?;
+    entry.id = match lines.last() {
+        Some(last) => parse(&path, last)?.id + 1,
+        None => 1,
+    };
+    let line = serde_json::to_string(&entry)?;
+
+    if lines.len() < 2 * MAX_ENTRIES {
+        fs::create_dir_all(path.parent().unwrap())?;
+        let mut file = fs::OpenOptions::new().create(true).append(true).open(&path)?;
+        writeln!(file, "{}", line)?;
+        return Ok(());
+    }
+
+    lines.push(line);
+    lines.drain(..lines.len() - MAX_ENTRIES);
+    save(&path, &lines)
+}
+
+fn save(path: &Path, lines: &[String]) -> Result<()> {
+    fs::create_dir_all(path.parent().unwrap())?;
+    let mut text = String::new();
+    for line in lines {
+        text.push_str(line);
+        text.push('\n');
+    }
+    fs::write(path, text)?;
+    Ok(())
+}
+
+/// Undoes the last `count` actions, newest first, and removes them from
+/// the journal. Each one is checked first: if something it changed has
+/// changed again since, undo stops unless `force` is set. Returns the
+/// undone entries.
+pub fn undo(repo: &Repository, count: usize, force: bool) -> Result<Vec<JournalEntry>> {
+    let path = journal_path(repo);
+    let mut lines = lines(&path)?;
+    if lines.is_empty() {
+        anyhow::bail!("Nothing to undo: the journal is empty");
+    }
+
+    let mut undone = Vec::new();
+    for _ in 0..count.max(1) {
+        let Some(line) = lines.last() else {
+            break;
+        };
+        let entry = parse(&path, line)?;
+        if !force {
+            check_unchanged(repo, &entry)?;
+        }
+        restore(repo, &entry).with_context(|| format!("Could not undo {}", entry.action))?;
+        lines.pop();
+        save(&path, &lines)?;
+        undone.push(entry);
+    }
+    Ok(undone)
+}
+
+/// Fails if HEAD, a ref, the index or a file no longer looks like the
+/// action left it.
+fn check_unchanged(repo: &Repository, entry: &JournalEntry) -> Result<()> {
+    let files: Vec<String> = entry.files.keys().cloned().collect();
+    let now = Snapshot::take(repo, &files)?;
+    let hint = "undo it first, or pass --force";
+
+    if let Some(head) = &entry.head {
+        if now.head != head.after {
+            anyhow::bail!("HEAD has moved since '{}'; {}", entry.action, hint);
+        }
+    }
+    for (name, change) in &entry.refs {
+        if now.refs.get(name) != change.after.as_ref() {
+            anyhow::bail!("{} has changed since '{}'; {}", name, entry.action, hint);
+        }
+    }
+    if let Some(index) = &entry.index {
+        if !entry.worktree && now.index != index.after {
+            anyhow::bail!("The index has changed since '{}'; {}", entry.action, hint);
+        }
+    }
+    if let Some(stash) = &entry.stash {
+        if now.stash != stash.after {
+            anyhow::bail!("The stash has changed since '{}'; {}", entry.action, hint);
+        }
+    }
+    for (path, change) in &entry.files {
+        if now.files.get(path).cloned().flatten() != change.after {
+            anyhow::bail!("{} has changed since '{}'; {}", path, entry.action, hint);
+        }
+    }
+    Ok(())
+}
+
+fn restore(repo: &Repository, entry: &JournalEntry) -> Result<()> {
+    let mut refs = entry.refs.clone();
+
+    if entry.worktree {
+        // Let git move the work tree back; it refuses to overwrite local
+        // changes
+        if let Some(stash) = &entry.stash {
+            // Undoing a stash puts its changes back before the entry goes
+            let before = stash.before.clone().unwrap_or_default();
+            for added in stash.after.iter().flatten().filter(|line| !before.contains(line)) {
+                let (id, _) = added.split_once(' ').unwrap_or((added, ""));
+                git::run_git_in(repo, &["stash", "apply", "--index", id])?;
+            }
+        }
+        if let Some(head) = &entry.head {
+            let target = head.before.as_deref().context("HEAD did not exist before")?;
+            match target.strip_prefix("refs/heads/") {
+                Some(branch) => git::run_git_in(repo, &["checkout", branch])?,
+                None => git::run_git_in(repo, &["checkout", "--detach", target])?,
+            };
+        } else if let Some(branch) = git::current_branch_name(repo) {
+            let name = format!("refs/heads/{}", branch);
+            if let Some(change) = refs.remove(&name) {
+                let before = change.before.with_context(|| format!("{} did not exist before", name))?;
+                git::run_git_in(repo, &["reset", "--keep", &before])?;
+            }
+        }
+    }
+
+    for (name, change) in &refs {
+        match &change.before {
+            Some(id) => {
+                repo.reference(name, Oid::from_str(id)?, true, "commit-buddy: undo")?;
+            }
+            None => {
+                if let Ok(mut reference) = repo.find_reference(name) {
+                    reference.delete()?;
+                }
+            }
+        }
+    }
+
+    if let Some(stash) = &entry.stash {
+        restore_stash(repo, stash.before.as_deref())?;
+    }
+
+    if !entry.worktree {
+        if let Some(Change { before: Some(head), .. }) = &entry.head {
+            match Oid::from_str(head) {
+                Ok(id) => repo.set_head_detached(id)?,
+                Err(_) => repo.set_head(head)?,
+            }
+        }
+        if let Some(Change { before: Some(tree), .. }) = &entry.index {
+            let mut index = repo.index()?;
+            index.read_tree(&repo.find_tree(Oid::from_str(tree)?)?)?;
+            index.write()?;
+        }
+    }
+
+    let workdir = repo.workdir();
+    for (path, change) in &entry.files {
+        let Some(workdir) = workdir else {
+            break;
+        };
+        let target = workdir.join(path);
+        match &change.before {
+            Some(id) => {
+                let blob = repo.find_blob(Oid::from_str(id)?)
+                    .with_context(|| format!("The saved contents of {} are gone from the object database", path))?;
+                if let Some(parent) = target.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::write(&target, blob.content())?;
+            }
+            None => {
+                if target.exists() {
+                    fs::remove_file(&target)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Points `refs/stash` back at `entries` (oldest first) by rewriting its
+/// reflog, or removes it when there was no stash.
+fn restore_stash(repo: &Repository, entries: Option<&[String]>) -> Result<()> {
+    let parse = |line: &String| -> Result<(Oid, String)> {
+        let (id, message) = line.split_once(' ').unwrap_or((line, ""));
+        Ok((Oid::from_str(id)?, message.to_string()))
+    };
+    let Some((newest, message)) = entries.and_then(|entries| entries.last()).map(parse).transpose()? else {
+        if let Ok(mut reference) = repo.find_reference(STASH_REF) {
+            reference.delete()?;
+        }
+        return Ok(());
+    };
+
+    repo.reference(STASH_REF, newest, true, &message)?;
+    let mut reflog = repo.reflog(STASH_REF)?;
+    while !reflog.is_empty() {
+        reflog.remove(0, false)?;
+    }
+    let signature = repo.signature()?;
+    for line in entries.unwrap_or_default() {
+        let (id, message) = parse(line)?;
+        reflog.append(id, &signature, Some(&message))?;
+    }
+    reflog.write()?;
+    Ok(())
+}
+
+/// `undo`: undoes the last `count` actions, or lists the journal.
+pub fn undo_command(count: usize, list: bool, force: bool) -> Result<()> {
+    let repo = git::open_repository()?;
+    if list {
+        let all = entries(&repo)?;
+        if all.is_empty() {
+            println!("The journal is empty.");
+        }
+        for entry in all.iter().rev() {
+            let date = chrono::DateTime::from_timestamp(entry.time, 0)
+                .map(|date| date.format("%Y-%m-%d %H:%M").to_string())
+                .unwrap_or_default();
+            println!("{:>4} {} {}", entry.id, date, entry.summary());
+        }
+        return Ok(());
+    }
+
+    for entry in undo(&repo, count, force)? {
+        println!("↩️ Undid {}", entry.summary());
+    }
+    Ok(())
+}
@@ -2,6 +2,26 @@
 // This allows integration tests to import the modules
 
 pub mod git;
+pub mod bisect;
+pub mod blame;
+pub mod branches;
+pub mod changelog;
+pub mod committer;
+pub mod conflicts;
+pub mod commit_linter;
+pub mod conventional;
+pub mod explain;
+pub mod history;
+pub mod hooks;
+pub mod journal;
+pub mod patches;
+pub mod release;
+pub mod rewrite;
+pub mod split;
+pub mod stash;
+pub mod staging;
+pub mod tidy;
+pub mod trailers;
 pub mod ai;
 pub mod github;
 pub mod utils;
@@ -1,5 +1,5 @@
 use clap::{Parser, Subcommand};
-use anyhow::Result;
+use anyhow::{Context, Result};
 
 // Re-export modules from lib
 use commit_buddy::*;
@@ -11,6 +11,9 @@ pub mod test_linter;
 #[command(about = "AI-powered git companion for enhanced development workflow")]
 #[command(version)]
 struct Cli {
+    /// Run as if commit-buddy was started in PATH instead of the current directory
+    #[arg(short = 'C', global = true, value_name = "PATH")]
+    directory: Option<std::path::PathBuf>,
     #[command(subcommand)]
     command: Commands,
 }
@@ -40,24 +43,76 @@ enum Commands {
         /// Commit hash to improve (default: HEAD)
         #[arg(short, long)]
         commit: Option<String>,
+        /// Reword HEAD with the suggested message
+        #[arg(long, group = "rewrite")]
+        apply: bool,
+        /// Reword every commit in a range ending at HEAD (e.g. main..HEAD)
+        #[arg(short, long, group = "rewrite", conflicts_with = "commit")]
+        range: Option<String>,
+        /// Allow rewriting commits that are already on the upstream branch
+        #[arg(long, requires = "rewrite")]
+        force: bool,
     },
         /// Interactive commit message assistant
         Commit {
             /// Stage all changes before committing
             #[arg(short, long)]
             all: bool,
+            /// Skip the pre-commit and commit-msg hooks
+            #[arg(short = 'n', long)]
+            no_verify: bool,
+            /// Add a Signed-off-by trailer
+            #[arg(short, long)]
+            signoff: bool,
+            /// Add a Co-authored-by trailer (a name or email from history, or "Name <email>")
+            #[arg(long = "co-author", value_name = "AUTHOR")]
+            co_authors: Vec<String>,
         },
         /// AI-powered conventional commit
         AiCommit {
             /// Stage all changes before committing
             #[arg(short, long)]
             all: bool,
+            /// Skip the pre-commit and commit-msg hooks
+            #[arg(short = 'n', long)]
+            no_verify: bool,
+            /// Split the staged changes into several logical commits
+            #[arg(long)]
+            split: bool,
+            /// Add a Signed-off-by trailer
+            #[arg(short, long)]
+            signoff: bool,
+            /// Add a Co-authored-by trailer (a name or email from history, or "Name <email>")
+            #[arg(long = "co-author", value_name = "AUTHOR")]
+            co_authors: Vec<String>,
         },
-    /// Generate changelog from commits
+    /// Explain what a commit or range did, or how a file evolved
+    Explain {
+        /// A commit, a range (e.g. main..feature) or a path
+        target: String,
+        /// Output format (text, markdown)
+        #[arg(short, long, default_value = "text")]
+        format: String,
+    },
+    /// Explain why a range of lines looks the way it does, from its blame
+    Why {
+        /// Lines to explain, e.g. src/git.rs:10-40
+        target: String,
+    },
+    /// Generate a Keep a Changelog changelog from tags and conventional commits
     Changelog {
-        /// Base branch to compare against (default: master)
-        #[arg(short, long, default_value = "master")]
-        base: String,
+        /// Only include commits after this branch or revision
+        #[arg(short, long, conflicts_with = "from_tag")]
+        base: Option<String>,
+        /// Start after this tag (default: the full history)
+        #[arg(long)]
+        from_tag: Option<String>,
+        /// End at this tag instead of HEAD
+        #[arg(long)]
+        to_tag: Option<String>,
+        /// Polish the wording with AI
+        #[arg(long)]
+        ai: bool,
         /// Output file (default: stdout)
         #[arg(short, long)]
         output: Option<String>,
@@ -76,14 +131,146 @@ enum Commands {
             #[arg(short, long, default_value = "tests/")]
             directory: String,
         },
+    /// Check commit messages against the configured rules
+    LintCommits {
+        /// Commits to check (default: <default branch>..HEAD)
+        #[arg(short, long, conflicts_with = "message_file")]
+        range: Option<String>,
+        /// Check a commit message file instead (used by the commit-msg hook)
+        #[arg(long)]
+        message_file: Option<String>,
+        /// Output format (text, json)
+        #[arg(short, long, default_value = "text")]
+        format: String,
+    },
+    /// Bump the version from the commits since the last tag, update the
+    /// changelog and create an annotated release tag
+    Release {
+        /// Show the plan without changing anything
+        #[arg(long)]
+        dry_run: bool,
+        /// Force the bump instead of deriving it from the commits (major, minor, patch)
+        #[arg(short, long)]
+        bump: Option<String>,
+        /// Make a pre-release on this channel (e.g. alpha, beta, rc)
+        #[arg(long, value_name = "CHANNEL")]
+        pre: Option<String>,
+        /// Skip the pre-commit and commit-msg hooks
+        #[arg(short = 'n', long)]
+        no_verify: bool,
+    },
+    /// Manage the git hooks that pre-fill and check `git commit` messages
+    Hook {
+        #[command(subcommand)]
+        action: HookAction,
+    },
+    /// Find the commit that broke a test command with `git bisect`
+    Bisect {
+        #[command(subcommand)]
+        action: BisectAction,
+    },
+    /// Squash "wip", "fix typo" and fixup! commits into the commits they touch up
+    Tidy {
+        /// Base of the range to tidy (default: the configured default branch)
+        #[arg(short, long)]
+        base: Option<String>,
+        /// Rewrite the history as proposed, keeping a backup ref
+        #[arg(long)]
+        apply: bool,
+        /// Allow rewriting commits that are already on the upstream branch
+        #[arg(long, requires = "apply")]
+        force: bool,
+    },
+    /// Write a patch series with a cover letter for mailing-list review
+    FormatPatch {
+        /// Commits to send, as <base>..<tip> or <base> for <base>..HEAD (default: the configured default branch)
+        range: Option<String>,
+        /// Version of the series, e.g. -v2 for [PATCH v2]
+        #[arg(short = 'v', long, default_value = "1")]
+        reroll_count: usize,
+        /// Previous version to list the changes against: a directory of its
+        /// patches, a range or a branch (default: the v<N-1> patches in the
+        /// output directory)
+        #[arg(long)]
+        previous: Option<String>,
+        /// Directory to write the patches to
+        #[arg(short, long, default_value = ".")]
+        output_dir: String,
+    },
+    /// Undo the last commit-buddy actions recorded in the journal
+    Undo {
+        /// Number of actions to undo
+        #[arg(default_value = "1")]
+        count: usize,
+        /// List the journal instead of undoing anything
+        #[arg(long, conflicts_with = "force")]
+        list: bool,
+        /// Undo even if something the action changed has changed since
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum HookAction {
+    /// Install the prepare-commit-msg hook, or the commit-msg hook with --commit-msg
+    Install {
+        /// Replace an existing hook that was not installed by commit-buddy
+        #[arg(short, long)]
+        force: bool,
+        /// Install the commit-msg hook that runs lint-commits instead
+        #[arg(long)]
+        commit_msg: bool,
+    },
+    /// Remove the prepare-commit-msg hook, or the commit-msg hook with --commit-msg
+    Uninstall {
+        /// Remove the commit-msg lint hook instead
+        #[arg(long)]
+        commit_msg: bool,
+    },
+    /// Run the hook (invoked by git)
+    Run {
+        /// Path to the commit message file
+        file: String,
+        /// Source of the message (message, template, merge, squash, commit)
+        source: Option<String>,
+        /// Commit hash for amends and -c/-C
+        sha: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum BisectAction {
+    /// Start a session and test commits until the first bad one is found
+    Start {
+        /// A commit where the test command passes
+        #[arg(short, long)]
+        good: String,
+        /// A commit where the test command fails
+        #[arg(short, long, default_value = "HEAD")]
+        bad: String,
+        /// Shell command that exits 0 for good commits, 125 to skip and 1-127 for bad ones
+        command: String,
+    },
+    /// Continue an interrupted session
+    Resume,
+    /// Show the steps recorded so far
+    Log,
+    /// End the session and return to where it started
+    Reset,
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    let cli = Cli::parse();
+    if let Some(directory) = &cli.directory {
+        std::env::set_current_dir(directory)
+            .with_context(|| format!("Cannot change to {}", directory.display()))?;
+    }
+
     // Load environment variables from .env file
     dotenvy::dotenv().ok();
-    
-    let cli = Cli::parse();
+
     let config = config::Config::load()?;
 
     match cli.command {
@@ -103,22 +290,33 @@ async fn main() -> Result<()> {
             };
             git::generate_tests(effective_base, &framework, &config).await?;
         }
-        Commands::ImproveCommit { commit } => {
-            git::improve_commit_message(commit.as_deref(), &config).await?;
+        Commands::ImproveCommit { commit, apply, range, force } => {
+            match range {
+                Some(range) => git::improve_commit_range(&range, force, &config).await?,
+                None => git::improve_commit_message(commit.as_deref(), apply, force, &config).await?,
+            }
         }
-            Commands::Commit { all } => {
-                git::interactive_commit(all, &config).await?;
+            Commands::Commit { all, no_verify, signoff, co_authors } => {
+                let trailer_options = trailers::TrailerOptions { signoff, co_authors };
+                git::interactive_commit(all, no_verify, &trailer_options, &config).await?;
             }
-            Commands::AiCommit { all } => {
-                git::ai_commit(all, &config).await?;
+            Commands::AiCommit { all, no_verify, split, signoff, co_authors } => {
+                let trailer_options = trailers::TrailerOptions { signoff, co_authors };
+                if split {
+                    git::split_commit(all, no_verify, &trailer_options, &config).await?;
+                } else {
+                    git::ai_commit(all, no_verify, &trailer_options, &config).await?;
+                }
             }
-        Commands::Changelog { base, output } => {
-            let effective_base = if base == "master" { 
-                config.get_default_branch() 
-            } else { 
-                &base 
-            };
-            git::generate_changelog(effective_base, output.as_deref(), &config).await?;
+        Commands::Explain { target, format } => {
+            explain::explain(&target, &format, &config).await?;
+        }
+        Commands::Why { target } => {
+            blame::why(&target, &config).await?;
+        }
+        Commands::Changelog { base, from_tag, to_tag, ai, output } => {
+            let from = base.or(from_tag);
+            changelog::generate(from.as_deref(), to_tag.as_deref(), ai, output.as_deref(), &config).await?;
         }
         Commands::Review { base } => {
             let effective_base = if base == "master" { 
@@ -134,10 +332,51 @@ async fn main() -> Result<()> {
         }
         Commands::LintTests { directory } => {
             let mut linter = test_linter::TestLinter::new(config);
-            let results = linter.lint_and_fix_tests(&directory).await?;
+            let directory = git::workdir_path(&directory);
+            let results = linter.lint_and_fix_tests(&directory.to_string_lossy()).await?;
             linter.print_summary(&results);
         }
+        Commands::LintCommits { range, message_file, format } => {
+            let passed = commit_linter::lint_commits(range.as_deref(), message_file.as_deref(), &format, &config)?;
+            if !passed {
+                std::process::exit(1);
+            }
+        }
+        Commands::Release { dry_run, bump, pre, no_verify } => {
+            let options = release::ReleaseOptions {
+                bump: bump.as_deref().map(release::Bump::parse).transpose()?,
+                channel: pre,
+                no_verify,
+            };
+            release::release(&options, dry_run, &config)?;
+        }
+        Commands::Bisect { action } => match action {
+            BisectAction::Start { good, bad, command } => bisect::start_session(&good, &bad, &command, &config).await?,
+            BisectAction::Resume => bisect::resume_session(&config).await?,
+            BisectAction::Log => bisect::print_log()?,
+            BisectAction::Reset => bisect::reset_session()?,
+        },
+        Commands::Tidy { base, apply, force } => tidy::tidy(base.as_deref(), apply, force, &config).await?,
+        Commands::FormatPatch { range, reroll_count, previous, output_dir } => {
+            patches::format_patch(range.as_deref(), reroll_count, previous.as_deref(), &output_dir, &config).await?;
+        }
+        Commands::Undo { count, list, force } => journal::undo_command(count, list, force)?,
+        Commands::Hook { action } => match action {
+            HookAction::Install { force, commit_msg } => hooks::install(hook_kind(commit_msg), force)?,
+            HookAction::Uninstall { commit_msg } => hooks::uninstall(hook_kind(commit_msg))?,
+            HookAction::Run { file, source, sha: _ } => {
+                hooks::run(&file, source.as_deref(), &config).await?;
+            }
+        },
     }
 
     Ok(())
 }
+
+fn hook_kind(commit_msg: bool) -> hooks::HookKind {
+    if commit_msg {
+        hooks::HookKind::CommitMsg
+    } else {
+        hooks::HookKind::PrepareCommitMsg
+    }
+}
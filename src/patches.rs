@@ -0,0 +1,531 @@
+use anyhow::{Context, Result};
+use git2::{DiffFindOptions, DiffOptions, Email, EmailCreateOptions, Oid, Repository};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::ai;
+use crate::config::Config;
+use crate::git::{self, DiffInfo};
+use crate::journal;
+use crate::rewrite;
+
+/// Widest diffstat bar, like `git diff --stat`.
+const STAT_WIDTH: usize = 40;
+
+/// A patch of a series, as read back from its mbox text.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SeriesPatch {
+    /// Subject without the `[PATCH ...]` tag
+    pub subject: String,
+    /// Paths the patch changes, as in its `diff --git` headers
+    pub files: Vec<String>,
+    /// The diff part of the patch
+    pub diff: String,
+}
+
+/// The parts of a cover letter that are written rather than computed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CoverLetter {
+    pub subject: String,
+    pub overview: String,
+    /// One summary per patch, in series order
+    pub summaries: Vec<String>,
+}
+
+/// Resolves `base..tip`, or a bare `base` for `base..HEAD`, to the base and
+/// the commits of the series, oldest first. Merges and empty commits are
+/// rejected, since every commit has to become exactly one patch.
+pub fn resolve_series(repo: &Repository, spec: &str) -> Result<(Option<Oid>, Vec<Oid>)> {
+    let (base, tip) = git::resolve_range(repo, spec)?;
+    let commits = rewrite::commits_between(repo, base, tip)?;
+    for id in &commits {
+        let commit = repo.find_commit(*id)?;
+        if commit.parent_count() > 1 {
+            anyhow::bail!("{} is a merge commit; a patch series has to be linear", git::short_oid(*id));
+        }
+        let empty = match commit.parent(0) {
+            Ok(parent) => parent.tree_id() == commit.tree_id(),
+            Err(_) => commit.tree()?.is_empty(),
+        };
+        if empty {
+            anyhow::bail!(
+                "{} \"{}\" changes no files; drop or squash it before formatting the series",
+                git::short_oid(*id),
+                commit.summary().unwrap_or("")
+            );
+        }
+    }
+    Ok((base, commits))
+}
+
+/// File name of a patch the way `git format-patch` names it, e.g.
+/// `v2-0001-fix-the-parser.patch`.
+pub fn patch_file_name(number: usize, subject: &str, reroll_count: usize) -> String {
+    let mut slug = String::new();
+    for c in subject.chars() {
+        if c.is_ascii_alphanumeric() || c == '_' || c == '.' {
+            slug.push(c);
+        } else if !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let mut slug: String = slug.trim_matches(['-', '.']).chars().take(52).collect();
+    while slug.ends_with(['-', '.']) {
+        slug.pop();
+    }
+    let prefix = if reroll_count > 1 { format!("v{}-", reroll_count) } else { String::new() };
+    format!("{}{:04}-{}.patch", prefix, number, slug)
+}
+
+/// Subject prefix of the series, e.g. `[PATCH v2 1/3]`.
+fn subject_tag(number: usize, total: usize, reroll_count: usize) -> String {
+    match reroll_count {
+        0 | 1 => format!("[PATCH {}/{}]", number, total),
+        version => format!("[PATCH v{} {}/{}]", version, number, total),
+    }
+}
+
+/// `git diff --stat` style summary of every file the series changes.
+pub fn diffstat(diff_info: &DiffInfo) -> String {
+    let mut files: BTreeMap<&str, (usize, usize, bool)> = BTreeMap::new();
+    for change in diff_info.commits.iter().flat_map(|c| &c.file_changes) {
+        let entry = files.entry(change.path.as_str()).or_default();
+        entry.0 += change.additions;
+        entry.1 += change.deletions;
+        entry.2 |= change.binary;
+    }
+    if files.is_empty() {
+        return String::new();
+    }
+
+    let name_width = files.keys().map(|path| path.chars().count()).max().unwrap_or(0);
+    let most = files.values().map(|(added, deleted, _)| added + deleted).max().unwrap_or(0);
+    let count_width = most.to_string().len();
+    let (mut insertions, mut deletions) = (0, 0);
+
+    let mut text = String::new();
+    for (path, (added, deleted, binary)) in &files {
+        insertions += added;
+        deletions += deleted;
+        if *binary {
+            text.push_str(&format!(" {:<name_width$} | Bin\n", path));
+            continue;
+        }
+        let total = added + deleted;
+        let (mut plus, mut minus) = (*added, *deleted);
+        if most > STAT_WIDTH {
+            plus = (added * STAT_WIDTH).div_ceil(most);
+            minus = (deleted * STAT_WIDTH).div_ceil(most);
+        }
+        text.push_str(&format!(
+            " {:<name_width$} | {:>count_width$} {}{}\n",
+            path,
+            total,
+            "+".repeat(plus),
+            "-".repeat(minus)
+        ));
+    }
+
+    let plural = |n: usize, word: &str| format!("{} {}{}", n, word, if n == 1 { "" } else { "s" });
+    text.push_str(&format!(" {} changed", plural(files.len(), "file")));
+    // Like git, a side is only left out when the other one has lines
+    if insertions > 0 || deletions == 0 {
+        text.push_str(&format!(", {}(+)", plural(insertions, "insertion")));
+    }
+    if deletions > 0 || insertions == 0 {
+        text.push_str(&format!(", {}(-)", plural(deletions, "deletion")));
+    }
+    text.push('\n');
+    text
+}
+
+/// Reads back a patch written by `git format-patch` or by this module.
+/// Returns `None` for cover letters and files that hold no patch.
+pub fn parse_patch_file(text: &str) -> Option<SeriesPatch> {
+    // Drop the signature after the last hunk: `-- ` from git, `--` from
+    // libgit2
+    let text = match text.rfind("\n-- \n").or_else(|| text.rfind("\n--\n")) {
+        Some(end) if text[end..].lines().filter(|line| !line.trim().is_empty()).count() <= 3 => &text[..=end],
+        _ => text,
+    };
+    let mut lines = text.lines();
+    let mut subject: Option<String> = None;
+    let mut in_subject = false;
+    // Headers end at the first empty line; long ones are folded onto lines
+    // starting with whitespace
+    for line in lines.by_ref() {
+        if line.is_empty() {
+            break;
+        }
+        if line.starts_with([' ', '\t']) {
+            if let Some(subject) = subject.as_mut().filter(|_| in_subject) {
+                subject.push(' ');
+                subject.push_str(line.trim());
+            }
+            continue;
+        }
+        in_subject = line.starts_with("Subject:");
+        if let Some(value) = line.strip_prefix("Subject:") {
+            subject = Some(value.trim().to_string());
+        }
+    }
+
+    let subject = subject?;
+    let subject = match subject.strip_prefix('[') {
+        Some(rest) if rest.to_uppercase().contains("PATCH") => rest.split_once(']').map(|(_, s)| s.trim()).unwrap_or(rest),
+        _ => subject.as_str(),
+    };
+
+    let mut diff = String::new();
+    let mut files = Vec::new();
+    let mut in_diff = false;
+    for line in lines {
+        if line.starts_with("diff --git ") {
+            in_diff = true;
+            if let Some((_, path)) = line.rsplit_once(" b/") {
+                files.push(path.to_string());
+            }
+        }
+        if in_diff {
+            diff.push_str(line);
+            diff.push('\n');
+        }
+    }
+    if files.is_empty() {
+        return None;
+    }
+    Some(SeriesPatch { subject: subject.to_string(), files, diff })
+}
+
+/// Reads back the patches of an earlier series, skipping the cover letter.
+pub fn read_series_files(files: &[PathBuf]) -> Result<Vec<SeriesPatch>> {
+    files.iter()
+        .map(|file| fs::read_to_string(file).with_context(|| format!("Could not read {}", file.display())))
+        .filter_map(|text| text.map(|text| parse_patch_file(&text)).transpose())
+        .collect()
+}
+
+/// Files `git format-patch` wrote for version `reroll_count` of a series
+/// in `dir`: `v2-0001-*.patch` and so on, or `0001-*.patch` for the first
+/// version. The cover letter is left out.
+pub fn find_series_files(dir: &Path, reroll_count: usize) -> Vec<PathBuf> {
+    let prefix = if reroll_count > 1 { format!("v{}-", reroll_count) } else { String::new() };
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut found: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let Some(rest) = name.strip_prefix(&prefix) else {
+                return false;
+            };
+            let Some(number) = rest.get(..5) else {
+                return false;
+            };
+            number[..4].chars().all(|c| c.is_ascii_digit())
+                && number.ends_with('-')
+                && number != "0000-"
+                && name.ends_with(".patch")
+        })
+        .map(|entry| entry.path())
+        .collect();
+    found.sort();
+    found
+}
+
+/// The diff of each file, without index lines and hunk positions, which
+/// change whenever an earlier patch does.
+fn normalized_diffs(diff: &str) -> BTreeMap<String, Vec<&str>> {
+    let mut files: BTreeMap<String, Vec<&str>> = BTreeMap::new();
+    let mut current: Option<String> = None;
+    for line in diff.lines() {
+        if line.starts_with("diff --git ") {
+            current = line.rsplit_once(" b/").map(|(_, path)| path.to_string());
+            continue;
+        }
+        let Some(path) = &current else {
+            continue;
+        };
+        if line.starts_with("index ") || line.starts_with("@@") {
+            continue;
+        }
+        files.entry(path.clone()).or_default().push(line);
+    }
+    files
+}
+
+/// What changed between two versions of a series, one line per change:
+/// patches added, dropped, reworded or with a different diff. Patches are
+/// matched by subject, then by identical diffs.
+pub fn compare_series(previous: &[SeriesPatch], current: &[SeriesPatch]) -> Vec<String> {
+    let mut matched: Vec<Option<usize>> = vec![None; current.len()];
+    let mut used = vec![false; previous.len()];
+
+    for (i, patch) in current.iter().enumerate() {
+        if let Some(j) = (0..previous.len()).find(|&j| !used[j] && previous[j].subject == patch.subject) {
+            matched[i] = Some(j);
+            used[j] = true;
+        }
+    }
+    for (i, patch) in current.iter().enumerate() {
+        if matched[i].is_some() {
+            continue;
+        }
+        let diff = normalized_diffs(&patch.diff);
+        if let Some(j) = (0..previous.len()).find(|&j| !used[j] && normalized_diffs(&previous[j].diff) == diff) {
+            matched[i] = Some(j);
+            used[j] = true;
+        }
+    }
+
+    let total = current.len();
+    let mut changes = Vec::new();
+    for (i, patch) in current.iter().enumerate() {
+        let label = format!("{}/{} \"{}\"", i + 1, total, patch.subject);
+        let Some(j) = matched[i] else {
+            changes.push(format!("{}: new patch", label));
+            continue;
+        };
+        let old = &previous[j];
+        let mut notes = Vec::new();
+        if old.subject != patch.subject {
+            notes.push(format!("reworded from \"{}\"", old.subject));
+        }
+        let (before, after) = (normalized_diffs(&old.diff), normalized_diffs(&patch.diff));
+        let paths: BTreeSet<&String> = before.keys().chain(after.keys()).collect();
+        let touched: Vec<&str> = paths.into_iter()
+            .filter(|path| before.get(*path) != after.get(*path))
+            .map(String::as_str)
+            .collect();
+        if !touched.is_empty() {
+            notes.push(format!("changed {}", touched.join(", ")));
+        }
+        if !notes.is_empty() {
+            changes.push(format!("{}: {}", label, notes.join("; ")));
+        }
+    }
+    for (j, patch) in previous.iter().enumerate() {
+        if !used[j] {
+            changes.push(format!("dropped \"{}\"", patch.subject));
+        }
+    }
+    changes
+}
+
+/// How the previous version of the series is referred to: `v1` for a v2.
+fn previous_label(reroll_count: usize) -> String {
+    if reroll_count > 1 {
+        format!("v{}", reroll_count - 1)
+    } else {
+        "the previous version".to_string()
+    }
+}
+
+/// The cover letter of the series as an mbox message, with the changes
+/// since the previous version, a summary per patch, the shortlog and the
+/// diffstat.
+pub fn render_cover_letter(
+    repo: &Repository,
+    letter: &CoverLetter,
+    patches: &[SeriesPatch],
+    diff_info: &DiffInfo,
+    changes_since: Option<&[String]>,
+    reroll_count: usize,
+) -> Result<String> {
+    let sender = repo.signature()?;
+    let total = patches.len();
+    let mut text = format!(
+        "From {} Mon Sep 17 00:00:00 2001\nFrom: {} <{}>\nDate: {}\nSubject: {} {}\n\n",
+        Oid::zero(),
+        sender.name().unwrap_or(""),
+        sender.email().unwrap_or(""),
+        chrono::Local::now().to_rfc2822(),
+        subject_tag(0, total, reroll_count),
+        letter.subject.trim()
+    );
+
+    text.push_str(letter.overview.trim());
+    text.push_str("\n\n");
+
+    if let Some(changes) = changes_since {
+        text.push_str(&format!("Changes since {}:\n", previous_label(reroll_count)));
+        if changes.is_empty() {
+            text.push_str("- no changes to the patches\n");
+        }
+        for change in changes {
+            text.push_str(&format!("- {}\n", change));
+        }
+        text.push('\n');
+    }
+
+    for (i, patch) in patches.iter().enumerate() {
+        text.push_str(&format!("[{}/{}] {}\n", i + 1, total, patch.subject));
+        if let Some(summary) = letter.summaries.get(i).map(|s| s.trim()).filter(|s| !s.is_empty()) {
+            for line in summary.lines() {
+                text.push_str(&format!("      {}\n", line.trim()));
+            }
+        }
+    }
+    text.push('\n');
+
+    // Shortlog, grouped by author in order of appearance
+    let mut authors: Vec<(&str, Vec<&str>)> = Vec::new();
+    for (info, patch) in diff_info.commits.iter().zip(patches) {
+        match authors.iter_mut().find(|(author, _)| *author == info.author) {
+            Some((_, subjects)) => subjects.push(&patch.subject),
+            None => authors.push((&info.author, vec![&patch.subject])),
+        }
+    }
+    for (author, subjects) in authors {
+        text.push_str(&format!("{} ({}):\n", author, subjects.len()));
+        for subject in subjects {
+            text.push_str(&format!("  {}\n", subject));
+        }
+        text.push('\n');
+    }
+
+    text.push_str(&diffstat(diff_info));
+    text.push_str("\n-- \ncommit-buddy\n\n");
+    Ok(text)
+}
+
+/// Formats every commit as an mbox patch numbered after the cover letter,
+/// like `git format-patch`. Returns the file names and contents.
+pub fn format_patches(repo: &Repository, commits: &[Oid], reroll_count: usize) -> Result<Vec<(String, String)>> {
+    let total = commits.len();
+    let mut patches = Vec::new();
+    for (i, id) in commits.iter().enumerate() {
+        let commit = repo.find_commit(*id)?;
+        let parent_tree = match commit.parent(0) {
+            Ok(parent) => Some(parent.tree()?),
+            Err(_) => None,
+        };
+        let mut diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&commit.tree()?), Some(&mut DiffOptions::new()))?;
+        diff.find_similar(Some(DiffFindOptions::new().renames(true)))?;
+
+        let mut options = EmailCreateOptions::new();
+        options.always_number(true);
+        if reroll_count > 1 {
+            options.reroll_number(reroll_count);
+        }
+        let summary = commit.summary().unwrap_or("").to_string();
+        let body = commit.body().unwrap_or("").to_string();
+        let email = Email::from_diff(&diff, i + 1, total, id, summary.as_str(), body.as_str(), &commit.author(), &mut options)
+            .with_context(|| format!("Could not format {} as a patch", git::short_oid(*id)))?;
+        patches.push((
+            patch_file_name(i + 1, &summary, reroll_count),
+            String::from_utf8_lossy(email.as_slice()).into_owned(),
+        ));
+    }
+    Ok(patches)
+}
+
+/// Writes the cover letter and the patches into `dir`. Returns the paths
+/// written, cover letter first. The writes are journaled, so
+/// `commit-buddy undo` removes or restores them.
+pub fn write_series(repo: &Repository, patches: &[(String, String)], cover_letter: &str, dir: &Path, reroll_count: usize) -> Result<Vec<PathBuf>> {
+    let prefix = if reroll_count > 1 { format!("v{}-", reroll_count) } else { String::new() };
+    let cover = (format!("{}0000-cover-letter.patch", prefix), cover_letter.to_string());
+    let files: Vec<(PathBuf, &String)> = std::iter::once(&cover)
+        .chain(patches)
+        .map(|(name, content)| (dir.join(name), content))
+        .collect();
+
+    let paths: Vec<PathBuf> = files.iter().map(|(path, _)| path.clone()).collect();
+    journal::action(repo, format!("format-patch v{} ({} patches)", reroll_count.max(1), patches.len()))
+        .files(&paths)
+        .run(|| {
+            fs::create_dir_all(dir)?;
+            for (path, content) in &files {
+                fs::write(path, content)?;
+            }
+            Ok(())
+        })?;
+    Ok(paths)
+}
+
+/// Reads back formatted patches.
+fn parse_patches(patches: &[(String, String)]) -> Vec<SeriesPatch> {
+    patches.iter().filter_map(|(_, text)| parse_patch_file(text)).collect()
+}
+
+/// The previous version of the series: the files of version
+/// `reroll_count - 1` in the directory `spec` (or `dir` without `spec`),
+/// or else the commits of a range or of a tip sharing the current base.
+fn previous_series(repo: &Repository, spec: Option<&str>, base: Option<Oid>, dir: &Path, reroll_count: usize) -> Result<Option<Vec<SeriesPatch>>> {
+    let previous = reroll_count.saturating_sub(1);
+    let Some(spec) = spec else {
+        let patches = read_series_files(&find_series_files(dir, previous))?;
+        return Ok(Some(patches).filter(|patches| !patches.is_empty()));
+    };
+
+    if Path::new(spec).is_dir() {
+        let files = find_series_files(Path::new(spec), previous);
+        if files.is_empty() {
+            anyhow::bail!("No {} patches found in {}", previous_label(reroll_count), spec);
+        }
+        return read_series_files(&files).map(Some);
+    }
+    let commits = if spec.contains("..") {
+        resolve_series(repo, spec)?.1
+    } else {
+        rewrite::commits_between(repo, base, git::resolve_commit(repo, spec)?)?
+    };
+    Ok(Some(parse_patches(&format_patches(repo, &commits, previous)?)))
+}
+
+/// `format-patch`: writes the commits of `range` as mbox patches with a
+/// cover letter, and lists the changes since the previous version for a
+/// reroll.
+pub async fn format_patch(range: Option<&str>, reroll_count: usize, previous: Option<&str>, output_dir: &str, config: &Config) -> Result<()> {
+    let repo = git::open_repository()?;
+    let range = range.unwrap_or(config.get_default_branch());
+    let (base, commits) = resolve_series(&repo, range)?;
+    if commits.is_empty() {
+        println!("No commits found in {}.", if range.contains("..") { range.to_string() } else { format!("{}..HEAD", range) });
+        return Ok(());
+    }
+
+    let infos = commits.iter()
+        .map(|id| git::commit_info(&repo, &repo.find_commit(*id)?))
+        .collect::<Result<Vec<_>>>()?;
+    let formatted = format_patches(&repo, &commits, reroll_count)?;
+    let patches = parse_patches(&formatted);
+    let diff_info = DiffInfo::from_commits(infos).with_workdir(&repo);
+
+    let dir = PathBuf::from(output_dir);
+    let changes_since = if reroll_count > 1 || previous.is_some() {
+        match previous_series(&repo, previous, base, &dir, reroll_count)? {
+            Some(old) => Some(compare_series(&old, &patches)),
+            None => {
+                println!(
+                    "⚠️ No v{} patches found in {}; pass --previous to list the changes since then",
+                    reroll_count - 1,
+                    dir.display()
+                );
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    println!("✍️ Writing the cover letter for {} patch(es)...", patches.len());
+    let letter = ai::write_cover_letter(&diff_info, changes_since.as_deref().unwrap_or(&[]), config).await?;
+    let cover_letter = render_cover_letter(&repo, &letter, &patches, &diff_info, changes_since.as_deref(), reroll_count)?;
+
+    for path in write_series(&repo, &formatted, &cover_letter, &dir, reroll_count)? {
+        println!("{}", path.display());
+    }
+    if let Some(changes) = &changes_since {
+        let dropped = changes.iter().filter(|change| change.starts_with("dropped")).count();
+        println!(
+            "🔁 {} patch(es) changed or added, {} dropped since {}",
+            changes.len() - dropped,
+            dropped,
+            previous_label(reroll_count)
+        );
+    }
+    Ok(())
+}
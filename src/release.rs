@@ -0,0 +1,484 @@
+use anyhow::{Context, Result};
+use git2::{Oid, Repository};
+use regex::Regex;
+use std::cmp::Ordering;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use crate::changelog::{self, ForgeLinks, ReleaseSection};
+use crate::committer::Committer;
+use crate::config::Config;
+use crate::conventional::ConventionalCommit;
+use crate::git;
+use crate::journal;
+use crate::rewrite;
+use crate::trailers::{self, TrailerOptions};
+
+/// A semantic version, `MAJOR.MINOR.PATCH[-PRERELEASE]`. Build metadata is
+/// accepted when parsing and dropped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Version {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+    /// Dot-separated pre-release identifiers, e.g. `rc.1`
+    pub pre: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Bump {
+    Patch,
+    Minor,
+    Major,
+}
+
+impl Version {
+    pub fn new(major: u64, minor: u64, patch: u64) -> Self {
+        Self { major, minor, patch, pre: None }
+    }
+
+    /// Parses `1.2.3`, `v1.2.3` or `1.2.3-beta.2+build.5`.
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        let text = text.strip_prefix('v').unwrap_or(text);
+        let text = text.split('+').next()?;
+        let (core, pre) = match text.split_once('-') {
+            Some((core, pre)) if !pre.is_empty() => (core, Some(pre.to_string())),
+            Some(_) => return None,
+            None => (text, None),
+        };
+
+        let numbers: Vec<u64> = core.split('.')
+            .map(|part| part.parse().ok())
+            .collect::<Option<_>>()?;
+        match numbers.as_slice() {
+            [major, minor, patch] => Some(Self { major: *major, minor: *minor, patch: *patch, pre }),
+            _ => None,
+        }
+    }
+
+    /// The same version without its pre-release part.
+    pub fn release(&self) -> Self {
+        Self::new(self.major, self.minor, self.patch)
+    }
+
+    pub fn bump(&self, bump: Bump) -> Self {
+        match bump {
+            Bump::Major => Self::new(self.major + 1, 0, 0),
+            Bump::Minor => Self::new(self.major, self.minor + 1, 0),
+            Bump::Patch => Self::new(self.major, self.minor, self.patch + 1),
+        }
+    }
+
+    /// The channel and number of a `channel.N` pre-release.
+    fn channel(&self) -> Option<(&str, u64)> {
+        let (name, number) = self.pre.as_deref()?.rsplit_once('.')?;
+        Some((name, number.parse().ok()?))
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)?;
+        if let Some(pre) = &self.pre {
+            write!(f, "-{}", pre)?;
+        }
+        Ok(())
+    }
+}
+
+impl Ord for Version {
+    /// Precedence as defined by semver: a pre-release sorts before its
+    /// release, and numeric identifiers compare numerically.
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.major, self.minor, self.patch)
+            .cmp(&(other.major, other.minor, other.patch))
+            .then_with(|| match (&self.pre, &other.pre) {
+                (None, None) => Ordering::Equal,
+                (None, Some(_)) => Ordering::Greater,
+                (Some(_), None) => Ordering::Less,
+                (Some(a), Some(b)) => compare_pre(a, b),
+            })
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+fn compare_pre(a: &str, b: &str) -> Ordering {
+    let mut left = a.split('.');
+    let mut right = b.split('.');
+    loop {
+        let ordering = match (left.next(), right.next()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) => match (x.parse::<u64>(), y.parse::<u64>()) {
+                (Ok(x), Ok(y)) => x.cmp(&y),
+                (Ok(_), Err(_)) => Ordering::Less,
+                (Err(_), Ok(_)) => Ordering::Greater,
+                (Err(_), Err(_)) => x.cmp(y),
+            },
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+}
+
+impl Bump {
+    pub fn parse(text: &str) -> Result<Self> {
+        match text {
+            "major" => Ok(Bump::Major),
+            "minor" => Ok(Bump::Minor),
+            "patch" => Ok(Bump::Patch),
+            other => anyhow::bail!("Unknown bump '{}' (expected major, minor or patch)", other),
+        }
+    }
+}
+
+/// The bump the commits call for: breaking changes are major, features
+/// minor, and fixes or performance work patch. Before 1.0.0 everything
+/// moves down one level, so a breaking change only bumps the minor version.
+pub fn bump_for(current: &Version, commits: &[ConventionalCommit]) -> Option<Bump> {
+    let bump = commits.iter()
+        .filter_map(|commit| match commit.kind().as_str() {
+            _ if commit.breaking => Some(Bump::Major),
+            "feat" => Some(Bump::Minor),
+            "fix" | "perf" => Some(Bump::Patch),
+            _ => None,
+        })
+        .max()?;
+
+    if current.major == 0 {
+        Some(match bump {
+            Bump::Major => Bump::Minor,
+            _ => Bump::Patch,
+        })
+    } else {
+        Some(bump)
+    }
+}
+
+/// The version after `current` (the latest tag) when `stable` is the
+/// latest release and `bump` applies to it. A pending pre-release is
+/// reused unless the bump goes past it: without a channel it is promoted,
+/// and on the same channel it counts up (`1.2.0-rc.1` → `1.2.0-rc.2`).
+/// A new channel starts at 1.
+pub fn next_version(current: &Version, stable: &Version, bump: Bump, channel: Option<&str>) -> Version {
+    let target = stable.bump(bump);
+    let pending = current.pre.is_some() && current.release() >= target;
+    let base = if pending { current.release() } else { target };
+
+    let Some(channel) = channel else {
+        return base;
+    };
+    let number = match current.channel() {
+        Some((name, number)) if pending && name == channel => number + 1,
+        _ => 1,
+    };
+    Version {
+        pre: Some(format!("{}.{}", channel, number)),
+        ..base
+    }
+}
+
+/// The latest semver tag reachable from HEAD, with the commit it points at.
+/// Pre-release tags are skipped unless `include_pre` is set.
+pub fn latest_tag(repo: &Repository, include_pre: bool) -> Result<Option<(String, Version, Oid)>> {
+    let Some(head) = git::head_commit(repo)? else {
+        return Ok(None);
+    };
+
+    let mut latest: Option<(String, Version, Oid)> = None;
+    for name in repo.tag_names(None)?.iter().flatten() {
+        let Some(version) = Version::parse(name).filter(|v| include_pre || v.pre.is_none()) else {
+            continue;
+        };
+        let target = repo.revparse_single(&format!("refs/tags/{}", name))?.peel_to_commit()?.id();
+        let reachable = target == head.id() || repo.graph_descendant_of(head.id(), target)?;
+        if reachable && latest.as_ref().is_none_or(|(_, best, _)| version > *best) {
+            latest = Some((name.to_string(), version, target));
+        }
+    }
+    Ok(latest)
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ReleaseOptions {
+    /// Overrides the bump derived from the commits
+    pub bump: Option<Bump>,
+    /// Pre-release channel such as `alpha`, `beta` or `rc`
+    pub channel: Option<String>,
+    pub no_verify: bool,
+}
+
+/// A commit since the previous stable release, parsed when it is
+/// conventional.
+#[derive(Debug)]
+pub struct ReleaseCommit {
+    pub id: Oid,
+    pub summary: String,
+    pub parsed: Option<ConventionalCommit>,
+}
+
+#[derive(Debug)]
+pub struct ReleasePlan {
+    pub previous_tag: Option<String>,
+    pub current: Version,
+    pub next: Version,
+    pub tag: String,
+    pub commits: Vec<ReleaseCommit>,
+    /// Version files found in the working tree, relative to it
+    pub version_files: Vec<String>,
+    pub section: ReleaseSection,
+    /// The rendered changelog section
+    pub notes: String,
+}
+
+/// Works out the next release from the commits since the latest tag.
+pub fn plan(repo: &Repository, options: &ReleaseOptions) -> Result<ReleasePlan> {
+    let head = git::head_commit(repo)?
+        .context("The repository has no commits to release")?
+        .id();
+    let previous = latest_tag(repo, true)?;
+    let stable = latest_tag(repo, false)?;
+    let current = previous.as_ref()
+        .map(|(_, version, _)| version.clone())
+        .unwrap_or_else(|| Version::new(0, 0, 0));
+
+    let promoting = current.pre.is_some() && options.channel.is_none();
+    let unreleased = rewrite::commits_between(repo, previous.as_ref().map(|(_, _, id)| *id), head)?;
+    if unreleased.is_empty() && !promoting {
+        anyhow::bail!("No commits since {}", previous.as_ref().map(|(name, _, _)| name.as_str()).unwrap_or("the first commit"));
+    }
+
+    // Pre-releases are cumulative: bump and notes cover everything since
+    // the last stable release
+    let mut commits = Vec::new();
+    for id in rewrite::commits_between(repo, stable.as_ref().map(|(_, _, id)| *id), head)? {
+        let commit = repo.find_commit(id)?;
+        if commit.parent_count() > 1 {
+            continue;
+        }
+        commits.push(ReleaseCommit {
+            id,
+            summary: commit.summary().unwrap_or("").to_string(),
+            parsed: ConventionalCommit::parse(commit.message().unwrap_or("")).ok(),
+        });
+    }
+    let stable_version = stable.as_ref()
+        .map(|(_, version, _)| version.clone())
+        .unwrap_or_else(|| Version::new(0, 0, 0));
+    let parsed: Vec<ConventionalCommit> = commits.iter().filter_map(|c| c.parsed.clone()).collect();
+    let bump = match options.bump {
+        Some(bump) => bump,
+        // The first release is 0.1.0 unless a bump is forced
+        None if stable.is_none() => Bump::Minor,
+        None => match bump_for(&stable_version, &parsed) {
+            Some(bump) => bump,
+            // Promoting a pre-release needs no releasable commits of its own
+            None if current.pre.is_some() => Bump::Patch,
+            None => anyhow::bail!("No feat, fix or breaking commits since the last release; pass --bump to release anyway"),
+        },
+    };
+    let next = next_version(&current, &stable_version, bump, options.channel.as_deref());
+
+    // Keep the previous tag's prefix (`v1.2.0` or `1.2.0`)
+    let prefix = match &previous {
+        Some((name, _, _)) if !name.starts_with('v') => "",
+        _ => "v",
+    };
+    let workdir = repo.workdir().context("Cannot release in a bare repository")?;
+    let version_files = VERSION_FILES.iter()
+        .filter(|file| workdir.join(file).is_file())
+        .map(|file| file.to_string())
+        .collect();
+
+    let tag = format!("{}{}", prefix, next);
+    let section = ReleaseSection {
+        version: Some(next.to_string()),
+        tag: Some(tag.clone()),
+        previous: stable.as_ref().map(|(name, _, _)| name.clone()),
+        date: Some(chrono::Local::now().format("%Y-%m-%d").to_string()),
+        entries: changelog::entries(repo, stable.as_ref().map(|(_, _, id)| *id), head)?,
+    };
+    let notes = changelog::render_section(&section, ForgeLinks::detect(repo).as_ref());
+
+    Ok(ReleasePlan {
+        previous_tag: previous.map(|(name, _, _)| name),
+        current,
+        next,
+        tag,
+        commits,
+        version_files,
+        section,
+        notes,
+    })
+}
+
+const VERSION_FILES: [&str; 4] = ["Cargo.toml", "Cargo.lock", "package.json", "pyproject.toml"];
+
+/// Sets the version of the package described by a manifest. Cargo and
+/// Python manifests are edited line by line so formatting and comments
+/// survive; for `package.json` the top-level `"version"` is replaced.
+pub fn set_manifest_version(file: &str, content: &str, version: &Version) -> Result<Option<String>> {
+    match file {
+        "Cargo.toml" => Ok(set_toml_version(content, &["package", "workspace.package"], version)),
+        "pyproject.toml" => Ok(set_toml_version(content, &["project", "tool.poetry"], version)),
+        "package.json" => {
+            let pattern = Regex::new(r#"(?m)^(\s{0,4}"version"\s*:\s*)"[^"]*""#)?;
+            Ok(pattern.find(content).map(|_| {
+                pattern.replace(content, format!("${{1}}\"{}\"", version).as_str()).to_string()
+            }))
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Replaces `version = "..."` in the first of `sections` that has one.
+fn set_toml_version(content: &str, sections: &[&str], version: &Version) -> Option<String> {
+    let mut section = String::new();
+    let mut lines: Vec<String> = content.lines().map(str::to_string).collect();
+    for line in lines.iter_mut() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') {
+            section = trimmed.trim_matches(|c| c == '[' || c == ']').trim().to_string();
+            continue;
+        }
+        let is_version = trimmed.strip_prefix("version")
+            .is_some_and(|rest| rest.trim_start().starts_with('='));
+        if is_version && sections.contains(&section.as_str()) && trimmed.contains('"') {
+            // Only the quoted value changes, so trailing comments survive
+            let start = line.find('"')?;
+            let end = start + 1 + line[start + 1..].find('"')?;
+            line.replace_range(start..=end, &format!("\"{}\"", version));
+            return Some(join_lines(&lines, content));
+        }
+    }
+    None
+}
+
+/// Updates the root package's entry in `Cargo.lock` so the release commit
+/// does not leave the lock file stale.
+fn set_lockfile_version(lock: &str, manifest: &str, version: &Version) -> Option<String> {
+    let name = manifest.lines()
+        .skip_while(|line| line.trim() != "[package]")
+        .find_map(|line| line.trim().strip_prefix("name")
+            .and_then(|rest| rest.trim_start().strip_prefix('='))
+            .map(|value| value.trim().trim_matches('"').to_string()))?;
+
+    let mut lines: Vec<String> = lock.lines().map(str::to_string).collect();
+    let entry = lines.iter().position(|line| line.trim() == format!("name = \"{}\"", name))?;
+    let version_line = lines.get_mut(entry + 1).filter(|line| line.trim().starts_with("version = "))?;
+    *version_line = format!("version = \"{}\"", version);
+    Some(join_lines(&lines, lock))
+}
+
+fn join_lines(lines: &[String], original: &str) -> String {
+    let mut joined = lines.join("\n");
+    if original.ends_with('\n') {
+        joined.push('\n');
+    }
+    joined
+}
+
+/// Writes the version files and changelog, commits them, and creates an
+/// annotated tag on the release commit. Returns the commit id.
+pub fn execute(repo: &Repository, committer: &Committer, plan: &ReleasePlan) -> Result<Oid> {
+    let workdir = repo.workdir().context("Cannot release in a bare repository")?;
+    let written: Vec<PathBuf> = plan.version_files.iter()
+        .map(|file| workdir.join(file))
+        .chain(std::iter::once(workdir.join("CHANGELOG.md")))
+        .collect();
+    journal::action(repo, format!("release {}", plan.tag))
+        .files(&written)
+        .run(|| write_and_tag(repo, workdir, committer, plan))
+}
+
+fn write_and_tag(repo: &Repository, workdir: &Path, committer: &Committer, plan: &ReleasePlan) -> Result<Oid> {
+    let mut changed = Vec::new();
+
+    for file in &plan.version_files {
+        let path = workdir.join(file);
+        let content = std::fs::read_to_string(&path)?;
+        let updated = if file == "Cargo.lock" {
+            let manifest = std::fs::read_to_string(workdir.join("Cargo.toml")).unwrap_or_default();
+            set_lockfile_version(&content, &manifest, &plan.next)
+        } else {
+            set_manifest_version(file, &content, &plan.next)?
+        };
+        if let Some(updated) = updated.filter(|updated| *updated != content) {
+            std::fs::write(&path, updated)?;
+            changed.push(file.clone());
+        }
+    }
+
+    let changelog = workdir.join("CHANGELOG.md");
+    let existing = std::fs::read_to_string(&changelog).ok();
+    let generated = changelog::render(std::slice::from_ref(&plan.section), ForgeLinks::detect(repo).as_ref());
+    let (updated, _) = changelog::update(existing.as_deref(), &generated, true);
+    std::fs::write(&changelog, updated)?;
+    changed.push("CHANGELOG.md".to_string());
+
+    let mut index = repo.index()?;
+    for file in &changed {
+        index.add_path(Path::new(file))?;
+    }
+    index.write()?;
+
+    let outcome = committer.commit(&format!("chore(release): {}", plan.next))?;
+
+    let tagger = repo.signature()?;
+    let target = repo.find_object(outcome.id, None)?;
+    let message = format!("Release {}\n\n{}", plan.next, plan.notes);
+    repo.tag(&plan.tag, &target, &tagger, &message, false)
+        .with_context(|| format!("Could not create tag {}", plan.tag))?;
+
+    Ok(outcome.id)
+}
+
+/// `release`: prints the plan and, unless `dry_run` is set, carries it out.
+pub fn release(options: &ReleaseOptions, dry_run: bool, config: &Config) -> Result<()> {
+    let repo = git::open_repository()?;
+    let plan = plan(&repo, options)?;
+
+    println!("🚀 Release plan");
+    println!("   Previous release: {}", plan.previous_tag.as_deref().unwrap_or("none"));
+    println!("   Next version:     {} → {}", plan.current, plan.next);
+    println!("   Tag:              {}", plan.tag);
+    let conventional = plan.commits.iter().filter(|c| c.parsed.is_some()).count();
+    println!("   Commits:          {} ({} conventional)", plan.commits.len(), conventional);
+    for commit in &plan.commits {
+        let marker = match &commit.parsed {
+            Some(parsed) => parsed.emoji(),
+            None => "  ",
+        };
+        println!("     {} {} {}", marker, git::short_oid(commit.id), commit.summary);
+    }
+    let files = if plan.version_files.is_empty() {
+        "none found".to_string()
+    } else {
+        plan.version_files.join(", ")
+    };
+    println!("   Version files:    {}", files);
+    println!("\n📋 CHANGELOG.md section:\n\n{}", plan.notes);
+
+    if dry_run {
+        println!("🔍 Dry run: nothing was changed");
+        return Ok(());
+    }
+
+    git::ensure_clean_worktree(&repo, "releasing")?;
+    let trailers = trailers::for_commit(&repo, config, &TrailerOptions::default())?;
+    let committer = Committer::new(&repo)
+        .no_verify(options.no_verify)
+        .trailers(trailers);
+    let id = execute(&repo, &committer, &plan)?;
+
+    println!("✅ Released {} ({})", plan.tag, git::short_oid(id));
+    println!("   Publish with: git push --follow-tags");
+    Ok(())
+}
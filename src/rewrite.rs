@@ -0,0 +1,190 @@
+use anyhow::Result;
+use git2::{BranchType, Oid, Repository, Sort};
+use std::collections::HashMap;
+use crate::committer;
+use crate::git;
+use crate::journal;
+use crate::trailers;
+
+/// Result of rewriting a range of commits.
+#[derive(Debug)]
+pub struct RewriteOutcome {
+    pub backup_ref: String,
+    pub old_head: Oid,
+    pub new_head: Oid,
+    /// Old commit id → new commit id, oldest first
+    pub mapping: Vec<(Oid, Oid)>,
+}
+
+/// A commit range resolved against HEAD. `base` is `None` when the range
+/// starts at the root commit.
+#[derive(Debug)]
+pub struct HeadRange {
+    pub base: Option<Oid>,
+    pub head: Oid,
+    /// Commits in `base..head`, oldest first
+    pub commits: Vec<Oid>,
+}
+
+/// Resolves `base..HEAD` (or a bare `base`) and checks that the range ends
+/// at HEAD, since only HEAD's branch can be rewritten in place.
+pub fn resolve_head_range(repo: &Repository, spec: &str) -> Result<HeadRange> {
+    let head = repo.head()?.peel_to_commit()?.id();
+    let (base, tip) = git::resolve_range(repo, spec)?;
+
+    if tip != head {
+        anyhow::bail!("The range must end at HEAD ({}), got {}", git::short_oid(head), git::short_oid(tip));
+    }
+
+    Ok(HeadRange {
+        base,
+        head,
+        commits: commits_between(repo, base, head)?,
+    })
+}
+
+/// Commits reachable from `head` but not from `base`, oldest first.
+pub fn commits_between(repo: &Repository, base: Option<Oid>, head: Oid) -> Result<Vec<Oid>> {
+    let mut walk = repo.revwalk()?;
+    walk.set_sorting(Sort::TOPOLOGICAL | Sort::REVERSE)?;
+    walk.push(head)?;
+    if let Some(base) = base {
+        walk.hide(base)?;
+    }
+    walk.map(|id| id.map_err(Into::into)).collect()
+}
+
+/// Refuses to rewrite commits that are already on the current branch's
+/// upstream unless `force` is set.
+pub fn ensure_unpublished(repo: &Repository, commits: &[Oid], force: bool) -> Result<()> {
+    let Some(upstream) = upstream_tip(repo)? else {
+        return Ok(());
+    };
+
+    let published: Vec<Oid> = commits.iter()
+        .copied()
+        .filter(|&id| id == upstream || repo.graph_descendant_of(upstream, id).unwrap_or(false))
+        .collect();
+
+    if published.is_empty() {
+        return Ok(());
+    }
+    if force {
+        println!("⚠️ Rewriting {} commit(s) that are already on the upstream (--force)", published.len());
+        return Ok(());
+    }
+
+    anyhow::bail!(
+        "{} commit(s) in the range are already on the upstream branch (e.g. {}). Rewriting them would diverge from the remote; pass --force to do it anyway.",
+        published.len(),
+        git::short_oid(published[0])
+    )
+}
+
+fn upstream_tip(repo: &Repository) -> Result<Option<Oid>> {
+    let head = repo.head()?;
+    if !head.is_branch() {
+        return Ok(None);
+    }
+    let Some(name) = head.shorthand() else {
+        return Ok(None);
+    };
+    let branch = repo.find_branch(name, BranchType::Local)?;
+    let upstream = match branch.upstream() {
+        Ok(upstream) => upstream,
+        Err(_) => return Ok(None),
+    };
+    Ok(upstream.get().target())
+}
+
+/// Rebuilds every commit in `range` with an identical tree, swapping in the
+/// messages from `messages` (commits without an entry keep theirs). Authors
+/// are preserved; the committer becomes the current user, as with a rebase.
+/// Trailers of the original messages are carried over to the new ones.
+/// The old HEAD is kept under a backup ref before HEAD is moved.
+pub fn reword(repo: &Repository, range: &HeadRange, messages: &HashMap<Oid, String>) -> Result<RewriteOutcome> {
+    let committer_sig = repo.signature()?;
+    let mut rewritten: HashMap<Oid, Oid> = HashMap::new();
+    let mut mapping = Vec::new();
+
+    for &id in &range.commits {
+        let commit = repo.find_commit(id)?;
+
+        let parents = commit.parent_ids()
+            .map(|parent| repo.find_commit(*rewritten.get(&parent).unwrap_or(&parent)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let parent_refs: Vec<&git2::Commit> = parents.iter().collect();
+
+        let original = commit.message().unwrap_or("");
+        let message = match messages.get(&id) {
+            Some(message) => trailers::preserve(original, message),
+            None => original.to_string(),
+        };
+
+        let new_id = committer::write_commit(
+            repo,
+            &commit.author(),
+            &committer_sig,
+            &message,
+            &commit.tree()?,
+            &parent_refs,
+        )?;
+
+        rewritten.insert(id, new_id);
+        mapping.push((id, new_id));
+    }
+
+    let new_head = *rewritten.get(&range.head).unwrap_or(&range.head);
+    let backup_ref = create_backup_ref(repo, range.head)?;
+    journal::action(repo, format!("reword {} commit(s)", messages.len()))
+        .run(|| committer::update_head(repo, new_head, "commit-buddy: reword commits"))?;
+
+    Ok(RewriteOutcome {
+        backup_ref,
+        old_head: range.head,
+        new_head,
+        mapping,
+    })
+}
+
+/// Records `id` under `refs/commit-buddy/backup/<branch>-<timestamp>` so a
+/// rewrite can be undone with `git reset --soft <ref>`. Rewrites within the
+/// same second get a `-2`, `-3`, ... suffix instead of replacing a backup.
+pub fn create_backup_ref(repo: &Repository, id: Oid) -> Result<String> {
+    let branch = repo.head().ok()
+        .and_then(|head| head.shorthand().map(str::to_string))
+        .unwrap_or_else(|| "HEAD".to_string())
+        .replace('/', "-");
+    let stem = format!("refs/commit-buddy/backup/{}-{}", branch, chrono::Utc::now().timestamp());
+
+    let mut name = stem.clone();
+    for attempt in 2.. {
+        match repo.reference(&name, id, false, "commit-buddy: backup before rewrite") {
+            Ok(_) => break,
+            Err(e) if e.code() == git2::ErrorCode::Exists => name = format!("{}-{}", stem, attempt),
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(name)
+}
+
+/// Prints a before/after table of the first message line of each commit.
+pub fn print_reword_table(repo: &Repository, outcome: &RewriteOutcome) -> Result<()> {
+    println!("\nBefore    After     Old message                                  New message");
+    println!("{}", "─".repeat(110));
+    for (old, new) in &outcome.mapping {
+        let old_summary = repo.find_commit(*old)?.summary().unwrap_or("").to_string();
+        let new_summary = repo.find_commit(*new)?.summary().unwrap_or("").to_string();
+        println!(
+            "{:<9} {:<9} {:<44} {}",
+            git::short_oid(*old),
+            git::short_oid(*new),
+            crate::utils::truncate_string(&old_summary, 44),
+            new_summary
+        );
+    }
+    println!("\n💾 Backup of the previous history: {}", outcome.backup_ref);
+    println!("   Restore with: git reset --soft {}", outcome.backup_ref);
+    Ok(())
+}
+
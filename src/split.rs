@@ -0,0 +1,376 @@
+use anyhow::{Context, Result};
+use git2::{ApplyOptions, Diff, DiffFindOptions, Oid, Patch, Repository};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashSet};
+use std::rc::Rc;
+use crate::ai;
+use crate::committer::{self, Committer};
+use crate::config::Config;
+use crate::git::{self, ChangeKind, CommitInfo, DiffInfo, FileChange};
+use crate::journal;
+
+/// The smallest piece of the staged diff that can go into a commit on its
+/// own: a single hunk of a modified text file, or a whole file for
+/// additions, deletions, renames and binary changes.
+#[derive(Debug, Clone)]
+pub struct SplitUnit {
+    pub id: usize,
+    pub change: FileChange,
+    /// Hunk header, or `None` when the unit is the whole file
+    pub header: Option<String>,
+    pub patch: String,
+    signature: String,
+}
+
+/// One commit of a split plan.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SplitGroup {
+    pub message: String,
+    pub units: Vec<usize>,
+}
+
+/// Breaks the staged changes (HEAD → index) into split units.
+pub fn staged_units(repo: &Repository) -> Result<Vec<SplitUnit>> {
+    let head_tree = base_tree(repo)?;
+    let index_tree = repo.find_tree(repo.index()?.write_tree()?)?;
+    let diff = similar_diff(repo, &head_tree, &index_tree)?;
+
+    let mut units = Vec::new();
+    for (idx, delta) in diff.deltas().enumerate() {
+        let change = git::file_change_from_delta(&delta);
+        let patch = Patch::from_diff(&diff, idx)?;
+        let binary = patch.as_ref().is_some_and(|p| p.delta().flags().is_binary());
+
+        let hunks = match &patch {
+            Some(patch) if !binary && is_hunk_splittable(&change) => hunk_texts(patch)?,
+            _ => Vec::new(),
+        };
+
+        if hunks.is_empty() {
+            let text = match patch {
+                Some(mut patch) if !binary => String::from_utf8_lossy(&patch.to_buf()?).to_string(),
+                _ => format!("{}\n", change.describe()),
+            };
+            units.push(SplitUnit {
+                id: units.len(),
+                signature: file_signature(&change),
+                header: None,
+                patch: text,
+                change,
+            });
+            continue;
+        }
+
+        for (header, body) in hunks {
+            units.push(SplitUnit {
+                id: units.len(),
+                signature: hunk_signature(&change.path, &body),
+                header: Some(header.trim_end().to_string()),
+                patch: format!("{}{}", header, body),
+                change: change.clone(),
+            });
+        }
+    }
+
+    Ok(units)
+}
+
+/// Groups units into commits, asking the model when one is configured and
+/// falling back to a directory/kind based grouping otherwise.
+pub async fn plan(units: &[SplitUnit], config: &Config) -> Result<Vec<SplitGroup>> {
+    if config.has_openai_key() {
+        match ai::plan_commit_split(units, config).await {
+            Ok(groups) => {
+                if let Some(groups) = normalize_plan(groups, units.len()) {
+                    return Ok(groups);
+                }
+                println!("⚠️ The AI plan did not cover every hunk exactly once; using the heuristic grouping instead.");
+            }
+            Err(e) => println!("⚠️ AI planning failed ({}); using the heuristic grouping instead.", e),
+        }
+    }
+    Ok(heuristic_plan(units))
+}
+
+/// Drops unknown and duplicate unit ids and appends anything left out to a
+/// catch-all commit. Returns `None` when the plan is unusable.
+pub fn normalize_plan(groups: Vec<SplitGroup>, unit_count: usize) -> Option<Vec<SplitGroup>> {
+    let mut seen = HashSet::new();
+    let mut normalized: Vec<SplitGroup> = groups.into_iter()
+        .map(|group| SplitGroup {
+            message: group.message.trim().to_string(),
+            units: group.units.into_iter()
+                .filter(|id| *id < unit_count && seen.insert(*id))
+                .collect(),
+        })
+        .filter(|group| !group.units.is_empty() && !group.message.is_empty())
+        .collect();
+
+    if normalized.is_empty() {
+        return None;
+    }
+
+    let missing: Vec<usize> = (0..unit_count).filter(|id| !seen.contains(id)).collect();
+    if !missing.is_empty() {
+        normalized.push(SplitGroup {
+            message: "chore: remaining changes".to_string(),
+            units: missing,
+        });
+    }
+    Some(normalized)
+}
+
+/// Groups units by what they touch: docs, tests, CI, build files, or the
+/// top-level source directory.
+pub fn heuristic_plan(units: &[SplitUnit]) -> Vec<SplitGroup> {
+    let mut buckets: BTreeMap<String, Vec<&SplitUnit>> = BTreeMap::new();
+    for unit in units {
+        buckets.entry(bucket_for(&unit.change.path)).or_default().push(unit);
+    }
+
+    buckets.into_values()
+        .map(|bucket| {
+            let mut file_changes: Vec<FileChange> = Vec::new();
+            for unit in &bucket {
+                if !file_changes.iter().any(|c| c.path == unit.change.path) {
+                    file_changes.push(unit.change.clone());
+                }
+            }
+            let diff_info = DiffInfo::from_commits(vec![CommitInfo {
+                hash: "SPLIT".to_string(),
+                message: String::new(),
+                author: String::new(),
+                date: String::new(),
+                files_changed: file_changes.iter().map(|c| c.path.clone()).collect(),
+                file_changes,
+                diff: String::new(),
+            }]);
+            let message = ai::heuristic_commit_suggestions(&diff_info)
+                .into_iter()
+                .next()
+                .unwrap_or_else(|| "chore: update code".to_string());
+            SplitGroup {
+                message,
+                units: bucket.iter().map(|u| u.id).collect(),
+            }
+        })
+        .collect()
+}
+
+fn bucket_for(path: &str) -> String {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    if path.starts_with("docs/") || name.ends_with(".md") {
+        "docs".to_string()
+    } else if path.starts_with("tests/") || name.contains("_test.") || name.contains(".test.") {
+        "tests".to_string()
+    } else if path.starts_with(".github/") || path.starts_with(".gitlab-ci") {
+        "ci".to_string()
+    } else if matches!(name, "Cargo.toml" | "Cargo.lock" | "package.json" | "package-lock.json" | "pyproject.toml" | "go.mod" | "go.sum") {
+        "build".to_string()
+    } else {
+        match path.split_once('/') {
+            Some(("src", rest)) => rest.split_once('/')
+                .map(|(sub, _)| format!("src/{}", sub))
+                .unwrap_or_else(|| "src".to_string()),
+            Some((dir, _)) => dir.to_string(),
+            None => "root".to_string(),
+        }
+    }
+}
+
+pub fn print_plan(units: &[SplitUnit], groups: &[SplitGroup]) {
+    println!("\n🧩 Proposed split into {} commit(s):", groups.len());
+    for (i, group) in groups.iter().enumerate() {
+        println!("\n{}. {}", i + 1, group.message.lines().next().unwrap_or(""));
+        for id in &group.units {
+            let unit = &units[*id];
+            match &unit.header {
+                Some(header) => println!("   - {} {}", unit.change.path, header),
+                None => println!("   - {}", unit.change.describe()),
+            }
+        }
+    }
+}
+
+/// Creates one commit per group on top of HEAD. Each commit's tree is
+/// built by applying that group's hunks to the previous tree in memory,
+/// so neither the working tree nor the index is touched. HEAD only moves
+/// once every commit has been created.
+pub fn execute(repo: &Repository, committer: &Committer, units: &[SplitUnit], groups: &[SplitGroup]) -> Result<Vec<Oid>> {
+    let hook_output = committer.pre_commit()?;
+    if !hook_output.trim().is_empty() {
+        println!("🪝 Hook output:\n{}", hook_output.trim_end());
+    }
+
+    let final_tree = repo.find_tree(repo.index()?.write_tree()?)?;
+    let mut current_tree = base_tree(repo)?;
+    let mut parent = git::head_commit(repo)?;
+    let mut created = Vec::new();
+
+    for group in groups {
+        let wanted: HashSet<&str> = group.units.iter()
+            .map(|id| units[*id].signature.as_str())
+            .collect();
+        let tree = apply_units(repo, &current_tree, &final_tree, &wanted)
+            .with_context(|| format!("Could not build the commit '{}'", group.message))?;
+
+        let parents: Vec<&git2::Commit> = parent.iter().collect();
+        let outcome = committer.commit_tree(&group.message, &tree, &parents)?;
+        if !outcome.hook_output.trim().is_empty() {
+            println!("🪝 Hook output:\n{}", outcome.hook_output.trim_end());
+        }
+
+        created.push(outcome.id);
+        parent = Some(repo.find_commit(outcome.id)?);
+        current_tree = tree;
+    }
+
+    if current_tree.id() != final_tree.id() {
+        anyhow::bail!("The split commits do not add up to the staged changes; nothing was committed");
+    }
+
+    if let Some(last) = created.last() {
+        let reflog = format!("commit-buddy: split into {} commits", created.len());
+        journal::action(repo, format!("split into {} commits", created.len()))
+            .run(|| committer::update_head(repo, *last, &reflog))?;
+        let output = committer.post_commit();
+        if !output.trim().is_empty() {
+            println!("🪝 Hook output:\n{}", output.trim_end());
+        }
+    }
+
+    Ok(created)
+}
+
+/// Applies the hunks of `current → target` whose signatures are in `wanted`
+/// to `current` and returns the resulting tree. The diff is recomputed at
+/// every step so hunk positions always match the tree being patched.
+fn apply_units<'r>(
+    repo: &'r Repository,
+    current: &git2::Tree<'r>,
+    target: &git2::Tree<'r>,
+    wanted: &HashSet<&str>,
+) -> Result<git2::Tree<'r>> {
+    let diff = similar_diff(repo, current, target)?;
+
+    // For every delta: whether the whole file is wanted, and per hunk flags
+    let mut selection: Vec<(bool, Vec<bool>)> = Vec::new();
+    for (idx, delta) in diff.deltas().enumerate() {
+        let change = git::file_change_from_delta(&delta);
+        let patch = Patch::from_diff(&diff, idx)?;
+        let binary = patch.as_ref().is_some_and(|p| p.delta().flags().is_binary());
+
+        let hunks = match &patch {
+            Some(patch) if !binary && is_hunk_splittable(&change) => hunk_texts(patch)?,
+            _ => Vec::new(),
+        };
+
+        if hunks.is_empty() {
+            selection.push((wanted.contains(file_signature(&change).as_str()), Vec::new()));
+        } else {
+            let flags: Vec<bool> = hunks.iter()
+                .map(|(_, body)| wanted.contains(hunk_signature(&change.path, body).as_str()))
+                .collect();
+            selection.push((flags.iter().any(|f| *f), flags));
+        }
+    }
+
+    struct Cursor {
+        delta: Option<usize>,
+        hunk: usize,
+    }
+    let selection = Rc::new(selection);
+    let cursor = Rc::new(RefCell::new(Cursor { delta: None, hunk: 0 }));
+
+    let mut options = ApplyOptions::new();
+    {
+        let selection = Rc::clone(&selection);
+        let cursor = Rc::clone(&cursor);
+        options.delta_callback(move |_delta| {
+            let mut cursor = cursor.borrow_mut();
+            let next = cursor.delta.map_or(0, |d| d + 1);
+            cursor.delta = Some(next);
+            cursor.hunk = 0;
+            selection.get(next).is_some_and(|(include, _)| *include)
+        });
+    }
+    {
+        let selection = Rc::clone(&selection);
+        let cursor = Rc::clone(&cursor);
+        options.hunk_callback(move |_hunk| {
+            let mut cursor = cursor.borrow_mut();
+            let Some(delta) = cursor.delta else {
+                return false;
+            };
+            let hunk = cursor.hunk;
+            cursor.hunk += 1;
+            match selection.get(delta) {
+                // Whole-file units carry all of their hunks
+                Some((include, flags)) if flags.is_empty() => *include,
+                Some((_, flags)) => flags.get(hunk).copied().unwrap_or(false),
+                None => false,
+            }
+        });
+    }
+
+    let mut index = repo.apply_to_tree(current, &diff, Some(&mut options))?;
+    let tree_id = index.write_tree_to(repo)?;
+    Ok(repo.find_tree(tree_id)?)
+}
+
+fn similar_diff<'r>(repo: &'r Repository, old: &git2::Tree, new: &git2::Tree) -> Result<Diff<'r>> {
+    let mut diff = repo.diff_tree_to_tree(Some(old), Some(new), None)?;
+    let mut find_opts = DiffFindOptions::new();
+    find_opts.renames(true);
+    diff.find_similar(Some(&mut find_opts))?;
+    Ok(diff)
+}
+
+/// HEAD's tree, or the empty tree on an unborn branch.
+fn base_tree(repo: &Repository) -> Result<git2::Tree<'_>> {
+    match git::head_commit(repo)? {
+        Some(commit) => Ok(commit.tree()?),
+        None => {
+            let empty = repo.treebuilder(None)?.write()?;
+            Ok(repo.find_tree(empty)?)
+        }
+    }
+}
+
+fn is_hunk_splittable(change: &FileChange) -> bool {
+    change.kind == ChangeKind::Modified && !change.submodule && !change.binary
+}
+
+/// Header and body (+/-/context lines) of every hunk in a patch.
+fn hunk_texts(patch: &Patch) -> Result<Vec<(String, String)>> {
+    let mut hunks = Vec::new();
+    for hunk_idx in 0..patch.num_hunks() {
+        let (hunk, line_count) = patch.hunk(hunk_idx)?;
+        let header = String::from_utf8_lossy(hunk.header()).to_string();
+        let mut body = String::new();
+        for line_idx in 0..line_count {
+            let line = patch.line_in_hunk(hunk_idx, line_idx)?;
+            let origin = line.origin();
+            if matches!(origin, '+' | '-' | ' ') {
+                body.push(origin);
+            }
+            body.push_str(&String::from_utf8_lossy(line.content()));
+        }
+        hunks.push((header, body));
+    }
+    Ok(hunks)
+}
+
+fn file_signature(change: &FileChange) -> String {
+    format!("file:{}:{}", change.old_path.as_deref().unwrap_or(""), change.path)
+}
+
+/// Identifies a hunk by its changed lines only; context lines and line
+/// numbers shift as other hunks of the same file are committed.
+fn hunk_signature(path: &str, body: &str) -> String {
+    let changed: Vec<&str> = body.lines()
+        .filter(|line| line.starts_with('+') || line.starts_with('-'))
+        .collect();
+    format!("hunk:{}:{}", path, changed.join("\n"))
+}
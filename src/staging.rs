@@ -0,0 +1,249 @@
+use anyhow::{Context, Result};
+use git2::{DiffOptions, IndexEntry, IndexTime, Oid, Patch, Repository};
+use std::ops::RangeInclusive;
+use std::path::Path;
+use crate::git;
+use crate::journal;
+
+/// Which diff of a file the hunks come from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DiffSide {
+    /// Index → working tree; applying a hunk stages it
+    Unstaged,
+    /// HEAD → index; applying a hunk unstages it
+    Staged,
+}
+
+impl DiffSide {
+    pub fn other(self) -> Self {
+        match self {
+            DiffSide::Unstaged => DiffSide::Staged,
+            DiffSide::Staged => DiffSide::Unstaged,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct HunkLine {
+    /// `' '`, `'+'` or `'-'`
+    pub origin: char,
+    pub content: Vec<u8>,
+}
+
+impl HunkLine {
+    pub fn is_change(&self) -> bool {
+        self.origin == '+' || self.origin == '-'
+    }
+
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.content).trim_end_matches(['\n', '\r']).to_string()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FileHunk {
+    pub header: String,
+    pub old_start: u32,
+    pub old_lines: u32,
+    pub new_start: u32,
+    pub new_lines: u32,
+    pub lines: Vec<HunkLine>,
+}
+
+/// The hunks of one file on the given side, like the chunks `git add -p`
+/// walks through.
+pub fn file_hunks(repo: &Repository, path: &str, side: DiffSide) -> Result<Vec<FileHunk>> {
+    let mut opts = DiffOptions::new();
+    opts.pathspec(path).disable_pathspec_match(true);
+
+    let diff = match side {
+        DiffSide::Unstaged => {
+            opts.include_untracked(true).show_untracked_content(true);
+            repo.diff_index_to_workdir(None, Some(&mut opts))?
+        }
+        DiffSide::Staged => {
+            let head_tree = git::head_commit(repo)?.map(|c| c.tree()).transpose()?;
+            repo.diff_tree_to_index(head_tree.as_ref(), None, Some(&mut opts))?
+        }
+    };
+
+    let mut hunks = Vec::new();
+    for idx in 0..diff.deltas().len() {
+        let Some(patch) = Patch::from_diff(&diff, idx)? else {
+            continue;
+        };
+        if patch.delta().flags().is_binary() {
+            anyhow::bail!("{} is a binary file; stage it as a whole", path);
+        }
+
+        for hunk_idx in 0..patch.num_hunks() {
+            let (hunk, line_count) = patch.hunk(hunk_idx)?;
+            let mut lines = Vec::new();
+            for line_idx in 0..line_count {
+                let line = patch.line_in_hunk(hunk_idx, line_idx)?;
+                // Skip the "\ No newline at end of file" markers
+                if matches!(line.origin(), ' ' | '+' | '-') {
+                    lines.push(HunkLine {
+                        origin: line.origin(),
+                        content: line.content().to_vec(),
+                    });
+                }
+            }
+            hunks.push(FileHunk {
+                header: String::from_utf8_lossy(hunk.header()).trim_end().to_string(),
+                old_start: hunk.old_start(),
+                old_lines: hunk.old_lines(),
+                new_start: hunk.new_start(),
+                new_lines: hunk.new_lines(),
+                lines,
+            });
+        }
+    }
+
+    Ok(hunks)
+}
+
+/// Stages (on the unstaged side) or unstages (on the staged side) a hunk,
+/// or only the changed lines of it whose indices fall in `selection`.
+///
+/// Either way the index copy of the file is rewritten; the working tree is
+/// never touched.
+pub fn apply_hunk(
+    repo: &Repository,
+    path: &str,
+    side: DiffSide,
+    hunk: &FileHunk,
+    selection: Option<RangeInclusive<usize>>,
+) -> Result<()> {
+    let verb = match side {
+        DiffSide::Unstaged => "stage",
+        DiffSide::Staged => "unstage",
+    };
+    journal::action(repo, format!("{} a hunk of {}", verb, path))
+        .run(|| update_index(repo, path, side, hunk, selection))
+}
+
+fn update_index(
+    repo: &Repository,
+    path: &str,
+    side: DiffSide,
+    hunk: &FileHunk,
+    selection: Option<RangeInclusive<usize>>,
+) -> Result<()> {
+    let mut index = repo.index()?;
+    let existing = index.get_path(Path::new(path), 0);
+
+    let base = match &existing {
+        Some(entry) => repo.find_blob(entry.id)?.content().to_vec(),
+        None => Vec::new(),
+    };
+    let mut base_lines: Vec<&[u8]> = base.split_inclusive(|b| *b == b'\n').collect();
+
+    // The index is the "old" side of an unstaged diff and the "new" side of
+    // a staged one
+    let (base_origin, start, count) = match side {
+        DiffSide::Unstaged => ('-', hunk.old_start, hunk.old_lines),
+        DiffSide::Staged => ('+', hunk.new_start, hunk.new_lines),
+    };
+    let first = if count == 0 { start as usize } else { start as usize - 1 };
+    let last = first + count as usize;
+    if last > base_lines.len() {
+        anyhow::bail!("The diff of {} is out of date; refresh and try again", path);
+    }
+
+    let selected = |i: usize| selection.as_ref().is_none_or(|range| range.contains(&i));
+    let mut replacement: Vec<&[u8]> = Vec::new();
+    let mut expected: Vec<&[u8]> = Vec::new();
+    for (i, line) in hunk.lines.iter().enumerate() {
+        let content = line.content.as_slice();
+        if line.origin == ' ' {
+            expected.push(content);
+            replacement.push(content);
+        } else if line.origin == base_origin {
+            // A line the index has: dropped only when selected
+            expected.push(content);
+            if !selected(i) {
+                replacement.push(content);
+            }
+        } else if selected(i) {
+            // A line the index lacks: brought in only when selected
+            replacement.push(content);
+        }
+    }
+
+    if base_lines[first..last] != expected[..] {
+        anyhow::bail!("The diff of {} is out of date; refresh and try again", path);
+    }
+    base_lines.splice(first..last, replacement);
+    let content: Vec<u8> = base_lines.concat();
+
+    // Applying every line of a deletion (or unstaging every line of an
+    // added file) removes the entry instead of leaving an empty file
+    let target_exists = match side {
+        DiffSide::Unstaged => repo.workdir()
+            .map(|dir| dir.join(path).symlink_metadata().is_ok())
+            .unwrap_or(false),
+        DiffSide::Staged => match git::head_commit(repo)? {
+            Some(head) => head.tree()?.get_path(Path::new(path)).is_ok(),
+            None => false,
+        },
+    };
+    if content.is_empty() && !target_exists {
+        index.remove_path(Path::new(path))?;
+        index.write()?;
+        return Ok(());
+    }
+
+    // Keep the mode of an existing entry but not its stat data: that
+    // describes the file on disk, and reusing it for a different blob makes
+    // git trust the entry without re-reading the file
+    let entry = match existing {
+        Some(entry) => IndexEntry {
+            ctime: IndexTime::new(0, 0),
+            mtime: IndexTime::new(0, 0),
+            dev: 0,
+            ino: 0,
+            file_size: 0,
+            ..entry
+        },
+        None => new_entry(repo, path)?,
+    };
+    index.add_frombuffer(&entry, &content)
+        .with_context(|| format!("Could not update {} in the index", path))?;
+    index.write()?;
+    Ok(())
+}
+
+fn new_entry(repo: &Repository, path: &str) -> Result<IndexEntry> {
+    let workdir = repo.workdir().context("Cannot stage files in a bare repository")?;
+    let metadata = std::fs::symlink_metadata(workdir.join(path))?;
+    if metadata.file_type().is_symlink() {
+        anyhow::bail!("{} is a symlink; stage it as a whole", path);
+    }
+
+    Ok(IndexEntry {
+        ctime: IndexTime::new(0, 0),
+        mtime: IndexTime::new(0, 0),
+        dev: 0,
+        ino: 0,
+        mode: if is_executable(&metadata) { 0o100755 } else { 0o100644 },
+        uid: 0,
+        gid: 0,
+        file_size: 0,
+        id: Oid::zero(),
+        flags: 0,
+        flags_extended: 0,
+        path: path.as_bytes().to_vec(),
+    })
+}
+
+#[cfg(unix)]
+fn is_executable(metadata: &std::fs::Metadata) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    metadata.permissions().mode() & 0o111 != 0
+}
+
+#[cfg(not(unix))]
+fn is_executable(_metadata: &std::fs::Metadata) -> bool {
+    false
+}
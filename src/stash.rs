@@ -0,0 +1,166 @@
+use anyhow::{Context, Result};
+use git2::{ErrorCode, ObjectType, Oid, Repository, StashFlags, TreeWalkMode, TreeWalkResult};
+use std::path::{Path, PathBuf};
+use crate::conflicts;
+use crate::git::{self, DiffInfo};
+use crate::journal;
+
+/// An entry of the stash, as listed by `git stash list`.
+#[derive(Debug, Clone)]
+pub struct StashEntry {
+    /// Position in the stash, as in `stash@{index}`; 0 is the newest
+    pub index: usize,
+    pub id: Oid,
+    pub message: String,
+    /// Creation time in seconds since the epoch
+    pub time: i64,
+}
+
+impl StashEntry {
+    pub fn name(&self) -> String {
+        format!("stash@{{{}}}", self.index)
+    }
+}
+
+/// Every stash entry, newest first.
+pub fn list(repo: &mut Repository) -> Result<Vec<StashEntry>> {
+    let mut found = Vec::new();
+    repo.stash_foreach(|index, message, id| {
+        found.push((index, message.to_string(), *id));
+        true
+    })?;
+
+    found.into_iter()
+        .map(|(index, message, id)| {
+            let time = repo.find_commit(id)?.time().seconds();
+            Ok(StashEntry { index, id, message, time })
+        })
+        .collect()
+}
+
+/// The changes of tracked files a stash entry holds, against the commit
+/// it was created on.
+pub fn changes(repo: &Repository, entry: &StashEntry) -> Result<DiffInfo> {
+    let commit = repo.find_commit(entry.id)?;
+    let mut info = git::commit_info(repo, &commit)?;
+    info.message = entry.message.clone();
+    Ok(DiffInfo::from_commits(vec![info]))
+}
+
+/// Stashes the uncommitted changes, including untracked files when
+/// `include_untracked` is set, and returns the new stash commit.
+pub fn create(repo: &mut Repository, message: &str, include_untracked: bool) -> Result<Oid> {
+    let message = Some(message.trim()).filter(|m| !m.is_empty());
+    let action = match message {
+        Some(message) => format!("stash: {}", message),
+        None => "stash changes".to_string(),
+    };
+    journaled(repo, action, true, Vec::new(), |repo| {
+        let stasher = repo.signature()?;
+        let flags = if include_untracked { StashFlags::INCLUDE_UNTRACKED } else { StashFlags::DEFAULT };
+        match repo.stash_save2(&stasher, message, Some(flags)) {
+            Ok(id) => Ok(id),
+            Err(e) if e.code() == ErrorCode::NotFound => anyhow::bail!("There are no local changes to stash"),
+            Err(e) => Err(e).context("Could not stash the changes"),
+        }
+    })
+}
+
+/// Applies a stash entry to the work tree, and drops it afterwards when
+/// `pop` is set. Fails without touching anything when uncommitted changes
+/// would be overwritten. When the changes apply with conflicts, the
+/// conflicts are left to resolve and the entry is kept, like `git stash pop`.
+pub fn apply(repo: &mut Repository, index: usize, pop: bool) -> Result<()> {
+    let name = format!("stash@{{{}}}", index);
+    let action = format!("{} {}", if pop { "pop" } else { "apply" }, name);
+    // Journal the files the entry writes, so undo takes its changes back
+    // out of the work tree as well
+    let files = touched_files(repo, index)?;
+    journaled(repo, action, false, files, |repo| apply_entry(repo, index, &name, pop))
+}
+
+fn apply_entry(repo: &mut Repository, index: usize, name: &str, pop: bool) -> Result<()> {
+    if let Err(e) = repo.stash_apply(index, None) {
+        if e.code() == ErrorCode::Conflict {
+            anyhow::bail!(
+                "Applying {} would overwrite uncommitted changes to {}. Commit or stash them first; {} was kept.",
+                name,
+                overwritten_files(repo, index).unwrap_or_default().join(", "),
+                name
+            );
+        }
+        return Err(e).with_context(|| format!("Could not apply {}", name));
+    }
+
+    if conflicts::has_conflicts(repo)? {
+        let files: Vec<String> = conflicts::conflicted_files(repo)?.into_iter().map(|file| file.path).collect();
+        anyhow::bail!(
+            "{} was applied with conflicts in {}. Resolve them from \"Resolve conflicts\"; {} was kept.",
+            name,
+            files.join(", "),
+            name
+        );
+    }
+
+    if pop {
+        repo.stash_drop(index).with_context(|| format!("Applied {} but could not drop it", name))?;
+    }
+    Ok(())
+}
+
+/// Every file applying a stash entry can write: its tracked changes,
+/// including the old side of renames, and its untracked files.
+fn touched_files(repo: &mut Repository, index: usize) -> Result<Vec<PathBuf>> {
+    let entry = list(repo)?.into_iter().find(|entry| entry.index == index).context("No such stash entry")?;
+    let mut files: Vec<PathBuf> = Vec::new();
+    for change in changes(repo, &entry)?.commits.remove(0).file_changes {
+        files.extend(change.old_path.map(PathBuf::from));
+        files.push(PathBuf::from(change.path));
+    }
+
+    // Untracked files are stashed in a third parent
+    let commit = repo.find_commit(entry.id)?;
+    if let Ok(untracked) = commit.parent(2) {
+        untracked.tree()?.walk(TreeWalkMode::PreOrder, |dir, item| {
+            if item.kind() == Some(ObjectType::Blob) {
+                files.push(Path::new(dir).join(item.name().unwrap_or_default()));
+            }
+            TreeWalkResult::Ok
+        })?;
+    }
+    Ok(files)
+}
+
+/// Files a stash entry changes that also have uncommitted changes.
+fn overwritten_files(repo: &mut Repository, index: usize) -> Result<Vec<String>> {
+    let entry = list(repo)?.into_iter().find(|entry| entry.index == index).context("No such stash entry")?;
+    let stashed = changes(repo, &entry)?.commits.remove(0).files_changed;
+    let dirty = git::get_uncommitted_changes(repo)?.commits.into_iter()
+        .flat_map(|commit| commit.files_changed)
+        .collect::<Vec<_>>();
+    Ok(stashed.into_iter().filter(|file| dirty.contains(file)).collect())
+}
+
+pub fn drop(repo: &mut Repository, index: usize) -> Result<()> {
+    journaled(repo, format!("drop stash@{{{}}}", index), false, Vec::new(), |repo| {
+        repo.stash_drop(index).with_context(|| format!("Could not drop stash@{{{}}}", index))?;
+        Ok(())
+    })
+}
+
+/// Runs a stash operation as a journaled action. git2's stash calls need
+/// the repository mutably, so the journal looks on through a second handle.
+fn journaled<T>(
+    repo: &mut Repository,
+    name: String,
+    worktree: bool,
+    files: Vec<PathBuf>,
+    f: impl FnOnce(&mut Repository) -> Result<T>,
+) -> Result<T> {
+    let observer = Repository::open(repo.path())?;
+    let mut action = journal::action(&observer, name).files(&files);
+    if worktree {
+        action = action.worktree();
+    }
+    action.run(|| f(repo))
+}
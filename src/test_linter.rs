@@ -1,6 +1,8 @@
 use anyhow::Result;
 use crate::config::Config;
 use crate::ai::call_openai_api;
+use crate::git;
+use crate::journal;
 use std::fs;
 use std::path::Path;
 use std::process::Command;
@@ -73,7 +75,7 @@ impl TestLinter {
 
         // Run cargo check on the specific test file
         let output = Command::new("cargo")
-            .args(&["check", "--tests", "--message-format=json"])
+            .args(["check", "--tests", "--message-format=json"])
             .current_dir(file_path.parent().unwrap_or(Path::new(".")))
             .output()?;
 
@@ -143,7 +145,7 @@ impl TestLinter {
 
         // Apply fixes if any were made
         if !fixes_applied.is_empty() {
-            fs::write(file_path, content)?;
+            write_fixed(file_path, &content, "fix generated tests")?;
             result.fixes_applied.extend(fixes_applied);
             println!("✅ Applied {} simple fixes", result.fixes_applied.len());
         }
@@ -262,7 +264,7 @@ impl TestLinter {
                 let cleaned_content = self.clean_ai_response(&fixed_content);
                 
                 // Write the fixed content
-                fs::write(file_path, cleaned_content)?;
+                write_fixed(file_path, &cleaned_content, "fix generated tests with AI")?;
                 
                 result.fixes_applied.push(format!("AI fix attempt #{}", self.current_attempts));
                 println!("🤖 Applied AI fix attempt #{}", self.current_attempts);
@@ -305,7 +307,7 @@ impl TestLinter {
             let entry = entry?;
             let path = entry.path();
             
-            if path.is_file() && path.extension().map_or(false, |ext| ext == "rs") {
+            if path.is_file() && path.extension().is_some_and(|ext| ext == "rs") {
                 test_files.push(path);
             }
         }
@@ -350,13 +352,14 @@ impl TestLinter {
     }
 }
 
-// Helper function to create a default config for testing
-impl TestLinter {
-    fn create_default_config() -> Config {
-        Config {
-            default_branch: "master".to_string(),
-            openai_api_key: None,
-            github_token: None,
-        }
-    }
+/// Writes a fixed test file, journaled so `commit-buddy undo` can bring
+/// back the previous version. Outside a repository it is just written.
+fn write_fixed(path: &Path, content: &str, action: &str) -> Result<()> {
+    let Ok(repo) = git::open_repository() else {
+        return Ok(fs::write(path, content)?);
+    };
+    let absolute = std::env::current_dir()?.join(path);
+    journal::action(&repo, action)
+        .files(&[absolute])
+        .run(|| Ok(fs::write(path, content)?))
 }
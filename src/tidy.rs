@@ -0,0 +1,299 @@
+use anyhow::Result;
+use git2::{Oid, Repository};
+use std::collections::HashSet;
+use crate::ai;
+use crate::committer;
+use crate::config::Config;
+use crate::git::{self, DiffInfo};
+use crate::journal;
+use crate::rewrite::{self, HeadRange, RewriteOutcome};
+use crate::trailers;
+use crate::utils;
+
+/// Subjects of commits that only touch up an earlier one. Matched against
+/// the lowercased subject, with and without a `type:` prefix.
+const TRIVIAL_SUBJECTS: [&str; 19] = [
+    "wip",
+    "fix typo",
+    "fix typos",
+    "typo",
+    "typos",
+    "address review",
+    "address comments",
+    "address feedback",
+    "review feedback",
+    "review comments",
+    "apply review",
+    "oops",
+    "fmt",
+    "cargo fmt",
+    "fix formatting",
+    "lint",
+    "fix lint",
+    "fix build",
+    "minor fix",
+];
+
+const AUTOSQUASH_PREFIXES: [&str; 3] = ["fixup!", "squash!", "amend!"];
+
+/// One commit of the tidied history: `target` with the `folded` commits
+/// squashed into it.
+#[derive(Debug, Clone)]
+pub struct TidyGroup {
+    pub target: Oid,
+    /// Commits squashed into the target, oldest first
+    pub folded: Vec<Oid>,
+    pub message: String,
+}
+
+impl TidyGroup {
+    /// The target followed by the folded commits, in the order they are
+    /// applied.
+    pub fn commits(&self) -> Vec<Oid> {
+        std::iter::once(self.target).chain(self.folded.iter().copied()).collect()
+    }
+}
+
+/// Whether a subject reads like a "wip", "fix typo" or "address review"
+/// commit.
+pub fn is_trivial(subject: &str) -> bool {
+    let subject = subject.trim().to_lowercase();
+    // "chore: fix typo" counts, and so does "wip: parser"
+    let unprefixed = match subject.split_once(':') {
+        Some((prefix, rest)) if !prefix.contains(' ') => rest.trim(),
+        _ => subject.as_str(),
+    };
+    [subject.as_str(), unprefixed].iter().any(|candidate| {
+        let candidate = candidate.trim_end_matches(['.', '!']);
+        TRIVIAL_SUBJECTS.iter().any(|phrase| {
+            candidate == *phrase
+                || candidate.strip_prefix(phrase).is_some_and(|rest| rest.starts_with(|c: char| !c.is_alphanumeric()))
+        })
+    })
+}
+
+/// The subject a `fixup!`, `squash!` or `amend!` commit names, with nested
+/// prefixes removed.
+pub fn autosquash_target(subject: &str) -> Option<&str> {
+    let mut rest = subject.trim();
+    let mut found = false;
+    while let Some(stripped) = AUTOSQUASH_PREFIXES.iter().find_map(|prefix| rest.strip_prefix(prefix)) {
+        rest = stripped.trim_start();
+        found = true;
+    }
+    (found && !rest.is_empty()).then_some(rest)
+}
+
+/// Proposes a cleaned history for `commits` (oldest first). Autosquash
+/// commits go into the earlier commit whose subject or hash they name;
+/// trivial commits go into the earlier commit sharing the most touched
+/// files, the latest one on a tie. Trivial commits that share no files
+/// with an earlier commit are kept. Each group starts out with its
+/// target's message.
+pub fn plan(repo: &Repository, commits: &[Oid]) -> Result<Vec<TidyGroup>> {
+    let mut groups: Vec<TidyGroup> = Vec::new();
+    let mut group_files: Vec<HashSet<String>> = Vec::new();
+
+    for &id in commits {
+        let commit = repo.find_commit(id)?;
+        if commit.parent_count() > 1 {
+            anyhow::bail!("{} is a merge commit; tidy only rewrites linear history", git::short_oid(id));
+        }
+        let subject = commit.summary().unwrap_or("").to_string();
+        let files: HashSet<String> = git::commit_info(repo, &commit)?.files_changed.into_iter().collect();
+
+        let named = autosquash_target(&subject).and_then(|name| {
+            groups.iter().rposition(|group| {
+                group.commits().iter().any(|&candidate| {
+                    let summary = repo.find_commit(candidate).ok()
+                        .and_then(|c| c.summary().map(str::to_string))
+                        .unwrap_or_default();
+                    summary.starts_with(name) || (name.len() >= 7 && candidate.to_string().starts_with(name))
+                })
+            })
+        });
+        let target = named.or_else(|| {
+            if !is_trivial(&subject) {
+                return None;
+            }
+            let mut best: Option<(usize, usize)> = None;
+            for (index, touched) in group_files.iter().enumerate() {
+                let overlap = touched.intersection(&files).count();
+                if overlap > 0 && best.is_none_or(|(_, most)| overlap >= most) {
+                    best = Some((index, overlap));
+                }
+            }
+            best.map(|(index, _)| index)
+        });
+
+        match target {
+            Some(index) => {
+                groups[index].folded.push(id);
+                group_files[index].extend(files);
+            }
+            None => {
+                groups.push(TidyGroup {
+                    target: id,
+                    folded: Vec::new(),
+                    message: commit.message().unwrap_or("").to_string(),
+                });
+                group_files.push(files);
+            }
+        }
+    }
+
+    Ok(groups)
+}
+
+/// Rewrites the message of every group that folds in other commits from
+/// the combined messages and diff. Without an API key the target's message
+/// is kept.
+pub async fn write_messages(repo: &Repository, groups: &mut [TidyGroup], config: &Config) -> Result<()> {
+    for group in groups.iter_mut().filter(|group| !group.folded.is_empty()) {
+        let infos = group.commits().into_iter()
+            .map(|id| git::commit_info(repo, &repo.find_commit(id)?))
+            .collect::<Result<Vec<_>>>()?;
+        let message = ai::combine_commit_messages(&DiffInfo::from_commits(infos), config).await?;
+        group.message = utils::clean_commit_message(&message);
+    }
+    Ok(())
+}
+
+pub fn print_plan(repo: &Repository, groups: &[TidyGroup]) -> Result<()> {
+    let total: usize = groups.iter().map(|group| group.folded.len() + 1).sum();
+    println!("\n🧹 Proposed history: {} commit(s) instead of {}", groups.len(), total);
+    for group in groups {
+        let summary = group.message.lines().next().unwrap_or("");
+        if group.folded.is_empty() {
+            println!("\n  {} {}", git::short_oid(group.target), summary);
+            continue;
+        }
+        println!("\n✏️ {}", summary);
+        for id in group.commits() {
+            let commit = repo.find_commit(id)?;
+            println!("   ← {} {}", git::short_oid(id), commit.summary().unwrap_or(""));
+        }
+    }
+    Ok(())
+}
+
+/// Rebuilds `range` with one commit per group, applying the folded
+/// commits on top of their target in memory. Commits before the first
+/// change are reused as they are. Fails without touching anything if a
+/// commit does not apply cleanly in its new place or the result does not
+/// end in HEAD's tree; otherwise the old HEAD is kept under a backup ref
+/// before HEAD is moved.
+pub fn apply(repo: &Repository, range: &HeadRange, groups: &[TidyGroup]) -> Result<RewriteOutcome> {
+    let planned: Vec<Oid> = groups.iter().flat_map(TidyGroup::commits).collect();
+    let mut sorted = planned.clone();
+    sorted.sort();
+    sorted.dedup();
+    let mut expected = range.commits.clone();
+    expected.sort();
+    if sorted != expected || planned.len() != range.commits.len() {
+        anyhow::bail!("The plan does not cover every commit in the range exactly once");
+    }
+
+    let committer_sig = repo.signature()?;
+    let mut parent = range.base.map(|id| repo.find_commit(id)).transpose()?;
+    let mut tree = match &parent {
+        Some(commit) => commit.tree()?,
+        None => repo.find_tree(repo.treebuilder(None)?.write()?)?,
+    };
+    let mut mapping = Vec::new();
+
+    for group in groups {
+        let target = repo.find_commit(group.target)?;
+        if group.folded.is_empty() && target.parent_id(0).ok() == parent.as_ref().map(|p| p.id()) {
+            mapping.push((target.id(), target.id()));
+            tree = target.tree()?;
+            parent = Some(target);
+            continue;
+        }
+
+        let mut message = group.message.clone();
+        for id in group.commits() {
+            let commit = repo.find_commit(id)?;
+            let commit_tree = commit.tree()?;
+            let base_tree = match commit.parent(0) {
+                Ok(base) => base.tree()?,
+                Err(_) => repo.find_tree(repo.treebuilder(None)?.write()?)?,
+            };
+            tree = if base_tree.id() == tree.id() {
+                commit_tree
+            } else {
+                let mut index = repo.merge_trees(&base_tree, &tree, &commit_tree, None)?;
+                if index.has_conflicts() {
+                    anyhow::bail!(
+                        "{} {} does not apply cleanly on top of {}; nothing was changed",
+                        git::short_oid(id),
+                        commit.summary().unwrap_or(""),
+                        git::short_oid(group.target)
+                    );
+                }
+                repo.find_tree(index.write_tree_to(repo)?)?
+            };
+            if !group.folded.is_empty() {
+                message = trailers::preserve(commit.message().unwrap_or(""), &message);
+            }
+        }
+
+        let parents: Vec<&git2::Commit> = parent.iter().collect();
+        let new_id = committer::write_commit(repo, &target.author(), &committer_sig, &message, &tree, &parents)?;
+        for id in group.commits() {
+            mapping.push((id, new_id));
+        }
+        parent = Some(repo.find_commit(new_id)?);
+    }
+
+    let new_head = parent.map(|commit| commit.id()).unwrap_or(range.head);
+    if tree.id() != repo.find_commit(range.head)?.tree_id() {
+        anyhow::bail!("The tidied history would not end in the same tree as HEAD; nothing was changed");
+    }
+
+    let backup_ref = rewrite::create_backup_ref(repo, range.head)?;
+    journal::action(repo, format!("tidy {} commit(s) into {}", range.commits.len(), groups.len()))
+        .run(|| committer::update_head(repo, new_head, "commit-buddy: tidy history"))?;
+    mapping.sort_by_key(|(old, _)| range.commits.iter().position(|id| id == old));
+
+    Ok(RewriteOutcome {
+        backup_ref,
+        old_head: range.head,
+        new_head,
+        mapping,
+    })
+}
+
+/// `tidy`: proposes squashing the trivial and fixup commits of
+/// `base..HEAD` into their targets, and rewrites the history with `apply`.
+pub async fn tidy(base: Option<&str>, apply_plan: bool, force: bool, config: &Config) -> Result<()> {
+    let repo = git::open_repository()?;
+    let base = base.unwrap_or(config.get_default_branch());
+    let range = rewrite::resolve_head_range(&repo, base)?;
+
+    if range.commits.is_empty() {
+        println!("No commits found in {}..HEAD.", base);
+        return Ok(());
+    }
+
+    let mut groups = plan(&repo, &range.commits)?;
+    if groups.iter().all(|group| group.folded.is_empty()) {
+        println!("✨ Nothing to tidy: no trivial or fixup commits in {}..HEAD", base);
+        return Ok(());
+    }
+
+    println!("💬 Writing messages for the combined commits...");
+    write_messages(&repo, &mut groups, config).await?;
+    print_plan(&repo, &groups)?;
+
+    if !apply_plan {
+        println!("\nRun again with --apply to rewrite the history; the current one is kept under a backup ref.");
+        return Ok(());
+    }
+
+    rewrite::ensure_unpublished(&repo, &range.commits, force)?;
+    let outcome = apply(&repo, &range, &groups)?;
+    rewrite::print_reword_table(&repo, &outcome)?;
+    println!("✅ Tidied {} commit(s) into {}", range.commits.len(), groups.len());
+    Ok(())
+}
@@ -0,0 +1,201 @@
+use anyhow::{Context, Result};
+use git2::Repository;
+use regex::Regex;
+use crate::config::Config;
+use crate::git;
+
+/// A `Key: value` line in the last paragraph of a commit message.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trailer {
+    pub key: String,
+    pub value: String,
+}
+
+impl Trailer {
+    pub fn new(key: &str, value: &str) -> Self {
+        Self {
+            key: key.to_string(),
+            value: value.to_string(),
+        }
+    }
+}
+
+impl std::fmt::Display for Trailer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.key, self.value)
+    }
+}
+
+/// Per-commit trailer choices from the command line or the TUI.
+#[derive(Debug, Clone, Default)]
+pub struct TrailerOptions {
+    pub signoff: bool,
+    /// `Name <email>` identities, or names/emails to look up in history
+    pub co_authors: Vec<String>,
+}
+
+/// Splits a message into its body and trailer block. A trailer block is a
+/// final paragraph (after the subject) made only of `Key: value` lines and
+/// their indented continuation lines. `BREAKING CHANGE` is the one key with
+/// a space, as in conventional commit footers.
+pub fn parse(message: &str) -> (String, Vec<Trailer>) {
+    let trimmed = message.trim_end();
+    let Some(split) = trimmed.rfind("\n\n") else {
+        return (trimmed.to_string(), Vec::new());
+    };
+    let (body, block) = (&trimmed[..split], &trimmed[split + 2..]);
+
+    let key_pattern = |key: &str| {
+        key == "BREAKING CHANGE" || (!key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-'))
+    };
+    let mut trailers: Vec<Trailer> = Vec::new();
+    for line in block.lines() {
+        if line.starts_with(' ') || line.starts_with('\t') {
+            match trailers.last_mut() {
+                Some(last) => {
+                    last.value.push('\n');
+                    last.value.push_str(line);
+                }
+                None => return (trimmed.to_string(), Vec::new()),
+            }
+            continue;
+        }
+        match line.split_once(':') {
+            Some((key, value)) if key_pattern(key) => trailers.push(Trailer::new(key, value.trim())),
+            _ => return (trimmed.to_string(), Vec::new()),
+        }
+    }
+
+    (body.trim_end().to_string(), trailers)
+}
+
+/// Joins a body and trailers back into a message ending in a newline.
+pub fn format(body: &str, trailers: &[Trailer]) -> String {
+    let mut message = body.trim_end().to_string();
+    if !trailers.is_empty() {
+        message.push_str("\n\n");
+        message.push_str(&trailers.iter().map(Trailer::to_string).collect::<Vec<_>>().join("\n"));
+    }
+    message.push('\n');
+    message
+}
+
+/// Appends `extra` to the message's trailers, skipping any that are
+/// already present.
+pub fn merge(message: &str, extra: &[Trailer]) -> String {
+    let (body, mut trailers) = parse(message);
+    for trailer in extra {
+        let present = trailers.iter()
+            .any(|t| t.key.eq_ignore_ascii_case(&trailer.key) && t.value == trailer.value);
+        if !present {
+            trailers.push(trailer.clone());
+        }
+    }
+    format(&body, &trailers)
+}
+
+/// Carries the trailers of `original` over to a reworded message.
+pub fn preserve(original: &str, reworded: &str) -> String {
+    merge(reworded, &parse(original).1)
+}
+
+/// The trailers a new commit should get: ticket references from the branch
+/// name, co-authors, and a sign-off last, like `git commit -s`.
+pub fn for_commit(repo: &Repository, config: &Config, options: &TrailerOptions) -> Result<Vec<Trailer>> {
+    let mut trailers = Vec::new();
+
+    if let Some(pattern) = &config.ticket_pattern {
+        let branch = git::current_branch_name(repo).unwrap_or_default();
+        for ticket in ticket_ids(&branch, pattern)? {
+            trailers.push(Trailer::new(&config.ticket_trailer, &ticket));
+        }
+    }
+
+    for co_author in &options.co_authors {
+        trailers.push(Trailer::new("Co-authored-by", &resolve_co_author(repo, co_author)?));
+    }
+
+    if options.signoff || config.signoff {
+        let signature = repo.signature()?;
+        let identity = format!("{} <{}>", signature.name().unwrap_or(""), signature.email().unwrap_or(""));
+        trailers.push(Trailer::new("Signed-off-by", &identity));
+    }
+
+    Ok(trailers)
+}
+
+/// Ticket IDs in a branch name, e.g. `PROJ-123` in `feature/PROJ-123-login`.
+pub fn ticket_ids(branch: &str, pattern: &str) -> Result<Vec<String>> {
+    let regex = Regex::new(pattern)
+        .with_context(|| format!("Invalid ticket pattern '{}'", pattern))?;
+    let mut ids: Vec<String> = Vec::new();
+    for found in regex.find_iter(branch) {
+        if !ids.iter().any(|id| id == found.as_str()) {
+            ids.push(found.as_str().to_string());
+        }
+    }
+    Ok(ids)
+}
+
+/// Accepts a full `Name <email>` as is; anything else must match exactly
+/// one recent author.
+pub fn resolve_co_author(repo: &Repository, query: &str) -> Result<String> {
+    if query.contains('<') && query.ends_with('>') {
+        return Ok(query.trim().to_string());
+    }
+
+    let needle = query.to_lowercase();
+    let matches: Vec<String> = recent_authors(repo, usize::MAX)?
+        .into_iter()
+        .filter(|author| author.to_lowercase().contains(&needle))
+        .collect();
+
+    match matches.as_slice() {
+        [author] => Ok(author.clone()),
+        [] => anyhow::bail!("No recent author matches '{}'; pass 'Name <email>' instead", query),
+        _ => anyhow::bail!("'{}' matches several authors: {}", query, matches.join(", ")),
+    }
+}
+
+/// Authors of recent commits, most recent first, canonicalised through
+/// `.mailmap`, followed by identities only known from `.mailmap`. The
+/// current user is left out.
+pub fn recent_authors(repo: &Repository, limit: usize) -> Result<Vec<String>> {
+    let me = repo.signature().ok()
+        .and_then(|s| s.email().map(str::to_lowercase));
+    let mailmap = repo.mailmap()?;
+    let mut authors: Vec<String> = Vec::new();
+    let mut push = |name: &str, email: &str| {
+        if me.as_deref() == Some(email.to_lowercase().as_str()) {
+            return;
+        }
+        let identity = format!("{} <{}>", name, email);
+        if !authors.contains(&identity) {
+            authors.push(identity);
+        }
+    };
+
+    if let Some(head) = git::head_commit(repo)? {
+        let mut walk = repo.revwalk()?;
+        walk.push(head.id())?;
+        for id in walk.take(1000) {
+            let commit = repo.find_commit(id?)?;
+            let author = commit.author_with_mailmap(&mailmap)?;
+            push(author.name().unwrap_or(""), author.email().unwrap_or(""));
+        }
+    }
+
+    if let Some(workdir) = repo.workdir() {
+        if let Ok(content) = std::fs::read_to_string(workdir.join(".mailmap")) {
+            let canonical = Regex::new(r"^\s*([^<#]+?)\s*<([^>]+)>")?;
+            for line in content.lines() {
+                if let Some(caps) = canonical.captures(line) {
+                    push(&caps[1], &caps[2]);
+                }
+            }
+        }
+    }
+
+    authors.truncate(limit);
+    Ok(authors)
+}
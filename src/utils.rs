@@ -1,17 +1,12 @@
 use anyhow::Result;
-use std::process::Command;
+use crate::conventional::ConventionalCommit;
+use crate::git;
 
+/// Runs `git` at the top of the work tree of the repository containing the
+/// current directory; see `git::run_git_in`.
 pub fn run_git_command(args: &[&str]) -> Result<String> {
-    let output = Command::new("git")
-        .args(args)
-        .output()?;
-    
-    if !output.status.success() {
-        let error = String::from_utf8_lossy(&output.stderr);
-        anyhow::bail!("Git command failed: {}", error);
-    }
-    
-    Ok(String::from_utf8(output.stdout)?)
+    let repo = git::open_repository()?;
+    git::run_git_in(&repo, args)
 }
 
 pub fn get_current_branch() -> Result<String> {
@@ -41,29 +36,33 @@ pub fn format_file_size(bytes: u64) -> String {
 }
 
 pub fn truncate_string(s: &str, max_len: usize) -> String {
-    if s.len() <= max_len {
+    if s.chars().count() <= max_len {
         s.to_string()
     } else {
-        format!("{}...", &s[..max_len - 3])
+        let kept: String = s.chars().take(max_len.saturating_sub(3)).collect();
+        format!("{}...", kept)
     }
 }
 
-pub fn extract_commit_type(message: &str) -> Option<&str> {
-    let message = message.trim();
-    if let Some(colon_pos) = message.find(':') {
-        let prefix = &message[..colon_pos];
-        if prefix.len() <= 20 && prefix.chars().all(|c| c.is_ascii_lowercase() || c == '-') {
-            return Some(prefix);
-        }
+/// Strips the wrapping AI responses tend to add around a commit message:
+/// markdown code fences and surrounding quotes.
+pub fn clean_commit_message(message: &str) -> String {
+    let mut cleaned = message.trim();
+    if let Some(rest) = cleaned.strip_prefix("```") {
+        // Drop an optional language tag on the opening fence
+        cleaned = rest.split_once('\n').map(|(_, body)| body).unwrap_or(rest);
+        cleaned = cleaned.trim_end().strip_suffix("```").unwrap_or(cleaned);
     }
-    None
+    let cleaned = cleaned.trim();
+    let cleaned = cleaned.strip_prefix('"').and_then(|c| c.strip_suffix('"')).unwrap_or(cleaned);
+    format!("{}\n", cleaned.trim())
 }
 
 pub fn is_conventional_commit(message: &str) -> bool {
-    extract_commit_type(message).is_some()
+    ConventionalCommit::parse(message).is_ok()
 }
 
-pub fn get_commit_emoji(commit_type: &str) -> &str {
+pub fn get_commit_emoji(commit_type: &str) -> &'static str {
     match commit_type {
         "feat" => "✨",
         "fix" => "🐛",
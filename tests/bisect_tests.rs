@@ -0,0 +1,74 @@
+use commit_buddy::bisect::{self, BisectOutcome, Verdict};
+use anyhow::Result;
+use git2::{Oid, Repository};
+use std::path::PathBuf;
+
+mod common;
+use common::{temp_repo, commit};
+
+/// Eight commits; `status.txt` says `fail` from the fifth one on.
+fn regressed_repo(name: &str) -> Result<(PathBuf, Repository, Vec<Oid>)> {
+    let (dir, repo) = temp_repo(name)?;
+    let mut ids = Vec::new();
+    for i in 1..=8 {
+        commit(&repo, "counter.txt", &i.to_string(), &format!("chore: step {}", i))?;
+        let status = if i >= 5 { "fail" } else { "pass" };
+        ids.push(commit(&repo, "status.txt", status, &format!("feat: status {}", i))?);
+    }
+    Ok((dir, repo, ids))
+}
+
+#[test]
+fn test_verdict_follows_bisect_run_exit_codes() {
+    assert_eq!(Verdict::from_exit_code(0), Some(Verdict::Good));
+    assert_eq!(Verdict::from_exit_code(1), Some(Verdict::Bad));
+    assert_eq!(Verdict::from_exit_code(125), Some(Verdict::Skip));
+    assert_eq!(Verdict::from_exit_code(127), Some(Verdict::Bad));
+    assert_eq!(Verdict::from_exit_code(128), None);
+    assert_eq!(Verdict::from_exit_code(-1), None);
+}
+
+#[test]
+fn test_bisect_finds_first_bad_commit_and_records_steps() -> Result<()> {
+    let (dir, repo, ids) = regressed_repo("bisect-run")?;
+    let branch = repo.head()?.shorthand().unwrap().to_string();
+
+    let mut session = bisect::start(&repo, &ids[0].to_string(), "HEAD", "grep -q pass status.txt")?;
+    assert!(bisect::start(&repo, &ids[0].to_string(), "HEAD", "true").is_err(), "one session at a time");
+
+    let mut seen = 0;
+    let outcome = bisect::run_steps(&repo, &mut session, |_| seen += 1)?;
+    assert_eq!(outcome, BisectOutcome::FirstBad(ids[4]));
+    assert_eq!(seen, session.steps.len());
+    assert!(!session.steps.is_empty() && session.steps.len() <= 4, "{} steps", session.steps.len());
+
+    let saved = bisect::load(&repo)?.expect("session is saved under .git");
+    assert_eq!(saved.first_bad, Some(ids[4].to_string()));
+    assert_eq!(saved.steps.len(), session.steps.len());
+    assert!(saved.steps.iter().any(|step| step.verdict == Verdict::Bad));
+
+    bisect::reset(&repo)?;
+    assert!(bisect::load(&repo)?.is_none());
+    assert_eq!(repo.head()?.shorthand(), Some(branch.as_str()));
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_interrupted_bisect_can_be_resumed() -> Result<()> {
+    let (dir, repo, ids) = regressed_repo("bisect-resume")?;
+
+    let mut session = bisect::start(&repo, &ids[0].to_string(), "HEAD", "exit 200")?;
+    let err = bisect::run_steps(&repo, &mut session, |_| {}).unwrap_err();
+    assert!(err.to_string().contains("exited with 200"), "{}", err);
+
+    let mut resumed = bisect::load(&repo)?.unwrap();
+    assert!(resumed.steps.is_empty());
+    resumed.command = "grep -q pass status.txt".to_string();
+    assert_eq!(bisect::run_steps(&repo, &mut resumed, |_| {})?, BisectOutcome::FirstBad(ids[4]));
+
+    bisect::reset(&repo)?;
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
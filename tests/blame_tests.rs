@@ -0,0 +1,85 @@
+use commit_buddy::blame::{self, BlameSource};
+use anyhow::Result;
+use git2::{Oid, Repository, Signature, Time};
+use std::path::Path;
+
+mod common;
+use common::temp_repo;
+
+/// Commits `content` to `path` (removing `remove` first) by `author` at a
+/// fixed time.
+fn commit(repo: &Repository, path: &str, content: &str, remove: Option<&str>, author: &str, time: i64) -> Result<Oid> {
+    let workdir = repo.workdir().unwrap();
+    let mut index = repo.index()?;
+    if let Some(old) = remove {
+        std::fs::remove_file(workdir.join(old))?;
+        index.remove_path(Path::new(old))?;
+    }
+    std::fs::write(workdir.join(path), content)?;
+    index.add_path(Path::new(path))?;
+    index.write()?;
+    let tree = repo.find_tree(index.write_tree()?)?;
+    let signature = Signature::new(author, "test@example.com", &Time::new(time, 0))?;
+    let parents: Vec<git2::Commit> = repo.head().ok()
+        .and_then(|h| h.peel_to_commit().ok())
+        .into_iter()
+        .collect();
+    let parent_refs: Vec<&git2::Commit> = parents.iter().collect();
+    Ok(repo.commit(Some("HEAD"), &signature, &signature, &format!("update {}", path), &tree, &parent_refs)?)
+}
+
+#[test]
+fn test_parse_line_range() -> Result<()> {
+    assert_eq!(blame::parse_line_range("src/git.rs:10-40")?, ("src/git.rs".to_string(), 10, 40));
+    assert_eq!(blame::parse_line_range("a.rs:7")?, ("a.rs".to_string(), 7, 7));
+    assert_eq!(blame::parse_line_range("C:/a.rs:1-2")?.0, "C:/a.rs");
+    for bad in ["a.rs", "a.rs:0-3", "a.rs:5-2", ":1-2", "a.rs:x-2"] {
+        assert!(blame::parse_line_range(bad).is_err(), "{}", bad);
+    }
+    Ok(())
+}
+
+#[test]
+fn test_blame_groups_lines_by_commit() -> Result<()> {
+    let (dir, repo) = temp_repo("blame-lines")?;
+    let first = commit(&repo, "a.txt", "one\ntwo\nthree\nfour\n", None, "Alice", 1000)?;
+    let second = commit(&repo, "a.txt", "one\nTWO\nTHREE\nfour\n", None, "Bob", 2000)?;
+    std::fs::write(dir.join("a.txt"), "one\nTWO\nTHREE\nfour\nfive\n")?;
+
+    let hunks = blame::blame_lines(&repo, "a.txt", 1, 5, BlameSource::Workdir)?;
+    let spans: Vec<(Option<Oid>, usize, usize)> = hunks.iter().map(|h| (h.commit, h.start, h.end())).collect();
+    assert_eq!(spans, vec![(Some(first), 1, 1), (Some(second), 2, 3), (Some(first), 4, 4), (None, 5, 5)]);
+    assert_eq!(hunks[1].author, "Bob");
+    assert_eq!(hunks[1].lines, vec!["TWO".to_string(), "THREE".to_string()]);
+
+    let table = blame::format_blame(&hunks);
+    assert!(table.contains(&format!("2-3 {} Bob 1970-01-01 update a.txt", &second.to_string()[..7])), "{}", table);
+    assert!(table.contains("5-5 ------- Not committed yet"), "{}", table);
+
+    assert!(blame::blame_lines(&repo, "a.txt", 2, 5, BlameSource::Head).is_err(), "HEAD has four lines");
+    let commits = blame::blamed_commits(&repo, &hunks)?;
+    let ids: Vec<&str> = commits.commits.iter().map(|c| c.hash.as_str()).collect();
+    assert_eq!(ids, vec![first.to_string(), second.to_string()]);
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_blame_reports_path_before_rename() -> Result<()> {
+    let (dir, repo) = temp_repo("blame-rename")?;
+    let content = "fn parse() {}\nfn helper() {}\nfn other() {}\n";
+    let added = commit(&repo, "old.rs", content, None, "Alice", 1000)?;
+    commit(&repo, "new.rs", content, Some("old.rs"), "Bob", 2000)?;
+
+    let hunks = blame::blame_lines(&repo, "new.rs", 1, 2, BlameSource::Head)?;
+    assert_eq!(hunks.len(), 1);
+    assert_eq!(hunks[0].commit, Some(added));
+    assert_eq!(hunks[0].path, "old.rs");
+
+    let commits = blame::blamed_commits(&repo, &hunks)?;
+    assert_eq!(commits.commits[0].files_changed, vec!["old.rs".to_string()]);
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
@@ -0,0 +1,197 @@
+use commit_buddy::branches::{self, BranchKind};
+use anyhow::Result;
+use git2::{BranchType, Repository};
+
+mod common;
+use common::temp_repo;
+
+fn commit(repo: &Repository, path: &str, message: &str) -> Result<git2::Oid> {
+    common::commit(repo, path, message, message)
+}
+
+fn current_branch(repo: &Repository) -> Result<String> {
+    Ok(repo.head()?.shorthand().unwrap().to_string())
+}
+
+#[test]
+fn test_list_reports_ahead_behind_against_upstream() -> Result<()> {
+    let (dir, repo) = temp_repo("branches-list")?;
+    commit(&repo, "a.txt", "chore: initial")?;
+    let base = repo.head()?.peel_to_commit()?;
+    repo.reference("refs/remotes/origin/main", base.id(), true, "test remote")?;
+    repo.remote("origin", "https://example.com/repo.git")?;
+    let current = current_branch(&repo)?;
+    repo.find_branch(&current, BranchType::Local)?.set_upstream(Some("origin/main"))?;
+    commit(&repo, "b.txt", "feat: add b")?;
+    repo.branch("topic", &base, false)?;
+
+    let list = branches::list(&repo)?;
+    assert_eq!(list[0].name, current);
+    assert!(list[0].is_head);
+    assert_eq!(list[0].upstream.as_deref(), Some("origin/main"));
+    assert_eq!(list[0].ahead_behind, Some((1, 0)));
+    assert_eq!(list[0].last_summary, "feat: add b");
+
+    let topic = list.iter().find(|b| b.name == "topic").unwrap();
+    assert_eq!(topic.ahead_behind, None, "no upstream to compare with");
+
+    let remote = list.iter().find(|b| b.kind == BranchKind::Remote).unwrap();
+    assert_eq!(remote.name, "origin/main");
+    assert_eq!(remote.local_name(), "main");
+    assert_eq!(remote.ahead_behind, Some((0, 1)), "remote branches compare with HEAD");
+    assert_eq!(list.last().unwrap().kind, BranchKind::Remote);
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_fuzzy_filter_ranks_closer_matches_first() -> Result<()> {
+    let (dir, repo) = temp_repo("branches-filter")?;
+    commit(&repo, "a.txt", "chore: initial")?;
+    let head = repo.head()?.peel_to_commit()?;
+    for name in ["feat/branch-panel", "fix/bad-parse", "feature-flags"] {
+        repo.branch(name, &head, false)?;
+    }
+    let list = branches::list(&repo)?;
+
+    let names = |query: &str| -> Vec<String> {
+        branches::filter(&list, query).into_iter().map(|i| list[i].name.clone()).collect()
+    };
+    assert_eq!(names("bp"), vec!["fix/bad-parse", "feat/branch-panel"], "tighter matches rank higher");
+    assert_eq!(names("panel"), vec!["feat/branch-panel"]);
+    assert_eq!(names("FEAT")[0], "feat/branch-panel");
+    assert!(names("zzz").is_empty());
+    assert_eq!(names("").len(), list.len());
+
+    assert!(branches::fuzzy_score("fbp", "feat/branch-panel").is_some());
+    assert!(branches::fuzzy_score("pbf", "feat/branch-panel").is_none());
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_checkout_refuses_dirty_tree_and_tracks_remote_branches() -> Result<()> {
+    let (dir, repo) = temp_repo("branches-checkout")?;
+    commit(&repo, "a.txt", "one")?;
+    let main = current_branch(&repo)?;
+    let first = repo.head()?.peel_to_commit()?;
+    repo.branch("topic", &first, false)?;
+    commit(&repo, "a.txt", "two")?;
+    let second = repo.head()?.peel_to_commit()?.id();
+    repo.reference("refs/remotes/origin/feature", second, true, "test remote")?;
+    repo.remote("origin", "https://example.com/repo.git")?;
+
+    std::fs::write(dir.join("a.txt"), "dirty")?;
+    let list = branches::list(&repo)?;
+    let topic = list.iter().find(|b| b.name == "topic").unwrap();
+    let err = branches::checkout(&repo, topic).unwrap_err();
+    assert!(err.to_string().contains("uncommitted changes"), "{}", err);
+    assert_eq!(current_branch(&repo)?, main);
+
+    std::fs::write(dir.join("a.txt"), "two")?;
+    assert_eq!(branches::checkout(&repo, topic)?, "topic");
+    assert_eq!(current_branch(&repo)?, "topic");
+    assert_eq!(std::fs::read_to_string(dir.join("a.txt"))?, "one");
+
+    let remote = list.iter().find(|b| b.name == "origin/feature").unwrap();
+    assert_eq!(branches::checkout(&repo, remote)?, "feature");
+    let local = repo.find_branch("feature", BranchType::Local)?;
+    assert_eq!(local.upstream()?.name()?, Some("origin/feature"));
+    assert_eq!(std::fs::read_to_string(dir.join("a.txt"))?, "two");
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_create_rename_and_delete() -> Result<()> {
+    let (dir, repo) = temp_repo("branches-manage")?;
+    commit(&repo, "a.txt", "one")?;
+    let main = current_branch(&repo)?;
+
+    assert!(branches::create(&repo, "bad..name").is_err());
+    branches::create(&repo, "feat/new")?;
+    assert_eq!(current_branch(&repo)?, "feat/new");
+    assert!(branches::create(&repo, "feat/new").is_err());
+
+    branches::rename(&repo, "feat/new", "feat/renamed")?;
+    assert_eq!(current_branch(&repo)?, "feat/renamed");
+    assert!(branches::delete(&repo, "feat/renamed", true).is_err(), "current branch");
+
+    commit(&repo, "b.txt", "two")?;
+    repo.set_head(&format!("refs/heads/{}", main))?;
+    repo.checkout_head(Some(git2::build::CheckoutBuilder::new().force()))?;
+    let err = branches::delete(&repo, "feat/renamed", false).unwrap_err();
+    assert!(err.to_string().contains("not merged"), "{}", err);
+    branches::delete(&repo, "feat/renamed", true)?;
+    assert!(repo.find_branch("feat/renamed", BranchType::Local).is_err());
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_create_on_unborn_branch_moves_head() -> Result<()> {
+    let (dir, repo) = temp_repo("branches-unborn")?;
+    branches::create(&repo, "first")?;
+    assert_eq!(repo.find_reference("HEAD")?.symbolic_target(), Some("refs/heads/first"));
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_merge_refuses_dirty_tree() -> Result<()> {
+    let (dir, repo) = temp_repo("branches-merge")?;
+    commit(&repo, "a.txt", "one")?;
+    let head = repo.head()?.peel_to_commit()?;
+    repo.branch("topic", &head, false)?;
+    std::fs::write(dir.join("a.txt"), "dirty")?;
+
+    assert!(branches::merge(&repo, "topic").is_err());
+    assert!(branches::rebase(&repo, "topic").is_err());
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_first_push_sets_upstream_and_tracking_counts() -> Result<()> {
+    let (dir, repo) = temp_repo("branches-push")?;
+    let bare = dir.with_extension("remote.git");
+    let _ = std::fs::remove_dir_all(&bare);
+    Repository::init_bare(&bare)?;
+    repo.remote("backup", "https://example.com/backup.git")?;
+    repo.remote("origin", bare.to_str().unwrap())?;
+    assert_eq!(branches::remotes(&repo)?, vec!["origin".to_string(), "backup".to_string()]);
+
+    commit(&repo, "a.txt", "chore: initial")?;
+    let current = current_branch(&repo)?;
+    assert_eq!(branches::tracking(&repo)?.describe(), "no upstream");
+    assert!(branches::push(&repo, None).is_err(), "no upstream and no remote chosen");
+
+    branches::push(&repo, Some("origin"))?;
+    let tracking = branches::tracking(&repo)?;
+    assert_eq!(tracking.upstream, Some(format!("origin/{}", current)));
+    assert_eq!(tracking.describe(), format!("origin/{} (up to date)", current));
+
+    commit(&repo, "b.txt", "feat: add b")?;
+    commit(&repo, "c.txt", "feat: add c")?;
+    assert_eq!(branches::tracking(&repo)?.describe(), format!("origin/{} ↑2", current));
+    branches::push(&repo, None)?;
+    assert_eq!((branches::tracking(&repo)?.ahead, branches::tracking(&repo)?.behind), (0, 0));
+
+    std::fs::remove_dir_all(dir)?;
+    std::fs::remove_dir_all(bare)?;
+    Ok(())
+}
+
+#[test]
+fn test_branch_name_from_message() {
+    assert_eq!(branches::branch_name_from_message("feat(ui): Add branch panel"), "feat/add-branch-panel");
+    assert_eq!(branches::branch_name_from_message("Fix the parser!\n\nbody"), "fix-the-parser");
+    assert_eq!(branches::sanitize_branch_name("`fix/Handle Empty Input`"), "fix/handle-empty-input");
+    assert_eq!(branches::sanitize_branch_name("a/b/c"), "a/b-c");
+}
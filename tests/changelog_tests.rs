@@ -0,0 +1,242 @@
+use commit_buddy::changelog::{self, ForgeLinks};
+use anyhow::Result;
+use git2::{Oid, Repository, Signature};
+
+mod common;
+use common::temp_repo;
+
+fn commit(repo: &Repository, path: &str, message: &str) -> Result<Oid> {
+    common::commit(repo, path, message, message)
+}
+
+fn tag(repo: &Repository, name: &str) -> Result<()> {
+    let head = repo.head()?.peel_to_commit()?;
+    repo.tag(name, head.as_object(), &repo.signature()?, name, false)?;
+    Ok(())
+}
+
+fn short(id: Oid) -> String {
+    id.to_string()[..7].to_string()
+}
+
+#[test]
+fn test_forge_links_from_remotes() {
+    let github = ForgeLinks::from_remote_url("git@github.com:acme/widgets.git").unwrap();
+    assert_eq!(github.base, "https://github.com/acme/widgets");
+    assert_eq!(github.pull_request(7), "https://github.com/acme/widgets/pull/7");
+    assert_eq!(github.compare("v1.0.0", "v1.1.0"), "https://github.com/acme/widgets/compare/v1.0.0...v1.1.0");
+
+    let gitlab = ForgeLinks::from_remote_url("https://token@gitlab.com/group/sub/project.git").unwrap();
+    assert_eq!(gitlab.base, "https://gitlab.com/group/sub/project");
+    assert_eq!(gitlab.pull_request(7), "https://gitlab.com/group/sub/project/-/merge_requests/7");
+    assert_eq!(gitlab.tag("v1.0.0"), "https://gitlab.com/group/sub/project/-/tags/v1.0.0");
+
+    let ssh = ForgeLinks::from_remote_url("ssh://git@git.example.com:2222/team/app").unwrap();
+    assert_eq!(ssh.base, "https://git.example.com/team/app");
+
+    assert!(ForgeLinks::from_remote_url("/srv/git/app.git").is_none());
+}
+
+#[test]
+fn test_sections_between_tags() -> Result<()> {
+    let (dir, repo) = temp_repo("changelog-sections")?;
+    commit(&repo, "a.txt", "feat: initial api")?;
+    tag(&repo, "v0.1.0")?;
+    let fix = commit(&repo, "b.txt", "fix(api): reject empty names (#12)")?;
+    commit(&repo, "c.txt", "docs: describe names")?;
+    tag(&repo, "v0.2.0")?;
+    let breaking = commit(&repo, "d.txt", "feat!: rename the client\n\nBREAKING CHANGE: Client is now ApiClient")?;
+    commit(&repo, "e.txt", "Unconventional message")?;
+
+    let sections = changelog::collect(&repo, None, None)?;
+    let labels: Vec<&str> = sections.iter().map(|s| s.label()).collect();
+    assert_eq!(labels, vec!["Unreleased", "0.2.0", "0.1.0"]);
+    assert_eq!(sections[0].previous.as_deref(), Some("v0.2.0"));
+    assert_eq!(sections[1].entries.len(), 2);
+    assert_eq!(sections[1].entries[0].pull_request, Some(12));
+    assert_eq!(sections[1].entries[0].commit.subject, "reject empty names");
+
+    let links = ForgeLinks::from_remote_url("https://github.com/acme/widgets").unwrap();
+    let text = changelog::render(&sections, Some(&links));
+    assert!(text.starts_with("# Changelog\n"));
+    assert!(text.contains(&format!(
+        "## [Unreleased]\n\n### ⚠ BREAKING CHANGES\n\n- Client is now ApiClient ([{0}](https://github.com/acme/widgets/commit/{1}))\n\n### Added\n\n- rename the client ([{0}]",
+        short(breaking),
+        breaking
+    )));
+    assert!(text.contains(&format!(
+        "### Fixed\n\n- **api:** reject empty names ([#12](https://github.com/acme/widgets/pull/12), [{}](",
+        short(fix)
+    )));
+    assert!(!text.contains("describe names"));
+    assert!(!text.contains("Unconventional"));
+    assert!(text.ends_with(
+        "[Unreleased]: https://github.com/acme/widgets/compare/v0.2.0...HEAD\n\
+         [0.2.0]: https://github.com/acme/widgets/compare/v0.1.0...v0.2.0\n\
+         [0.1.0]: https://github.com/acme/widgets/releases/tag/v0.1.0\n"
+    ));
+
+    // A window covers only the releases inside it
+    let window = changelog::collect(&repo, Some("v0.1.0"), Some("v0.2.0"))?;
+    assert_eq!(window.len(), 1);
+    assert_eq!(window[0].label(), "0.2.0");
+    assert_eq!(window[0].previous.as_deref(), Some("v0.1.0"));
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_merged_pull_requests() -> Result<()> {
+    let (dir, repo) = temp_repo("changelog-merges")?;
+    let base = commit(&repo, "a.txt", "chore: initial")?;
+    let feature = commit(&repo, "b.txt", "feat: add search")?;
+
+    // Merge the feature onto a sibling commit of main, as GitHub does
+    repo.set_head_detached(base)?;
+    repo.checkout_head(Some(git2::build::CheckoutBuilder::new().force()))?;
+    let sibling = commit(&repo, "c.txt", "fix: typo")?;
+    let signature = Signature::now("Test User", "test@example.com")?;
+    let mut index = repo.merge_commits(&repo.find_commit(sibling)?, &repo.find_commit(feature)?, None)?;
+    let tree = repo.find_tree(index.write_tree_to(&repo)?)?;
+    let merge = repo.commit(
+        None,
+        &signature,
+        &signature,
+        "Merge pull request #34 from acme/search\n\nfeat: add search",
+        &tree,
+        &[&repo.find_commit(sibling)?, &repo.find_commit(feature)?],
+    )?;
+
+    let entries = changelog::entries(&repo, Some(base), merge)?;
+    let numbers: Vec<(String, Option<u64>)> = entries.iter()
+        .map(|e| (e.commit.subject.clone(), e.pull_request))
+        .collect();
+    assert_eq!(numbers, vec![("typo".to_string(), None), ("add search".to_string(), Some(34))]);
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+const EXISTING: &str = "# Changelog
+
+Hand-written intro.
+
+## [Unreleased]
+
+- stale note
+
+## [1.0.0] - 2024-01-01
+
+### Added
+
+- First release, edited by hand
+
+[Unreleased]: https://github.com/acme/widgets/compare/v1.0.0...HEAD
+[1.0.0]: https://github.com/acme/widgets/releases/tag/v1.0.0
+[docs]: https://example.com/docs
+";
+
+#[test]
+fn test_update_existing_changelog() {
+    let generated = "# Changelog
+
+## [Unreleased]
+
+### Added
+
+- search
+
+## [1.1.0] - 2024-02-01
+
+### Fixed
+
+- crash
+
+## [1.0.0] - 2024-01-01
+
+### Added
+
+- first release
+
+## [0.9.0] - 2023-12-01
+
+### Added
+
+- beta
+
+[Unreleased]: https://github.com/acme/widgets/compare/v1.1.0...HEAD
+[1.1.0]: https://github.com/acme/widgets/compare/v1.0.0...v1.1.0
+[1.0.0]: https://github.com/acme/widgets/compare/v0.9.0...v1.0.0
+[0.9.0]: https://github.com/acme/widgets/releases/tag/v0.9.0
+";
+
+    let (updated, changes) = changelog::update(Some(EXISTING), generated, true);
+    assert_eq!(changes, vec!["updated [Unreleased]", "added [1.1.0]", "added [0.9.0]"]);
+    assert_eq!(updated, "# Changelog
+
+Hand-written intro.
+
+## [Unreleased]
+
+### Added
+
+- search
+
+## [1.1.0] - 2024-02-01
+
+### Fixed
+
+- crash
+
+## [1.0.0] - 2024-01-01
+
+### Added
+
+- First release, edited by hand
+
+## [0.9.0] - 2023-12-01
+
+### Added
+
+- beta
+
+[Unreleased]: https://github.com/acme/widgets/compare/v1.1.0...HEAD
+[1.1.0]: https://github.com/acme/widgets/compare/v1.0.0...v1.1.0
+[1.0.0]: https://github.com/acme/widgets/releases/tag/v1.0.0
+[0.9.0]: https://github.com/acme/widgets/releases/tag/v0.9.0
+[docs]: https://example.com/docs
+");
+
+    // Running it again changes nothing
+    let (again, changes) = changelog::update(Some(&updated), generated, true);
+    assert!(changes.is_empty());
+    assert_eq!(again, updated);
+}
+
+#[test]
+fn test_release_replaces_unreleased() {
+    let generated = "# Changelog
+
+## [1.1.0] - 2024-02-01
+
+### Fixed
+
+- crash
+
+[1.1.0]: https://github.com/acme/widgets/compare/v1.0.0...v1.1.0
+";
+    let (updated, changes) = changelog::update(Some(EXISTING), generated, true);
+    assert_eq!(changes, vec!["removed [Unreleased]", "added [1.1.0]"]);
+    assert!(!updated.contains("stale note"));
+    assert!(updated.contains("Hand-written intro.\n\n## [1.1.0] - 2024-02-01\n"));
+    assert!(updated.ends_with(
+        "[1.1.0]: https://github.com/acme/widgets/compare/v1.0.0...v1.1.0\n\
+         [1.0.0]: https://github.com/acme/widgets/releases/tag/v1.0.0\n\
+         [docs]: https://example.com/docs\n"
+    ));
+
+    // A window that stops at a tag leaves [Unreleased] alone
+    let (updated, _) = changelog::update(Some(EXISTING), generated, false);
+    assert!(updated.contains("## [Unreleased]\n\n- stale note\n"));
+}
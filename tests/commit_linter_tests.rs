@@ -0,0 +1,70 @@
+use commit_buddy::commit_linter;
+use commit_buddy::config::LintRules;
+use anyhow::Result;
+use git2::Repository;
+
+mod common;
+use common::temp_repo;
+
+fn commit(repo: &Repository, path: &str, message: &str) -> Result<git2::Oid> {
+    common::commit(repo, path, message, message)
+}
+
+fn rules_broken(message: &str, rules: &LintRules) -> Vec<&'static str> {
+    commit_linter::lint_message(message, rules).into_iter().map(|v| v.rule).collect()
+}
+
+#[test]
+fn test_default_rules() {
+    let rules = LintRules::default();
+    assert!(rules_broken("feat(api): add pagination", &rules).is_empty());
+    assert!(rules_broken("Merge branch 'main' into feature", &rules).is_empty());
+
+    assert_eq!(rules_broken("Feat: add thing", &rules), vec!["type-case"]);
+    assert_eq!(rules_broken("feature: add thing", &rules), vec!["type-enum"]);
+    assert_eq!(rules_broken("fix: Handle it.", &rules), vec!["subject-case", "subject-full-stop"]);
+    assert_eq!(rules_broken("fixup! fix: handle it", &rules), vec!["no-wip", "header-format"]);
+    assert_eq!(rules_broken("chore: wip", &rules), vec!["no-wip"]);
+    assert_eq!(rules_broken(&format!("fix: {}", "a".repeat(80)), &rules), vec!["subject-max-length"]);
+    assert_eq!(
+        rules_broken(&format!("fix: wrap\n\n{}\n\nSee https://example.com/{}", "b".repeat(120), "c".repeat(120)), &rules),
+        vec!["body-max-line-length"]
+    );
+}
+
+#[test]
+fn test_configured_rules() {
+    let rules = LintRules {
+        scopes: vec!["api".to_string()],
+        subject_case: "sentence".to_string(),
+        required_trailers: vec!["Signed-off-by".to_string()],
+        allow_wip: true,
+        ..LintRules::default()
+    };
+    assert_eq!(rules_broken("feat(ui): Add button", &rules), vec!["scope-enum", "trailer-required"]);
+    assert!(rules_broken("feat(api): Add WIP endpoint\n\nSigned-off-by: A <a@example.com>", &rules).is_empty());
+    assert_eq!(rules_broken("feat: add thing\n\nSigned-off-by: A <a@example.com>", &rules), vec!["subject-case"]);
+    assert!(rules_broken("feat(api)!: Drop v1\n\nBREAKING CHANGE: v1 is gone\nSigned-off-by: A <a@example.com>", &rules).is_empty());
+}
+
+#[test]
+fn test_lint_range_and_message_file() -> Result<()> {
+    let (dir, repo) = temp_repo("lint-range")?;
+    let base = commit(&repo, "a.txt", "chore: initial")?;
+    commit(&repo, "b.txt", "feat: add b")?;
+    commit(&repo, "c.txt", "Add c")?;
+
+    let summary = commit_linter::lint_range(&repo, &format!("{}..HEAD", base), &LintRules::default())?;
+    assert_eq!(summary.checked, 2);
+    assert_eq!(summary.failed, 1);
+    assert_eq!(summary.commits[1].subject, "Add c");
+
+    let message_file = dir.join("COMMIT_EDITMSG");
+    std::fs::write(&message_file, "fix: handle empty input\n# Please enter the commit message\n# ------------------------ >8 ------------------------\nDiff: noise\n")?;
+    let summary = commit_linter::lint_message_file(&repo, message_file.to_str().unwrap(), &LintRules::default())?;
+    assert_eq!(summary.failed, 0);
+    assert_eq!(summary.commits[0].subject, "fix: handle empty input");
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
@@ -0,0 +1,101 @@
+use commit_buddy::committer::Committer;
+use commit_buddy::git;
+use anyhow::Result;
+use git2::Repository;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+
+mod common;
+use common::{temp_repo, commit};
+
+fn stage(repo: &Repository, path: &str, content: &str) -> Result<()> {
+    std::fs::write(repo.workdir().unwrap().join(path), content)?;
+    let mut index = repo.index()?;
+    index.add_path(Path::new(path))?;
+    index.write()?;
+    Ok(())
+}
+
+fn write_hook(repo: &Repository, name: &str, script: &str) -> Result<()> {
+    let dir = repo.path().join("hooks");
+    std::fs::create_dir_all(&dir)?;
+    let hook = dir.join(name);
+    std::fs::write(&hook, script)?;
+    std::fs::set_permissions(&hook, std::fs::Permissions::from_mode(0o755))?;
+    Ok(())
+}
+
+#[test]
+fn test_failing_pre_commit_hook_stops_the_commit() -> Result<()> {
+    let (dir, repo) = temp_repo("committer-pre-commit")?;
+    let initial = commit(&repo, "a.txt", "a\n", "initial")?;
+    write_hook(&repo, "pre-commit", "#!/bin/sh\necho 'lint failed: a.txt'\nexit 1\n")?;
+    stage(&repo, "a.txt", "b\n")?;
+
+    let error = Committer::new(&repo).commit("feat: change a").unwrap_err().to_string();
+    assert!(error.contains("pre-commit hook failed"), "{}", error);
+    assert!(error.contains("lint failed: a.txt"), "the hook output is reported: {}", error);
+    assert_eq!(git::head_commit(&repo)?.map(|c| c.id()), Some(initial));
+
+    let outcome = Committer::new(&repo).no_verify(true).commit("feat: change a")?;
+    assert_eq!(git::head_commit(&repo)?.map(|c| c.id()), Some(outcome.id));
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_commit_msg_hook_edits_are_kept() -> Result<()> {
+    let (dir, repo) = temp_repo("committer-commit-msg")?;
+    write_hook(&repo, "commit-msg", "#!/bin/sh\nprintf '\\nReviewed-by: Hook <hook@example.com>\\n' >> \"$1\"\n")?;
+    stage(&repo, "a.txt", "a\n")?;
+
+    let outcome = Committer::new(&repo).commit("feat: add a")?;
+    assert_eq!(outcome.message, "feat: add a\n\nReviewed-by: Hook <hook@example.com>\n");
+    assert_eq!(repo.find_commit(outcome.id)?.message(), Some(outcome.message.as_str()));
+    assert_eq!(git::head_commit(&repo)?.map(|c| c.id()), Some(outcome.id), "the first commit starts the branch");
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_commit_concludes_a_merge_in_progress() -> Result<()> {
+    let (dir, repo) = temp_repo("committer-merge")?;
+    let base = commit(&repo, "a.txt", "a\n", "initial")?;
+    let main = repo.head()?.shorthand().unwrap().to_string();
+    repo.branch("topic", &repo.find_commit(base)?, false)?;
+    repo.set_head("refs/heads/topic")?;
+    let topic = commit(&repo, "b.txt", "b\n", "feat: add b")?;
+    repo.set_head(&format!("refs/heads/{}", main))?;
+    repo.checkout_head(Some(git2::build::CheckoutBuilder::new().force()))?;
+    let ours = commit(&repo, "c.txt", "c\n", "feat: add c")?;
+
+    repo.merge(&[&repo.find_annotated_commit(topic)?], None, None)?;
+    assert_eq!(repo.state(), git2::RepositoryState::Merge);
+
+    let outcome = Committer::new(&repo).commit("Merge branch 'topic'")?;
+    let merge = repo.find_commit(outcome.id)?;
+    assert_eq!(merge.parent_ids().collect::<Vec<_>>(), vec![ours, topic]);
+    assert_eq!(repo.state(), git2::RepositoryState::Clean);
+    assert!(!dir.join(".git/MERGE_HEAD").exists());
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_commit_refuses_while_a_rebase_is_in_progress() -> Result<()> {
+    let (dir, repo) = temp_repo("committer-rebase")?;
+    let initial = commit(&repo, "a.txt", "a\n", "initial")?;
+    std::fs::create_dir_all(dir.join(".git/rebase-merge"))?;
+    stage(&repo, "a.txt", "b\n")?;
+
+    let error = Committer::new(&repo).commit("fix: change a").unwrap_err().to_string();
+    assert!(error.contains("rebase is in progress"), "{}", error);
+    assert_eq!(git::head_commit(&repo)?.map(|c| c.id()), Some(initial));
+    assert!(dir.join(".git/rebase-merge").exists(), "the rebase state is kept");
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
@@ -0,0 +1,42 @@
+#![allow(dead_code)]
+
+use anyhow::Result;
+use git2::{Oid, Repository, Signature};
+use std::path::{Path, PathBuf};
+
+/// Create an empty repository under the temp dir with a test identity configured.
+pub fn temp_repo(name: &str) -> Result<(PathBuf, Repository)> {
+    let dir = std::env::temp_dir().join(format!("commit-buddy-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir)?;
+    let repo = Repository::init(&dir)?;
+    {
+        let mut config = repo.config()?;
+        config.set_str("user.name", "Test User")?;
+        config.set_str("user.email", "test@example.com")?;
+    }
+    Ok((dir.canonicalize()?, repo))
+}
+
+/// Write `content` to `path`, stage it and commit on HEAD as the test user.
+pub fn commit(repo: &Repository, path: &str, content: &str, message: &str) -> Result<Oid> {
+    let signature = Signature::now("Test User", "test@example.com")?;
+    commit_as(repo, &signature, path, content, message)
+}
+
+/// Like [`commit`], with an explicit author and committer.
+pub fn commit_as(repo: &Repository, signature: &Signature, path: &str, content: &str, message: &str) -> Result<Oid> {
+    let file = repo.workdir().unwrap().join(path);
+    std::fs::create_dir_all(file.parent().unwrap())?;
+    std::fs::write(file, content)?;
+    let mut index = repo.index()?;
+    index.add_path(Path::new(path))?;
+    index.write()?;
+    let tree = repo.find_tree(index.write_tree()?)?;
+    let parents: Vec<git2::Commit> = repo.head().ok()
+        .and_then(|h| h.peel_to_commit().ok())
+        .into_iter()
+        .collect();
+    let parent_refs: Vec<&git2::Commit> = parents.iter().collect();
+    Ok(repo.commit(Some("HEAD"), signature, signature, message, &tree, &parent_refs)?)
+}
@@ -0,0 +1,128 @@
+use commit_buddy::branches;
+use commit_buddy::conflicts::{self, ConflictHunk, Resolution, Segment};
+use anyhow::Result;
+use git2::{Repository, Signature};
+use std::path::{Path, PathBuf};
+
+mod common;
+use common::temp_repo;
+
+fn commit(repo: &Repository, path: &str, content: Option<&str>, message: &str) -> Result<()> {
+    let file = repo.workdir().unwrap().join(path);
+    let mut index = repo.index()?;
+    match content {
+        Some(content) => {
+            std::fs::write(file, content)?;
+            index.add_path(Path::new(path))?;
+        }
+        None => {
+            std::fs::remove_file(file)?;
+            index.remove_path(Path::new(path))?;
+        }
+    }
+    index.write()?;
+    let tree = repo.find_tree(index.write_tree()?)?;
+    let signature = Signature::now("Test User", "test@example.com")?;
+    let parent = repo.head()?.peel_to_commit()?;
+    repo.commit(Some("HEAD"), &signature, &signature, message, &tree, &[&parent])?;
+    Ok(())
+}
+
+/// Commits `base`, then `ours` on the current branch and `theirs` on
+/// `topic`, and merges `topic` so that the merge stops on a conflict.
+fn conflicted_repo(name: &str, base: &str, ours: Option<&str>, theirs: Option<&str>) -> Result<(PathBuf, Repository)> {
+    let (dir, repo) = temp_repo(name)?;
+    std::fs::write(dir.join("a.txt"), base)?;
+    let root = {
+        let mut index = repo.index()?;
+        index.add_path(Path::new("a.txt"))?;
+        index.write()?;
+        let tree = repo.find_tree(index.write_tree()?)?;
+        let signature = Signature::now("Test User", "test@example.com")?;
+        repo.commit(Some("HEAD"), &signature, &signature, "base", &tree, &[])?
+    };
+    let main = repo.head()?.name().unwrap().to_string();
+
+    repo.branch("topic", &repo.find_commit(root)?, false)?;
+    repo.set_head("refs/heads/topic")?;
+    commit(&repo, "a.txt", theirs, "theirs")?;
+    repo.set_head(&main)?;
+    repo.checkout_head(Some(git2::build::CheckoutBuilder::new().force()))?;
+    commit(&repo, "a.txt", ours, "ours")?;
+
+    assert!(branches::merge(&repo, "topic").is_err());
+    Ok((dir, repo))
+}
+
+#[test]
+fn test_parse_conflict_markers() {
+    let text = "a\n<<<<<<< ours\nb\n||||||| base\nB\n=======\nc\n>>>>>>> theirs\nd\n";
+    assert_eq!(conflicts::parse_conflict_markers(text), vec![
+        Segment::Clean("a\n".to_string()),
+        Segment::Conflict(ConflictHunk {
+            ours: "b\n".to_string(),
+            base: Some("B\n".to_string()),
+            theirs: "c\n".to_string(),
+        }),
+        Segment::Clean("d\n".to_string()),
+    ]);
+
+    let two_way = conflicts::parse_conflict_markers("<<<<<<< HEAD\nx\n=======\ny\n>>>>>>> topic\n");
+    assert_eq!(two_way, vec![Segment::Conflict(ConflictHunk {
+        ours: "x\n".to_string(),
+        base: None,
+        theirs: "y\n".to_string(),
+    })]);
+
+    let unterminated = "a\n<<<<<<< ours\nb\n";
+    assert_eq!(conflicts::parse_conflict_markers(unterminated), vec![Segment::Clean(unterminated.to_string())]);
+    assert!(!conflicts::has_markers("a\n======= not a marker\n"));
+}
+
+#[test]
+fn test_conflicted_files_have_base_and_resolve_per_hunk() -> Result<()> {
+    let base = "1\n2\n3\n4\n5\n6\n7\n8\n9\n";
+    let ours = "1\nours-2\n3\n4\n5\n6\n7\nours-8\n9\n";
+    let theirs = "1\ntheirs-2\n3\n4\n5\n6\n7\ntheirs-8\n9\n";
+    let (dir, repo) = conflicted_repo("conflicts-hunks", base, Some(ours), Some(theirs))?;
+    assert!(conflicts::has_conflicts(&repo)?);
+
+    let files = conflicts::conflicted_files(&repo)?;
+    assert_eq!(files.len(), 1);
+    let file = &files[0];
+    assert_eq!(file.path, "a.txt");
+    let hunks = file.hunks();
+    assert_eq!(hunks.len(), 2);
+    assert_eq!(hunks[0].ours, "ours-2\n");
+    assert_eq!(hunks[0].base.as_deref(), Some("2\n"));
+    assert_eq!(hunks[0].theirs, "theirs-2\n");
+
+    let unresolved = [Resolution::Text("<<<<<<< ours\nx\n=======\ny\n>>>>>>> theirs\n".to_string()), Resolution::Ours];
+    assert!(conflicts::resolve(&repo, file, &unresolved).is_err());
+    assert!(conflicts::resolve(&repo, file, &[Resolution::Ours]).is_err(), "one resolution per hunk");
+
+    conflicts::resolve(&repo, file, &[Resolution::Theirs, Resolution::Text("both-8\n".to_string())])?;
+    assert_eq!(std::fs::read_to_string(dir.join("a.txt"))?, "1\ntheirs-2\n3\n4\n5\n6\n7\nboth-8\n9\n");
+    assert!(!conflicts::has_conflicts(&repo)?);
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_modify_delete_conflict_can_take_the_deletion() -> Result<()> {
+    let (dir, repo) = conflicted_repo("conflicts-delete", "a\n", Some("changed\n"), None)?;
+
+    let files = conflicts::conflicted_files(&repo)?;
+    assert_eq!(files.len(), 1);
+    assert!(files[0].theirs_deleted);
+    assert_eq!(files[0].hunks()[0].ours, "changed\n");
+
+    conflicts::resolve(&repo, &files[0], &[Resolution::Theirs])?;
+    assert!(!dir.join("a.txt").exists());
+    assert!(!conflicts::has_conflicts(&repo)?);
+    assert!(repo.index()?.get_path(Path::new("a.txt"), 0).is_none());
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
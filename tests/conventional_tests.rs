@@ -0,0 +1,58 @@
+use commit_buddy::conventional::{ConventionalCommit, ParseError};
+use commit_buddy::utils;
+
+#[test]
+fn test_parse_header_with_scope_and_breaking_marker() {
+    let parsed = ConventionalCommit::parse("feat(api)!: drop v1 endpoints").unwrap();
+    assert_eq!(parsed.commit_type, "feat");
+    assert_eq!(parsed.scope.as_deref(), Some("api"));
+    assert!(parsed.breaking);
+    assert_eq!(parsed.subject, "drop v1 endpoints");
+    assert_eq!(parsed.body, None);
+    assert!(parsed.footers.is_empty());
+    assert_eq!(parsed.breaking_description(), Some("drop v1 endpoints"));
+    assert_eq!(parsed.emoji(), "💥");
+}
+
+#[test]
+fn test_parse_body_and_footers() {
+    let message = "fix: handle empty input\n\nThe parser crashed on empty files.\n\nSecond paragraph.\n\nBREAKING CHANGE: empty files are now an error\nRefs: PROJ-7\nCloses #12\n";
+    let parsed = ConventionalCommit::parse(message).unwrap();
+    assert_eq!(parsed.kind(), "fix");
+    assert_eq!(parsed.body.as_deref(), Some("The parser crashed on empty files.\n\nSecond paragraph."));
+    assert!(parsed.breaking);
+    assert_eq!(parsed.breaking_description(), Some("empty files are now an error"));
+
+    let tokens: Vec<&str> = parsed.footers.iter().map(|f| f.token.as_str()).collect();
+    assert_eq!(tokens, vec!["BREAKING CHANGE", "Refs", "Closes"]);
+    assert_eq!(parsed.footers[2].value, "#12");
+}
+
+#[test]
+fn test_body_without_footers() {
+    let parsed = ConventionalCommit::parse("docs: explain setup\n\nSee issue #4 for details.").unwrap();
+    assert_eq!(parsed.body.as_deref(), Some("See issue #4 for details."));
+    assert!(parsed.footers.is_empty());
+    assert!(!parsed.breaking);
+    assert_eq!(parsed.emoji(), "📚");
+}
+
+#[test]
+fn test_parse_errors() {
+    assert_eq!(ConventionalCommit::parse(""), Err(ParseError::Empty));
+    assert_eq!(ConventionalCommit::parse("Update readme"), Err(ParseError::MissingSeparator));
+    assert_eq!(ConventionalCommit::parse("feat:missing space"), Err(ParseError::MissingSeparator));
+    assert_eq!(ConventionalCommit::parse("feat(): x"), Err(ParseError::EmptyScope));
+    assert_eq!(ConventionalCommit::parse("feat(api: x"), Err(ParseError::UnclosedScope));
+    assert_eq!(ConventionalCommit::parse("fix: "), Err(ParseError::EmptySubject));
+    assert_eq!(ConventionalCommit::parse(": x"), Err(ParseError::MissingType));
+    assert_eq!(ConventionalCommit::parse("fix: x\nbody"), Err(ParseError::MissingBlankLine));
+    assert!(matches!(ConventionalCommit::parse("[WIP] stuff"), Err(ParseError::InvalidType(_))));
+}
+
+#[test]
+fn test_is_conventional_commit_accepts_scopes() {
+    assert!(utils::is_conventional_commit("feat(api): x"));
+    assert!(utils::is_conventional_commit("Fix: case-insensitive types"));
+    assert!(!utils::is_conventional_commit("Merge branch 'main'"));
+}
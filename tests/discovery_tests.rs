@@ -0,0 +1,54 @@
+use commit_buddy::committer;
+use commit_buddy::git;
+use anyhow::Result;
+use git2::Repository;
+
+mod common;
+use common::temp_repo;
+
+fn commit(repo: &Repository, path: &str, message: &str) -> Result<git2::Oid> {
+    common::commit(repo, path, message, message)
+}
+
+// The only test in this file that changes the working directory, since it
+// is shared by every test in the process
+#[test]
+fn test_commands_work_from_subdirectories() -> Result<()> {
+    let (dir, repo) = temp_repo("discovery-subdir")?;
+    commit(&repo, "a.txt", "chore: initial")?;
+    let base = repo.head()?.peel_to_commit()?.id();
+    commit(&repo, "src/deep/lib.rs", "feat: add lib")?;
+
+    std::env::set_current_dir(dir.join("src/deep"))?;
+    let opened = git::open_repository()?;
+    assert_eq!(opened.workdir().unwrap().canonicalize()?, dir);
+
+    let diff_info = git::get_diff_info(&base.to_string())?;
+    assert_eq!(diff_info.commits[0].files_changed, vec!["src/deep/lib.rs"]);
+    assert_eq!(diff_info.path("tests/"), dir.join("tests/"));
+    assert_eq!(git::workdir_path("tests/"), dir.join("tests/"));
+
+    let status = commit_buddy::utils::run_git_command(&["rev-parse", "--show-prefix"])?;
+    assert_eq!(status.trim(), "", "git runs at the top of the work tree");
+
+    std::env::set_current_dir(std::env::temp_dir())?;
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_worktrees_share_hooks() -> Result<()> {
+    let (dir, repo) = temp_repo("discovery-worktree")?;
+    commit(&repo, "a.txt", "chore: initial")?;
+    let worktree_dir = dir.with_file_name(format!("{}-wt", dir.file_name().unwrap().to_string_lossy()));
+    let _ = std::fs::remove_dir_all(&worktree_dir);
+    repo.worktree("wt", &worktree_dir, None)?;
+
+    let linked = Repository::discover(&worktree_dir)?;
+    assert!(linked.is_worktree());
+    assert_eq!(committer::hooks_dir(&linked), dir.join(".git/hooks"));
+
+    std::fs::remove_dir_all(worktree_dir)?;
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
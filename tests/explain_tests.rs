@@ -0,0 +1,99 @@
+use commit_buddy::explain::{self, ExplainTarget};
+use anyhow::Result;
+use git2::{Oid, Repository, Signature};
+use std::path::Path;
+
+mod common;
+use common::temp_repo;
+
+/// Writes `files` (a `None` content deletes the file) and commits them.
+fn commit(repo: &Repository, files: &[(&str, Option<&str>)], message: &str) -> Result<Oid> {
+    let workdir = repo.workdir().unwrap();
+    let mut index = repo.index()?;
+    for (path, content) in files {
+        match content {
+            Some(content) => {
+                let file = workdir.join(path);
+                std::fs::create_dir_all(file.parent().unwrap())?;
+                std::fs::write(file, content)?;
+                index.add_path(Path::new(path))?;
+            }
+            None => {
+                std::fs::remove_file(workdir.join(path))?;
+                index.remove_path(Path::new(path))?;
+            }
+        }
+    }
+    index.write()?;
+    let tree = repo.find_tree(index.write_tree()?)?;
+    let signature = Signature::now("Test User", "test@example.com")?;
+    let parents: Vec<git2::Commit> = repo.head().ok()
+        .and_then(|h| h.peel_to_commit().ok())
+        .into_iter()
+        .collect();
+    let parent_refs: Vec<&git2::Commit> = parents.iter().collect();
+    Ok(repo.commit(Some("HEAD"), &signature, &signature, message, &tree, &parent_refs)?)
+}
+
+const PARSER: &str = "fn parse(input: &str) -> Vec<String> {\n    input.split(',').map(str::to_string).collect()\n}\n\nfn helper() -> usize {\n    42\n}\n";
+
+#[test]
+fn test_resolve_target_prefers_ranges_then_revisions_then_paths() -> Result<()> {
+    let (dir, repo) = temp_repo("explain-resolve")?;
+    let first = commit(&repo, &[("src/parser.rs", Some(PARSER))], "feat: add parser")?;
+    let second = commit(&repo, &[("README.md", Some("docs\n"))], "docs: add readme")?;
+    repo.branch("src", &repo.find_commit(first)?, false)?;
+    let cwd = std::env::current_dir()?;
+
+    assert_eq!(explain::resolve_target(&repo, "HEAD", &cwd)?, ExplainTarget::Commit(second));
+    assert_eq!(explain::resolve_target(&repo, "HEAD~1..HEAD", &cwd)?, ExplainTarget::Range { base: Some(first), tip: second });
+    assert_eq!(explain::resolve_target(&repo, "src/parser.rs", &cwd)?, ExplainTarget::Path("src/parser.rs".to_string()));
+    assert_eq!(explain::resolve_target(&repo, "src", &cwd)?, ExplainTarget::Commit(first), "revisions win like in git");
+    assert_eq!(explain::resolve_target(&repo, "./src", &cwd)?, ExplainTarget::Path("src".to_string()));
+    assert_eq!(explain::resolve_target(&repo, "parser.rs", &dir.join("src"))?, ExplainTarget::Path("src/parser.rs".to_string()));
+    assert!(explain::resolve_target(&repo, "missing.rs", &cwd).is_err());
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_file_history_follows_renames() -> Result<()> {
+    let (dir, repo) = temp_repo("explain-history")?;
+    let added = commit(&repo, &[("parser.rs", Some(PARSER))], "feat: add parser")?;
+    commit(&repo, &[("other.rs", Some("fn other() {}\n"))], "chore: unrelated")?;
+    let renamed = commit(&repo, &[("parser.rs", None), ("src/parser.rs", Some(PARSER))], "refactor: move parser into src")?;
+    let changed = commit(&repo, &[("src/parser.rs", Some(&PARSER.replace("42", "43")))], "fix: correct helper")?;
+
+    let history = explain::file_history(&repo, "src/parser.rs", 10)?;
+    let ids: Vec<Oid> = history.iter().map(|(id, _)| *id).collect();
+    assert_eq!(ids, vec![changed, renamed, added]);
+    assert_eq!(history[1].1, vec!["src/parser.rs".to_string(), "parser.rs".to_string()]);
+    assert_eq!(history[2].1, vec!["parser.rs".to_string()]);
+    assert_eq!(explain::file_history(&repo, "src/parser.rs", 1)?.len(), 1);
+
+    let diff_info = explain::collect(&repo, &ExplainTarget::Path("src/parser.rs".to_string()))?;
+    let subjects: Vec<&str> = diff_info.commits.iter().map(|c| c.message.as_str()).collect();
+    assert_eq!(subjects, vec!["feat: add parser", "refactor: move parser into src", "fix: correct helper"]);
+    assert!(diff_info.commits.iter().all(|c| !c.files_changed.contains(&"other.rs".to_string())), "diffs are limited to the path");
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_collect_range_is_oldest_first() -> Result<()> {
+    let (dir, repo) = temp_repo("explain-range")?;
+    let base = commit(&repo, &[("a.txt", Some("a\n"))], "chore: initial")?;
+    commit(&repo, &[("b.txt", Some("b\n"))], "feat: add b")?;
+    let tip = commit(&repo, &[("c.txt", Some("c\n"))], "feat: add c")?;
+
+    let diff_info = explain::collect(&repo, &ExplainTarget::Range { base: Some(base), tip })?;
+    let subjects: Vec<&str> = diff_info.commits.iter().map(|c| c.message.as_str()).collect();
+    assert_eq!(subjects, vec!["feat: add b", "feat: add c"]);
+    assert_eq!(diff_info.total_files_changed, 2);
+    assert_eq!(explain::describe_target(&repo, &ExplainTarget::Path("a.txt".to_string())), "the history of a.txt");
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
@@ -0,0 +1,137 @@
+use commit_buddy::git::{self, ChangeKind, CommitInfo};
+use anyhow::Result;
+use git2::{FileMode, Oid, Repository, Signature};
+
+mod common;
+use common::temp_repo;
+
+const SOURCE: &str = "fn parse() {}\nfn lex() {}\nfn emit() {}\nfn check() {}\nfn run() {}\nfn main() {}\n";
+
+/// Commit a flat tree made of `(path, content, mode)` entries on HEAD. A
+/// `FileMode::Commit` entry is a submodule pointer and takes its content as
+/// the hex commit id.
+fn commit_tree(repo: &Repository, entries: &[(&str, &[u8], FileMode)], message: &str) -> Result<Oid> {
+    let mut builder = repo.treebuilder(None)?;
+    for (path, content, mode) in entries {
+        let id = match mode {
+            FileMode::Commit => Oid::from_str(std::str::from_utf8(content)?)?,
+            _ => repo.blob(content)?,
+        };
+        builder.insert(path, id, i32::from(*mode))?;
+    }
+    let tree = repo.find_tree(builder.write()?)?;
+    let signature = Signature::now("Test User", "test@example.com")?;
+    let parents: Vec<git2::Commit> = repo.head().ok()
+        .and_then(|h| h.peel_to_commit().ok())
+        .into_iter()
+        .collect();
+    let parent_refs: Vec<&git2::Commit> = parents.iter().collect();
+    Ok(repo.commit(Some("HEAD"), &signature, &signature, message, &tree, &parent_refs)?)
+}
+
+/// The diff of the HEAD commit, as the commands that read history see it.
+fn head_info() -> Result<CommitInfo> {
+    let mut diff_info = git::get_diff_info("HEAD~1")?;
+    assert_eq!(diff_info.commits.len(), 1);
+    Ok(diff_info.commits.remove(0))
+}
+
+// The only test in this file that changes the working directory, since it
+// is shared by every test in the process
+#[test]
+fn test_delta_kinds_are_described() -> Result<()> {
+    let (dir, repo) = temp_repo("git-deltas")?;
+    std::env::set_current_dir(&dir)?;
+    let logo: &[u8] = &[0x89, b'P', b'N', b'G', 0, 0, 1, 2];
+    let vendor = "1111111111111111111111111111111111111111";
+    commit_tree(&repo, &[
+        ("parser.rs", SOURCE.as_bytes(), FileMode::Blob),
+        ("logo.png", logo, FileMode::Blob),
+        ("build.sh", b"make\n", FileMode::Blob),
+        ("vendor", vendor.as_bytes(), FileMode::Commit),
+    ], "initial")?;
+
+    // A rename is one change that keeps the old path
+    commit_tree(&repo, &[
+        ("syntax.rs", SOURCE.as_bytes(), FileMode::Blob),
+        ("logo.png", logo, FileMode::Blob),
+        ("build.sh", b"make\n", FileMode::Blob),
+        ("vendor", vendor.as_bytes(), FileMode::Commit),
+    ], "rename")?;
+    let info = head_info()?;
+    assert_eq!(info.file_changes.len(), 1, "{:?}", info.file_changes);
+    let change = &info.file_changes[0];
+    assert_eq!(change.kind, ChangeKind::Renamed);
+    assert_eq!(change.path, "syntax.rs");
+    assert_eq!(change.old_path.as_deref(), Some("parser.rs"));
+    assert_eq!(change.describe(), "renamed parser.rs → syntax.rs");
+    assert!(info.diff.contains("rename from parser.rs\nrename to syntax.rs\n"), "{}", info.diff);
+
+    // A copy of a modified file keeps its source
+    let edited = format!("{}fn extra() {{}}\n", SOURCE);
+    commit_tree(&repo, &[
+        ("syntax.rs", edited.as_bytes(), FileMode::Blob),
+        ("syntax_copy.rs", SOURCE.as_bytes(), FileMode::Blob),
+        ("logo.png", logo, FileMode::Blob),
+        ("build.sh", b"make\n", FileMode::Blob),
+        ("vendor", vendor.as_bytes(), FileMode::Commit),
+    ], "copy")?;
+    let info = head_info()?;
+    let copy = info.file_changes.iter().find(|c| c.path == "syntax_copy.rs").expect("copy is listed");
+    assert_eq!(copy.kind, ChangeKind::Copied);
+    assert_eq!(copy.old_path.as_deref(), Some("syntax.rs"));
+    let source = info.file_changes.iter().find(|c| c.path == "syntax.rs").expect("source is listed");
+    assert_eq!((source.kind, source.additions, source.deletions), (ChangeKind::Modified, 1, 0));
+
+    // Binary changes, mode changes and submodule bumps are summarized
+    // instead of diffed
+    let new_vendor = "2222222222222222222222222222222222222222";
+    commit_tree(&repo, &[
+        ("syntax.rs", edited.as_bytes(), FileMode::Blob),
+        ("syntax_copy.rs", SOURCE.as_bytes(), FileMode::Blob),
+        ("logo.png", &[0x89, b'P', b'N', b'G', 0, 0, 3, 4, 5, 6], FileMode::Blob),
+        ("build.sh", b"make\n", FileMode::BlobExecutable),
+        ("vendor", new_vendor.as_bytes(), FileMode::Commit),
+    ], "assets")?;
+    let info = head_info()?;
+    assert_eq!(info.files_changed, vec!["build.sh", "logo.png", "vendor"]);
+    let changes: Vec<String> = info.file_changes.iter().map(|c| c.describe()).collect();
+    assert_eq!(changes, vec![
+        "modified build.sh [mode 100644 → 100755]",
+        "modified logo.png (binary)",
+        "modified vendor (submodule pointer)",
+    ]);
+    let logo_change = &info.file_changes[1];
+    assert!(logo_change.binary);
+    assert_eq!((logo_change.additions, logo_change.deletions), (0, 0));
+    assert!(info.file_changes[2].submodule);
+    assert!(info.diff.contains("old mode 100644\nnew mode 100755\n"), "{}", info.diff);
+    assert!(info.diff.contains("Binary file logo.png changed (8.0 B → 10.0 B)\n"), "{}", info.diff);
+    assert!(info.diff.contains("Submodule vendor 1111111..2222222\n"), "{}", info.diff);
+
+    std::env::set_current_dir(std::env::temp_dir())?;
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_resolve_range_forms() -> Result<()> {
+    let (dir, repo) = temp_repo("git-range")?;
+    let first = commit_tree(&repo, &[("a.txt", b"1\n", FileMode::Blob)], "first")?;
+    let second = commit_tree(&repo, &[("a.txt", b"2\n", FileMode::Blob)], "second")?;
+    let third = commit_tree(&repo, &[("a.txt", b"3\n", FileMode::Blob)], "third")?;
+
+    assert_eq!(git::resolve_range(&repo, &format!("{}..{}", first, second))?, (Some(first), second));
+    assert_eq!(git::resolve_range(&repo, &format!("{}..", first))?, (Some(first), third));
+    assert_eq!(git::resolve_range(&repo, &first.to_string())?, (Some(first), third));
+    assert_eq!(git::resolve_range(&repo, "HEAD~1")?, (Some(second), third));
+    assert_eq!(git::resolve_commit(&repo, "HEAD~2")?, first);
+    assert_eq!(git::resolve_range(&repo, &format!("..{}", second))?, (Some(third), second));
+
+    let error = git::resolve_range(&repo, "no-such-branch").unwrap_err().to_string();
+    assert_eq!(error, "Could not resolve no-such-branch");
+    assert!(git::resolve_range(&repo, "no-such-branch..HEAD").is_err());
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
@@ -0,0 +1,90 @@
+use commit_buddy::history;
+use anyhow::Result;
+use git2::{Oid, Repository, Signature, Time};
+use std::path::Path;
+
+mod common;
+use common::temp_repo;
+
+/// Commits `content` to `path` on top of `parents` at a fixed time, moving
+/// `update_ref` when given.
+fn commit_at(repo: &Repository, update_ref: Option<&str>, path: &str, content: &str, time: i64, parents: &[Oid]) -> Result<Oid> {
+    std::fs::write(repo.workdir().unwrap().join(path), content)?;
+    let mut index = repo.index()?;
+    index.add_path(Path::new(path))?;
+    index.write()?;
+    let tree = repo.find_tree(index.write_tree()?)?;
+    let signature = Signature::new("Test User", "test@example.com", &Time::new(time, 0))?;
+    let parents: Vec<git2::Commit> = parents.iter().map(|id| repo.find_commit(*id)).collect::<Result<_, _>>()?;
+    let parent_refs: Vec<&git2::Commit> = parents.iter().collect();
+    Ok(repo.commit(update_ref, &signature, &signature, content, &tree, &parent_refs)?)
+}
+
+#[test]
+fn test_log_graph_draws_lanes_for_merges() -> Result<()> {
+    let (dir, repo) = temp_repo("history-graph")?;
+    let a = commit_at(&repo, Some("HEAD"), "a.txt", "A", 1000, &[])?;
+    let b = commit_at(&repo, Some("HEAD"), "a.txt", "B", 2000, &[a])?;
+    let c = commit_at(&repo, Some("refs/heads/topic"), "b.txt", "C", 3000, &[a])?;
+    let m = commit_at(&repo, Some("HEAD"), "b.txt", "M", 4000, &[b, c])?;
+
+    let rows = history::log_graph(&repo, 100)?;
+    let ids: Vec<Oid> = rows.iter().map(|row| row.id).collect();
+    assert_eq!(ids, vec![m, c, b, a]);
+    let graph: Vec<&str> = rows.iter().map(|row| row.graph.as_str()).collect();
+    assert_eq!(graph, vec!["●─╮", "│ ●", "● │", "●─╯"]);
+    assert_eq!(rows[0].parent_count, 2);
+    assert_eq!(rows[0].summary, "M");
+
+    let branch = repo.head()?.shorthand().unwrap().to_string();
+    assert_eq!(rows[0].refs, vec![format!("HEAD -> {}", branch)]);
+    assert_eq!(rows[1].refs, vec!["topic".to_string()]);
+    assert_eq!(history::log_graph(&repo, 2)?.len(), 2);
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_log_graph_marks_tags_and_detached_head() -> Result<()> {
+    let (dir, repo) = temp_repo("history-refs")?;
+    let a = commit_at(&repo, Some("HEAD"), "a.txt", "A", 1000, &[])?;
+    commit_at(&repo, Some("HEAD"), "a.txt", "B", 2000, &[a])?;
+    repo.tag_lightweight("v1.0.0", &repo.find_object(a, None)?, false)?;
+    repo.set_head_detached(a)?;
+
+    let rows = history::log_graph(&repo, 100)?;
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[1].refs, vec!["HEAD".to_string(), "tag: v1.0.0".to_string()]);
+    assert_eq!(rows[0].graph, "●");
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_cherry_pick_and_revert() -> Result<()> {
+    let (dir, repo) = temp_repo("history-apply")?;
+    let a = commit_at(&repo, Some("HEAD"), "a.txt", "A", 1000, &[])?;
+    let picked = commit_at(&repo, Some("refs/heads/topic"), "b.txt", "picked", 2000, &[a])?;
+    repo.checkout_head(Some(git2::build::CheckoutBuilder::new().force()))?;
+    assert!(!dir.join("b.txt").exists());
+
+    std::fs::write(dir.join("a.txt"), "dirty")?;
+    let err = history::cherry_pick(&repo, picked).unwrap_err();
+    assert!(err.to_string().contains("uncommitted changes"), "{}", err);
+    std::fs::write(dir.join("a.txt"), "A")?;
+
+    history::cherry_pick(&repo, picked)?;
+    assert_eq!(std::fs::read_to_string(dir.join("b.txt"))?, "picked");
+    let head = repo.head()?.peel_to_commit()?;
+    assert_eq!(head.summary(), Some("picked"));
+    assert_eq!(head.parent_id(0)?, a);
+
+    history::revert(&repo, head.id())?;
+    assert!(!dir.join("b.txt").exists());
+    assert!(repo.head()?.peel_to_commit()?.summary().unwrap().starts_with("Revert"));
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
@@ -0,0 +1,125 @@
+use commit_buddy::ai;
+use commit_buddy::config::Config;
+use commit_buddy::git::{ChangeKind, CommitInfo, DiffInfo, FileChange};
+use commit_buddy::hooks::{self, HookKind};
+use anyhow::Result;
+use std::os::unix::fs::PermissionsExt;
+
+mod common;
+use common::temp_repo;
+
+fn change(path: &str, old_path: Option<&str>, kind: ChangeKind) -> FileChange {
+    FileChange {
+        path: path.to_string(),
+        old_path: old_path.map(str::to_string),
+        kind,
+        binary: false,
+        submodule: false,
+        old_mode: None,
+        new_mode: None,
+        additions: 1,
+        deletions: 0,
+    }
+}
+
+fn staged(changes: Vec<FileChange>) -> DiffInfo {
+    DiffInfo::from_commits(vec![CommitInfo {
+        hash: "STAGED".to_string(),
+        message: "Staged changes".to_string(),
+        author: "Current user".to_string(),
+        date: String::new(),
+        files_changed: changes.iter().map(|c| c.path.clone()).collect(),
+        file_changes: changes,
+        diff: String::new(),
+    }])
+}
+
+// The only test in this file that changes the working directory, since it
+// is shared by every test in the process
+#[test]
+fn test_install_and_uninstall_leave_foreign_hooks_alone() -> Result<()> {
+    let (dir, _repo) = temp_repo("hooks-install")?;
+    let hooks_dir = dir.join(".git/hooks");
+    std::fs::create_dir_all(&hooks_dir)?;
+    let installed = hooks_dir.join("prepare-commit-msg");
+    let foreign = "#!/bin/sh\nexec ./scripts/prepare-message \"$@\"\n";
+    std::fs::write(&installed, foreign)?;
+    std::env::set_current_dir(&dir)?;
+
+    let error = hooks::install(HookKind::PrepareCommitMsg, false).unwrap_err().to_string();
+    assert!(error.contains("--force"), "{}", error);
+    assert!(hooks::uninstall(HookKind::PrepareCommitMsg).is_err());
+    assert_eq!(std::fs::read_to_string(&installed)?, foreign, "a foreign hook is kept");
+
+    hooks::install(HookKind::PrepareCommitMsg, true)?;
+    let script = std::fs::read_to_string(&installed)?;
+    assert!(script.starts_with("#!/bin/sh\n# Installed by commit-buddy\n"), "{}", script);
+    assert!(script.ends_with(" hook run \"$@\"\n"), "{}", script);
+    assert_eq!(std::fs::metadata(&installed)?.permissions().mode() & 0o777, 0o755);
+    hooks::install(HookKind::PrepareCommitMsg, false).expect("reinstalling our own hook needs no --force");
+
+    hooks::uninstall(HookKind::PrepareCommitMsg)?;
+    assert!(!installed.exists());
+    hooks::uninstall(HookKind::PrepareCommitMsg).expect("uninstalling twice is not an error");
+
+    // Each kind has its own file, so the commit-msg hook is independent
+    hooks::install(HookKind::CommitMsg, false)?;
+    assert!(std::fs::read_to_string(hooks_dir.join("commit-msg"))?.contains("lint-commits --message-file \"$1\""));
+    assert!(!installed.exists());
+    hooks::uninstall(HookKind::CommitMsg)?;
+    assert!(!hooks_dir.join("commit-msg").exists());
+
+    std::env::set_current_dir(std::env::temp_dir())?;
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_hook_leaves_merge_amend_and_message_commits_untouched() -> Result<()> {
+    let dir = std::env::temp_dir().join(format!("commit-buddy-hooks-skip-{}", std::process::id()));
+    std::fs::create_dir_all(&dir)?;
+    let message_file = dir.join("COMMIT_EDITMSG");
+    let config = Config::default();
+
+    // git passes "merge" for merges, "commit" for --amend/-c/-C and
+    // "message" for -m/-F
+    for (source, existing) in [
+        ("merge", "Merge branch 'topic'\n"),
+        ("commit", "feat: the commit being amended\n"),
+        ("message", "fix: typed with -m\n"),
+    ] {
+        std::fs::write(&message_file, existing)?;
+        hooks::run(&message_file.to_string_lossy(), Some(source), &config).await?;
+        assert_eq!(std::fs::read_to_string(&message_file)?, existing, "source {}", source);
+    }
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_heuristic_suggestions_follow_the_shape_of_the_change() {
+    assert_eq!(ai::heuristic_commit_suggestions(&staged(vec![change("src/parser.rs", None, ChangeKind::Added)])), vec![
+        "feat(parser): add parser.rs",
+        "feat: add parser.rs",
+        "feat: add new functionality",
+    ]);
+    assert_eq!(ai::heuristic_commit_suggestions(&staged(vec![
+        change("README.md", None, ChangeKind::Modified),
+        change("docs/guide.md", None, ChangeKind::Modified),
+    ])), vec![
+        "docs: update 2 files",
+        "docs: update documentation",
+        "chore: update documentation",
+    ]);
+    assert_eq!(ai::heuristic_commit_suggestions(&staged(vec![change("src/lexer.rs", Some("src/scan.rs"), ChangeKind::Renamed)])), vec![
+        "refactor(lexer): rename scan.rs to lexer.rs",
+        "refactor: rename scan.rs to lexer.rs",
+        "refactor: restructure code",
+    ]);
+    assert_eq!(ai::heuristic_commit_suggestions(&staged(Vec::new())), vec![
+        "chore: update code",
+        "chore: update project files",
+        "chore: misc changes",
+    ]);
+}
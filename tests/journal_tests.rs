@@ -0,0 +1,159 @@
+use commit_buddy::branches;
+use commit_buddy::committer::Committer;
+use commit_buddy::git;
+use commit_buddy::journal;
+use anyhow::Result;
+use git2::{Oid, Repository};
+
+mod common;
+use common::temp_repo;
+
+fn commit(repo: &Repository, path: &str, content: &str) -> Result<Oid> {
+    common::commit(repo, path, content, &format!("update {}", path))
+}
+
+fn staged_files(repo: &Repository) -> Result<Vec<String>> {
+    let head = repo.head()?.peel_to_tree()?;
+    let diff = repo.diff_tree_to_index(Some(&head), None, None)?;
+    Ok(diff.deltas()
+        .filter_map(|delta| delta.new_file().path().map(|p| p.to_string_lossy().to_string()))
+        .collect())
+}
+
+#[test]
+fn test_undo_restores_refs_and_the_index() -> Result<()> {
+    let (dir, repo) = temp_repo("journal-commit")?;
+    let base = commit(&repo, "a.txt", "one\n")?;
+    std::fs::write(dir.join("a.txt"), "two\n")?;
+    std::fs::write(dir.join("b.txt"), "new\n")?;
+
+    git::stage_paths(&repo, &["a.txt", "b.txt"])?;
+    let outcome = Committer::new(&repo).commit("feat: add b")?;
+    branches::create(&repo, "topic")?;
+
+    let entries = journal::entries(&repo)?;
+    let actions: Vec<&str> = entries.iter().map(|entry| entry.action.as_str()).collect();
+    assert_eq!(actions, vec!["stage a.txt b.txt", "commit: feat: add b", "create branch topic"]);
+    assert_eq!(entries[1].id, 2);
+
+    let undone = journal::undo(&repo, 1, false)?;
+    assert_eq!(undone[0].action, "create branch topic");
+    assert!(repo.find_reference("refs/heads/topic").is_err());
+    assert_eq!(repo.head()?.peel_to_commit()?.id(), outcome.id);
+
+    journal::undo(&repo, 1, false)?;
+    assert_eq!(repo.head()?.peel_to_commit()?.id(), base);
+    assert_eq!(staged_files(&repo)?, vec!["a.txt".to_string(), "b.txt".to_string()], "the commit's changes stay staged");
+
+    journal::undo(&repo, 1, false)?;
+    assert!(staged_files(&repo)?.is_empty());
+    assert_eq!(std::fs::read_to_string(dir.join("a.txt"))?, "two\n", "the work tree is left alone");
+    assert!(journal::entries(&repo)?.is_empty());
+    assert!(journal::undo(&repo, 1, false).is_err(), "nothing left to undo");
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_undo_restores_written_files_and_removes_created_ones() -> Result<()> {
+    let (dir, repo) = temp_repo("journal-files")?;
+    commit(&repo, "a.txt", "one\n")?;
+    std::fs::create_dir_all(dir.join("tests"))?;
+    std::fs::write(dir.join("tests/old.rs"), "// old\n")?;
+    std::fs::write(dir.join("CHANGELOG.md"), "# Changelog\n")?;
+
+    journal::action(&repo, "generate tests")
+        .files(&[dir.join("tests"), dir.join("CHANGELOG.md")])
+        .run(|| {
+            std::fs::write(dir.join("tests/old.rs"), "// regenerated\n")?;
+            std::fs::write(dir.join("tests/new.rs"), "// new\n")?;
+            std::fs::write(dir.join("CHANGELOG.md"), "# Changelog\n\n## 1.0.0\n")?;
+            Ok(())
+        })?;
+    journal::action(&repo, "does nothing").run(|| Ok(()))?;
+
+    let entries = journal::entries(&repo)?;
+    assert_eq!(entries.len(), 1, "actions that change nothing are not journaled");
+    assert_eq!(entries[0].files.keys().cloned().collect::<Vec<_>>(), vec!["CHANGELOG.md", "tests/new.rs", "tests/old.rs"]);
+
+    journal::undo(&repo, 1, false)?;
+    assert_eq!(std::fs::read_to_string(dir.join("tests/old.rs"))?, "// old\n");
+    assert!(!dir.join("tests/new.rs").exists());
+    assert_eq!(std::fs::read_to_string(dir.join("CHANGELOG.md"))?, "# Changelog\n");
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_undo_restores_files_that_are_not_utf8() -> Result<()> {
+    let (dir, repo) = temp_repo("journal-binary")?;
+    commit(&repo, "a.txt", "one\n")?;
+    let latin1 = b"caf\xe9\n".to_vec();
+    std::fs::write(dir.join("notes.txt"), &latin1)?;
+
+    journal::action(&repo, "rewrite notes")
+        .files(&[dir.join("notes.txt")])
+        .run(|| {
+            std::fs::write(dir.join("notes.txt"), "café\n")?;
+            Ok(())
+        })?;
+    let entries = journal::entries(&repo)?;
+    let before = entries[0].files["notes.txt"].before.as_deref().expect("the file existed");
+    assert_eq!(repo.find_blob(Oid::from_str(before)?)?.content(), latin1.as_slice());
+
+    journal::undo(&repo, 1, false)?;
+    assert_eq!(std::fs::read(dir.join("notes.txt"))?, latin1, "the file is restored, not removed");
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_undo_refuses_when_things_changed_since_unless_forced() -> Result<()> {
+    let (dir, repo) = temp_repo("journal-changed")?;
+    let base = commit(&repo, "a.txt", "one\n")?;
+    std::fs::write(dir.join("a.txt"), "two\n")?;
+    git::stage_paths(&repo, &["a.txt"])?;
+    Committer::new(&repo).commit("fix: update a")?;
+
+    // A commit made outside commit-buddy moves the branch again
+    commit(&repo, "c.txt", "outside\n")?;
+    let err = journal::undo(&repo, 1, false).unwrap_err().to_string();
+    assert!(err.contains("has changed since 'commit: fix: update a'"), "{}", err);
+    assert_eq!(journal::entries(&repo)?.len(), 2);
+
+    journal::undo(&repo, 1, true)?;
+    assert_eq!(repo.head()?.peel_to_commit()?.id(), base);
+    assert_eq!(journal::entries(&repo)?.len(), 1);
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_journal_keeps_only_the_latest_actions() -> Result<()> {
+    let (dir, repo) = temp_repo("journal-prune")?;
+    commit(&repo, "a.txt", "one\n")?;
+
+    for n in 1..=201 {
+        journal::action(&repo, format!("write {}", n))
+            .files(&[dir.join("notes.txt")])
+            .run(|| {
+                std::fs::write(dir.join("notes.txt"), format!("{}\n", n))?;
+                Ok(())
+            })?;
+    }
+    let entries = journal::entries(&repo)?;
+    assert_eq!(entries.len(), 100);
+    assert_eq!(entries.first().map(|e| e.action.as_str()), Some("write 102"));
+    assert_eq!(entries.last().map(|e| (e.id, e.action.as_str())), Some((201, "write 201")));
+
+    journal::undo(&repo, 1, false)?;
+    assert_eq!(std::fs::read_to_string(dir.join("notes.txt"))?, "200\n");
+    assert_eq!(journal::entries(&repo)?.len(), 99);
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
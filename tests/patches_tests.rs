@@ -0,0 +1,141 @@
+use commit_buddy::git::{self, DiffInfo};
+use commit_buddy::journal;
+use commit_buddy::patches::{self, CoverLetter, SeriesPatch};
+use anyhow::Result;
+
+mod common;
+use common::{temp_repo, commit};
+
+fn patch(subject: &str, path: &str, hunk: &str, lines: &str) -> SeriesPatch {
+    SeriesPatch {
+        subject: subject.to_string(),
+        files: vec![path.to_string()],
+        diff: format!("diff --git a/{path} b/{path}\nindex 1111111..2222222 100644\n--- a/{path}\n+++ b/{path}\n{hunk}\n{lines}"),
+    }
+}
+
+#[test]
+fn test_writes_a_numbered_series_that_reads_back() -> Result<()> {
+    let (dir, repo) = temp_repo("patches-write")?;
+    let base = commit(&repo, "README.md", "# demo\n", "initial")?;
+    commit(&repo, "parser.rs", "fn parse() {}\n", "feat: add parser\n\nThe parser reads tokens.\n")?;
+    commit(&repo, "lexer.rs", "fn lex() {}\n-- \n", "fix(lexer): handle \"quoted\" input")?;
+
+    let (series_base, commits) = patches::resolve_series(&repo, &base.to_string())?;
+    assert_eq!(series_base, Some(base));
+    let formatted = patches::format_patches(&repo, &commits, 2)?;
+    let out = dir.join("out");
+    let written = patches::write_series(&repo, &formatted, "cover\n", &out, 2)?;
+
+    let names: Vec<String> = written.iter().map(|p| p.file_name().unwrap().to_string_lossy().to_string()).collect();
+    assert_eq!(names, vec!["v2-0000-cover-letter.patch", "v2-0001-feat-add-parser.patch", "v2-0002-fix-lexer-handle-quoted-input.patch"]);
+    assert!(formatted[0].1.contains("Subject: [PATCH v2 1/2] feat: add parser"));
+    assert!(formatted[0].1.contains("The parser reads tokens."));
+
+    let found = patches::find_series_files(&out, 2);
+    assert_eq!(found.len(), 2, "the cover letter is not a patch");
+    assert!(patches::find_series_files(&out, 1).is_empty());
+    let read = patches::read_series_files(&found)?;
+    assert_eq!(read[1].subject, "fix(lexer): handle \"quoted\" input");
+    assert_eq!(read[1].files, vec!["lexer.rs".to_string()]);
+    assert!(read[1].diff.ends_with("+-- \n"), "a diff line that looks like a signature is kept: {:?}", read[1].diff);
+    assert!(!read[0].diff.contains("libgit2"));
+    assert_eq!(journal::entries(&repo)?.last().map(|entry| entry.action.clone()), Some("format-patch v2 (2 patches)".to_string()));
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_series_with_an_empty_commit_is_rejected() -> Result<()> {
+    let (dir, repo) = temp_repo("patches-empty")?;
+    let base = commit(&repo, "README.md", "# demo\n", "initial")?;
+    commit(&repo, "parser.rs", "fn parse() {}\n", "feat: add parser")?;
+    let head = repo.head()?.peel_to_commit()?;
+    let signature = repo.signature()?;
+    let empty = repo.commit(Some("HEAD"), &signature, &signature, "chore: trigger CI", &head.tree()?, &[&head])?;
+    commit(&repo, "lexer.rs", "fn lex() {}\n", "feat: add lexer")?;
+
+    let error = patches::resolve_series(&repo, &base.to_string()).unwrap_err().to_string();
+    assert_eq!(error, format!("{} \"chore: trigger CI\" changes no files; drop or squash it before formatting the series", &empty.to_string()[..7]));
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_compare_series_lists_what_changed_since_the_previous_version() {
+    let previous = vec![
+        patch("feat: add parser", "parser.rs", "@@ -1 +1 @@", "-a\n+b\n"),
+        patch("feat: add lexer", "lexer.rs", "@@ -1 +1 @@", "-c\n+d\n"),
+        patch("docs: explain tokens", "README.md", "@@ -1 +1 @@", "-e\n+f\n"),
+        patch("chore: drop script", "run.sh", "@@ -1 +0,0 @@", "-g\n"),
+    ];
+    let current = vec![
+        patch("feat: add a parser", "parser.rs", "@@ -1 +1 @@", "-a\n+b\n"),
+        patch("feat: add lexer", "lexer.rs", "@@ -1 +1 @@", "-c\n+D\n"),
+        patch("docs: explain tokens", "README.md", "@@ -3 +3 @@", "-e\n+f\n"),
+        patch("test: cover the lexer", "tests.rs", "@@ -0,0 +1 @@", "+h\n"),
+    ];
+
+    assert_eq!(patches::compare_series(&previous, &current), vec![
+        "1/4 \"feat: add a parser\": reworded from \"feat: add parser\"".to_string(),
+        "2/4 \"feat: add lexer\": changed lexer.rs".to_string(),
+        "4/4 \"test: cover the lexer\": new patch".to_string(),
+        "dropped \"chore: drop script\"".to_string(),
+    ]);
+    assert!(patches::compare_series(&current, &current).is_empty());
+
+    let both = |first: &str, second: &str| SeriesPatch {
+        subject: "feat: parse and lex".to_string(),
+        files: vec!["lexer.rs".to_string(), "parser.rs".to_string()],
+        diff: format!("{}{}", patch("", "lexer.rs", "@@ -1 +1 @@", first).diff, patch("", "parser.rs", "@@ -1 +1 @@", second).diff),
+    };
+    assert_eq!(
+        patches::compare_series(&[both("-a\n+b\n", "-c\n+d\n")], &[both("-a\n+B\n", "-c\n+D\n")]),
+        vec!["1/1 \"feat: parse and lex\": changed lexer.rs, parser.rs".to_string()]
+    );
+    assert_eq!(
+        patches::compare_series(&[both("-a\n+b\n", "-c\n+d\n")], &[both("-a\n+b\n", "-c\n+D\n")]),
+        vec!["1/1 \"feat: parse and lex\": changed parser.rs".to_string()]
+    );
+}
+
+#[test]
+fn test_cover_letter_has_summaries_shortlog_and_diffstat() -> Result<()> {
+    let (dir, repo) = temp_repo("patches-cover")?;
+    let base = commit(&repo, "README.md", "# demo\n", "initial")?;
+    commit(&repo, "parser.rs", "fn parse() {}\nfn parse_all() {}\n", "feat: add parser")?;
+    commit(&repo, "README.md", "# parser demo\n", "docs: rename the demo")?;
+
+    let (_, commits) = patches::resolve_series(&repo, &format!("{}..HEAD", base))?;
+    let infos = commits.iter()
+        .map(|id| git::commit_info(&repo, &repo.find_commit(*id)?))
+        .collect::<Result<Vec<_>>>()?;
+    let diff_info = DiffInfo::from_commits(infos);
+    assert_eq!(
+        patches::diffstat(&diff_info),
+        " README.md | 2 +-\n parser.rs | 2 ++\n 2 files changed, 3 insertions(+), 1 deletion(-)\n"
+    );
+
+    let letter = CoverLetter {
+        subject: "Add a parser".to_string(),
+        overview: "Parse the demo input.".to_string(),
+        summaries: vec!["Adds parse and parse_all.".to_string(), String::new()],
+    };
+    let series: Vec<SeriesPatch> = patches::format_patches(&repo, &commits, 2)?.iter()
+        .filter_map(|(_, text)| patches::parse_patch_file(text))
+        .collect();
+    let changes = vec!["2/2 \"docs: rename the demo\": new patch".to_string()];
+    let text = patches::render_cover_letter(&repo, &letter, &series, &diff_info, Some(&changes), 2)?;
+
+    assert!(text.contains("Subject: [PATCH v2 0/2] Add a parser\n\nParse the demo input.\n"), "{}", text);
+    assert!(text.contains("Changes since v1:\n- 2/2 \"docs: rename the demo\": new patch\n"));
+    assert!(text.contains("[1/2] feat: add parser\n      Adds parse and parse_all.\n[2/2] docs: rename the demo\n"));
+    assert!(text.contains("Test User (2):\n  feat: add parser\n  docs: rename the demo\n"));
+    assert!(text.contains(" 2 files changed, 3 insertions(+), 1 deletion(-)"));
+    assert_eq!(patches::parse_patch_file(&text), None, "a cover letter is not a patch");
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
@@ -0,0 +1,107 @@
+use commit_buddy::committer::Committer;
+use commit_buddy::conventional::ConventionalCommit;
+use commit_buddy::release::{self, Bump, ReleaseOptions, Version};
+use anyhow::Result;
+
+mod common;
+use common::{temp_repo, commit};
+
+fn version(text: &str) -> Version {
+    Version::parse(text).unwrap()
+}
+
+#[test]
+fn test_version_parsing_and_order() {
+    assert_eq!(version("v1.2.3"), Version::new(1, 2, 3));
+    assert_eq!(version("1.2.3-rc.1+build.7").pre.as_deref(), Some("rc.1"));
+    assert_eq!(version("1.2.3-rc.1").to_string(), "1.2.3-rc.1");
+    assert!(Version::parse("1.2").is_none());
+    assert!(Version::parse("release-1").is_none());
+
+    let mut versions: Vec<Version> = ["1.0.0", "1.0.0-rc.10", "1.0.0-alpha", "1.0.0-rc.2", "0.9.9"]
+        .iter()
+        .map(|v| version(v))
+        .collect();
+    versions.sort();
+    let sorted: Vec<String> = versions.iter().map(Version::to_string).collect();
+    assert_eq!(sorted, vec!["0.9.9", "1.0.0-alpha", "1.0.0-rc.2", "1.0.0-rc.10", "1.0.0"]);
+}
+
+#[test]
+fn test_bump_and_next_version() {
+    let parse = |messages: &[&str]| -> Vec<ConventionalCommit> {
+        messages.iter().map(|m| ConventionalCommit::parse(m).unwrap()).collect()
+    };
+    let stable = version("1.4.2");
+    assert_eq!(release::bump_for(&stable, &parse(&["docs: readme"])), None);
+    assert_eq!(release::bump_for(&stable, &parse(&["fix: a", "docs: b"])), Some(Bump::Patch));
+    assert_eq!(release::bump_for(&stable, &parse(&["fix: a", "feat: b"])), Some(Bump::Minor));
+    assert_eq!(release::bump_for(&stable, &parse(&["feat!: b"])), Some(Bump::Major));
+    assert_eq!(release::bump_for(&version("0.4.0"), &parse(&["feat!: b"])), Some(Bump::Minor));
+
+    let next = |current: &str, bump, channel| release::next_version(&version(current), &stable, bump, channel).to_string();
+    assert_eq!(next("1.4.2", Bump::Minor, None), "1.5.0");
+    assert_eq!(next("1.4.2", Bump::Minor, Some("rc")), "1.5.0-rc.1");
+    assert_eq!(next("1.5.0-rc.1", Bump::Minor, Some("rc")), "1.5.0-rc.2");
+    assert_eq!(next("1.5.0-rc.1", Bump::Minor, Some("beta")), "1.5.0-beta.1");
+    assert_eq!(next("1.5.0-rc.1", Bump::Patch, None), "1.5.0");
+    // A breaking change after a minor release candidate starts a new one
+    assert_eq!(next("1.5.0-rc.2", Bump::Major, Some("rc")), "2.0.0-rc.1");
+}
+
+#[test]
+fn test_manifest_versions() -> Result<()> {
+    let next = version("2.0.0");
+    let cargo = "[package]\nname = \"demo\"\nversion = \"1.0.0\" # managed by release\n\n[dependencies]\nserde = { version = \"1\" }\n";
+    assert_eq!(
+        release::set_manifest_version("Cargo.toml", cargo, &next)?.unwrap(),
+        "[package]\nname = \"demo\"\nversion = \"2.0.0\" # managed by release\n\n[dependencies]\nserde = { version = \"1\" }\n"
+    );
+
+    let workspace = "[workspace]\nmembers = []\n\n[workspace.package]\nversion = \"1.0.0\"\n";
+    assert!(release::set_manifest_version("Cargo.toml", workspace, &next)?.unwrap().contains("version = \"2.0.0\""));
+
+    let pyproject = "[build-system]\nrequires = [\"hatchling\"]\n\n[project]\nname = \"demo\"\nversion = \"1.0.0\"\n";
+    assert!(release::set_manifest_version("pyproject.toml", pyproject, &next)?.unwrap().ends_with("version = \"2.0.0\"\n"));
+
+    let package = "{\n  \"name\": \"demo\",\n  \"version\": \"1.0.0\",\n  \"devDependencies\": {}\n}\n";
+    assert_eq!(
+        release::set_manifest_version("package.json", package, &next)?.unwrap(),
+        "{\n  \"name\": \"demo\",\n  \"version\": \"2.0.0\",\n  \"devDependencies\": {}\n}\n"
+    );
+
+    assert!(release::set_manifest_version("Cargo.toml", "[package]\nversion.workspace = true\n", &next)?.is_none());
+    Ok(())
+}
+
+#[test]
+fn test_release_commits_and_tags() -> Result<()> {
+    let (dir, repo) = temp_repo("release")?;
+    commit(&repo, "Cargo.toml", "[package]\nname = \"demo\"\nversion = \"1.0.0\"\n", "chore: initial")?;
+    let head = repo.head()?.peel_to_commit()?;
+    repo.tag("v1.0.0", head.as_object(), &repo.signature()?, "Release 1.0.0", false)?;
+    commit(&repo, "a.txt", "a", "feat(api): add pagination")?;
+    commit(&repo, "b.txt", "b", "docs: explain pagination")?;
+
+    let plan = release::plan(&repo, &ReleaseOptions::default())?;
+    assert_eq!(plan.previous_tag.as_deref(), Some("v1.0.0"));
+    assert_eq!(plan.next.to_string(), "1.1.0");
+    assert_eq!(plan.tag, "v1.1.0");
+    assert_eq!(plan.version_files, vec!["Cargo.toml"]);
+    assert!(plan.notes.starts_with("## [1.1.0] - "));
+    assert!(plan.notes.contains("### Added\n\n- **api:** add pagination"));
+    assert!(!plan.notes.contains("explain pagination"));
+
+    let id = release::execute(&repo, &Committer::new(&repo), &plan)?;
+    let release_commit = repo.find_commit(id)?;
+    assert_eq!(release_commit.summary(), Some("chore(release): 1.1.0"));
+    assert_eq!(repo.revparse_single("v1.1.0")?.peel_to_commit()?.id(), id);
+    assert!(std::fs::read_to_string(dir.join("Cargo.toml"))?.contains("version = \"1.1.0\""));
+    assert!(std::fs::read_to_string(dir.join("CHANGELOG.md"))?.starts_with("# Changelog\n"));
+
+    // Nothing left to release
+    assert!(release::plan(&repo, &ReleaseOptions::default()).is_err());
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
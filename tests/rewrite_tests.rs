@@ -0,0 +1,81 @@
+use commit_buddy::rewrite;
+use anyhow::Result;
+use git2::{Oid, Repository, Signature};
+use std::collections::HashMap;
+
+mod common;
+use common::temp_repo;
+
+fn commit_file(repo: &Repository, path: &str, content: &str, message: &str) -> Result<Oid> {
+    let signature = Signature::now("Original Author", "author@example.com")?;
+    common::commit_as(repo, &signature, path, content, message)
+}
+
+#[test]
+fn test_reword_keeps_trees_and_authors() -> Result<()> {
+    let (dir, repo) = temp_repo("reword")?;
+    let base = commit_file(&repo, "a.txt", "one", "initial")?;
+    let first = commit_file(&repo, "a.txt", "two", "wip")?;
+    let second = commit_file(&repo, "b.txt", "three", "stuff")?;
+
+    let range = rewrite::resolve_head_range(&repo, &format!("{}..HEAD", base))?;
+    assert_eq!(range.commits, vec![first, second]);
+
+    let messages = HashMap::from([(first, "fix: correct the value\n".to_string())]);
+    let outcome = rewrite::reword(&repo, &range, &messages)?;
+
+    let new_head = repo.head()?.peel_to_commit()?;
+    assert_eq!(new_head.id(), outcome.new_head);
+    assert_eq!(new_head.tree_id(), repo.find_commit(second)?.tree_id());
+    assert_eq!(new_head.message(), Some("stuff"));
+    assert_eq!(new_head.author().name(), Some("Original Author"));
+
+    let new_first = new_head.parent(0)?;
+    assert_eq!(new_first.message(), Some("fix: correct the value\n"));
+    assert_eq!(new_first.tree_id(), repo.find_commit(first)?.tree_id());
+    assert_eq!(new_first.parent_id(0)?, base);
+
+    let backup = repo.find_reference(&outcome.backup_ref)?;
+    assert_eq!(backup.target(), Some(second));
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_range_must_end_at_head() -> Result<()> {
+    let (dir, repo) = temp_repo("range-head")?;
+    let base = commit_file(&repo, "a.txt", "one", "initial")?;
+    let middle = commit_file(&repo, "a.txt", "two", "second")?;
+    commit_file(&repo, "a.txt", "three", "third")?;
+
+    assert!(rewrite::resolve_head_range(&repo, &format!("{}..{}", base, middle)).is_err());
+
+    let range = rewrite::resolve_head_range(&repo, &base.to_string())?;
+    assert_eq!(range.commits.len(), 2);
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_backups_in_the_same_second_do_not_replace_each_other() -> Result<()> {
+    let (dir, repo) = temp_repo("backup-unique")?;
+    let first = commit_file(&repo, "a.txt", "one", "initial")?;
+    let second = commit_file(&repo, "a.txt", "two", "second")?;
+
+    let backups = [
+        rewrite::create_backup_ref(&repo, first)?,
+        rewrite::create_backup_ref(&repo, second)?,
+        rewrite::create_backup_ref(&repo, first)?,
+    ];
+    assert_ne!(backups[0], backups[1]);
+    assert_ne!(backups[1], backups[2]);
+    assert_ne!(backups[0], backups[2]);
+    assert_eq!(repo.refname_to_id(&backups[0])?, first);
+    assert_eq!(repo.refname_to_id(&backups[1])?, second);
+    assert_eq!(repo.refname_to_id(&backups[2])?, first);
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
@@ -0,0 +1,79 @@
+use commit_buddy::committer::Committer;
+use commit_buddy::split::{self, SplitGroup};
+use anyhow::Result;
+use git2::{Repository, Signature};
+use std::path::Path;
+
+mod common;
+use common::temp_repo;
+
+fn stage(repo: &Repository, path: &str, content: &str) -> Result<()> {
+    std::fs::write(repo.workdir().unwrap().join(path), content)?;
+    let mut index = repo.index()?;
+    index.add_path(Path::new(path))?;
+    index.write()?;
+    Ok(())
+}
+
+fn numbered_lines(changed: &[(usize, &str)]) -> String {
+    (1..=40)
+        .map(|n| changed.iter()
+            .find(|(line, _)| *line == n)
+            .map(|(_, text)| text.to_string())
+            .unwrap_or_else(|| n.to_string()))
+        .map(|line| line + "\n")
+        .collect()
+}
+
+#[test]
+fn test_split_hunks_of_one_file_into_separate_commits() -> Result<()> {
+    let (dir, repo) = temp_repo("split")?;
+    stage(&repo, "a.txt", &numbered_lines(&[]))?;
+    let tree = repo.find_tree(repo.index()?.write_tree()?)?;
+    let signature = Signature::now("Test User", "test@example.com")?;
+    let base = repo.commit(Some("HEAD"), &signature, &signature, "initial", &tree, &[])?;
+
+    stage(&repo, "a.txt", &numbered_lines(&[(2, "two"), (35, "thirty-five")]))?;
+    stage(&repo, "b.txt", "new file\n")?;
+    let staged_tree = repo.index()?.write_tree()?;
+
+    let units = split::staged_units(&repo)?;
+    assert_eq!(units.len(), 3);
+
+    // Commit the second hunk first to make sure positions are recomputed
+    let groups = vec![
+        SplitGroup { message: "fix: late line".to_string(), units: vec![1] },
+        SplitGroup { message: "feat: add b and early line".to_string(), units: vec![0, 2] },
+    ];
+    let created = split::execute(&repo, &Committer::new(&repo).no_verify(true), &units, &groups)?;
+    assert_eq!(created.len(), 2);
+
+    let head = repo.head()?.peel_to_commit()?;
+    assert_eq!(head.id(), created[1]);
+    assert_eq!(head.tree_id(), staged_tree);
+    assert_eq!(repo.index()?.write_tree()?, staged_tree);
+
+    let first = repo.find_commit(created[0])?;
+    assert_eq!(first.parent_id(0)?, base);
+    let blob = first.tree()?.get_path(Path::new("a.txt"))?.to_object(&repo)?.peel_to_blob()?;
+    assert_eq!(String::from_utf8_lossy(blob.content()), numbered_lines(&[(35, "thirty-five")]));
+    assert!(first.tree()?.get_path(Path::new("b.txt")).is_err());
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_normalize_plan_assigns_every_unit_once() {
+    let groups = vec![
+        SplitGroup { message: "feat: one".to_string(), units: vec![0, 0, 7] },
+        SplitGroup { message: "fix: two".to_string(), units: vec![0, 2] },
+    ];
+    let plan = split::normalize_plan(groups, 4).unwrap();
+
+    assert_eq!(plan.len(), 3);
+    assert_eq!(plan[0].units, vec![0]);
+    assert_eq!(plan[1].units, vec![2]);
+    assert_eq!(plan[2].units, vec![1, 3]);
+    assert!(split::normalize_plan(Vec::new(), 2).is_none());
+}
@@ -0,0 +1,162 @@
+use commit_buddy::committer::Committer;
+use commit_buddy::git::{self, ChangeKind};
+use commit_buddy::staging::{self, DiffSide};
+use anyhow::Result;
+use git2::{Repository, Signature};
+use std::path::Path;
+
+mod common;
+use common::temp_repo;
+
+fn commit_lines(repo: &Repository, path: &str, content: &str) -> Result<()> {
+    std::fs::write(repo.workdir().unwrap().join(path), content)?;
+    let mut index = repo.index()?;
+    index.add_path(Path::new(path))?;
+    index.write()?;
+    let tree = repo.find_tree(index.write_tree()?)?;
+    let signature = Signature::now("Test User", "test@example.com")?;
+    repo.commit(Some("HEAD"), &signature, &signature, "initial", &tree, &[])?;
+    Ok(())
+}
+
+fn indexed_content(repo: &Repository, path: &str) -> Result<String> {
+    let index = repo.index()?;
+    let entry = index.get_path(Path::new(path), 0).expect("file is in the index");
+    Ok(String::from_utf8(repo.find_blob(entry.id)?.content().to_vec())?)
+}
+
+fn numbered(lines: &[&str]) -> String {
+    lines.iter().map(|l| format!("{}\n", l)).collect()
+}
+
+#[test]
+fn test_stage_and_unstage_single_hunk() -> Result<()> {
+    let (dir, repo) = temp_repo("stage-hunk")?;
+    let original: Vec<String> = (1..=20).map(|n| n.to_string()).collect();
+    let original: Vec<&str> = original.iter().map(String::as_str).collect();
+    commit_lines(&repo, "a.txt", &numbered(&original))?;
+
+    let mut changed = original.clone();
+    changed[1] = "two";
+    changed[17] = "eighteen";
+    std::fs::write(dir.join("a.txt"), numbered(&changed))?;
+
+    let hunks = staging::file_hunks(&repo, "a.txt", DiffSide::Unstaged)?;
+    assert_eq!(hunks.len(), 2);
+
+    staging::apply_hunk(&repo, "a.txt", DiffSide::Unstaged, &hunks[1], None)?;
+    let mut expected = original.clone();
+    expected[17] = "eighteen";
+    assert_eq!(indexed_content(&repo, "a.txt")?, numbered(&expected));
+    assert_eq!(staging::file_hunks(&repo, "a.txt", DiffSide::Unstaged)?.len(), 1);
+
+    let staged = staging::file_hunks(&repo, "a.txt", DiffSide::Staged)?;
+    assert_eq!(staged.len(), 1);
+    staging::apply_hunk(&repo, "a.txt", DiffSide::Staged, &staged[0], None)?;
+    assert_eq!(indexed_content(&repo, "a.txt")?, numbered(&original));
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_stage_selected_lines_only() -> Result<()> {
+    let (dir, repo) = temp_repo("stage-lines")?;
+    commit_lines(&repo, "a.txt", &numbered(&["a", "b", "c"]))?;
+    std::fs::write(dir.join("a.txt"), numbered(&["a", "B", "new", "c"]))?;
+
+    let hunks = staging::file_hunks(&repo, "a.txt", DiffSide::Unstaged)?;
+    assert_eq!(hunks.len(), 1);
+    let added = hunks[0].lines.iter()
+        .position(|l| l.origin == '+' && l.text() == "new")
+        .unwrap();
+
+    // Only the addition, not the b → B replacement
+    staging::apply_hunk(&repo, "a.txt", DiffSide::Unstaged, &hunks[0], Some(added..=added))?;
+    assert_eq!(indexed_content(&repo, "a.txt")?, numbered(&["a", "b", "new", "c"]));
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_stage_untracked_file_and_unstage_it_again() -> Result<()> {
+    let (dir, repo) = temp_repo("stage-new")?;
+    commit_lines(&repo, "a.txt", "a\n")?;
+    std::fs::write(dir.join("new.txt"), "one\ntwo\n")?;
+
+    let hunks = staging::file_hunks(&repo, "new.txt", DiffSide::Unstaged)?;
+    staging::apply_hunk(&repo, "new.txt", DiffSide::Unstaged, &hunks[0], Some(0..=0))?;
+    assert_eq!(indexed_content(&repo, "new.txt")?, "one\n");
+
+    let staged = staging::file_hunks(&repo, "new.txt", DiffSide::Staged)?;
+    staging::apply_hunk(&repo, "new.txt", DiffSide::Staged, &staged[0], None)?;
+    assert!(repo.index()?.get_path(Path::new("new.txt"), 0).is_none());
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_unstaging_a_same_length_hunk_leaves_the_file_modified() -> Result<()> {
+    let (dir, repo) = temp_repo("stage-same-length")?;
+    commit_lines(&repo, "a.txt", "a\n")?;
+
+    // Stage the whole file with stat data old enough that git trusts it
+    // instead of re-reading the file
+    std::fs::write(dir.join("a.txt"), "b\n")?;
+    let an_hour_ago = std::time::SystemTime::now() - std::time::Duration::from_secs(3600);
+    std::fs::File::options().write(true).open(dir.join("a.txt"))?.set_modified(an_hour_ago)?;
+    let mut index = repo.index()?;
+    index.add_path(Path::new("a.txt"))?;
+    index.write()?;
+
+    let staged = staging::file_hunks(&repo, "a.txt", DiffSide::Staged)?;
+    staging::apply_hunk(&repo, "a.txt", DiffSide::Staged, &staged[0], None)?;
+    assert_eq!(indexed_content(&repo, "a.txt")?, "a\n");
+
+    let output = std::process::Command::new("git")
+        .args(["status", "--porcelain"])
+        .current_dir(&dir)
+        .output()?;
+    assert_eq!(String::from_utf8(output.stdout)?, " M a.txt\n");
+    assert_eq!(staging::file_hunks(&repo, "a.txt", DiffSide::Unstaged)?.len(), 1);
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+// The only test in this file that changes the working directory, since it
+// is shared by every test in the process
+#[test]
+fn test_stage_and_unstage_on_an_unborn_branch() -> Result<()> {
+    let (dir, repo) = temp_repo("stage-unborn")?;
+    std::fs::write(dir.join("a.txt"), "a\n")?;
+    std::fs::write(dir.join("b.txt"), "b\n")?;
+    assert!(git::head_commit(&repo)?.is_none());
+
+    let mut index = repo.index()?;
+    index.add_path(Path::new("a.txt"))?;
+    index.add_path(Path::new("b.txt"))?;
+    index.write()?;
+    std::env::set_current_dir(&dir)?;
+    let staged = git::get_staged_changes()?;
+    assert_eq!(staged.commits[0].files_changed, vec!["a.txt", "b.txt"]);
+    assert!(staged.commits[0].file_changes.iter().all(|change| change.kind == ChangeKind::Added));
+
+    git::unstage_paths(&["a.txt"])?;
+    let staged = git::get_staged_changes()?;
+    assert_eq!(staged.commits[0].files_changed, vec!["b.txt"]);
+    index.read(true)?;
+    assert!(index.get_path(Path::new("a.txt"), 0).is_none());
+    assert!(dir.join("a.txt").exists(), "unstaging keeps the file");
+
+    let root = Committer::new(&repo).commit("feat: add b")?.id;
+    assert_eq!(repo.find_commit(root)?.parent_count(), 0, "the first commit starts the branch");
+    assert_eq!(git::head_commit(&repo)?.map(|c| c.id()), Some(root));
+    assert!(git::get_staged_changes()?.commits.is_empty());
+
+    std::env::set_current_dir(std::env::temp_dir())?;
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
@@ -0,0 +1,157 @@
+use commit_buddy::journal;
+use commit_buddy::stash;
+use anyhow::Result;
+use git2::{Oid, Repository};
+
+mod common;
+use common::temp_repo;
+
+fn commit(repo: &Repository, path: &str, content: &str) -> Result<Oid> {
+    common::commit(repo, path, content, &format!("update {}", path))
+}
+
+#[test]
+fn test_create_list_show_and_pop() -> Result<()> {
+    let (dir, mut repo) = temp_repo("stash-create")?;
+    commit(&repo, "a.txt", "one\n")?;
+    assert!(stash::create(&mut repo, "nothing", false).is_err(), "no local changes");
+
+    std::fs::write(dir.join("a.txt"), "two\n")?;
+    std::fs::write(dir.join("new.txt"), "untracked\n")?;
+    stash::create(&mut repo, "first", false)?;
+    assert!(dir.join("new.txt").exists(), "untracked files stay without the flag");
+    std::fs::write(dir.join("a.txt"), "three\n")?;
+    stash::create(&mut repo, "second", true)?;
+    assert!(!dir.join("new.txt").exists());
+
+    let entries = stash::list(&mut repo)?;
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].name(), "stash@{0}");
+    assert!(entries[0].message.ends_with("second"), "{}", entries[0].message);
+    assert!(entries[1].message.ends_with("first"));
+
+    let changes = stash::changes(&repo, &entries[1])?;
+    assert_eq!(changes.commits[0].files_changed, vec!["a.txt".to_string()]);
+    assert!(changes.commits[0].diff.contains("+two"));
+
+    stash::apply(&mut repo, 0, true)?;
+    assert_eq!(std::fs::read_to_string(dir.join("a.txt"))?, "three\n");
+    assert!(dir.join("new.txt").exists());
+    assert_eq!(stash::list(&mut repo)?.len(), 1);
+
+    stash::drop(&mut repo, 0)?;
+    assert!(stash::list(&mut repo)?.is_empty());
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_apply_over_local_changes_names_the_files_and_keeps_the_stash() -> Result<()> {
+    let (dir, mut repo) = temp_repo("stash-dirty")?;
+    commit(&repo, "a.txt", "one\n")?;
+    std::fs::write(dir.join("a.txt"), "stashed\n")?;
+    stash::create(&mut repo, "work", false)?;
+
+    std::fs::write(dir.join("a.txt"), "local\n")?;
+    let err = stash::apply(&mut repo, 0, true).unwrap_err().to_string();
+    assert!(err.contains("overwrite uncommitted changes to a.txt"), "{}", err);
+    assert_eq!(std::fs::read_to_string(dir.join("a.txt"))?, "local\n");
+    assert_eq!(stash::list(&mut repo)?.len(), 1);
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_pop_with_conflicts_keeps_the_stash() -> Result<()> {
+    let (dir, mut repo) = temp_repo("stash-conflict")?;
+    commit(&repo, "a.txt", "1\n2\n3\n")?;
+    std::fs::write(dir.join("a.txt"), "1\nstashed\n3\n")?;
+    stash::create(&mut repo, "work", false)?;
+    commit(&repo, "a.txt", "1\ncommitted\n3\n")?;
+
+    let err = stash::apply(&mut repo, 0, true).unwrap_err().to_string();
+    assert!(err.contains("applied with conflicts in a.txt"), "{}", err);
+    assert!(std::fs::read_to_string(dir.join("a.txt"))?.contains("<<<<<<<"));
+    assert_eq!(stash::list(&mut repo)?.len(), 1);
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_undo_brings_back_dropped_and_popped_entries() -> Result<()> {
+    let (dir, mut repo) = temp_repo("stash-undo-drop")?;
+    commit(&repo, "a.txt", "one\n")?;
+    std::fs::write(dir.join("a.txt"), "first\n")?;
+    let first = stash::create(&mut repo, "first", false)?;
+    std::fs::write(dir.join("a.txt"), "second\n")?;
+    let second = stash::create(&mut repo, "second", false)?;
+    let listed = |repo: &mut Repository| -> Result<Vec<(Oid, String)>> {
+        Ok(stash::list(repo)?.into_iter().map(|entry| (entry.id, entry.message)).collect())
+    };
+    let both = listed(&mut repo)?;
+    assert_eq!(both.iter().map(|(id, _)| *id).collect::<Vec<_>>(), vec![second, first]);
+
+    stash::drop(&mut repo, 1)?;
+    assert_eq!(journal::entries(&repo)?.last().unwrap().summary(), "drop stash@{1} (stash)");
+    journal::undo(&repo, 1, false)?;
+    assert_eq!(listed(&mut repo)?, both);
+
+    stash::apply(&mut repo, 0, true)?;
+    assert_eq!(std::fs::read_to_string(dir.join("a.txt"))?, "second\n");
+    journal::undo(&repo, 1, false)?;
+    assert_eq!(listed(&mut repo)?, both, "the popped entry is back");
+    assert_eq!(std::fs::read_to_string(dir.join("a.txt"))?, "one\n", "the applied changes are taken back");
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_undoing_a_stash_puts_the_changes_back() -> Result<()> {
+    let (dir, mut repo) = temp_repo("stash-undo-create")?;
+    commit(&repo, "a.txt", "one\n")?;
+    std::fs::write(dir.join("a.txt"), "staged\n")?;
+    let mut index = repo.index()?;
+    index.add_path(std::path::Path::new("a.txt"))?;
+    index.write()?;
+    std::fs::write(dir.join("a.txt"), "unstaged\n")?;
+
+    stash::create(&mut repo, "work", false)?;
+    assert_eq!(std::fs::read_to_string(dir.join("a.txt"))?, "one\n");
+
+    journal::undo(&repo, 1, false)?;
+    assert!(stash::list(&mut repo)?.is_empty());
+    assert!(repo.find_reference("refs/stash").is_err());
+    assert_eq!(std::fs::read_to_string(dir.join("a.txt"))?, "unstaged\n");
+    let mut index = repo.index()?;
+    index.read(true)?;
+    let staged = index.get_path(std::path::Path::new("a.txt"), 0).unwrap();
+    assert_eq!(repo.find_blob(staged.id)?.content(), b"staged\n", "the index comes back too");
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_undoing_an_apply_removes_the_untracked_files_it_wrote() -> Result<()> {
+    let (dir, mut repo) = temp_repo("stash-undo-apply")?;
+    commit(&repo, "a.txt", "one\n")?;
+    std::fs::write(dir.join("a.txt"), "two\n")?;
+    std::fs::create_dir_all(dir.join("notes"))?;
+    std::fs::write(dir.join("notes/todo.txt"), "later\n")?;
+    stash::create(&mut repo, "with notes", true)?;
+    assert!(!dir.join("notes/todo.txt").exists());
+
+    stash::apply(&mut repo, 0, false)?;
+    assert_eq!(std::fs::read_to_string(dir.join("notes/todo.txt"))?, "later\n");
+    journal::undo(&repo, 1, false)?;
+    assert!(!dir.join("notes/todo.txt").exists());
+    assert_eq!(std::fs::read_to_string(dir.join("a.txt"))?, "one\n");
+    assert_eq!(stash::list(&mut repo)?.len(), 1);
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
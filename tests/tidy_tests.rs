@@ -0,0 +1,85 @@
+use commit_buddy::rewrite;
+use commit_buddy::tidy;
+use anyhow::Result;
+use git2::Oid;
+use std::path::Path;
+
+mod common;
+use common::{temp_repo, commit};
+
+#[test]
+fn test_trivial_and_autosquash_subjects() {
+    for subject in ["wip", "WIP: parser", "fix typo", "docs: fix typos.", "Address review comments", "oops", "cargo fmt"] {
+        assert!(tidy::is_trivial(subject), "{}", subject);
+    }
+    for subject in ["feat: add parser", "typography settings", "format dates in the log", "wipe cache on logout"] {
+        assert!(!tidy::is_trivial(subject), "{}", subject);
+    }
+
+    assert_eq!(tidy::autosquash_target("fixup! feat: add parser"), Some("feat: add parser"));
+    assert_eq!(tidy::autosquash_target("squash! fixup! add parser"), Some("add parser"));
+    assert_eq!(tidy::autosquash_target("amend!"), None);
+    assert_eq!(tidy::autosquash_target("feat: add parser"), None);
+}
+
+#[test]
+fn test_plan_folds_fixups_into_commits_touching_the_same_files() -> Result<()> {
+    let (dir, repo) = temp_repo("tidy-plan")?;
+    let base = commit(&repo, "README.md", "# demo\n", "initial")?;
+    let wip = commit(&repo, "notes.txt", "draft\n", "wip")?;
+    let parser = commit(&repo, "parser.rs", "fn parse() {}\n", "feat: add parser")?;
+    let lexer = commit(&repo, "lexer.rs", "fn lex() {}\n", "feat: add lexer")?;
+    let typo = commit(&repo, "parser.rs", "fn parse() { }\n", "fix typo")?;
+    let fixup = commit(&repo, "lexer.rs", "fn lex() { }\n", "fixup! feat: add lexer")?;
+    let unrelated = commit(&repo, "other.rs", "\n", "address review")?;
+
+    let range = rewrite::resolve_head_range(&repo, &base.to_string())?;
+    let groups = tidy::plan(&repo, &range.commits)?;
+    let shape: Vec<(Oid, Vec<Oid>)> = groups.iter().map(|g| (g.target, g.folded.clone())).collect();
+    assert_eq!(shape, vec![
+        (wip, vec![]),
+        (parser, vec![typo]),
+        (lexer, vec![fixup]),
+        (unrelated, vec![]),
+    ]);
+    assert_eq!(groups[1].message, "feat: add parser");
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
+
+#[test]
+fn test_apply_squashes_history_and_keeps_a_backup() -> Result<()> {
+    let (dir, repo) = temp_repo("tidy-apply")?;
+    let base = commit(&repo, "README.md", "# demo\n", "initial")?;
+    let kept = commit(&repo, "notes.txt", "notes\n", "docs: add notes")?;
+    commit(&repo, "parser.rs", "fn parse() {}\n", "feat: add parser\n\nRefs: #12\n")?;
+    commit(&repo, "lexer.rs", "fn lex() {}\n", "feat: add lexer")?;
+    commit(&repo, "parser.rs", "fn parse() { }\n", "fix typo\n\nSigned-off-by: Test User <test@example.com>\n")?;
+    let old_head = repo.head()?.peel_to_commit()?;
+
+    let range = rewrite::resolve_head_range(&repo, &base.to_string())?;
+    let mut groups = tidy::plan(&repo, &range.commits)?;
+    groups[1].message = "feat: add a parser\n".to_string();
+    let outcome = tidy::apply(&repo, &range, &groups)?;
+
+    let head = repo.head()?.peel_to_commit()?;
+    assert_eq!(outcome.new_head, head.id());
+    assert_eq!(head.tree_id(), old_head.tree_id());
+    assert_eq!(repo.refname_to_id(&outcome.backup_ref)?, old_head.id());
+    assert_eq!(outcome.mapping.len(), 4);
+    assert_eq!(outcome.mapping[0], (kept, kept), "commits before the first change are reused");
+
+    let summaries: Vec<String> = rewrite::commits_between(&repo, Some(base), head.id())?.iter()
+        .map(|id| repo.find_commit(*id).map(|c| c.message().unwrap_or("").to_string()))
+        .collect::<Result<_, _>>()?;
+    assert_eq!(summaries.len(), 3);
+    assert_eq!(summaries[0], "docs: add notes");
+    assert_eq!(summaries[1], "feat: add a parser\n\nRefs: #12\nSigned-off-by: Test User <test@example.com>\n");
+    assert_eq!(summaries[2], "feat: add lexer");
+    let parser = repo.find_commit(rewrite::commits_between(&repo, Some(base), head.id())?[1])?;
+    assert_eq!(parser.tree()?.get_path(Path::new("parser.rs"))?.id(), old_head.tree()?.get_path(Path::new("parser.rs"))?.id());
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
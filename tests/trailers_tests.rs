@@ -0,0 +1,102 @@
+use commit_buddy::conventional::ConventionalCommit;
+use commit_buddy::trailers::{self, Trailer};
+use anyhow::Result;
+use git2::{Repository, Signature};
+use std::path::Path;
+
+mod common;
+use common::temp_repo;
+
+fn commit_as(repo: &Repository, name: &str, email: &str, path: &str) -> Result<()> {
+    std::fs::write(repo.workdir().unwrap().join(path), name)?;
+    let mut index = repo.index()?;
+    index.add_path(Path::new(path))?;
+    index.write()?;
+    let tree = repo.find_tree(index.write_tree()?)?;
+    let signature = Signature::now(name, email)?;
+    let parents: Vec<git2::Commit> = repo.head().ok()
+        .and_then(|h| h.peel_to_commit().ok())
+        .into_iter()
+        .collect();
+    let parent_refs: Vec<&git2::Commit> = parents.iter().collect();
+    repo.commit(Some("HEAD"), &signature, &signature, "change", &tree, &parent_refs)?;
+    Ok(())
+}
+
+#[test]
+fn test_parse_trailer_block() {
+    let message = "feat: add login\n\nLonger body.\n\nRefs: PROJ-1\nSigned-off-by: A <a@example.com>\n";
+    let (body, parsed) = trailers::parse(message);
+    assert_eq!(body, "feat: add login\n\nLonger body.");
+    assert_eq!(parsed, vec![
+        Trailer::new("Refs", "PROJ-1"),
+        Trailer::new("Signed-off-by", "A <a@example.com>"),
+    ]);
+
+    // A body paragraph that merely contains a colon is not a trailer block
+    let (_, parsed) = trailers::parse("fix: thing\n\nNote: this explains\nmore prose here\n");
+    assert!(parsed.is_empty());
+    let (_, parsed) = trailers::parse("Refs: PROJ-1\n");
+    assert!(parsed.is_empty());
+}
+
+#[test]
+fn test_merge_and_preserve_skip_duplicates() {
+    let merged = trailers::merge("fix: bug", &[Trailer::new("Refs", "PROJ-2")]);
+    assert_eq!(merged, "fix: bug\n\nRefs: PROJ-2\n");
+
+    let merged = trailers::merge(&merged, &[
+        Trailer::new("refs", "PROJ-2"),
+        Trailer::new("Signed-off-by", "A <a@example.com>"),
+    ]);
+    assert_eq!(merged, "fix: bug\n\nRefs: PROJ-2\nSigned-off-by: A <a@example.com>\n");
+
+    let reworded = trailers::preserve(&merged, "fix(auth): handle expired tokens\n");
+    assert_eq!(reworded, "fix(auth): handle expired tokens\n\nRefs: PROJ-2\nSigned-off-by: A <a@example.com>\n");
+}
+
+#[test]
+fn test_signing_off_keeps_breaking_change_footers() {
+    for token in ["BREAKING CHANGE", "BREAKING-CHANGE"] {
+        let message = format!("feat(api): drop v1\n\nOld clients stop working.\n\n{}: v1 endpoints are gone\nRefs: PROJ-9\n", token);
+        let signed = trailers::merge(&message, &[Trailer::new("Signed-off-by", "A <a@example.com>")]);
+        assert_eq!(signed, format!("feat(api): drop v1\n\nOld clients stop working.\n\n{}: v1 endpoints are gone\nRefs: PROJ-9\nSigned-off-by: A <a@example.com>\n", token));
+
+        let parsed = ConventionalCommit::parse(&signed).unwrap();
+        assert!(parsed.breaking, "{}", signed);
+        assert_eq!(parsed.breaking_description(), Some("v1 endpoints are gone"));
+        assert_eq!(parsed.body.as_deref(), Some("Old clients stop working."));
+    }
+}
+
+#[test]
+fn test_ticket_ids_from_branch() -> Result<()> {
+    let ids = trailers::ticket_ids("feature/PROJ-123-login-PROJ-123", "[A-Z]+-[0-9]+")?;
+    assert_eq!(ids, vec!["PROJ-123".to_string()]);
+    assert!(trailers::ticket_ids("main", "[A-Z]+-[0-9]+")?.is_empty());
+    assert!(trailers::ticket_ids("main", "[").is_err());
+    Ok(())
+}
+
+#[test]
+fn test_recent_authors_use_mailmap() -> Result<()> {
+    let (dir, repo) = temp_repo("authors")?;
+    std::fs::write(dir.join(".mailmap"), "Jane Doe <jane@example.com> <jd@old.example.com>\nPat Lee <pat@example.com>\n")?;
+    commit_as(&repo, "jd", "jd@old.example.com", "a.txt")?;
+    commit_as(&repo, "Test User", "test@example.com", "b.txt")?;
+    commit_as(&repo, "Sam Roe", "sam@example.com", "c.txt")?;
+
+    let authors = trailers::recent_authors(&repo, 10)?;
+    assert_eq!(authors, vec![
+        "Sam Roe <sam@example.com>".to_string(),
+        "Jane Doe <jane@example.com>".to_string(),
+        "Pat Lee <pat@example.com>".to_string(),
+    ]);
+
+    assert_eq!(trailers::resolve_co_author(&repo, "jane")?, "Jane Doe <jane@example.com>");
+    assert!(trailers::resolve_co_author(&repo, "example.com").is_err());
+    assert_eq!(trailers::resolve_co_author(&repo, "New Person <new@example.com>")?, "New Person <new@example.com>");
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}